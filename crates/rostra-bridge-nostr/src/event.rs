@@ -0,0 +1,169 @@
+use std::str::FromStr;
+
+use data_encoding::{HEXLOWER, HEXLOWER_PERMISSIVE};
+use k256::schnorr::{Signature, SigningKey, VerifyingKey};
+use rand::RngCore as _;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest as _, Sha256};
+use snafu::{OptionExt as _, ResultExt as _, Snafu};
+
+/// User metadata (NIP-01)
+pub const KIND_METADATA: u16 = 0;
+/// Short text note (NIP-01)
+pub const KIND_TEXT_NOTE: u16 = 1;
+/// Reaction (NIP-25)
+pub const KIND_REACTION: u16 = 7;
+
+#[derive(Debug, Snafu)]
+pub enum NostrError {
+    #[snafu(display("Invalid JSON on line {line}: {source}"))]
+    InvalidJson {
+        source: serde_json::Error,
+        line: usize,
+    },
+    #[snafu(display("Invalid Nostr secret key"))]
+    InvalidSecretKey,
+    #[snafu(display("Invalid event {id}"))]
+    InvalidEvent { id: String },
+    #[snafu(display("Invalid signature of event {id}"))]
+    InvalidSignature { id: String },
+}
+
+pub type NostrResult<T> = std::result::Result<T, NostrError>;
+
+/// A Nostr event, as defined in NIP-01
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NostrEvent {
+    /// Hex-encoded sha256 of the serialized event
+    pub id: String,
+    /// Hex-encoded x-only secp256k1 public key of the author
+    pub pubkey: String,
+    /// Unix timestamp in seconds
+    pub created_at: u64,
+    pub kind: u16,
+    pub tags: Vec<Vec<String>>,
+    pub content: String,
+    /// Hex-encoded BIP340 signature of `id`
+    pub sig: String,
+}
+
+impl NostrEvent {
+    /// Create an event signed by `secret`
+    pub fn new_signed(
+        secret: &NostrSecretKey,
+        created_at: u64,
+        kind: u16,
+        tags: Vec<Vec<String>>,
+        content: String,
+    ) -> Self {
+        let pubkey = secret.public_key();
+        let id = compute_id(&pubkey, created_at, kind, &tags, &content);
+
+        let mut aux_rand = [0u8; 32];
+        rand::thread_rng().fill_bytes(&mut aux_rand);
+        let sig = secret
+            .0
+            .sign_raw(&id, &aux_rand)
+            .expect("Signing a 32 byte digest can't fail");
+
+        Self {
+            id: HEXLOWER.encode(&id),
+            pubkey,
+            created_at,
+            kind,
+            tags,
+            content,
+            sig: HEXLOWER.encode(&sig.to_bytes()),
+        }
+    }
+
+    /// Check that the `id` matches the content and `sig` is valid for `pubkey`
+    pub fn verify(&self) -> NostrResult<()> {
+        let id = compute_id(
+            &self.pubkey,
+            self.created_at,
+            self.kind,
+            &self.tags,
+            &self.content,
+        );
+        if HEXLOWER_PERMISSIVE.decode(self.id.as_bytes()).ok() != Some(id.to_vec()) {
+            return InvalidEventSnafu { id: &self.id }.fail();
+        }
+
+        let key = HEXLOWER_PERMISSIVE
+            .decode(self.pubkey.as_bytes())
+            .ok()
+            .and_then(|bytes| VerifyingKey::from_bytes(&bytes).ok())
+            .context(InvalidEventSnafu { id: &self.id })?;
+        let sig = HEXLOWER_PERMISSIVE
+            .decode(self.sig.as_bytes())
+            .ok()
+            .and_then(|bytes| Signature::try_from(bytes.as_slice()).ok())
+            .context(InvalidSignatureSnafu { id: &self.id })?;
+
+        key.verify_raw(&id, &sig)
+            .ok()
+            .context(InvalidSignatureSnafu { id: &self.id })
+    }
+
+    /// The first value of each tag named `name`
+    pub fn tag_values<'s>(&'s self, name: &'s str) -> impl Iterator<Item = &'s str> + 's {
+        self.tags
+            .iter()
+            .filter(move |tag| tag.first().is_some_and(|tag_name| tag_name == name))
+            .filter_map(|tag| tag.get(1).map(String::as_str))
+    }
+
+    /// Parse events from JSON lines, skipping empty ones
+    pub fn from_json_lines(s: &str) -> NostrResult<Vec<Self>> {
+        s.lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(i, line)| serde_json::from_str(line).context(InvalidJsonSnafu { line: i + 1 }))
+            .collect()
+    }
+
+    /// Serialize events as JSON lines
+    pub fn to_json_lines(events: &[Self]) -> String {
+        events
+            .iter()
+            .map(|event| serde_json::to_string(event).expect("Can't fail") + "\n")
+            .collect()
+    }
+}
+
+/// Event id: sha256 of `[0, pubkey, created_at, kind, tags, content]`
+fn compute_id(
+    pubkey: &str,
+    created_at: u64,
+    kind: u16,
+    tags: &[Vec<String>],
+    content: &str,
+) -> [u8; 32] {
+    let serialized =
+        serde_json::to_vec(&(0, pubkey, created_at, kind, tags, content)).expect("Can't fail");
+    Sha256::digest(serialized).into()
+}
+
+/// Nostr secret key (hex-encoded secp256k1 scalar)
+pub struct NostrSecretKey(SigningKey);
+
+impl NostrSecretKey {
+    /// Hex-encoded x-only public key
+    pub fn public_key(&self) -> String {
+        HEXLOWER.encode(&self.0.verifying_key().to_bytes())
+    }
+}
+
+impl FromStr for NostrSecretKey {
+    type Err = NostrError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        HEXLOWER_PERMISSIVE
+            .decode(s.trim().as_bytes())
+            .ok()
+            .and_then(|bytes| SigningKey::from_bytes(&bytes).ok())
+            .map(Self)
+            .context(InvalidSecretKeySnafu)
+    }
+}
@@ -0,0 +1,114 @@
+use std::collections::HashMap;
+
+use rostra_core::ShortEventId;
+use rostra_core::event::{
+    Event, EventContent, EventExt as _, EventKind, SocialPost, SocialProfileUpdate,
+};
+
+use crate::{
+    KIND_METADATA, KIND_REACTION, KIND_TEXT_NOTE, NOSTR_DISLIKE, NOSTR_LIKE, NostrEvent,
+    NostrMetadata, NostrSecretKey, ROSTRA_DISLIKE, ROSTRA_LIKE,
+};
+
+/// Convert events of a single identity (in causal order) into Nostr events
+/// signed by `secret`
+///
+/// Posts become text notes, reactions to own posts become reactions and
+/// profile updates become metadata. Everything else, including events without
+/// content, is skipped. Replies keep their thread only if the post replied to
+/// was exported as well.
+pub fn export_events<'e>(
+    events: impl IntoIterator<Item = (&'e Event, Option<&'e EventContent>)>,
+    secret: &NostrSecretKey,
+) -> Vec<NostrEvent> {
+    let pubkey = secret.public_key();
+    // Rostra event id -> Nostr event id, of exported posts
+    let mut exported: HashMap<ShortEventId, String> = HashMap::new();
+    let mut out = vec![];
+
+    for (event, content) in events {
+        let Some(content) = content else {
+            continue;
+        };
+        let created_at = event.timestamp().0;
+
+        let nostr_event = match event.kind() {
+            EventKind::SOCIAL_POST => {
+                let Ok(post) = content.deserialize_cbor::<SocialPost>() else {
+                    continue;
+                };
+                // Nostr notes can't be edited, keep the original
+                if post.edit_of.is_some() {
+                    continue;
+                }
+                let reply_to = post
+                    .reply_to
+                    .filter(|reply_to| reply_to.rostra_id() == event.author())
+                    .and_then(|reply_to| exported.get(&reply_to.event_id()));
+
+                if let Some(reaction) = post.get_reaction() {
+                    let Some(reply_to) = reply_to else {
+                        continue;
+                    };
+                    let reaction = match reaction {
+                        ROSTRA_LIKE => NOSTR_LIKE,
+                        ROSTRA_DISLIKE => NOSTR_DISLIKE,
+                        reaction => reaction,
+                    };
+                    NostrEvent::new_signed(
+                        secret,
+                        created_at,
+                        KIND_REACTION,
+                        vec![
+                            vec!["e".into(), reply_to.clone()],
+                            vec!["p".into(), pubkey.clone()],
+                        ],
+                        reaction.to_owned(),
+                    )
+                } else {
+                    let Some(djot_content) = post.djot_content else {
+                        continue;
+                    };
+                    let mut tags = reply_to
+                        .map(|reply_to| {
+                            vec![vec![
+                                "e".into(),
+                                reply_to.clone(),
+                                String::new(),
+                                "reply".into(),
+                            ]]
+                        })
+                        .unwrap_or_default();
+                    // NIP-36
+                    if let Some(content_warning) = post.content_warning {
+                        tags.push(vec!["content-warning".into(), content_warning]);
+                    }
+                    NostrEvent::new_signed(secret, created_at, KIND_TEXT_NOTE, tags, djot_content)
+                }
+            }
+            EventKind::SOCIAL_PROFILE_UPDATE => {
+                let Ok(profile) = content.deserialize_cbor::<SocialProfileUpdate>() else {
+                    continue;
+                };
+                let metadata = NostrMetadata {
+                    name: Some(profile.display_name),
+                    display_name: None,
+                    about: Some(profile.bio),
+                };
+                NostrEvent::new_signed(
+                    secret,
+                    created_at,
+                    KIND_METADATA,
+                    vec![],
+                    serde_json::to_string(&metadata).expect("Can't fail"),
+                )
+            }
+            _ => continue,
+        };
+
+        exported.insert(event.compute_short_id(), nostr_event.id.clone());
+        out.push(nostr_event);
+    }
+
+    out
+}
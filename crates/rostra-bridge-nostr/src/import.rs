@@ -0,0 +1,171 @@
+use std::collections::HashMap;
+use std::time::{Duration, UNIX_EPOCH};
+
+use rostra_core::event::{
+    Event, EventContent, EventContentKind, EventKind, PersonaId, SocialPost, SocialProfileUpdate,
+    VerifiedEvent, VerifiedEventContent,
+};
+use rostra_core::id::RostraIdSecretKey;
+use rostra_core::{ExternalEventId, ShortEventId};
+
+use crate::{
+    KIND_METADATA, KIND_REACTION, KIND_TEXT_NOTE, NOSTR_DISLIKE, NOSTR_LIKE, NostrEvent,
+    NostrMetadata, ROSTRA_DISLIKE, ROSTRA_LIKE,
+};
+
+#[derive(Debug)]
+pub struct ImportOutcome {
+    /// Signed events ready to be processed, oldest first
+    pub events: Vec<VerifiedEventContent>,
+    /// Number of Nostr events that were not imported
+    pub skipped: usize,
+}
+
+/// Convert Nostr events into Rostra events authored by `id_secret`
+///
+/// Only events with a valid signature (and authored by `pubkey`, if set) are
+/// imported: text notes as posts of `persona`, reactions to imported notes as
+/// reactions and the newest metadata as a profile update. The original
+/// timestamps are preserved, and the events are chained after `parent`.
+pub fn import_events(
+    nostr_events: &[NostrEvent],
+    pubkey: Option<&str>,
+    id_secret: RostraIdSecretKey,
+    persona: PersonaId,
+    parent: Option<ShortEventId>,
+) -> ImportOutcome {
+    let author = id_secret.id();
+    let total = nostr_events.len();
+
+    let mut nostr_events: Vec<&NostrEvent> = nostr_events
+        .iter()
+        .filter(|event| pubkey.is_none_or(|pubkey| event.pubkey.eq_ignore_ascii_case(pubkey)))
+        .filter(|event| event.verify().is_ok())
+        .collect();
+    nostr_events.sort_by_key(|event| event.created_at);
+    let newest_metadata = nostr_events
+        .iter()
+        .rposition(|event| event.kind == KIND_METADATA);
+
+    // Nostr event id -> Rostra event id, of imported notes
+    let mut imported: HashMap<&str, ShortEventId> = HashMap::new();
+    let mut events = vec![];
+    let mut parent = parent;
+
+    for (i, nostr_event) in nostr_events.into_iter().enumerate() {
+        let encoded = match nostr_event.kind {
+            KIND_METADATA if Some(i) == newest_metadata => {
+                let Ok(metadata) = serde_json::from_str::<NostrMetadata>(&nostr_event.content)
+                else {
+                    continue;
+                };
+                encode(SocialProfileUpdate {
+                    display_name: metadata.display_name.or(metadata.name).unwrap_or_default(),
+                    bio: metadata.about.unwrap_or_default(),
+                    avatar: None,
+                    ..Default::default()
+                })
+            }
+            KIND_TEXT_NOTE => {
+                let reply_to = reply_target(nostr_event)
+                    .and_then(|target| imported.get(target))
+                    .map(|target| ExternalEventId::new(author, *target));
+                encode(SocialPost {
+                    persona,
+                    djot_content: Some(nostr_event.content.clone()),
+                    reply_to,
+                    reaction: None,
+                    attachments: vec![],
+                    edit_of: None,
+                    quote: None,
+                    poll: None,
+                    // NIP-36
+                    content_warning: nostr_event
+                        .tag_values("content-warning")
+                        .next()
+                        .filter(|cw| cw.len() <= SocialPost::MAX_CONTENT_WARNING_LEN)
+                        .map(ToOwned::to_owned),
+                })
+            }
+            KIND_REACTION => {
+                let reply_to = nostr_event
+                    .tag_values("e")
+                    .last()
+                    .and_then(|target| imported.get(target))
+                    .map(|target| ExternalEventId::new(author, *target));
+                let reaction = match nostr_event.content.as_str() {
+                    NOSTR_LIKE => ROSTRA_LIKE,
+                    NOSTR_DISLIKE => ROSTRA_DISLIKE,
+                    reaction => reaction,
+                };
+                let Some(reaction) = SocialPost::is_reaction(&reply_to, reaction) else {
+                    continue;
+                };
+                encode(SocialPost {
+                    persona,
+                    djot_content: None,
+                    reply_to,
+                    reaction: Some(reaction.to_owned()),
+                    attachments: vec![],
+                    edit_of: None,
+                    quote: None,
+                    poll: None,
+                    content_warning: None,
+                })
+            }
+            _ => continue,
+        };
+        let Some((kind, content, singleton)) = encoded else {
+            continue;
+        };
+
+        let signed = Event::builder()
+            .author(author)
+            .kind(kind)
+            .content(&content)
+            .maybe_parent_prev(parent)
+            .timestamp(UNIX_EPOCH + Duration::from_secs(nostr_event.created_at))
+            .singleton(singleton)
+            .build()
+            .signed_by(id_secret);
+        let event = VerifiedEvent::verify_signed(author, signed)
+            .expect("Can't fail to verify self-created event");
+        let event = VerifiedEventContent::verify(event, content)
+            .expect("Can't fail to verify self-created content");
+
+        let event_id = ShortEventId::from(event.event_id());
+        if nostr_event.kind == KIND_TEXT_NOTE {
+            imported.insert(&nostr_event.id, event_id);
+        }
+        parent = Some(event_id);
+        events.push(event);
+    }
+
+    ImportOutcome {
+        skipped: total - events.len(),
+        events,
+    }
+}
+
+/// Serialized `content`, along with its kind and singleton flag
+fn encode<C: EventContentKind>(content: C) -> Option<(EventKind, EventContent, bool)> {
+    let encoded = content.serialize_cbor().ok()?;
+    Some((C::KIND, encoded, C::SINGLETON))
+}
+
+/// The note replied to: the `e` tag marked as `reply` or `root` (NIP-10), or
+/// the last one for events using the deprecated positional scheme
+fn reply_target(event: &NostrEvent) -> Option<&str> {
+    let e_tags = || {
+        event
+            .tags
+            .iter()
+            .filter(|tag| tag.first().is_some_and(|name| name == "e"))
+    };
+    e_tags()
+        .find(|tag| tag.get(3).is_some_and(|marker| marker == "reply"))
+        .or_else(|| e_tags().find(|tag| tag.get(3).is_some_and(|marker| marker == "root")))
+        .or_else(|| e_tags().rfind(|tag| tag.get(3).is_none_or(String::is_empty)))
+        .and_then(|tag| tag.get(1))
+        .map(String::as_str)
+}
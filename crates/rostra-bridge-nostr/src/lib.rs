@@ -0,0 +1,39 @@
+//! Nostr import/export bridge
+//!
+//! Converts the [`SocialPost`] and [`SocialProfileUpdate`] history of a Rostra
+//! identity into signed Nostr events (NIP-01 text notes and metadata, NIP-10
+//! replies, NIP-25 reactions), and imports a dump of Nostr events (one JSON
+//! event per line) as Rostra events, keeping their original timestamps.
+
+mod event;
+mod export;
+mod import;
+
+pub use event::{
+    KIND_METADATA, KIND_REACTION, KIND_TEXT_NOTE, NostrError, NostrEvent, NostrResult,
+    NostrSecretKey,
+};
+pub use export::export_events;
+pub use import::{ImportOutcome, import_events};
+#[cfg(doc)]
+use rostra_core::event::content_kind::{SocialPost, SocialProfileUpdate};
+
+/// Reaction content meaning "like" in Nostr
+const NOSTR_LIKE: &str = "+";
+const NOSTR_DISLIKE: &str = "-";
+const ROSTRA_LIKE: &str = "👍";
+const ROSTRA_DISLIKE: &str = "👎";
+
+/// Profile metadata (content of [`KIND_METADATA`] events)
+#[derive(Debug, Default, serde::Serialize, serde::Deserialize)]
+struct NostrMetadata {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    display_name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    about: Option<String>,
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,220 @@
+use std::time::{Duration, UNIX_EPOCH};
+
+use rostra_core::ExternalEventId;
+use rostra_core::event::content_kind::EventContentKind as _;
+use rostra_core::event::{
+    Event, EventExt as _, PersonaId, SocialPost, SocialProfileUpdate, VerifiedEvent,
+    VerifiedEventContent,
+};
+use rostra_core::id::RostraIdSecretKey;
+
+use crate::{KIND_TEXT_NOTE, NostrEvent, NostrSecretKey, export_events, import_events};
+
+const FIXTURES: &str = include_str!("../fixtures/notes.jsonl");
+const FIXTURE_SECRET: &str = "1ce7222b1a829a546e1d28cd61ddd36dede587e46d9e446041d4e908e9c941c7";
+const FIXTURE_PUBKEY: &str = "544006a2191d7d6f25cff1c2d9d11d6eab99c610c903627e2ec8c0204230b18f";
+
+fn export(events: &[VerifiedEventContent], secret: &NostrSecretKey) -> Vec<NostrEvent> {
+    export_events(
+        events
+            .iter()
+            .map(|event| (&event.event.event, event.content.as_ref())),
+        secret,
+    )
+}
+
+fn build_event(
+    id_secret: RostraIdSecretKey,
+    parent: Option<&VerifiedEventContent>,
+    ts: u64,
+    content: SocialPost,
+) -> VerifiedEventContent {
+    let content = content.serialize_cbor().expect("Valid");
+    let signed = Event::builder()
+        .author(id_secret.id())
+        .kind(SocialPost::KIND)
+        .content(&content)
+        .maybe_parent_prev(parent.map(|parent| parent.event_id().into()))
+        .timestamp(UNIX_EPOCH + Duration::from_secs(ts))
+        .singleton(false)
+        .build()
+        .signed_by(id_secret);
+    let event = VerifiedEvent::verify_signed(id_secret.id(), signed).expect("Valid");
+    VerifiedEventContent::verify(event, content).expect("Valid")
+}
+
+#[test_log::test]
+fn fixtures_are_valid() {
+    let events = NostrEvent::from_json_lines(FIXTURES).expect("Valid");
+    assert_eq!(events.len(), 6);
+    for event in &events {
+        event.verify().expect("Valid");
+    }
+    assert_eq!(
+        FIXTURE_SECRET
+            .parse::<NostrSecretKey>()
+            .expect("Valid")
+            .public_key(),
+        FIXTURE_PUBKEY
+    );
+    assert_eq!(NostrEvent::to_json_lines(&events), FIXTURES);
+
+    let mut tampered = events[1].clone();
+    tampered.content = "Goodbye Nostr!".into();
+    assert!(tampered.verify().is_err());
+}
+
+#[test_log::test]
+fn nostr_round_trip() {
+    let mut nostr_events = NostrEvent::from_json_lines(FIXTURES).expect("Valid");
+    let mut tampered = nostr_events[1].clone();
+    tampered.content = "Goodbye Nostr!".into();
+    nostr_events.push(tampered);
+
+    let id_secret = RostraIdSecretKey::generate();
+    let outcome = import_events(
+        &nostr_events,
+        Some(FIXTURE_PUBKEY),
+        id_secret,
+        PersonaId(0),
+        None,
+    );
+    // Follow list and the tampered note
+    assert_eq!(outcome.skipped, 2);
+    assert_eq!(outcome.events.len(), 5);
+
+    let profile = outcome.events[0]
+        .content
+        .as_ref()
+        .expect("Has content")
+        .deserialize_cbor::<SocialProfileUpdate>()
+        .expect("Profile");
+    assert_eq!(profile.display_name, "Alice");
+    assert_eq!(profile.bio, "Testing the bridge");
+    assert_eq!(outcome.events[1].timestamp().0, 1700000100);
+
+    // Signing the imported events again results in exactly the same Nostr events
+    let secret = FIXTURE_SECRET.parse().expect("Valid");
+    let exported = export(&outcome.events, &secret);
+    let exported_ids: Vec<_> = exported.iter().map(|event| event.id.as_str()).collect();
+    let expected_ids: Vec<_> = nostr_events[..6]
+        .iter()
+        .filter(|event| event.kind != 3)
+        .map(|event| event.id.as_str())
+        .collect();
+    assert_eq!(exported_ids, expected_ids);
+    for event in &exported {
+        event.verify().expect("Valid");
+    }
+}
+
+#[test_log::test]
+fn rostra_round_trip() {
+    let id_secret = RostraIdSecretKey::generate();
+    let author = id_secret.id();
+
+    let post = build_event(
+        id_secret,
+        None,
+        1000,
+        SocialPost {
+            persona: PersonaId(1),
+            djot_content: Some("Hello _world_".into()),
+            reply_to: None,
+            reaction: None,
+            attachments: vec![],
+            edit_of: None,
+            quote: None,
+            poll: None,
+            content_warning: None,
+        },
+    );
+    let reply = build_event(
+        id_secret,
+        Some(&post),
+        2000,
+        SocialPost {
+            persona: PersonaId(1),
+            djot_content: Some("Replying to myself".into()),
+            reply_to: Some(ExternalEventId::new(author, post.event_id())),
+            reaction: None,
+            attachments: vec![],
+            edit_of: None,
+            quote: None,
+            poll: None,
+            content_warning: Some("Spoilers".into()),
+        },
+    );
+    let reaction = build_event(
+        id_secret,
+        Some(&reply),
+        3000,
+        SocialPost {
+            persona: PersonaId(1),
+            djot_content: None,
+            reply_to: Some(ExternalEventId::new(author, reply.event_id())),
+            reaction: Some("👎".into()),
+            attachments: vec![],
+            edit_of: None,
+            quote: None,
+            poll: None,
+            content_warning: None,
+        },
+    );
+    // Replies to other identities lose their thread
+    let foreign_reply = build_event(
+        id_secret,
+        Some(&reaction),
+        4000,
+        SocialPost {
+            persona: PersonaId(1),
+            djot_content: Some("Replying to someone else".into()),
+            reply_to: Some(ExternalEventId::new(
+                RostraIdSecretKey::generate().id(),
+                post.event_id(),
+            )),
+            reaction: None,
+            attachments: vec![],
+            edit_of: None,
+            quote: None,
+            poll: None,
+            content_warning: None,
+        },
+    );
+    let events = [post, reply, reaction, foreign_reply];
+
+    let secret = FIXTURE_SECRET.parse().expect("Valid");
+    let exported = export(&events, &secret);
+    assert_eq!(exported.len(), 4);
+    assert_eq!(
+        exported[1].tags,
+        [
+            vec!["e", exported[0].id.as_str(), "", "reply"],
+            vec!["content-warning", "Spoilers"]
+        ]
+    );
+    assert_eq!(exported[2].content, "-");
+    assert_eq!(exported[3].kind, KIND_TEXT_NOTE);
+    assert!(exported[3].tags.is_empty());
+
+    let imported = import_events(&exported, None, id_secret, PersonaId(1), None);
+    assert_eq!(imported.skipped, 0);
+    assert_eq!(imported.events.len(), events.len());
+
+    // Same author, content, timestamps and parents result in the same events
+    for (original, imported) in events[..3].iter().zip(&imported.events) {
+        assert_eq!(original.event_id(), imported.event_id());
+    }
+    let foreign_reply = imported.events[3]
+        .content
+        .as_ref()
+        .expect("Has content")
+        .deserialize_cbor::<SocialPost>()
+        .expect("Post");
+    assert_eq!(
+        foreign_reply.djot_content.as_deref(),
+        Some("Replying to someone else")
+    );
+    assert_eq!(foreign_reply.reply_to, None);
+    assert_eq!(imported.events[3].timestamp().0, 4000);
+}
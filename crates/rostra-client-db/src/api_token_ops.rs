@@ -0,0 +1,214 @@
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+
+use rand::Rng as _;
+use rostra_core::Timestamp;
+use rostra_core::id::{RostraId, RostraIdSecretKey};
+use snafu::{OptionExt as _, ResultExt as _, Snafu, ensure};
+
+use crate::{
+    ApiTokenHash, ApiTokenRecord, ApiTokenScope, Database, DatabaseSnafu, DbIdMismatchSnafu,
+    DbResult, api_tokens, ids_self,
+};
+
+/// Bearer token for the API
+///
+/// Encoded as `{rostra_id}.{hex secret}`, so it is known which identity's
+/// database to look it up in. Only the hash of the secret is stored.
+#[derive(Clone, Copy)]
+pub struct ApiToken {
+    rostra_id: RostraId,
+    secret: [u8; 32],
+}
+
+#[derive(Debug, Snafu)]
+pub enum ApiTokenParseError {
+    #[snafu(display("Missing separator"))]
+    Separator,
+    #[snafu(display("Invalid rostra id"))]
+    Id,
+    #[snafu(display("Invalid secret"))]
+    Secret,
+}
+
+/// An [`ApiToken`] found in the database
+#[derive(Debug, Clone, Copy)]
+pub struct VerifiedApiToken {
+    pub scope: ApiTokenScope,
+    /// Set only for scopes that can sign
+    pub id_secret: Option<RostraIdSecretKey>,
+}
+
+impl ApiToken {
+    const HASH_CONTEXT: &str = "rostra 2025 api token hash";
+    const WRAP_CONTEXT: &str = "rostra 2025 api token secret wrap";
+
+    pub fn generate(rostra_id: RostraId) -> Self {
+        Self {
+            rostra_id,
+            secret: rand::thread_rng().r#gen(),
+        }
+    }
+
+    pub fn rostra_id(&self) -> RostraId {
+        self.rostra_id
+    }
+
+    pub fn hash(&self) -> ApiTokenHash {
+        blake3::derive_key(Self::HASH_CONTEXT, &self.secret)
+    }
+
+    /// Encrypt (or decrypt) `bytes` with a key derived from the token
+    fn wrap(&self, bytes: [u8; 32]) -> [u8; 32] {
+        let key = blake3::derive_key(Self::WRAP_CONTEXT, &self.secret);
+        std::array::from_fn(|i| bytes[i] ^ key[i])
+    }
+}
+
+impl fmt::Debug for ApiToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Never print the secret
+        f.debug_struct("ApiToken")
+            .field("rostra_id", &self.rostra_id)
+            .finish_non_exhaustive()
+    }
+}
+
+impl fmt::Display for ApiToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}.{}",
+            self.rostra_id,
+            data_encoding::HEXLOWER.encode(&self.secret)
+        )
+    }
+}
+
+impl FromStr for ApiToken {
+    type Err = ApiTokenParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (rostra_id, secret) = s.split_once('.').context(SeparatorSnafu)?;
+        let rostra_id = RostraId::from_str(rostra_id).ok().context(IdSnafu)?;
+        let secret = data_encoding::HEXLOWER_PERMISSIVE
+            .decode(secret.as_bytes())
+            .ok()
+            .and_then(|secret| <[u8; 32]>::try_from(secret).ok())
+            .context(SecretSnafu)?;
+        Ok(Self { rostra_id, secret })
+    }
+}
+
+impl Database {
+    /// Create a new API token of the `self_id`
+    ///
+    /// The `id_secret` is only stored (encrypted with the token) for scopes
+    /// that can sign.
+    pub async fn create_api_token(
+        &self,
+        name: String,
+        scope: ApiTokenScope,
+        id_secret: RostraIdSecretKey,
+    ) -> DbResult<ApiToken> {
+        ensure!(id_secret.id() == self.self_id, DbIdMismatchSnafu);
+
+        let token = ApiToken::generate(self.self_id);
+        let record = ApiTokenRecord {
+            name,
+            scope,
+            created: Timestamp::now(),
+            wrapped_secret: scope.can_sign().then(|| token.wrap(id_secret.to_bytes())),
+        };
+
+        self.write_with(|tx| {
+            tx.open_table(&api_tokens::TABLE)?
+                .insert(&token.hash(), &record)?;
+            Ok(())
+        })
+        .await?;
+
+        Ok(token)
+    }
+
+    /// Look up an [`ApiToken`], returning `None` if it is not valid
+    pub async fn verify_api_token(&self, token: &ApiToken) -> DbResult<Option<VerifiedApiToken>> {
+        if token.rostra_id != self.self_id {
+            return Ok(None);
+        }
+
+        self.read_with(|tx| Self::verify_api_token_tx(token, &tx.open_table(&api_tokens::TABLE)?))
+            .await
+    }
+
+    /// Like [`Self::verify_api_token`], but reads the database at `path`
+    /// directly, without opening (initializing, migrating) it
+    ///
+    /// Meant for checking a token before a client is started for its
+    /// identity. The database at `path` must not be open already.
+    pub async fn verify_api_token_at(
+        path: &Path,
+        token: &ApiToken,
+    ) -> DbResult<Option<VerifiedApiToken>> {
+        let db = redb_bincode::Database::from(
+            tokio::task::block_in_place(|| redb::Database::open(path)).context(DatabaseSnafu)?,
+        );
+        Self::read_with_inner(&db, |tx| {
+            let Some(self_id) = Self::read_self_id_tx(&tx.open_table(&ids_self::TABLE)?)? else {
+                return Ok(None);
+            };
+            if token.rostra_id != self_id.rostra_id {
+                return Ok(None);
+            }
+            match tx.open_table(&api_tokens::TABLE) {
+                Ok(api_tokens_tbl) => Self::verify_api_token_tx(token, &api_tokens_tbl),
+                // Database from before the API tokens were added
+                Err(redb::TableError::TableDoesNotExist(_)) => Ok(None),
+                Err(err) => Err(err.into()),
+            }
+        })
+        .await
+    }
+
+    fn verify_api_token_tx(
+        token: &ApiToken,
+        api_tokens_table: &impl api_tokens::ReadableTable,
+    ) -> DbResult<Option<VerifiedApiToken>> {
+        let Some(record) = api_tokens_table.get(&token.hash())?.map(|g| g.value()) else {
+            return Ok(None);
+        };
+
+        let id_secret = record
+            .wrapped_secret
+            .map(|wrapped| RostraIdSecretKey::from_bytes(token.wrap(wrapped)));
+        if id_secret.is_some_and(|id_secret| id_secret.id() != token.rostra_id) {
+            return Ok(None);
+        }
+
+        Ok(Some(VerifiedApiToken {
+            scope: record.scope,
+            id_secret,
+        }))
+    }
+
+    pub async fn list_api_tokens(&self) -> DbResult<Vec<(ApiTokenHash, ApiTokenRecord)>> {
+        self.read_with(|tx| {
+            let tbl = tx.open_table(&api_tokens::TABLE)?;
+            let mut ret = vec![];
+            for record in tbl.range(..)? {
+                let (k, v) = record?;
+                ret.push((k.value(), v.value()));
+            }
+            ret.sort_by_key(|(_, record)| record.created);
+            Ok(ret)
+        })
+        .await
+    }
+
+    /// Revoke an API token, returning `false` if it did not exist
+    pub async fn revoke_api_token(&self, hash: ApiTokenHash) -> DbResult<bool> {
+        self.write_with(|tx| Ok(tx.open_table(&api_tokens::TABLE)?.remove(&hash)?.is_some()))
+            .await
+    }
+}
@@ -0,0 +1,231 @@
+use bincode::{Decode, Encode};
+use convi::ExpectInto as _;
+use rostra_core::ShortEventId;
+use rostra_core::bincode::STD_BINCODE_CONFIG;
+use rostra_core::event::{
+    EventContent, SignedEvent, VerifiedEvent, VerifiedEventContent, VerifiedEventError,
+};
+use rostra_core::id::RostraId;
+use serde::Serialize;
+use snafu::{ResultExt as _, Snafu};
+use tracing::info;
+
+use crate::event::EventContentState;
+use crate::{
+    Database, DbError, DbResult, InsertEventOutcome, LOG_TARGET, events, events_content,
+    events_heads,
+};
+
+/// Describes the content of an [`IdentityArchive`]
+#[derive(Debug, Encode, Decode, Clone, Serialize)]
+pub struct ArchiveManifest {
+    pub version: u32,
+    pub author: RostraId,
+    /// Heads of the `author`'s event DAG at the time of the export
+    pub heads: Vec<ShortEventId>,
+    pub num_events: u64,
+}
+
+#[derive(Debug, Encode, Decode, Clone)]
+pub struct ArchiveEvent {
+    pub signed: SignedEvent,
+    /// `None` if the content was not available (deleted, pruned, never
+    /// received or the event has no content)
+    pub content: Option<EventContent>,
+}
+
+/// All events (and their content) of a single identity
+///
+/// Everything in it is signed by the `author`, so it can be verified on
+/// import, and thus safely transported over untrusted channels.
+#[derive(Debug, Encode, Decode, Clone)]
+pub struct IdentityArchive {
+    pub manifest: ArchiveManifest,
+    /// Events in causal order: parents before children
+    pub events: Vec<ArchiveEvent>,
+}
+
+#[derive(Debug, Snafu)]
+pub enum ArchiveDecodeError {
+    #[snafu(display("Not a Rostra archive"))]
+    Magic,
+    #[snafu(display("Unsupported archive version: {version}"))]
+    Version {
+        version: u32,
+    },
+    #[snafu(display("Archive too large: {len} bytes"))]
+    TooLarge {
+        len: usize,
+    },
+    #[snafu(display("Trailing bytes after the archive"))]
+    TrailingBytes,
+    Decode {
+        source: bincode::error::DecodeError,
+    },
+}
+
+#[derive(Debug, Snafu)]
+pub enum ImportArchiveError {
+    #[snafu(transparent)]
+    Db { source: DbError },
+    #[snafu(display("Invalid event {event_id}"))]
+    InvalidEvent {
+        event_id: ShortEventId,
+        source: VerifiedEventError,
+    },
+    #[snafu(display("Invalid content of event {event_id}"))]
+    InvalidContent {
+        event_id: ShortEventId,
+        source: VerifiedEventError,
+    },
+    #[snafu(display("Manifest head {event_id} not in the archive"))]
+    UnknownHead { event_id: ShortEventId },
+}
+pub type ImportArchiveResult<T> = std::result::Result<T, ImportArchiveError>;
+
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct ArchiveImportOutcome {
+    pub num_events: u64,
+    /// Events that were not in the database before
+    pub num_new_events: u64,
+}
+
+impl IdentityArchive {
+    pub const MAGIC: [u8; 8] = *b"rostrarc";
+    pub const VERSION: u32 = 0;
+    /// Maximum size of an encoded archive
+    pub const MAX_SIZE: usize = 0x4000_0000;
+
+    pub fn encode_to_vec(&self) -> Vec<u8> {
+        let mut buf = Self::MAGIC.to_vec();
+        bincode::encode_into_std_write(self, &mut buf, STD_BINCODE_CONFIG)
+            .expect("Can't fail encoding");
+        buf
+    }
+
+    pub fn decode_from_slice(bytes: &[u8]) -> Result<Self, ArchiveDecodeError> {
+        let Some(bytes) = bytes.strip_prefix(Self::MAGIC.as_slice()) else {
+            return MagicSnafu.fail();
+        };
+        if Self::MAX_SIZE < bytes.len() {
+            return TooLargeSnafu { len: bytes.len() }.fail();
+        }
+        let (archive, consumed_len): (Self, _) = bincode::decode_from_slice(
+            bytes,
+            STD_BINCODE_CONFIG.with_limit::<{ IdentityArchive::MAX_SIZE }>(),
+        )
+        .context(DecodeSnafu)?;
+        if consumed_len != bytes.len() {
+            return TrailingBytesSnafu.fail();
+        }
+
+        if archive.manifest.version != Self::VERSION {
+            return VersionSnafu {
+                version: archive.manifest.version,
+            }
+            .fail();
+        }
+        Ok(archive)
+    }
+}
+
+impl Database {
+    /// Export all the known events of `author` as an [`IdentityArchive`]
+    pub async fn export_identity(&self, author: RostraId) -> DbResult<IdentityArchive> {
+        self.read_with(|tx| {
+            let events_tbl = tx.open_table(&events::TABLE)?;
+            let events_content_tbl = tx.open_table(&events_content::TABLE)?;
+            let events_heads_tbl = tx.open_table(&events_heads::TABLE)?;
+
+            let heads = Database::get_heads_events_tx(author, &events_heads_tbl)?;
+
+            let mut events = vec![];
+            for event_id in
+                Database::get_author_events_causal_order_tx(author, &events_tbl, &events_heads_tbl)?
+            {
+                let record =
+                    Database::get_event_tx(event_id, &events_tbl)?.expect("Must have the event");
+
+                let content = match Database::get_event_content_tx(event_id, &events_content_tbl)? {
+                    Some(
+                        EventContentState::Present(content) | EventContentState::Invalid(content),
+                    ) => Some(content.into_owned()),
+                    Some(EventContentState::Deleted { .. } | EventContentState::Pruned) | None => {
+                        None
+                    }
+                };
+                events.push(ArchiveEvent {
+                    signed: record.signed,
+                    content,
+                });
+            }
+
+            Ok(IdentityArchive {
+                manifest: ArchiveManifest {
+                    version: IdentityArchive::VERSION,
+                    author,
+                    heads,
+                    num_events: events.len().expect_into(),
+                },
+                events,
+            })
+        })
+        .await
+    }
+
+    /// Import an [`IdentityArchive`]
+    ///
+    /// Every event signature and content hash is verified before anything is
+    /// written, and the whole archive is imported in a single transaction.
+    pub async fn import_archive(
+        &self,
+        archive: &IdentityArchive,
+    ) -> ImportArchiveResult<ArchiveImportOutcome> {
+        let author = archive.manifest.author;
+
+        let mut verified = Vec::with_capacity(archive.events.len());
+        for ArchiveEvent { signed, content } in &archive.events {
+            let event_id = signed.compute_short_id();
+            let event = VerifiedEvent::verify_signed(author, *signed)
+                .context(InvalidEventSnafu { event_id })?;
+            let content = content
+                .as_ref()
+                .map(|content| VerifiedEventContent::verify(event, content.clone()))
+                .transpose()
+                .context(InvalidContentSnafu { event_id })?;
+            verified.push((event, content));
+        }
+
+        for head in &archive.manifest.heads {
+            if !verified
+                .iter()
+                .any(|(event, _)| ShortEventId::from(event.event_id) == *head)
+            {
+                return UnknownHeadSnafu { event_id: *head }.fail();
+            }
+        }
+
+        let num_new_events = self
+            .write_with(|tx| {
+                let mut num_new_events = 0;
+                for (event, content) in &verified {
+                    let (insert_outcome, _) = self.process_event_tx(event, tx)?;
+                    if let InsertEventOutcome::Inserted { .. } = insert_outcome {
+                        num_new_events += 1;
+                    }
+                    if let Some(content) = content {
+                        self.process_event_content_tx(content, tx)?;
+                    }
+                }
+                Ok(num_new_events)
+            })
+            .await?;
+
+        info!(target: LOG_TARGET, %author, num_events = verified.len(), num_new_events, "Imported archive");
+
+        Ok(ArchiveImportOutcome {
+            num_events: verified.len().expect_into(),
+            num_new_events,
+        })
+    }
+}
@@ -0,0 +1,54 @@
+use redb_bincode::ReadableTable as _;
+
+use crate::{Database, DbResult, SocialDraftRecord, social_drafts};
+
+impl Database {
+    /// Save a draft, as a new one if `id` is `None`, returning its id
+    pub async fn save_social_draft(
+        &self,
+        id: Option<u64>,
+        record: SocialDraftRecord,
+    ) -> DbResult<u64> {
+        self.write_with(|tx| {
+            let mut tbl = tx.open_table(&social_drafts::TABLE)?;
+            let id = match id {
+                Some(id) => id,
+                None => tbl.last()?.map(|g| g.0.value() + 1).unwrap_or_default(),
+            };
+            tbl.insert(&id, &record)?;
+            Ok(id)
+        })
+        .await
+    }
+
+    pub async fn get_social_draft(&self, id: u64) -> DbResult<Option<SocialDraftRecord>> {
+        self.read_with(|tx| {
+            Ok(tx
+                .open_table(&social_drafts::TABLE)?
+                .get(&id)?
+                .map(|g| g.value()))
+        })
+        .await
+    }
+
+    /// All drafts, most recently saved first
+    pub async fn list_social_drafts(&self) -> DbResult<Vec<(u64, SocialDraftRecord)>> {
+        self.read_with(|tx| {
+            let tbl = tx.open_table(&social_drafts::TABLE)?;
+            let mut ret = vec![];
+            for record in tbl.range(..)? {
+                let (k, v) = record?;
+                ret.push((k.value(), v.value()));
+            }
+            ret.sort_by_key(|(id, record)| std::cmp::Reverse((record.updated, *id)));
+            Ok(ret)
+        })
+        .await
+    }
+
+    /// Remove a draft, returning `false` if it did not exist
+    pub async fn remove_social_draft(&self, id: u64) -> DbResult<bool> {
+        self.write_with(|tx| Ok(tx.open_table(&social_drafts::TABLE)?.remove(&id)?.is_some()))
+            .await
+    }
+}
@@ -0,0 +1,408 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use rostra_core::event::{EventExt as _, VerifiedEvent, VerifiedEventContent};
+use rostra_core::id::{RostraId, ToShort as _};
+use rostra_core::{ContentHash, ShortEventId, Timestamp};
+use serde::Serialize;
+use tracing::{info, warn};
+
+use crate::event::{EventContentState, EventsMissingRecord};
+use crate::{
+    Database, DbResult, EventsHeadsTableRecord, LOG_TARGET, SocialPostRecord, WriteTransactionCtx,
+    events, events_by_time, events_content, events_content_missing, events_heads, events_missing,
+    events_self, social_posts, social_posts_reactions, social_posts_replies,
+};
+
+/// A single inconsistency found by [`Database::check_integrity`]
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "issue", rename_all = "snake_case")]
+pub enum IntegrityIssue {
+    /// Event record has an invalid signature or is stored under a wrong id
+    InvalidEvent {
+        event_id: ShortEventId,
+    },
+    /// Head entry pointing at an unknown event, or one that has children
+    HeadStale {
+        author: RostraId,
+        event_id: ShortEventId,
+    },
+    /// Event without any children that is not tracked as a head
+    HeadUntracked {
+        author: RostraId,
+        event_id: ShortEventId,
+    },
+    /// Missing entry for an event we already have, or no one references
+    MissingStale {
+        author: RostraId,
+        event_id: ShortEventId,
+    },
+    /// Referenced parent event we don't have and don't track as missing
+    MissingUntracked {
+        author: RostraId,
+        event_id: ShortEventId,
+        deleted_by: Option<ShortEventId>,
+    },
+    EventsByTimeStale {
+        ts: Timestamp,
+        event_id: ShortEventId,
+    },
+    EventsByTimeUntracked {
+        ts: Timestamp,
+        event_id: ShortEventId,
+    },
+    /// Content stored for an event we don't have
+    ContentOrphaned {
+        event_id: ShortEventId,
+    },
+    /// Stored content does not match event's `content_hash`/`content_len`
+    ContentMismatch {
+        event_id: ShortEventId,
+    },
+    ContentMissingStale {
+        event_id: ShortEventId,
+    },
+    ContentMissingUntracked {
+        event_id: ShortEventId,
+    },
+    /// `social_posts` counters disagree with `social_posts_replies` and
+    /// `social_posts_reactions`
+    SocialPostCountersMismatch {
+        event_id: ShortEventId,
+        recorded: SocialPostRecord,
+        actual: SocialPostRecord,
+    },
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct IntegrityReport {
+    pub events_checked: u64,
+    pub contents_checked: u64,
+    pub issues: Vec<IntegrityIssue>,
+    /// Whether the `issues` were fixed
+    pub repaired: bool,
+}
+
+impl IntegrityReport {
+    pub fn is_ok(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
+
+impl Database {
+    /// Cross-verify all event and social tables
+    ///
+    /// With `repair` set, all the found issues are fixed in the same
+    /// transaction: event bookkeeping entries are rebuilt from the raw events,
+    /// and tables derived from event content with [`Self::reindex_tx`].
+    pub async fn check_integrity(&self, repair: bool) -> DbResult<IntegrityReport> {
+        if repair {
+            self.write_with(|tx| {
+                let (mut report, needs_reindex) = self.repair_integrity_tx(tx)?;
+                if needs_reindex {
+                    self.reindex_tx(tx, |progress| {
+                        info!(target: LOG_TARGET, processed = progress.processed, total = progress.total, "Reindexing");
+                    })?;
+                }
+
+                if !report.is_ok() {
+                    info!(target: LOG_TARGET, num = report.issues.len(), "Repaired database integrity issues");
+                }
+                report.repaired = true;
+                Ok(report)
+            })
+            .await
+        } else {
+            self.read_with(|tx| {
+                Self::check_integrity_tx(
+                    &tx.open_table(&events::TABLE)?,
+                    &tx.open_table(&events_heads::TABLE)?,
+                    &tx.open_table(&events_missing::TABLE)?,
+                    &tx.open_table(&events_by_time::TABLE)?,
+                    &tx.open_table(&events_content::TABLE)?,
+                    &tx.open_table(&events_content_missing::TABLE)?,
+                    &tx.open_table(&social_posts::TABLE)?,
+                    &tx.open_table(&social_posts_replies::TABLE)?,
+                    &tx.open_table(&social_posts_reactions::TABLE)?,
+                )
+            })
+            .await
+        }
+    }
+
+    /// Check integrity and fix event bookkeeping issues in place
+    ///
+    /// Returns whether tables derived from event content need to be rebuilt.
+    fn repair_integrity_tx(&self, tx: &WriteTransactionCtx) -> DbResult<(IntegrityReport, bool)> {
+        let mut events_tbl = tx.open_table(&events::TABLE)?;
+        let mut events_self_tbl = tx.open_table(&events_self::TABLE)?;
+        let mut events_heads_tbl = tx.open_table(&events_heads::TABLE)?;
+        let mut events_missing_tbl = tx.open_table(&events_missing::TABLE)?;
+        let mut events_by_time_tbl = tx.open_table(&events_by_time::TABLE)?;
+        let mut events_content_tbl = tx.open_table(&events_content::TABLE)?;
+        let mut events_content_missing_tbl = tx.open_table(&events_content_missing::TABLE)?;
+        let social_posts_tbl = tx.open_table(&social_posts::TABLE)?;
+        let social_posts_replies_tbl = tx.open_table(&social_posts_replies::TABLE)?;
+        let social_posts_reactions_tbl = tx.open_table(&social_posts_reactions::TABLE)?;
+
+        let report = Self::check_integrity_tx(
+            &events_tbl,
+            &events_heads_tbl,
+            &events_missing_tbl,
+            &events_by_time_tbl,
+            &events_content_tbl,
+            &events_content_missing_tbl,
+            &social_posts_tbl,
+            &social_posts_replies_tbl,
+            &social_posts_reactions_tbl,
+        )?;
+
+        let mut needs_reindex = false;
+        for issue in &report.issues {
+            match *issue {
+                IntegrityIssue::InvalidEvent { event_id } => {
+                    events_tbl.remove(&event_id)?;
+                    events_self_tbl.remove(&event_id)?;
+                    needs_reindex = true;
+                }
+                IntegrityIssue::HeadStale { author, event_id } => {
+                    events_heads_tbl.remove(&(author, event_id))?;
+                }
+                IntegrityIssue::HeadUntracked { author, event_id } => {
+                    events_heads_tbl.insert(&(author, event_id), &EventsHeadsTableRecord)?;
+                }
+                IntegrityIssue::MissingStale { author, event_id } => {
+                    events_missing_tbl.remove(&(author, event_id))?;
+                }
+                IntegrityIssue::MissingUntracked {
+                    author,
+                    event_id,
+                    deleted_by,
+                } => {
+                    events_missing_tbl
+                        .insert(&(author, event_id), &EventsMissingRecord { deleted_by })?;
+                }
+                IntegrityIssue::EventsByTimeStale { ts, event_id } => {
+                    events_by_time_tbl.remove(&(ts, event_id))?;
+                }
+                IntegrityIssue::EventsByTimeUntracked { ts, event_id } => {
+                    events_by_time_tbl.insert(&(ts, event_id), &())?;
+                }
+                IntegrityIssue::ContentOrphaned { event_id }
+                | IntegrityIssue::ContentMismatch { event_id } => {
+                    events_content_tbl.remove(&event_id)?;
+                    needs_reindex = true;
+                }
+                IntegrityIssue::ContentMissingStale { event_id } => {
+                    events_content_missing_tbl.remove(&event_id)?;
+                }
+                IntegrityIssue::ContentMissingUntracked { event_id } => {
+                    events_content_missing_tbl.insert(&event_id, &())?;
+                }
+                IntegrityIssue::SocialPostCountersMismatch { .. } => {
+                    needs_reindex = true;
+                }
+            }
+        }
+
+        Ok((report, needs_reindex))
+    }
+
+    /// Compare the current state of tables with the one derived from the
+    /// (valid) events stored in `events`
+    #[allow(clippy::too_many_arguments)]
+    pub fn check_integrity_tx(
+        events_table: &impl events::ReadableTable,
+        events_heads_table: &impl events_heads::ReadableTable,
+        events_missing_table: &impl events_missing::ReadableTable,
+        events_by_time_table: &impl events_by_time::ReadableTable,
+        events_content_table: &impl events_content::ReadableTable,
+        events_content_missing_table: &impl events_content_missing::ReadableTable,
+        social_posts_table: &impl social_posts::ReadableTable,
+        social_posts_replies_table: &impl social_posts_replies::ReadableTable,
+        social_posts_reactions_table: &impl social_posts_reactions::ReadableTable,
+    ) -> DbResult<IntegrityReport> {
+        let mut issues = vec![];
+        let mut events_checked = 0;
+        let mut contents_checked = 0;
+
+        let mut valid_events = BTreeMap::new();
+        for record in events_table.range(..)? {
+            let (k, v) = record?;
+            let event_id = k.value();
+            events_checked += 1;
+
+            match VerifiedEvent::verify_received_as_is(v.value().signed) {
+                Ok(event) if event.event_id.to_short() == event_id => {
+                    valid_events.insert(event_id, event);
+                }
+                _ => {
+                    warn!(target: LOG_TARGET, %event_id, "Invalid event record");
+                    issues.push(IntegrityIssue::InvalidEvent { event_id });
+                }
+            }
+        }
+
+        // Mirror the accounting done in `insert_event_tx`
+        let mut expected_heads: BTreeSet<(RostraId, ShortEventId)> = valid_events
+            .iter()
+            .map(|(event_id, event)| (event.author(), *event_id))
+            .collect();
+        let mut expected_missing: BTreeMap<(RostraId, ShortEventId), Option<ShortEventId>> =
+            BTreeMap::new();
+        let mut expected_by_time: BTreeSet<(Timestamp, ShortEventId)> = BTreeSet::new();
+
+        for (event_id, event) in &valid_events {
+            let author = event.author();
+            expected_by_time.insert((event.timestamp(), *event_id));
+
+            for (parent_id, parent_is_aux) in
+                [(event.parent_aux(), true), (event.parent_prev(), false)]
+            {
+                let Some(parent_id) = parent_id else {
+                    continue;
+                };
+                expected_heads.remove(&(author, parent_id));
+
+                if !valid_events.contains_key(&parent_id) {
+                    let deleted_by = (event.is_delete_parent_aux_content_set() && parent_is_aux)
+                        .then_some(*event_id);
+                    let entry = expected_missing.entry((author, parent_id)).or_default();
+                    *entry = entry.or(deleted_by);
+                }
+            }
+        }
+
+        for record in events_heads_table.range(..)? {
+            let (k, _) = record?;
+            let (author, event_id) = k.value();
+            if !expected_heads.remove(&(author, event_id)) {
+                issues.push(IntegrityIssue::HeadStale { author, event_id });
+            }
+        }
+        issues.extend(
+            expected_heads
+                .into_iter()
+                .map(|(author, event_id)| IntegrityIssue::HeadUntracked { author, event_id }),
+        );
+
+        for record in events_missing_table.range(..)? {
+            let (k, _) = record?;
+            let (author, event_id) = k.value();
+            if expected_missing.remove(&(author, event_id)).is_none() {
+                issues.push(IntegrityIssue::MissingStale { author, event_id });
+            }
+        }
+        issues.extend(
+            expected_missing
+                .into_iter()
+                .map(
+                    |((author, event_id), deleted_by)| IntegrityIssue::MissingUntracked {
+                        author,
+                        event_id,
+                        deleted_by,
+                    },
+                ),
+        );
+
+        for record in events_by_time_table.range(..)? {
+            let (k, _) = record?;
+            let (ts, event_id) = k.value();
+            if !expected_by_time.remove(&(ts, event_id)) {
+                issues.push(IntegrityIssue::EventsByTimeStale { ts, event_id });
+            }
+        }
+        issues.extend(
+            expected_by_time
+                .into_iter()
+                .map(|(ts, event_id)| IntegrityIssue::EventsByTimeUntracked { ts, event_id }),
+        );
+
+        // Every event with a content is expected to either have a content state
+        // or be waiting for its content
+        let mut expected_content_missing: BTreeSet<ShortEventId> = valid_events
+            .iter()
+            .filter(|(_, event)| event.content_hash() != ContentHash::ZERO)
+            .map(|(event_id, _)| *event_id)
+            .collect();
+
+        for record in events_content_table.range(..)? {
+            let (k, v) = record?;
+            let event_id = k.value();
+            contents_checked += 1;
+
+            let Some(event) = valid_events.get(&event_id) else {
+                issues.push(IntegrityIssue::ContentOrphaned { event_id });
+                continue;
+            };
+
+            match v.value() {
+                EventContentState::Present(content) | EventContentState::Invalid(content) => {
+                    if VerifiedEventContent::verify(*event, content.into_owned()).is_err() {
+                        warn!(target: LOG_TARGET, %event_id, "Event content does not match the event");
+                        issues.push(IntegrityIssue::ContentMismatch { event_id });
+                        continue;
+                    }
+                }
+                EventContentState::Deleted { .. } | EventContentState::Pruned => {}
+            }
+            expected_content_missing.remove(&event_id);
+        }
+
+        for record in events_content_missing_table.range(..)? {
+            let (k, _) = record?;
+            let event_id = k.value();
+            if !expected_content_missing.remove(&event_id) {
+                issues.push(IntegrityIssue::ContentMissingStale { event_id });
+            }
+        }
+        issues.extend(
+            expected_content_missing
+                .into_iter()
+                .map(|event_id| IntegrityIssue::ContentMissingUntracked { event_id }),
+        );
+
+        let mut actual_counters: BTreeMap<ShortEventId, SocialPostRecord> = BTreeMap::new();
+        for record in social_posts_replies_table.range(..)? {
+            let (k, _) = record?;
+            actual_counters.entry(k.value().0).or_default().reply_count += 1;
+        }
+        for record in social_posts_reactions_table.range(..)? {
+            let (k, _) = record?;
+            actual_counters
+                .entry(k.value().0)
+                .or_default()
+                .reaction_count += 1;
+        }
+
+        for record in social_posts_table.range(..)? {
+            let (k, v) = record?;
+            let event_id = k.value();
+            let recorded = v.value();
+            let actual = actual_counters.remove(&event_id).unwrap_or_default();
+
+            if recorded.reply_count != actual.reply_count
+                || recorded.reaction_count != actual.reaction_count
+            {
+                issues.push(IntegrityIssue::SocialPostCountersMismatch {
+                    event_id,
+                    recorded,
+                    actual,
+                });
+            }
+        }
+        issues.extend(actual_counters.into_iter().map(|(event_id, actual)| {
+            IntegrityIssue::SocialPostCountersMismatch {
+                event_id,
+                recorded: SocialPostRecord::default(),
+                actual,
+            }
+        }));
+
+        Ok(IntegrityReport {
+            events_checked,
+            contents_checked,
+            issues,
+            repaired: false,
+        })
+    }
+}
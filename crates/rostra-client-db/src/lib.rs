@@ -1,11 +1,19 @@
+mod api_token_ops;
+mod archive_ops;
+mod draft_ops;
 mod events_content_missing_ops;
 mod id_nodes_ops;
+mod integrity_ops;
 mod migration_ops;
 mod models;
+mod outbox_ops;
 mod paginate;
 mod process_event_content_ops;
 mod process_event_ops;
+mod reindex_ops;
+mod snapshot_ops;
 pub mod social;
+mod stats_ops;
 mod table_ops;
 mod tables;
 mod tx_ops;
@@ -13,21 +21,30 @@ mod tx_ops;
 use std::borrow::Cow;
 use std::collections::{BTreeMap, HashMap, HashSet};
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::{io, ops, result};
 
+pub use api_token_ops::{ApiToken, ApiTokenParseError, VerifiedApiToken};
+pub use archive_ops::{
+    ArchiveDecodeError, ArchiveEvent, ArchiveImportOutcome, ArchiveManifest, IdentityArchive,
+    ImportArchiveError, ImportArchiveResult,
+};
 use event::EventContentState;
 pub use ids::{IdsFolloweesRecord, IdsFollowersRecord};
+pub use integrity_ops::{IntegrityIssue, IntegrityReport};
 use itertools::Itertools as _;
 use process_event_content_ops::ProcessEventError;
 use redb_bincode::{ReadTransaction, ReadableTable, WriteTransaction};
+pub use reindex_ops::ReindexProgress;
 use rostra_core::event::{
-    EventContent, EventExt as _, IrohNodeId, PersonaSelector, VerifiedEvent, VerifiedEventContent,
-    content_kind,
+    EventContent, EventExt as _, EventKind, IrohNodeId, PersonaSelector, VerifiedEvent,
+    VerifiedEventContent, content_kind,
 };
 use rostra_core::id::{RostraId, ToShort as _};
-use rostra_core::{ShortEventId, Timestamp};
+use rostra_core::{ContentHash, ShortEventId, Timestamp};
 use rostra_util_error::{BoxedError, FmtCompact as _};
 use snafu::{Location, ResultExt as _, Snafu};
+pub use stats_ops::{DbStats, IdStats, TableStats};
 use tokio::sync::{broadcast, watch};
 use tokio::task::JoinError;
 use tracing::{debug, info, instrument};
@@ -39,6 +56,9 @@ const LOG_TARGET: &str = "rostra::db";
 pub struct WriteTransactionCtx {
     dbtx: WriteTransaction,
     on_commit: std::sync::Mutex<Vec<Box<dyn FnOnce() + 'static>>>,
+    /// Already known events are being processed again (e.g. reindexing), so
+    /// they must not be announced as new
+    replaying: AtomicBool,
 }
 
 impl From<WriteTransaction> for WriteTransactionCtx {
@@ -46,6 +66,7 @@ impl From<WriteTransaction> for WriteTransactionCtx {
         Self {
             dbtx,
             on_commit: std::sync::Mutex::new(vec![]),
+            replaying: AtomicBool::new(false),
         }
     }
 }
@@ -71,8 +92,18 @@ impl WriteTransactionCtx {
             .push(Box::new(f));
     }
 
+    pub(crate) fn set_replaying(&self, replaying: bool) {
+        self.replaying.store(replaying, Ordering::Relaxed);
+    }
+
+    pub(crate) fn is_replaying(&self) -> bool {
+        self.replaying.load(Ordering::Relaxed)
+    }
+
     fn commit(self) -> result::Result<(), redb::CommitError> {
-        let Self { dbtx, on_commit } = self;
+        let Self {
+            dbtx, on_commit, ..
+        } = self;
 
         dbtx.commit()?;
 
@@ -142,6 +173,22 @@ pub enum DbError {
         #[snafu(implicit)]
         location: Location,
     },
+    #[snafu(display("Snapshot does not contain a self id"))]
+    SnapshotSelfIdMissing {
+        #[snafu(implicit)]
+        location: Location,
+    },
+    #[snafu(display("Previous database already exists at {}", path.display()))]
+    SnapshotOldDbExists {
+        path: PathBuf,
+        #[snafu(implicit)]
+        location: Location,
+    },
+    Io {
+        source: io::Error,
+        #[snafu(implicit)]
+        location: Location,
+    },
     Overflow,
 }
 pub type DbResult<T> = std::result::Result<T, DbError>;
@@ -158,10 +205,24 @@ pub struct Database {
     new_content_tx: broadcast::Sender<VerifiedEventContent>,
     new_posts_tx: broadcast::Sender<(VerifiedEventContent, content_kind::SocialPost)>,
     ids_with_missing_events_tx: dedup_chan::Sender<RostraId>,
+    social_outbox_updated: watch::Sender<()>,
 }
 
 impl Database {
     const MAX_CONTENT_LEN: u32 = 1_000_000u32;
+    /// [`content_kind::Blob::MAX_SIZE`] plus room for the mime type and the
+    /// cbor encoding
+    const MAX_BLOB_CONTENT_LEN: u32 = 10_010_000u32;
+
+    /// Max. content length stored for an event of `kind`
+    fn max_content_len(kind: EventKind) -> u32 {
+        if kind == EventKind::BLOB {
+            Self::MAX_BLOB_CONTENT_LEN
+        } else {
+            Self::MAX_CONTENT_LEN
+        }
+    }
+
     pub async fn mk_db_path(
         data_dir: &Path,
         self_id: RostraId,
@@ -227,6 +288,7 @@ impl Database {
         let (self_head_updated, _) = watch::channel(self_head);
         let (new_content_tx, _) = broadcast::channel(100);
         let (new_posts_tx, _) = broadcast::channel(100);
+        let (social_outbox_updated, _) = watch::channel(());
 
         let s = Self {
             inner,
@@ -238,8 +300,11 @@ impl Database {
             new_content_tx,
             new_posts_tx,
             ids_with_missing_events_tx: dedup_chan::Sender::new(),
+            social_outbox_updated,
         };
 
+        s.reindex_if_pending().await?;
+
         Ok(s)
     }
 
@@ -299,6 +364,10 @@ impl Database {
     ) -> broadcast::Receiver<(VerifiedEventContent, content_kind::SocialPost)> {
         self.new_posts_tx.subscribe()
     }
+    /// Notified every time the outbox changes
+    pub fn social_outbox_subscribe(&self) -> watch::Receiver<()> {
+        self.social_outbox_updated.subscribe()
+    }
     pub fn ids_with_missing_events_subscribe(
         &self,
         capacity: usize,
@@ -316,14 +385,28 @@ impl Database {
         .expect("Database panic")
     }
 
+    /// Missing events of `id`, including posts of `id` quoted by others
+    ///
+    /// Missing ancestors are only included for self and (extended) followees,
+    /// so a quoted post of anyone else doesn't pull in their whole history.
     pub async fn get_missing_events_for_id(&self, id: RostraId) -> Vec<ShortEventId> {
         self.read_with(|tx| {
             let events_missing_tbl = tx.open_table(&events_missing::TABLE)?;
-            Ok(
-                Database::get_missing_events_for_id_tx(id, &events_missing_tbl)?
-                    .into_iter()
-                    .collect(),
-            )
+            let quotes_missing_tbl = tx.open_table(&social_posts_quotes_missing::TABLE)?;
+            let ids_followees_tbl = tx.open_table(&ids_followees::TABLE)?;
+            let mut missing =
+                if Database::is_self_or_followee_extended_tx(self.self_id, id, &ids_followees_tbl)?
+                {
+                    Database::get_missing_events_for_id_tx(id, &events_missing_tbl)?
+                } else {
+                    vec![]
+                };
+            for record in
+                quotes_missing_tbl.range((id, ShortEventId::ZERO)..=(id, ShortEventId::MAX))?
+            {
+                missing.push(record?.0.value().1);
+            }
+            Ok(missing)
         })
         .await
         .expect("Database panic")
@@ -425,6 +508,36 @@ impl Database {
         .expect("Database panic")
     }
 
+    /// Was the content of `event_id` deleted by its author
+    pub async fn is_event_content_deleted(&self, event_id: impl Into<ShortEventId>) -> bool {
+        let event_id = event_id.into();
+        self.read_with(|tx| {
+            let events_content_table = tx.open_table(&crate::events_content::TABLE)?;
+            Ok(matches!(
+                Database::get_event_content_tx(event_id, &events_content_table)?,
+                Some(crate::event::EventContentState::Deleted { .. })
+            ))
+        })
+        .await
+        .expect("Database panic")
+    }
+
+    /// [`content_kind::Blob`] published by `author` as `event_id`, along with
+    /// its content hash, if the content is available
+    pub async fn get_blob(
+        &self,
+        author: RostraId,
+        event_id: ShortEventId,
+    ) -> Option<(ContentHash, content_kind::Blob)> {
+        let event = self.get_event(event_id).await?;
+        if event.author() != author || event.kind() != EventKind::BLOB {
+            return None;
+        }
+        let content = self.get_event_content(event_id).await?;
+        let blob = content.deserialize_cbor::<content_kind::Blob>().ok()?;
+        Some((event.content_hash(), blob))
+    }
+
     pub async fn get_self_current_head(&self) -> Option<ShortEventId> {
         self.read_with(|tx| {
             let events_heads_table = tx.open_table(&events_heads::TABLE)?;
@@ -493,7 +606,8 @@ impl Database {
 
         events_content_missing_table.remove(&event_content.event_id().to_short())?;
 
-        let can_insert = if u32::from(event_content.event.event.content_len) < Self::MAX_CONTENT_LEN
+        let can_insert = if u32::from(event_content.event.event.content_len)
+            < Self::max_content_len(event_content.event.event.kind)
         {
             Database::can_insert_event_content_tx(event_content, &mut events_content_table)?
         } else {
@@ -502,7 +616,7 @@ impl Database {
 
         if can_insert {
             if let Some(content) = event_content.content.as_ref() {
-                match self.process_event_content_inserted_tx(event_content, tx) {
+                match self.process_event_content_inserted_tx(event_content, &events_table, tx) {
                     Ok(()) => {
                         events_content_table.insert(
                             &event_content.event_id().to_short(),
@@ -669,6 +783,26 @@ impl Database {
         .expect("Database panic")
     }
 
+    /// Like [`Self::get_social_profile`], for many ids at once
+    pub async fn get_social_profiles(
+        &self,
+        ids: impl IntoIterator<Item = RostraId>,
+    ) -> HashMap<RostraId, IdSocialProfileRecord> {
+        self.read_with(|tx| {
+            let social_profiles_table = tx.open_table(&social_profiles::TABLE)?;
+
+            let mut ret = HashMap::new();
+            for id in ids {
+                if let Some(profile) = Self::get_social_profile_tx(id, &social_profiles_table)? {
+                    ret.insert(id, profile);
+                }
+            }
+            Ok(ret)
+        })
+        .await
+        .expect("Database panic")
+    }
+
     pub async fn get_id_endpoints(
         &self,
         id: RostraId,
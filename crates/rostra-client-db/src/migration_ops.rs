@@ -1,25 +1,23 @@
-use std::ops::Not as _;
-
-use redb::ReadableTable as _;
-use rostra_core::event::PersonaSelector;
 use tracing::{debug, info};
 
-use crate::ids::IdsFolloweesRecordV0;
 use crate::{
-    Database, DbResult, DbVersionTooHighSnafu, IdSocialProfileRecord, IdsFolloweesRecord,
-    LOG_TARGET, Latest, SocialPostRecord, WriteTransactionCtx, db_version, events, events_by_time,
-    events_content, events_content_missing, events_heads, events_missing, events_self,
-    ids_followees, ids_followees_v0, ids_followers, ids_full, ids_personas, ids_self,
-    ids_unfollowed, social_posts, social_posts_by_time, social_posts_reactions,
-    social_posts_replies, social_posts_v0, social_profiles, social_profiles_v0,
+    Database, DbResult, DbVersionTooHighSnafu, LOG_TARGET, Latest, WriteTransactionCtx, api_tokens,
+    db_reindex_pending, db_version, events, events_by_time, events_content, events_content_missing,
+    events_heads, events_missing, events_self, ids_followees, ids_followers, ids_full, ids_nodes,
+    ids_personas, ids_self, ids_unfollowed, social_drafts, social_mentions, social_outbox,
+    social_polls_votes, social_posts, social_posts_by_time, social_posts_edits,
+    social_posts_quotes, social_posts_quotes_missing, social_posts_reactions, social_posts_replies,
+    social_profiles, social_profiles_v0, social_tags,
 };
 
 impl Database {
     pub(crate) fn init_tables_tx(tx: &WriteTransactionCtx) -> DbResult<()> {
         tx.open_table(&db_version::TABLE)?;
+        tx.open_table(&db_reindex_pending::TABLE)?;
 
         tx.open_table(&ids_self::TABLE)?;
         tx.open_table(&ids_full::TABLE)?;
+        tx.open_table(&ids_nodes::TABLE)?;
         tx.open_table(&ids_followers::TABLE)?;
         tx.open_table(&ids_followees::TABLE)?;
         tx.open_table(&ids_unfollowed::TABLE)?;
@@ -38,11 +36,22 @@ impl Database {
         tx.open_table(&social_posts_by_time::TABLE)?;
         tx.open_table(&social_posts_replies::TABLE)?;
         tx.open_table(&social_posts_reactions::TABLE)?;
+        tx.open_table(&social_posts_edits::TABLE)?;
+        tx.open_table(&social_posts_quotes::TABLE)?;
+        tx.open_table(&social_posts_quotes_missing::TABLE)?;
+        tx.open_table(&social_tags::TABLE)?;
+        tx.open_table(&social_mentions::TABLE)?;
+        tx.open_table(&social_polls_votes::TABLE)?;
+
+        tx.open_table(&social_outbox::TABLE)?;
+        tx.open_table(&social_drafts::TABLE)?;
+
+        tx.open_table(&api_tokens::TABLE)?;
         Ok(())
     }
 
     pub(crate) fn handle_db_ver_migrations(dbtx: &WriteTransactionCtx) -> DbResult<()> {
-        const DB_VER: u64 = 3;
+        const DB_VER: u64 = 6;
 
         let mut table_db_ver = dbtx.open_table(&db_version::TABLE)?;
 
@@ -67,6 +76,9 @@ impl Database {
                 0 => Self::migrate_v0(dbtx)?,
                 1 => Self::migrate_v1(dbtx)?,
                 2 => Self::migrate_v2(dbtx)?,
+                3 => Self::migrate_v3(dbtx)?,
+                4 => Self::migrate_v4(dbtx)?,
+                5 => Self::migrate_v5(dbtx)?,
                 DB_VER => { /* ensures we didn't forget to increment DB_VER */ }
                 x => panic!("Unexpected db ver: {x}"),
             }
@@ -80,111 +92,45 @@ impl Database {
         Ok(())
     }
 
-    pub(crate) fn rename_table<KS, VS, KD, VD>(
-        dbtx: &WriteTransactionCtx,
-        src: &redb_bincode::TableDefinition<'_, KS, VS>,
-        dst: &redb_bincode::TableDefinition<'_, KD, VD>,
-    ) -> DbResult<()> {
-        let mut dst_tbl = dbtx.as_raw().open_table(dst.as_raw())?;
-        let mut src_table = dbtx.as_raw().open_table(src.as_raw())?;
-        for record in src_table.range::<&[u8]>(..)? {
-            let (k, v) = record?;
-            dst_tbl.insert(k.value(), v.value())?;
-        }
-        src_table.retain(|_, _| false)?;
-        Ok(())
-    }
-
     pub(crate) fn migrate_v0(dbtx: &WriteTransactionCtx) -> DbResult<()> {
-        Self::rename_table(dbtx, &social_profiles::TABLE, &social_profiles_v0::TABLE)?;
-
-        let table_v0 = dbtx.open_table(&social_profiles_v0::TABLE)?;
-        let mut table = dbtx.open_table(&social_profiles::TABLE)?;
-
-        for g in table_v0.range(..)? {
-            let (k, v_v0) = g?;
-            let v_v0 = v_v0.value();
-            table.insert(
-                &k.value(),
-                &Latest {
-                    ts: v_v0.ts,
-                    inner: IdSocialProfileRecord {
-                        event_id: v_v0.inner.event_id,
-                        display_name: v_v0.inner.display_name,
-                        bio: v_v0.inner.bio,
-                        avatar: if !v_v0.inner.img.is_empty() && v_v0.inner.img_mime.is_empty() {
-                            Some((v_v0.inner.img_mime, v_v0.inner.img))
-                        } else {
-                            None
-                        },
-                    },
-                },
-            )?;
-        }
-
-        drop(table);
-        drop(table_v0);
-
-        dbtx.as_raw()
-            .delete_table(social_profiles_v0::TABLE.as_raw())?
-            .not()
-            .then(|| panic!("Expected to delete the table"));
-        Ok(())
+        // `social_profiles` records changed format
+        Self::clear_table_tx(dbtx, &social_profiles::TABLE)?;
+        Self::set_reindex_pending_tx(dbtx)
     }
 
     pub(crate) fn migrate_v1(dbtx: &WriteTransactionCtx) -> DbResult<()> {
-        Self::rename_table(dbtx, &social_posts::TABLE, &social_posts_v0::TABLE)?;
-
-        let table_v0 = dbtx.open_table(&social_posts_v0::TABLE)?;
-        let mut table = dbtx.open_table(&social_posts::TABLE)?;
-
-        for g in table_v0.range(..)? {
-            let (k, v_v0) = g?;
-            let crate::SocialPostRecordV0 { reply_count } = v_v0.value();
-            table.insert(
-                &k.value(),
-                &SocialPostRecord {
-                    reply_count,
-                    reaction_count: 0,
-                },
-            )?;
-        }
-
-        drop(table);
-        drop(table_v0);
-
-        dbtx.as_raw()
-            .delete_table(social_posts_v0::TABLE.as_raw())?
-            .not()
-            .then(|| panic!("Expected to delete the table"));
-        Ok(())
+        // `social_posts` records gained `reaction_count`
+        Self::clear_table_tx(dbtx, &social_posts::TABLE)?;
+        Self::set_reindex_pending_tx(dbtx)
     }
 
     pub(crate) fn migrate_v2(dbtx: &WriteTransactionCtx) -> DbResult<()> {
-        Self::rename_table(dbtx, &ids_followees::TABLE, &ids_followees_v0::TABLE)?;
-
-        let table_v0 = dbtx.open_table(&ids_followees_v0::TABLE)?;
-        let mut table = dbtx.open_table(&ids_followees::TABLE)?;
-
-        for g in table_v0.range(..)? {
-            let (k, v_v0) = g?;
-            let IdsFolloweesRecordV0 { ts, persona: _ } = v_v0.value();
-            table.insert(
-                &k.value(),
-                &IdsFolloweesRecord {
-                    ts,
-                    selector: Some(PersonaSelector::Except { ids: vec![] }),
-                },
-            )?;
-        }
+        // `ids_followees` records switched from `persona` to `selector`
+        Self::clear_table_tx(dbtx, &ids_followees::TABLE)?;
+        Self::set_reindex_pending_tx(dbtx)
+    }
 
-        drop(table);
-        drop(table_v0);
+    pub(crate) fn migrate_v3(dbtx: &WriteTransactionCtx) -> DbResult<()> {
+        // `social_tags` was added, and needs to index existing posts
+        Self::set_reindex_pending_tx(dbtx)
+    }
 
-        dbtx.as_raw()
-            .delete_table(ids_followees_v0::TABLE.as_raw())?
-            .not()
-            .then(|| panic!("Expected to delete the table"));
-        Ok(())
+    pub(crate) fn migrate_v4(dbtx: &WriteTransactionCtx) -> DbResult<()> {
+        // `social_mentions` was added, and needs to index existing posts
+        Self::set_reindex_pending_tx(dbtx)
+    }
+
+    pub(crate) fn migrate_v5(dbtx: &WriteTransactionCtx) -> DbResult<()> {
+        // `social_profiles` records gained links, pronouns, location, header and
+        // persona descriptions
+        Self::migrate_table_tx(
+            dbtx,
+            &social_profiles_v0::TABLE,
+            &social_profiles::TABLE,
+            |Latest { ts, inner }| Latest {
+                ts,
+                inner: inner.into(),
+            },
+        )
     }
 }
@@ -0,0 +1,202 @@
+use redb_bincode::ReadableTable as _;
+use rostra_core::Timestamp;
+
+use crate::{
+    Database, DbResult, SocialOutboxRecord, SocialOutboxStatus, social_drafts, social_outbox,
+};
+
+impl Database {
+    /// Add a post to be published at `record.publish_at`, returning its id
+    ///
+    /// If the post was written as draft `draft_id`, the draft is removed in
+    /// the same transaction.
+    pub async fn insert_social_outbox(
+        &self,
+        record: SocialOutboxRecord,
+        draft_id: Option<u64>,
+    ) -> DbResult<u64> {
+        let id = self
+            .write_with(|tx| {
+                let mut tbl = tx.open_table(&social_outbox::TABLE)?;
+                let id = tbl.last()?.map(|g| g.0.value() + 1).unwrap_or_default();
+                tbl.insert(&id, &record)?;
+                if let Some(draft_id) = draft_id {
+                    tx.open_table(&social_drafts::TABLE)?.remove(&draft_id)?;
+                }
+                Ok(id)
+            })
+            .await?;
+        self.social_outbox_updated.send_replace(());
+        Ok(id)
+    }
+
+    pub async fn get_social_outbox(&self, id: u64) -> DbResult<Option<SocialOutboxRecord>> {
+        self.read_with(|tx| {
+            Ok(tx
+                .open_table(&social_outbox::TABLE)?
+                .get(&id)?
+                .map(|g| g.value()))
+        })
+        .await
+    }
+
+    /// All outbox entries, ordered by publish time
+    pub async fn list_social_outbox(&self) -> DbResult<Vec<(u64, SocialOutboxRecord)>> {
+        self.read_with(|tx| {
+            let tbl = tx.open_table(&social_outbox::TABLE)?;
+            let mut ret = vec![];
+            for record in tbl.range(..)? {
+                let (k, v) = record?;
+                ret.push((k.value(), v.value()));
+            }
+            ret.sort_by_key(|(id, record)| (record.publish_at, *id));
+            Ok(ret)
+        })
+        .await
+    }
+
+    /// Pending entries with `publish_at` not after `now`
+    pub async fn get_social_outbox_due(
+        &self,
+        now: Timestamp,
+    ) -> DbResult<Vec<(u64, SocialOutboxRecord)>> {
+        Ok(self
+            .list_social_outbox()
+            .await?
+            .into_iter()
+            .filter(|(_, record)| {
+                record.status == SocialOutboxStatus::Pending && record.publish_at <= now
+            })
+            .collect())
+    }
+
+    /// Earliest `publish_at` of all pending entries
+    pub async fn get_social_outbox_next_publish_at(&self) -> DbResult<Option<Timestamp>> {
+        Ok(self
+            .list_social_outbox()
+            .await?
+            .into_iter()
+            .filter(|(_, record)| record.status == SocialOutboxStatus::Pending)
+            .map(|(_, record)| record.publish_at)
+            .min())
+    }
+
+    /// Replace a still pending entry, returning `false` if there was none
+    pub async fn update_social_outbox(
+        &self,
+        id: u64,
+        record: SocialOutboxRecord,
+    ) -> DbResult<bool> {
+        let updated = self
+            .write_with(|tx| {
+                let mut tbl = tx.open_table(&social_outbox::TABLE)?;
+                let is_pending = tbl
+                    .get(&id)?
+                    .is_some_and(|g| g.value().status == SocialOutboxStatus::Pending);
+                if is_pending {
+                    tbl.insert(&id, &record)?;
+                }
+                Ok(is_pending)
+            })
+            .await?;
+        if updated {
+            self.social_outbox_updated.send_replace(());
+        }
+        Ok(updated)
+    }
+
+    /// Claim a due entry for publishing, returning its current version
+    ///
+    /// Returns `None` if the entry is gone, no longer pending or not due
+    /// anymore (e.g. got edited or cancelled in the meantime).
+    pub async fn claim_social_outbox(
+        &self,
+        id: u64,
+        now: Timestamp,
+    ) -> DbResult<Option<SocialOutboxRecord>> {
+        let claimed = self
+            .write_with(|tx| {
+                let mut tbl = tx.open_table(&social_outbox::TABLE)?;
+                let Some(record) = tbl.get(&id)?.map(|g| g.value()) else {
+                    return Ok(None);
+                };
+                if record.status != SocialOutboxStatus::Pending || now < record.publish_at {
+                    return Ok(None);
+                }
+                tbl.insert(
+                    &id,
+                    &SocialOutboxRecord {
+                        status: SocialOutboxStatus::Publishing,
+                        ..record.clone()
+                    },
+                )?;
+                Ok(Some(record))
+            })
+            .await?;
+        if claimed.is_some() {
+            self.social_outbox_updated.send_replace(());
+        }
+        Ok(claimed)
+    }
+
+    /// Mark entries left claimed by a publisher that didn't finish as failed
+    ///
+    /// They might or might not have been published, so they are not retried.
+    pub async fn fail_interrupted_social_outbox(&self) -> DbResult<()> {
+        self.write_with(|tx| {
+            let mut tbl = tx.open_table(&social_outbox::TABLE)?;
+            let mut interrupted = vec![];
+            for record in tbl.range(..)? {
+                let (k, v) = record?;
+                let v = v.value();
+                if v.status == SocialOutboxStatus::Publishing {
+                    interrupted.push((k.value(), v));
+                }
+            }
+            for (id, record) in interrupted {
+                tbl.insert(
+                    &id,
+                    &SocialOutboxRecord {
+                        status: SocialOutboxStatus::Failed(
+                            "Interrupted while publishing".to_owned(),
+                        ),
+                        ..record
+                    },
+                )?;
+            }
+            Ok(())
+        })
+        .await
+    }
+
+    /// Record the outcome of publishing an entry
+    pub async fn set_social_outbox_status(
+        &self,
+        id: u64,
+        status: SocialOutboxStatus,
+    ) -> DbResult<()> {
+        self.write_with(|tx| {
+            let mut tbl = tx.open_table(&social_outbox::TABLE)?;
+            let record = tbl.get(&id)?.map(|g| g.value());
+            if let Some(record) = record {
+                tbl.insert(&id, &SocialOutboxRecord { status, ..record })?;
+            }
+            Ok(())
+        })
+        .await?;
+        self.social_outbox_updated.send_replace(());
+        Ok(())
+    }
+
+    /// Remove an entry (cancelling it if still pending), returning `false` if
+    /// it did not exist
+    pub async fn remove_social_outbox(&self, id: u64) -> DbResult<bool> {
+        let removed = self
+            .write_with(|tx| Ok(tx.open_table(&social_outbox::TABLE)?.remove(&id)?.is_some()))
+            .await?;
+        if removed {
+            self.social_outbox_updated.send_replace(());
+        }
+        Ok(removed)
+    }
+}
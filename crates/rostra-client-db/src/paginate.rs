@@ -107,6 +107,10 @@ impl Database {
         Ok((ret, None))
     }
 
+    /// Like [`Self::paginate_table_partition`], but newest first
+    ///
+    /// The `cursor` is inclusive, just like the returned one, so following
+    /// pages never skip an entry.
     pub fn paginate_table_partition_rev<K, V, C, R>(
         table: &impl redb_bincode::ReadableTable<K, V>,
         prefix: ops::RangeInclusive<K>,
@@ -128,7 +132,7 @@ impl Database {
         } else {
             prefix_end
         };
-        for event in table.range(&prefix_start..&end)?.rev() {
+        for event in table.range(&prefix_start..=&end)?.rev() {
             let (k, v) = event?;
 
             let k = k.value();
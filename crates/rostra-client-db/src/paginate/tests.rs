@@ -216,7 +216,7 @@ async fn paginate_partition_rev() -> BoxedErrorResult<()> {
             (vec!["11-Eleven".into()], Some(10))
         );
 
-        // Test with cursor in first partition
+        // Test with cursor in first partition (cursor is inclusive)
         assert_eq!(
             Database::paginate_table_partition_rev(
                 &table,
@@ -226,6 +226,19 @@ async fn paginate_partition_rev() -> BoxedErrorResult<()> {
                 1,
                 |k, v| Ok(Some(format!("{k}-{v}")))
             )?,
+            (vec!["11-Eleven".into()], Some(10))
+        );
+
+        // Continuing from the returned cursor doesn't skip anything
+        assert_eq!(
+            Database::paginate_table_partition_rev(
+                &table,
+                10..=19,
+                |c: u64| c,
+                Some(10),
+                1,
+                |k, v| Ok(Some(format!("{k}-{v}")))
+            )?,
             (vec!["10-Ten".into()], None)
         );
 
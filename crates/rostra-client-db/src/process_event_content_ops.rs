@@ -8,8 +8,10 @@ use tracing::debug;
 
 use crate::{
     Database, DbError, IdSocialProfileRecord, IrohNodeRecord, LOG_TARGET, OverflowSnafu,
-    SocialPostsReactionsRecord, SocialPostsRepliesRecord, WriteTransactionCtx, social_posts,
-    social_posts_by_time, social_posts_reactions, social_posts_replies,
+    SocialPostsReactionsRecord, SocialPostsRepliesRecord, WriteTransactionCtx, events,
+    social_mentions, social_polls_votes, social_posts, social_posts_by_time, social_posts_edits,
+    social_posts_quotes, social_posts_quotes_missing, social_posts_reactions, social_posts_replies,
+    social_tags,
 };
 
 #[derive(Debug, Snafu)]
@@ -30,6 +32,7 @@ impl Database {
     pub fn process_event_content_inserted_tx(
         &self,
         event_content: &VerifiedEventContent,
+        events_table: &impl events::ReadableTable,
         tx: &WriteTransactionCtx,
     ) -> ProcessEventResult<()> {
         let author = event_content.event.event.author;
@@ -155,6 +158,11 @@ impl Database {
                             display_name: content.display_name,
                             bio: content.bio,
                             avatar: content.avatar,
+                            links: content.links,
+                            pronouns: content.pronouns,
+                            location: content.location,
+                            header: content.header,
+                            persona_descriptions: content.persona_descriptions,
                         },
                         &mut tx
                             .open_table(&crate::social_profiles::TABLE)
@@ -167,6 +175,96 @@ impl Database {
                             debug!(target: LOG_TARGET, err = %err.fmt_compact(), "Ignoring malformed SocialComment payload");
                         }).boxed().context(InvalidSnafu)?;
 
+                    // Edits are not posts on their own, and the original keeps
+                    // its replies and reactions
+                    if let Some(edit_of) = content.edit_of {
+                        tx.open_table(&social_posts_edits::TABLE)
+                            .map_err(DbError::from)?
+                            .insert(
+                                &(
+                                    edit_of,
+                                    event_content.timestamp(),
+                                    event_content.event_id().to_short(),
+                                ),
+                                &(),
+                            )
+                            .map_err(DbError::from)?;
+                        return Ok(());
+                    }
+
+                    // Nothing to display, e.g. the replacement of a deleted post
+                    if content.djot_content.is_none()
+                        && content.reaction.is_none()
+                        && content.quote.is_none()
+                        && content.poll.is_none()
+                    {
+                        return Ok(());
+                    }
+
+                    if let Some(quote) = content.quote {
+                        tx.open_table(&social_posts_quotes::TABLE)
+                            .map_err(DbError::from)?
+                            .insert(
+                                &(
+                                    quote.event_id(),
+                                    event_content.timestamp(),
+                                    event_content.event_id().to_short(),
+                                ),
+                                &(),
+                            )
+                            .map_err(DbError::from)?;
+
+                        // Quoted post is fetched even if its author is not followed
+                        if !Database::has_event_tx(quote.event_id(), events_table)? {
+                            tx.open_table(&social_posts_quotes_missing::TABLE)
+                                .map_err(DbError::from)?
+                                .insert(&(quote.rostra_id(), quote.event_id()), &())
+                                .map_err(DbError::from)?;
+
+                            let mut missing_event_tx = self.ids_with_missing_events_tx.clone();
+                            tx.on_commit(move || {
+                                missing_event_tx.send(quote.rostra_id());
+                            });
+                        }
+                    }
+
+                    let hashtags = content.hashtags();
+                    if !hashtags.is_empty() {
+                        let mut social_tags_tbl =
+                            tx.open_table(&social_tags::TABLE).map_err(DbError::from)?;
+                        for tag in hashtags {
+                            social_tags_tbl
+                                .insert(
+                                    &(
+                                        tag,
+                                        event_content.timestamp(),
+                                        event_content.event_id().to_short(),
+                                    ),
+                                    &(),
+                                )
+                                .map_err(DbError::from)?;
+                        }
+                    }
+
+                    let mentions = content.mentions();
+                    if !mentions.is_empty() {
+                        let mut social_mentions_tbl = tx
+                            .open_table(&social_mentions::TABLE)
+                            .map_err(DbError::from)?;
+                        for mentioned in mentions {
+                            social_mentions_tbl
+                                .insert(
+                                    &(
+                                        mentioned,
+                                        event_content.timestamp(),
+                                        event_content.event_id().to_short(),
+                                    ),
+                                    &(),
+                                )
+                                .map_err(DbError::from)?;
+                        }
+                    }
+
                     let mut social_post_by_time_tbl = tx
                         .open_table(&social_posts_by_time::TABLE)
                         .map_err(DbError::from)?;
@@ -180,14 +278,16 @@ impl Database {
                         )
                         .map_err(DbError::from)?;
 
-                    tx.on_commit({
-                        let event_content = event_content.clone();
-                        let content = content.clone();
-                        let new_posts_tx = self.new_posts_tx.clone();
-                        move || {
-                            let _ = new_posts_tx.send((event_content.to_owned(), content));
-                        }
-                    });
+                    if !tx.is_replaying() {
+                        tx.on_commit({
+                            let event_content = event_content.clone();
+                            let content = content.clone();
+                            let new_posts_tx = self.new_posts_tx.clone();
+                            move || {
+                                let _ = new_posts_tx.send((event_content.to_owned(), content));
+                            }
+                        });
+                    }
 
                     if let Some(reply_to) = content.reply_to {
                         let mut social_post_tbl =
@@ -247,6 +347,30 @@ impl Database {
                             .map_err(DbError::from)?;
                     }
                 }
+                EventKind::SOCIAL_POLL_VOTE => {
+                    let content = event_content
+                        .deserialize_cbor::<content_kind::PollVote>()
+                        .boxed()
+                        .context(InvalidSnafu)?;
+
+                    // A withdrawn vote, e.g. the replacement of a deleted one
+                    let Some(option) = content.option else {
+                        return Ok(());
+                    };
+
+                    tx.open_table(&social_polls_votes::TABLE)
+                        .map_err(DbError::from)?
+                        .insert(
+                            &(
+                                content.poll.event_id(),
+                                author,
+                                event_content.timestamp(),
+                                event_content.event_id().to_short(),
+                            ),
+                            &option,
+                        )
+                        .map_err(DbError::from)?;
+                }
                 _ => {}
             },
         };
@@ -267,6 +391,68 @@ impl Database {
                     .boxed()
                     .context(InvalidSnafu)?;
 
+                if let Some(edit_of) = content.edit_of {
+                    tx.open_table(&social_posts_edits::TABLE)
+                        .map_err(DbError::from)?
+                        .remove(&(
+                            edit_of,
+                            event_content.timestamp(),
+                            event_content.event_id().to_short(),
+                        ))
+                        .map_err(DbError::from)?;
+                    return Ok(());
+                }
+
+                if content.djot_content.is_none()
+                    && content.reaction.is_none()
+                    && content.quote.is_none()
+                    && content.poll.is_none()
+                {
+                    return Ok(());
+                }
+
+                if let Some(quote) = content.quote {
+                    tx.open_table(&social_posts_quotes::TABLE)
+                        .map_err(DbError::from)?
+                        .remove(&(
+                            quote.event_id(),
+                            event_content.timestamp(),
+                            event_content.event_id().to_short(),
+                        ))
+                        .map_err(DbError::from)?;
+                }
+
+                let hashtags = content.hashtags();
+                if !hashtags.is_empty() {
+                    let mut social_tags_tbl =
+                        tx.open_table(&social_tags::TABLE).map_err(DbError::from)?;
+                    for tag in hashtags {
+                        social_tags_tbl
+                            .remove(&(
+                                tag,
+                                event_content.timestamp(),
+                                event_content.event_id().to_short(),
+                            ))
+                            .map_err(DbError::from)?;
+                    }
+                }
+
+                let mentions = content.mentions();
+                if !mentions.is_empty() {
+                    let mut social_mentions_tbl = tx
+                        .open_table(&social_mentions::TABLE)
+                        .map_err(DbError::from)?;
+                    for mentioned in mentions {
+                        social_mentions_tbl
+                            .remove(&(
+                                mentioned,
+                                event_content.timestamp(),
+                                event_content.event_id().to_short(),
+                            ))
+                            .map_err(DbError::from)?;
+                    }
+                }
+
                 let mut social_post_by_time_tbl = tx
                     .open_table(&social_posts_by_time::TABLE)
                     .map_err(DbError::from)?;
@@ -328,6 +514,26 @@ impl Database {
                         .map_err(DbError::from)?;
                 }
             }
+            EventKind::SOCIAL_POLL_VOTE => {
+                let content = event_content
+                    .deserialize_cbor::<content_kind::PollVote>()
+                    .boxed()
+                    .context(InvalidSnafu)?;
+
+                if content.option.is_none() {
+                    return Ok(());
+                }
+
+                tx.open_table(&social_polls_votes::TABLE)
+                    .map_err(DbError::from)?
+                    .remove(&(
+                        content.poll.event_id(),
+                        event_content.author(),
+                        event_content.timestamp(),
+                        event_content.event_id().to_short(),
+                    ))
+                    .map_err(DbError::from)?;
+            }
             _ => {}
         }
 
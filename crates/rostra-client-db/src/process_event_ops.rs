@@ -8,7 +8,7 @@ use crate::process_event_content_ops::ProcessEventError;
 use crate::{
     Database, DbResult, InsertEventOutcome, LOG_TARGET, ProcessEventState, WriteTransactionCtx,
     events, events_by_time, events_content, events_content_missing, events_heads, events_missing,
-    ids_full,
+    ids_full, social_posts_quotes_missing,
 };
 
 impl Database {
@@ -78,6 +78,10 @@ impl Database {
                 }
             }
 
+            // Might have been quoted before we had it
+            tx.open_table(&social_posts_quotes_missing::TABLE)?
+                .remove(&(event.author(), event.event_id.to_short()))?;
+
             if !missing_parents.is_empty() {
                 let mut missing_event_tx = self.ids_with_missing_events_tx.clone();
                 let author = event.author();
@@ -113,7 +117,7 @@ impl Database {
 
         let process_event_content_state = if event.event.content_hash() == ContentHash::ZERO {
             ProcessEventState::NoContent
-        } else if Self::MAX_CONTENT_LEN < u32::from(event.event.content_len) {
+        } else if Self::max_content_len(event.event.kind) < u32::from(event.event.content_len) {
             if Database::prune_event_content_tx(
                 event.event_id,
                 &mut events_content_tbl,
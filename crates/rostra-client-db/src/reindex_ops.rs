@@ -0,0 +1,267 @@
+use std::borrow::Cow;
+use std::collections::{BTreeSet, HashMap};
+
+use convi::ExpectInto as _;
+use rostra_core::event::{EventExt as _, VerifiedEvent, VerifiedEventContent};
+use rostra_core::id::RostraId;
+use rostra_core::{ShortEventId, Timestamp};
+use rostra_util_error::FmtCompact as _;
+use serde::Serialize;
+use tracing::{debug, info};
+
+use crate::event::EventContentState;
+use crate::process_event_content_ops::ProcessEventError;
+use crate::{
+    Database, DbResult, LOG_TARGET, WriteTransactionCtx, db_reindex_pending, events,
+    events_content, events_heads, ids_followees, ids_followers, ids_personas, ids_unfollowed,
+    social_mentions, social_polls_votes, social_posts, social_posts_by_time, social_posts_edits,
+    social_posts_quotes, social_posts_quotes_missing, social_posts_reactions, social_posts_replies,
+    social_profiles, social_tags,
+};
+
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct ReindexProgress {
+    pub processed: u64,
+    pub total: u64,
+}
+
+impl Database {
+    /// How often (in number of events) to report reindex progress
+    const REINDEX_PROGRESS_INTERVAL: u64 = 1000;
+
+    /// Rebuild all tables derived from event content
+    ///
+    /// Clears the derived tables and replays every stored event content
+    /// through [`Self::process_event_content_inserted_tx`], in causal order,
+    /// without announcing any of the posts as new.
+    ///
+    /// Note: `ids_nodes` is not cleared, as it also tracks connection stats.
+    pub async fn reindex(&self, progress: impl Fn(ReindexProgress)) -> DbResult<ReindexProgress> {
+        self.write_with(|tx| self.reindex_tx(tx, progress)).await
+    }
+
+    /// Run [`Self::reindex`] if any migration requested it
+    pub(crate) async fn reindex_if_pending(&self) -> DbResult<()> {
+        self.write_with(|tx| {
+            let pending = tx
+                .open_table(&db_reindex_pending::TABLE)?
+                .remove(&())?
+                .is_some();
+
+            if pending {
+                info!(target: LOG_TARGET, "Reindexing database after migration");
+                self.reindex_tx(tx, |progress| {
+                    info!(target: LOG_TARGET, processed = progress.processed, total = progress.total, "Reindexing");
+                })?;
+            }
+            Ok(())
+        })
+        .await
+    }
+
+    pub(crate) fn set_reindex_pending_tx(dbtx: &WriteTransactionCtx) -> DbResult<()> {
+        dbtx.open_table(&db_reindex_pending::TABLE)?
+            .insert(&(), &())?;
+        Ok(())
+    }
+
+    pub fn reindex_tx(
+        &self,
+        tx: &WriteTransactionCtx,
+        progress: impl Fn(ReindexProgress),
+    ) -> DbResult<ReindexProgress> {
+        Self::clear_table_tx(tx, &social_posts::TABLE)?;
+        Self::clear_table_tx(tx, &social_posts_replies::TABLE)?;
+        Self::clear_table_tx(tx, &social_posts_reactions::TABLE)?;
+        Self::clear_table_tx(tx, &social_posts_by_time::TABLE)?;
+        Self::clear_table_tx(tx, &social_posts_edits::TABLE)?;
+        Self::clear_table_tx(tx, &social_posts_quotes::TABLE)?;
+        Self::clear_table_tx(tx, &social_posts_quotes_missing::TABLE)?;
+        Self::clear_table_tx(tx, &social_tags::TABLE)?;
+        Self::clear_table_tx(tx, &social_mentions::TABLE)?;
+        Self::clear_table_tx(tx, &social_polls_votes::TABLE)?;
+        Self::clear_table_tx(tx, &social_profiles::TABLE)?;
+        Self::clear_table_tx(tx, &ids_followees::TABLE)?;
+        Self::clear_table_tx(tx, &ids_followers::TABLE)?;
+        Self::clear_table_tx(tx, &ids_unfollowed::TABLE)?;
+        Self::clear_table_tx(tx, &ids_personas::TABLE)?;
+
+        let events_tbl = tx.open_table(&events::TABLE)?;
+        let mut events_content_tbl = tx.open_table(&events_content::TABLE)?;
+
+        let order = Self::get_events_causal_order_tx(&events_tbl)?;
+        let mut status = ReindexProgress {
+            processed: 0,
+            total: order.len().expect_into(),
+        };
+        progress(status);
+
+        tx.set_replaying(true);
+        for event_id in order {
+            status.processed += 1;
+            if status
+                .processed
+                .is_multiple_of(Self::REINDEX_PROGRESS_INTERVAL)
+            {
+                progress(status);
+            }
+
+            // Content that failed processing before might be valid now, so replay
+            // it as well
+            let content = match events_content_tbl.get(&event_id)?.map(|g| g.value()) {
+                Some(EventContentState::Present(content) | EventContentState::Invalid(content)) => {
+                    content.into_owned()
+                }
+                Some(EventContentState::Deleted { .. } | EventContentState::Pruned) | None => {
+                    continue;
+                }
+            };
+            let event = events_tbl
+                .get(&event_id)?
+                .expect("Must have the event")
+                .value();
+            let event_content = VerifiedEventContent::assume_verified(
+                VerifiedEvent::assume_verified_from_signed(event.signed),
+                content.clone(),
+            );
+
+            let state =
+                match self.process_event_content_inserted_tx(&event_content, &events_tbl, tx) {
+                    Ok(()) => EventContentState::Present(Cow::Owned(content)),
+                    Err(ProcessEventError::Invalid { source, location }) => {
+                        debug!(
+                            target: LOG_TARGET,
+                            err = %source.as_ref().fmt_compact(),
+                            %location,
+                            %event_id,
+                            "Invalid event content during reindex"
+                        );
+                        EventContentState::Invalid(Cow::Owned(content))
+                    }
+                    Err(ProcessEventError::Db { source }) => return Err(source),
+                };
+            events_content_tbl.insert(&event_id, &state)?;
+        }
+        tx.set_replaying(false);
+
+        if !status
+            .processed
+            .is_multiple_of(Self::REINDEX_PROGRESS_INTERVAL)
+        {
+            progress(status);
+        }
+
+        Ok(status)
+    }
+
+    /// All stored events, ordered so that parents always come before their
+    /// children, and otherwise by timestamp
+    pub fn get_events_causal_order_tx(
+        events_table: &impl events::ReadableTable,
+    ) -> DbResult<Vec<ShortEventId>> {
+        let mut timestamps: HashMap<ShortEventId, Timestamp> = HashMap::new();
+        let mut parents: HashMap<ShortEventId, Vec<ShortEventId>> = HashMap::new();
+
+        for record in events_table.range(..)? {
+            let (k, v) = record?;
+            let event_id = k.value();
+            let event = v.value();
+
+            timestamps.insert(event_id, event.timestamp());
+            parents.insert(
+                event_id,
+                [event.parent_prev(), event.parent_aux()]
+                    .into_iter()
+                    .flatten()
+                    .collect(),
+            );
+        }
+
+        Ok(Self::causal_order(timestamps, parents))
+    }
+
+    /// Like [`Self::get_events_causal_order_tx`], but only the events of
+    /// `author`
+    ///
+    /// Walks the DAG down from the `author`'s heads, so only the `author`'s
+    /// own events are visited.
+    pub fn get_author_events_causal_order_tx(
+        author: RostraId,
+        events_table: &impl events::ReadableTable,
+        events_heads_table: &impl events_heads::ReadableTable,
+    ) -> DbResult<Vec<ShortEventId>> {
+        let mut timestamps: HashMap<ShortEventId, Timestamp> = HashMap::new();
+        let mut parents: HashMap<ShortEventId, Vec<ShortEventId>> = HashMap::new();
+
+        let mut pending = Self::get_heads_events_tx(author, events_heads_table)?;
+        while let Some(event_id) = pending.pop() {
+            if timestamps.contains_key(&event_id) {
+                continue;
+            }
+            let Some(event) = Self::get_event_tx(event_id, events_table)? else {
+                continue;
+            };
+            if event.author() != author {
+                continue;
+            }
+
+            let event_parents: Vec<_> = [event.parent_prev(), event.parent_aux()]
+                .into_iter()
+                .flatten()
+                .collect();
+            pending.extend(event_parents.iter().copied());
+            timestamps.insert(event_id, event.timestamp());
+            parents.insert(event_id, event_parents);
+        }
+
+        Ok(Self::causal_order(timestamps, parents))
+    }
+
+    /// Order events so that parents always come before their children, and
+    /// otherwise by timestamp
+    ///
+    /// Parents not in `timestamps` are ignored.
+    fn causal_order(
+        timestamps: HashMap<ShortEventId, Timestamp>,
+        parents: HashMap<ShortEventId, Vec<ShortEventId>>,
+    ) -> Vec<ShortEventId> {
+        let mut children: HashMap<ShortEventId, Vec<ShortEventId>> = HashMap::new();
+        let mut num_pending_parents: HashMap<ShortEventId, usize> = HashMap::new();
+        let mut ready: BTreeSet<(Timestamp, ShortEventId)> = BTreeSet::new();
+
+        for (event_id, event_parents) in parents {
+            let event_parents: BTreeSet<_> = event_parents
+                .into_iter()
+                .filter(|parent_id| timestamps.contains_key(parent_id))
+                .collect();
+
+            if event_parents.is_empty() {
+                ready.insert((timestamps[&event_id], event_id));
+                continue;
+            }
+
+            num_pending_parents.insert(event_id, event_parents.len());
+            for parent_id in event_parents {
+                children.entry(parent_id).or_default().push(event_id);
+            }
+        }
+
+        let mut order = Vec::with_capacity(timestamps.len());
+        while let Some((_, event_id)) = ready.pop_first() {
+            order.push(event_id);
+
+            for child_id in children.remove(&event_id).unwrap_or_default() {
+                let pending = num_pending_parents
+                    .get_mut(&child_id)
+                    .expect("Must have pending parents");
+                *pending -= 1;
+                if *pending == 0 {
+                    ready.insert((timestamps[&child_id], child_id));
+                }
+            }
+        }
+        debug_assert_eq!(order.len(), timestamps.len());
+
+        order
+    }
+}
@@ -0,0 +1,162 @@
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+
+use redb::ReadableTable as _;
+use redb_bincode::{ReadTransaction, WriteTransaction};
+use rostra_core::Timestamp;
+use rostra_core::id::RostraId;
+use snafu::ResultExt as _;
+use tracing::info;
+
+use crate::{
+    CommitSnafu, Database, DatabaseSnafu, DbResult, IoSnafu, LOG_TARGET, SnapshotOldDbExistsSnafu,
+    SnapshotSelfIdMissingSnafu, TransactionSnafu, api_tokens, db_reindex_pending, db_version,
+    events, events_by_time, events_content, events_content_missing, events_heads, events_missing,
+    events_self, ids_followees, ids_followers, ids_full, ids_nodes, ids_personas, ids_self,
+    ids_unfollowed, social_drafts, social_mentions, social_outbox, social_polls_votes,
+    social_posts, social_posts_by_time, social_posts_edits, social_posts_quotes,
+    social_posts_quotes_missing, social_posts_reactions, social_posts_replies, social_profiles,
+    social_tags,
+};
+
+/// `path` with `suffix` appended to the file name
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = OsString::from(path.as_os_str());
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+impl Database {
+    /// Write a consistent copy of the whole database to `path`
+    ///
+    /// All data is read from a single read transaction, so this is safe to
+    /// call while the database is being used. The snapshot is written to a
+    /// temporary file first, and then atomically renamed to `path`.
+    pub async fn snapshot_to(&self, path: &Path) -> DbResult<()> {
+        let tmp_path = with_suffix(path, ".tmp");
+
+        self.read_with(|tx| {
+            // Leftover of an interrupted snapshot
+            if tmp_path.exists() {
+                std::fs::remove_file(&tmp_path).context(IoSnafu)?;
+            }
+            let dst = redb_bincode::Database::from(
+                redb::Database::create(&tmp_path).context(DatabaseSnafu)?,
+            );
+            let dst_tx = dst.begin_write().context(TransactionSnafu)?;
+
+            Self::copy_table_tx(tx, &dst_tx, &db_version::TABLE)?;
+            Self::copy_table_tx(tx, &dst_tx, &db_reindex_pending::TABLE)?;
+            Self::copy_table_tx(tx, &dst_tx, &ids_self::TABLE)?;
+            Self::copy_table_tx(tx, &dst_tx, &ids_full::TABLE)?;
+            Self::copy_table_tx(tx, &dst_tx, &ids_nodes::TABLE)?;
+            Self::copy_table_tx(tx, &dst_tx, &ids_followees::TABLE)?;
+            Self::copy_table_tx(tx, &dst_tx, &ids_followers::TABLE)?;
+            Self::copy_table_tx(tx, &dst_tx, &ids_unfollowed::TABLE)?;
+            Self::copy_table_tx(tx, &dst_tx, &ids_personas::TABLE)?;
+            Self::copy_table_tx(tx, &dst_tx, &events::TABLE)?;
+            Self::copy_table_tx(tx, &dst_tx, &events_missing::TABLE)?;
+            Self::copy_table_tx(tx, &dst_tx, &events_heads::TABLE)?;
+            Self::copy_table_tx(tx, &dst_tx, &events_self::TABLE)?;
+            Self::copy_table_tx(tx, &dst_tx, &events_content::TABLE)?;
+            Self::copy_table_tx(tx, &dst_tx, &events_content_missing::TABLE)?;
+            Self::copy_table_tx(tx, &dst_tx, &events_by_time::TABLE)?;
+            Self::copy_table_tx(tx, &dst_tx, &social_profiles::TABLE)?;
+            Self::copy_table_tx(tx, &dst_tx, &social_posts::TABLE)?;
+            Self::copy_table_tx(tx, &dst_tx, &social_posts_replies::TABLE)?;
+            Self::copy_table_tx(tx, &dst_tx, &social_posts_reactions::TABLE)?;
+            Self::copy_table_tx(tx, &dst_tx, &social_posts_by_time::TABLE)?;
+            Self::copy_table_tx(tx, &dst_tx, &social_posts_edits::TABLE)?;
+            Self::copy_table_tx(tx, &dst_tx, &social_posts_quotes::TABLE)?;
+            Self::copy_table_tx(tx, &dst_tx, &social_posts_quotes_missing::TABLE)?;
+            Self::copy_table_tx(tx, &dst_tx, &social_tags::TABLE)?;
+            Self::copy_table_tx(tx, &dst_tx, &social_mentions::TABLE)?;
+            Self::copy_table_tx(tx, &dst_tx, &social_polls_votes::TABLE)?;
+            Self::copy_table_tx(tx, &dst_tx, &social_outbox::TABLE)?;
+            Self::copy_table_tx(tx, &dst_tx, &social_drafts::TABLE)?;
+            Self::copy_table_tx(tx, &dst_tx, &api_tokens::TABLE)?;
+
+            dst_tx.commit().context(CommitSnafu)?;
+            drop(dst);
+
+            std::fs::rename(&tmp_path, path).context(IoSnafu)?;
+            Ok(())
+        })
+        .await?;
+
+        info!(target: LOG_TARGET, path = %path.display(), "Database snapshot written");
+        Ok(())
+    }
+
+    /// Replace the database at `db_path` with a snapshot
+    ///
+    /// The snapshot must belong to `self_id`, which is validated (on a copy)
+    /// before anything is replaced. The existing database is kept with a
+    /// `.<timestamp>.old` suffix, and its path returned.
+    ///
+    /// Must not be called while the database at `db_path` is open.
+    pub async fn restore_snapshot(
+        snapshot: &Path,
+        db_path: &Path,
+        self_id: RostraId,
+    ) -> DbResult<Option<PathBuf>> {
+        let tmp_path = with_suffix(db_path, ".restore");
+        let old_path = with_suffix(db_path, &format!(".{}.old", Timestamp::now()));
+
+        let old_path = tokio::task::block_in_place(|| {
+            if old_path.exists() {
+                return SnapshotOldDbExistsSnafu { path: old_path }.fail();
+            }
+
+            std::fs::copy(snapshot, &tmp_path).context(IoSnafu)?;
+
+            if let Err(err) = Self::validate_snapshot(&tmp_path, self_id) {
+                let _ = std::fs::remove_file(&tmp_path);
+                return Err(err);
+            }
+
+            let old_path = if db_path.exists() {
+                std::fs::rename(db_path, &old_path).context(IoSnafu)?;
+                Some(old_path)
+            } else {
+                None
+            };
+            std::fs::rename(&tmp_path, db_path).context(IoSnafu)?;
+            Ok(old_path)
+        })?;
+
+        info!(target: LOG_TARGET, snapshot = %snapshot.display(), path = %db_path.display(), old_path = ?old_path, "Database snapshot restored");
+        Ok(old_path)
+    }
+
+    fn validate_snapshot(path: &Path, self_id: RostraId) -> DbResult<()> {
+        let db = redb_bincode::Database::from(redb::Database::open(path).context(DatabaseSnafu)?);
+        let dbtx = db.begin_write().context(TransactionSnafu)?;
+        {
+            let mut ids_self_tbl = dbtx.open_table(&ids_self::TABLE)?;
+            if Self::read_self_id_tx(&ids_self_tbl)?.is_none() {
+                return SnapshotSelfIdMissingSnafu.fail();
+            }
+            Self::verify_self_tx(self_id, &mut ids_self_tbl)?;
+        }
+        dbtx.commit().context(CommitSnafu)?;
+        Ok(())
+    }
+
+    /// Copy all records of a table between databases
+    ///
+    /// Works on raw bytes, so records are not decoded.
+    fn copy_table_tx<K, V>(
+        src: &ReadTransaction,
+        dst: &WriteTransaction,
+        def: &redb_bincode::TableDefinition<'_, K, V>,
+    ) -> DbResult<()> {
+        let src_tbl = src.as_raw().open_table(def.as_raw())?;
+        let mut dst_tbl = dst.as_raw().open_table(def.as_raw())?;
+        for record in src_tbl.iter()? {
+            let (k, v) = record?;
+            dst_tbl.insert(k.value(), v.value())?;
+        }
+        Ok(())
+    }
+}
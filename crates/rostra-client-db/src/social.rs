@@ -1,4 +1,4 @@
-use std::collections::{BTreeMap, HashMap};
+use std::collections::{BTreeMap, HashMap, HashSet};
 
 use bincode::{Decode, Encode};
 use rostra_core::event::{EventExt as _, PersonaId, SocialPost, content_kind};
@@ -10,8 +10,9 @@ use tracing::{debug, warn};
 use super::Database;
 use crate::event::EventContentState;
 use crate::{
-    DbResult, LOG_TARGET, events, events_content, social_posts, social_posts_by_time,
-    social_posts_reactions, social_posts_replies, tables,
+    DbResult, LOG_TARGET, events, events_content, social_mentions, social_polls_votes,
+    social_posts, social_posts_by_time, social_posts_edits, social_posts_quotes,
+    social_posts_reactions, social_posts_replies, social_tags, tables,
 };
 
 #[derive(
@@ -40,6 +41,20 @@ pub struct SocialPostRecord<C> {
     pub reply_to: Option<ExternalEventId>,
     pub content: C,
     pub reply_count: u64,
+    /// Time of the latest edit, if `content` comes from one
+    pub edited: Option<Timestamp>,
+}
+
+#[derive(Clone, Debug)]
+pub struct SocialPollRecord {
+    pub ts: Timestamp,
+    pub event_id: ShortEventId,
+    pub author: RostraId,
+    pub content: content_kind::SocialPoll,
+    /// Number of counted votes for each of the options
+    pub tally: Vec<u64>,
+    /// Own counted vote: the vote event and the chosen option
+    pub self_vote: Option<(ShortEventId, u8)>,
 }
 
 impl Database {
@@ -56,64 +71,135 @@ impl Database {
             let events_table = tx.open_table(&events::TABLE)?;
             let social_posts_table = tx.open_table(&social_posts::TABLE)?;
             let social_posts_by_time_table = tx.open_table(&social_posts_by_time::TABLE)?;
+            let social_posts_edits_table = tx.open_table(&social_posts_edits::TABLE)?;
             let events_content_table = tx.open_table(&events_content::TABLE)?;
 
-            let (ret, cursor) = Self::paginate_table(&social_posts_by_time_table,
+            let (ret, cursor) = Self::paginate_table(
+                &social_posts_by_time_table,
                 cursor.map(|c| (c.ts, c.event_id)),
                 limit,
                 move |(ts, event_id), _| {
+                    Ok(Database::get_displayable_social_post_tx(
+                        ts,
+                        event_id,
+                        &events_table,
+                        &social_posts_table,
+                        &social_posts_edits_table,
+                        &events_content_table,
+                    )?
+                    .filter(|record| filter_fn(record)))
+                },
+            )?;
+
+            Ok((
+                ret,
+                cursor.map(|(ts, event_id)| EventPaginationCursor { ts, event_id }),
+            ))
+        })
+        .await
+        .expect("Storage error")
+    }
 
-                let Some(content_state) =
-                    Database::get_event_content_tx(event_id, &events_content_table)?
-                else {
-                    return Ok(None);
-                };
-                let EventContentState::Present(content) = content_state else {
-                    return Ok(None);
-                };
-
-                let Ok(social_post) = content.deserialize_cbor::<content_kind::SocialPost>() else {
-                    debug!(target: LOG_TARGET, %event_id, "Content invalid");
-                    return Ok(None);
-                };
-
-                let Some(event) = Database::get_event_tx(event_id, &events_table)? else {
-                    warn!(target: LOG_TARGET, %event_id, "Missing event for a post with social_post_record?!");
-                    return Ok(None);
-                };
-
-                let social_post_record =
-                    Database::get_social_post_tx(event_id, &social_posts_table)?.unwrap_or_default()
-                ;
-
-                let social_post_record = SocialPostRecord {
-                    ts,
-                    author: event.author(),
-                    event_id,
-                    reply_count: social_post_record.reply_count,
-                    reply_to: social_post.reply_to,
-                    content: social_post,
-                };
-
-                if !filter_fn(&social_post_record) {
-                    return Ok(None);
-                }
+    pub async fn paginate_social_posts_rev(
+        &self,
+        cursor: Option<EventPaginationCursor>,
+        limit: usize,
+        filter_fn: impl Fn(&SocialPostRecord<SocialPost>) -> bool + Send + 'static,
+    ) -> (
+        Vec<SocialPostRecord<content_kind::SocialPost>>,
+        Option<EventPaginationCursor>,
+    ) {
+        self.read_with(|tx| {
+            let events_table = tx.open_table(&events::TABLE)?;
+            let social_posts_table = tx.open_table(&social_posts::TABLE)?;
+            let social_posts_by_time_table = tx.open_table(&social_posts_by_time::TABLE)?;
+            let social_posts_edits_table = tx.open_table(&social_posts_edits::TABLE)?;
+            let events_content_table = tx.open_table(&events_content::TABLE)?;
 
-                Ok(Some(social_post_record))
-            })?;
+            let (ret, cursor) = Self::paginate_table_rev(
+                &social_posts_by_time_table,
+                cursor.map(|c| (c.ts, c.event_id)),
+                limit,
+                move |(ts, event_id), _| {
+                    Ok(Database::get_displayable_social_post_tx(
+                        ts,
+                        event_id,
+                        &events_table,
+                        &social_posts_table,
+                        &social_posts_edits_table,
+                        &events_content_table,
+                    )?
+                    .filter(|record| filter_fn(record)))
+                },
+            )?;
+
+            Ok((
+                ret,
+                cursor.map(|(ts, event_id)| EventPaginationCursor { ts, event_id }),
+            ))
+        })
+        .await
+        .expect("Storage error")
+    }
 
+    /// Posts of others replying to or mentioning `id`
+    ///
+    /// Mentions are looked up for each post, so this never loads all of them.
+    pub async fn paginate_social_notifications(
+        &self,
+        id: RostraId,
+        cursor: Option<EventPaginationCursor>,
+        limit: usize,
+    ) -> (
+        Vec<SocialPostRecord<content_kind::SocialPost>>,
+        Option<EventPaginationCursor>,
+    ) {
+        self.read_with(|tx| {
+            let events_table = tx.open_table(&events::TABLE)?;
+            let social_posts_table = tx.open_table(&social_posts::TABLE)?;
+            let social_posts_by_time_table = tx.open_table(&social_posts_by_time::TABLE)?;
+            let social_posts_edits_table = tx.open_table(&social_posts_edits::TABLE)?;
+            let events_content_table = tx.open_table(&events_content::TABLE)?;
+            let social_mentions_table = tx.open_table(&social_mentions::TABLE)?;
 
-            Ok((ret, cursor.map(|(ts, event_id)| EventPaginationCursor{ ts, event_id })))
+            let (ret, cursor) = Self::paginate_table(
+                &social_posts_by_time_table,
+                cursor.map(|c| (c.ts, c.event_id)),
+                limit,
+                move |(ts, event_id), _| {
+                    let Some(record) = Database::get_displayable_social_post_tx(
+                        ts,
+                        event_id,
+                        &events_table,
+                        &social_posts_table,
+                        &social_posts_edits_table,
+                        &events_content_table,
+                    )?
+                    else {
+                        return Ok(None);
+                    };
+                    Ok(
+                        Database::is_social_notification_tx(id, &record, &social_mentions_table)?
+                            .then_some(record),
+                    )
+                },
+            )?;
+
+            Ok((
+                ret,
+                cursor.map(|(ts, event_id)| EventPaginationCursor { ts, event_id }),
+            ))
         })
         .await
         .expect("Storage error")
     }
 
-    pub async fn paginate_social_posts_rev(
+    /// Like [`Self::paginate_social_notifications`], but newest first
+    pub async fn paginate_social_notifications_rev(
         &self,
+        id: RostraId,
         cursor: Option<EventPaginationCursor>,
         limit: usize,
-        filter_fn: impl Fn(&SocialPostRecord<SocialPost>) -> bool + Send + 'static,
     ) -> (
         Vec<SocialPostRecord<content_kind::SocialPost>>,
         Option<EventPaginationCursor>,
@@ -122,12 +208,131 @@ impl Database {
             let events_table = tx.open_table(&events::TABLE)?;
             let social_posts_table = tx.open_table(&social_posts::TABLE)?;
             let social_posts_by_time_table = tx.open_table(&social_posts_by_time::TABLE)?;
+            let social_posts_edits_table = tx.open_table(&social_posts_edits::TABLE)?;
             let events_content_table = tx.open_table(&events_content::TABLE)?;
+            let social_mentions_table = tx.open_table(&social_mentions::TABLE)?;
 
-            let (ret, cursor) = Self::paginate_table_rev(&social_posts_by_time_table,
+            let (ret, cursor) = Self::paginate_table_rev(
+                &social_posts_by_time_table,
                 cursor.map(|c| (c.ts, c.event_id)),
                 limit,
                 move |(ts, event_id), _| {
+                    let Some(record) = Database::get_displayable_social_post_tx(
+                        ts,
+                        event_id,
+                        &events_table,
+                        &social_posts_table,
+                        &social_posts_edits_table,
+                        &events_content_table,
+                    )?
+                    else {
+                        return Ok(None);
+                    };
+                    Ok(
+                        Database::is_social_notification_tx(id, &record, &social_mentions_table)?
+                            .then_some(record),
+                    )
+                },
+            )?;
+
+            Ok((
+                ret,
+                cursor.map(|(ts, event_id)| EventPaginationCursor { ts, event_id }),
+            ))
+        })
+        .await
+        .expect("Storage error")
+    }
+
+    fn is_social_notification_tx(
+        id: RostraId,
+        record: &SocialPostRecord<SocialPost>,
+        social_mentions_table: &impl social_mentions::ReadableTable,
+    ) -> DbResult<bool> {
+        if record.author == id {
+            return Ok(false);
+        }
+        if record.reply_to.map(|reply_to| reply_to.rostra_id()) == Some(id) {
+            return Ok(true);
+        }
+        Ok(social_mentions_table
+            .get(&(id, record.ts, record.event_id))?
+            .is_some())
+    }
+
+    /// A displayable post with its latest edit applied, if its content is
+    /// present and valid
+    fn get_displayable_social_post_tx(
+        ts: Timestamp,
+        event_id: ShortEventId,
+        events_table: &impl events::ReadableTable,
+        social_posts_table: &impl social_posts::ReadableTable,
+        social_posts_edits_table: &impl social_posts_edits::ReadableTable,
+        events_content_table: &impl events_content::ReadableTable,
+    ) -> DbResult<Option<SocialPostRecord<SocialPost>>> {
+        let Some(EventContentState::Present(content)) =
+            Database::get_event_content_tx(event_id, events_content_table)?
+        else {
+            return Ok(None);
+        };
+
+        let Ok(mut social_post) = content.deserialize_cbor::<content_kind::SocialPost>() else {
+            debug!(target: LOG_TARGET, %event_id, "Content invalid");
+            return Ok(None);
+        };
+
+        let Some(event) = Database::get_event_tx(event_id, events_table)? else {
+            warn!(target: LOG_TARGET, %event_id, "Missing event for a post with social_post_record?!");
+            return Ok(None);
+        };
+
+        let social_post_record =
+            Database::get_social_post_tx(event_id, social_posts_table)?.unwrap_or_default();
+
+        let edited = Database::apply_latest_edit_tx(
+            event_id,
+            event.author(),
+            &mut social_post,
+            social_posts_edits_table,
+            events_table,
+            events_content_table,
+        )?;
+
+        Ok(Some(SocialPostRecord {
+            ts,
+            author: event.author(),
+            event_id,
+            reply_count: social_post_record.reply_count,
+            reply_to: social_post.reply_to,
+            content: social_post,
+            edited,
+        }))
+    }
+
+    pub async fn paginate_social_post_comments_rev(
+        &self,
+        post_event_id: ShortEventId,
+        cursor: Option<EventPaginationCursor>,
+        limit: usize,
+    ) -> (
+        Vec<SocialPostRecord<content_kind::SocialPost>>,
+        Option<EventPaginationCursor>,
+    ) {
+        self.read_with(|tx| {
+            let events_table = tx.open_table(&events::TABLE)?;
+            let social_posts_tbl = tx.open_table(&social_posts::TABLE)?;
+            let social_post_replies_tbl = tx.open_table(&social_posts_replies::TABLE)?;
+            let social_posts_edits_table = tx.open_table(&social_posts_edits::TABLE)?;
+            let events_content_table = tx.open_table(&events_content::TABLE)?;
+
+            let (ret, cursor) = Database::paginate_table_partition_rev(&social_post_replies_tbl,
+                (post_event_id, Timestamp::ZERO, ShortEventId::ZERO)..=
+                (post_event_id, Timestamp::MAX, ShortEventId::MAX),
+                |(_, ts, event_id)| (post_event_id, ts, event_id),
+
+                 cursor.map(|c| (post_event_id, c.ts, c.event_id)), limit, move |(_, ts, event_id), _| {
+
+                let social_post_record = Database::get_social_post_tx(event_id, &social_posts_tbl)?.unwrap_or_default();
 
                 let Some(content_state) =
                     Database::get_event_content_tx(event_id, &events_content_table)?
@@ -135,11 +340,12 @@ impl Database {
                     return Ok(None);
                 };
                 let EventContentState::Present(content) = content_state else {
+                    debug!(target: LOG_TARGET, %event_id, "Skipping comment without content present");
                     return Ok(None);
                 };
 
-                let Ok(social_post) = content.deserialize_cbor::<content_kind::SocialPost>() else {
-                    debug!(target: LOG_TARGET, %event_id, "Content invalid");
+                let Ok(mut social_post) = content.deserialize_cbor::<content_kind::SocialPost>() else {
+                    debug!(target: LOG_TARGET, %event_id, "Skpping comment with invalid content");
                     return Ok(None);
                 };
 
@@ -148,38 +354,39 @@ impl Database {
                     return Ok(None);
                 };
 
-                let social_post_record =
-                    Database::get_social_post_tx(event_id, &social_posts_table)?.unwrap_or_default()
-                ;
+                let edited = Database::apply_latest_edit_tx(
+                    event_id,
+                    event.author(),
+                    &mut social_post,
+                    &social_posts_edits_table,
+                    &events_table,
+                    &events_content_table,
+                )?;
 
-                let social_post_record = SocialPostRecord {
+                Ok(Some(SocialPostRecord {
                     ts,
                     author: event.author(),
                     event_id,
-                    reply_count: social_post_record.reply_count,
                     reply_to: social_post.reply_to,
+                    reply_count: social_post_record.reply_count,
                     content: social_post,
-                };
-
-                if !filter_fn(&social_post_record) {
-                    return Ok(None);
-                }
-
-                Ok(Some(social_post_record))
+                    edited,
+                }))
             })?;
 
-
-            Ok((ret, cursor.map(|(ts, event_id)| EventPaginationCursor{ ts, event_id })))
+            Ok((ret, cursor.map(|(_, ts, event_id)| EventPaginationCursor { ts, event_id})))
         })
         .await
         .expect("Storage error")
     }
 
-    pub async fn paginate_social_post_comments_rev(
+    /// Posts tagged with (lowercased) `tag`, newest first
+    pub async fn paginate_social_posts_by_tag_rev(
         &self,
-        post_event_id: ShortEventId,
+        tag: String,
         cursor: Option<EventPaginationCursor>,
         limit: usize,
+        filter_fn: impl Fn(&SocialPostRecord<SocialPost>) -> bool + Send + 'static,
     ) -> (
         Vec<SocialPostRecord<content_kind::SocialPost>>,
         Option<EventPaginationCursor>,
@@ -187,15 +394,15 @@ impl Database {
         self.read_with(|tx| {
             let events_table = tx.open_table(&events::TABLE)?;
             let social_posts_tbl = tx.open_table(&social_posts::TABLE)?;
-            let social_post_replies_tbl = tx.open_table(&social_posts_replies::TABLE)?;
+            let social_tags_tbl = tx.open_table(&social_tags::TABLE)?;
+            let social_posts_edits_table = tx.open_table(&social_posts_edits::TABLE)?;
             let events_content_table = tx.open_table(&events_content::TABLE)?;
 
-            let (ret, cursor) = Database::paginate_table_partition_rev(&social_post_replies_tbl,
-                (post_event_id, Timestamp::ZERO, ShortEventId::ZERO)..=
-                (post_event_id, Timestamp::MAX, ShortEventId::MAX),
-                |(_, ts, event_id)| (post_event_id, ts, event_id),
-
-                 cursor.map(|c| (post_event_id, c.ts, c.event_id)), limit, move |(_, ts, event_id), _| {
+            let (ret, cursor) = Database::paginate_table_partition_rev(&social_tags_tbl,
+                (tag.clone(), Timestamp::ZERO, ShortEventId::ZERO)..=
+                (tag.clone(), Timestamp::MAX, ShortEventId::MAX),
+                |(tag, ts, event_id)| (tag, ts, event_id),
+                cursor.map(|c| (tag.clone(), c.ts, c.event_id)), limit, move |(_, ts, event_id), _| {
 
                 let social_post_record = Database::get_social_post_tx(event_id, &social_posts_tbl)?.unwrap_or_default();
 
@@ -205,12 +412,11 @@ impl Database {
                     return Ok(None);
                 };
                 let EventContentState::Present(content) = content_state else {
-                    debug!(target: LOG_TARGET, %event_id, "Skipping comment without content present");
                     return Ok(None);
                 };
 
-                let Ok(social_post) = content.deserialize_cbor::<content_kind::SocialPost>() else {
-                    debug!(target: LOG_TARGET, %event_id, "Skpping comment with invalid content");
+                let Ok(mut social_post) = content.deserialize_cbor::<content_kind::SocialPost>() else {
+                    debug!(target: LOG_TARGET, %event_id, "Content invalid");
                     return Ok(None);
                 };
 
@@ -219,14 +425,30 @@ impl Database {
                     return Ok(None);
                 };
 
-                Ok(Some(SocialPostRecord {
+                let edited = Database::apply_latest_edit_tx(
+                    event_id,
+                    event.author(),
+                    &mut social_post,
+                    &social_posts_edits_table,
+                    &events_table,
+                    &events_content_table,
+                )?;
+
+                let social_post_record = SocialPostRecord {
                     ts,
                     author: event.author(),
                     event_id,
                     reply_to: social_post.reply_to,
                     reply_count: social_post_record.reply_count,
                     content: social_post,
-                }))
+                    edited,
+                };
+
+                if !filter_fn(&social_post_record) {
+                    return Ok(None);
+                }
+
+                Ok(Some(social_post_record))
             })?;
 
             Ok((ret, cursor.map(|(_, ts, event_id)| EventPaginationCursor { ts, event_id})))
@@ -286,6 +508,7 @@ impl Database {
                     reply_to: social_post.reply_to,
                     reply_count: social_post_record.reply_count,
                     content: social_post,
+                    edited: None,
                 }))
             })?;
 
@@ -302,12 +525,13 @@ impl Database {
         self.read_with(|tx| {
             let events_table = tx.open_table(&events::TABLE)?;
             let social_posts_table = tx.open_table(&social_posts::TABLE)?;
+            let social_posts_edits_table = tx.open_table(&social_posts_edits::TABLE)?;
             let events_content_table = tx.open_table(&events_content::TABLE)?;
 
             let mut ret = HashMap::new();
 
             for event_id in post_ids {
-                let Some((social_post, event, social_post_record)) =
+                let Some((mut social_post, event, social_post_record)) =
                     Self::get_social_post_record_tx(
                         &events_table,
                         &social_posts_table,
@@ -317,6 +541,14 @@ impl Database {
                 else {
                     continue;
                 };
+                let edited = Self::apply_latest_edit_tx(
+                    event_id,
+                    event.author(),
+                    &mut social_post,
+                    &social_posts_edits_table,
+                    &events_table,
+                    &events_content_table,
+                )?;
 
                 ret.insert(
                     event_id,
@@ -327,6 +559,7 @@ impl Database {
                         reply_count: social_post_record.reply_count,
                         reply_to: social_post.reply_to,
                         content: social_post,
+                        edited,
                     },
                 );
             }
@@ -395,17 +628,27 @@ impl Database {
         self.read_with(|tx| {
             let events_table = tx.open_table(&events::TABLE)?;
             let social_posts_table = tx.open_table(&social_posts::TABLE)?;
+            let social_posts_edits_table = tx.open_table(&social_posts_edits::TABLE)?;
             let events_content_table = tx.open_table(&events_content::TABLE)?;
 
-            let Some((social_post, event, social_post_record)) = Self::get_social_post_record_tx(
-                &events_table,
-                &social_posts_table,
-                &events_content_table,
-                event_id,
-            )?
+            let Some((mut social_post, event, social_post_record)) =
+                Self::get_social_post_record_tx(
+                    &events_table,
+                    &social_posts_table,
+                    &events_content_table,
+                    event_id,
+                )?
             else {
                 return Ok(None);
             };
+            let edited = Self::apply_latest_edit_tx(
+                event_id,
+                event.author(),
+                &mut social_post,
+                &social_posts_edits_table,
+                &events_table,
+                &events_content_table,
+            )?;
 
             Ok(Some(SocialPostRecord {
                 ts: event.timestamp(),
@@ -414,12 +657,230 @@ impl Database {
                 reply_count: social_post_record.reply_count,
                 reply_to: social_post.reply_to,
                 content: social_post,
+                edited,
             }))
         })
         .await
         .expect("Storage error")
     }
 
+    /// The original version of a post, followed by all its edits, oldest first
+    ///
+    /// Empty if the original post is not available.
+    pub async fn get_social_post_versions(
+        &self,
+        author: RostraId,
+        event_id: ShortEventId,
+    ) -> Vec<SocialPostRecord<content_kind::SocialPost>> {
+        self.read_with(|tx| {
+            let events_table = tx.open_table(&events::TABLE)?;
+            let social_posts_table = tx.open_table(&social_posts::TABLE)?;
+            let social_posts_edits_table = tx.open_table(&social_posts_edits::TABLE)?;
+            let events_content_table = tx.open_table(&events_content::TABLE)?;
+
+            let mut ret = vec![];
+            for event_id in [event_id].into_iter().chain(
+                Self::get_social_post_edits_tx(event_id, &social_posts_edits_table)?
+                    .into_iter()
+                    .map(|(_, edit_id)| edit_id),
+            ) {
+                let Some((social_post, event, social_post_record)) =
+                    Self::get_social_post_record_tx(
+                        &events_table,
+                        &social_posts_table,
+                        &events_content_table,
+                        event_id,
+                    )?
+                    .filter(|(_, event, _)| event.author() == author)
+                else {
+                    if ret.is_empty() {
+                        // Without the original (e.g. deleted), there is no post
+                        break;
+                    }
+                    continue;
+                };
+
+                ret.push(SocialPostRecord {
+                    ts: event.timestamp(),
+                    author,
+                    event_id,
+                    reply_count: social_post_record.reply_count,
+                    reply_to: social_post.reply_to,
+                    content: social_post,
+                    edited: None,
+                });
+            }
+
+            Ok(ret)
+        })
+        .await
+        .expect("Storage error")
+    }
+
+    /// Posts mentioning `id`
+    pub async fn get_social_mentions(&self, id: RostraId) -> HashSet<ShortEventId> {
+        self.read_with(|tx| {
+            let social_mentions_table = tx.open_table(&social_mentions::TABLE)?;
+
+            let mut ret = HashSet::new();
+            for record in social_mentions_table.range(
+                &(id, Timestamp::ZERO, ShortEventId::ZERO)
+                    ..=&(id, Timestamp::MAX, ShortEventId::MAX),
+            )? {
+                let (k, _) = record?;
+                ret.insert(k.value().2);
+            }
+            Ok(ret)
+        })
+        .await
+        .expect("Storage error")
+    }
+
+    /// Number of known posts quoting `event_id`
+    pub async fn count_social_post_quotes(&self, event_id: ShortEventId) -> u64 {
+        self.read_with(|tx| {
+            let social_posts_quotes_table = tx.open_table(&social_posts_quotes::TABLE)?;
+
+            let mut count = 0;
+            for record in social_posts_quotes_table.range(
+                &(event_id, Timestamp::ZERO, ShortEventId::ZERO)
+                    ..=&(event_id, Timestamp::MAX, ShortEventId::MAX),
+            )? {
+                record?;
+                count += 1;
+            }
+            Ok(count)
+        })
+        .await
+        .expect("Storage error")
+    }
+
+    pub async fn get_social_poll(&self, event_id: ShortEventId) -> Option<SocialPollRecord> {
+        let self_id = self.self_id;
+        self.read_with(|tx| {
+            let events_table = tx.open_table(&events::TABLE)?;
+            let events_content_table = tx.open_table(&events_content::TABLE)?;
+            let social_polls_votes_table = tx.open_table(&social_polls_votes::TABLE)?;
+
+            let Some(EventContentState::Present(content)) =
+                Database::get_event_content_tx(event_id, &events_content_table)?
+            else {
+                return Ok(None);
+            };
+            let Ok(poll) = content.deserialize_cbor::<content_kind::SocialPoll>() else {
+                debug!(target: LOG_TARGET, %event_id, "Poll content invalid");
+                return Ok(None);
+            };
+            let Some(event) = Database::get_event_tx(event_id, &events_table)? else {
+                return Ok(None);
+            };
+
+            let votes =
+                Database::get_social_poll_votes_tx(event_id, &poll, &social_polls_votes_table)?;
+            let mut tally = vec![0; poll.options.len()];
+            for (_, option) in votes.values() {
+                if let Some(count) = tally.get_mut(usize::from(*option)) {
+                    *count += 1;
+                }
+            }
+            let self_vote = votes.get(&self_id).copied();
+
+            Ok(Some(SocialPollRecord {
+                ts: event.timestamp(),
+                event_id,
+                author: event.author(),
+                content: poll,
+                tally,
+                self_vote,
+            }))
+        })
+        .await
+        .expect("Storage error")
+    }
+
+    /// The counted vote of each voter in `poll_id`
+    ///
+    /// That is the latest vote cast before the poll closed. Votes for options
+    /// the poll doesn't have are ignored.
+    pub(crate) fn get_social_poll_votes_tx(
+        poll_id: ShortEventId,
+        poll: &content_kind::SocialPoll,
+        social_polls_votes_table: &impl social_polls_votes::ReadableTable,
+    ) -> DbResult<HashMap<RostraId, (ShortEventId, u8)>> {
+        let mut votes = HashMap::new();
+        for record in social_polls_votes_table.range(
+            &(poll_id, RostraId::ZERO, Timestamp::ZERO, ShortEventId::ZERO)
+                ..=&(poll_id, RostraId::MAX, Timestamp::MAX, ShortEventId::MAX),
+        )? {
+            let (k, v) = record?;
+            let (_, voter, ts, vote_id) = k.value();
+            let option = v.value();
+            if poll.is_closed(ts) || poll.options.len() <= usize::from(option) {
+                continue;
+            }
+            // Records are ordered by timestamp, so the latest one wins
+            votes.insert(voter, (vote_id, option));
+        }
+        Ok(votes)
+    }
+
+    /// All edits of `event_id`, oldest first, not filtered by author
+    fn get_social_post_edits_tx(
+        event_id: ShortEventId,
+        social_posts_edits_table: &impl social_posts_edits::ReadableTable,
+    ) -> DbResult<Vec<(Timestamp, ShortEventId)>> {
+        let mut ret = vec![];
+        for record in social_posts_edits_table.range(
+            &(event_id, Timestamp::ZERO, ShortEventId::ZERO)
+                ..=&(event_id, Timestamp::MAX, ShortEventId::MAX),
+        )? {
+            let (k, _) = record?;
+            let (_, ts, edit_id) = k.value();
+            ret.push((ts, edit_id));
+        }
+        Ok(ret)
+    }
+
+    /// Replace `social_post` with the content of its latest edit by `author`
+    ///
+    /// The edit can't change the persona or what the post replies to.
+    /// Returns the time of the edit used, if any.
+    fn apply_latest_edit_tx(
+        event_id: ShortEventId,
+        author: RostraId,
+        social_post: &mut SocialPost,
+        social_posts_edits_table: &impl social_posts_edits::ReadableTable,
+        events_table: &impl events::ReadableTable,
+        events_content_table: &impl events_content::ReadableTable,
+    ) -> DbResult<Option<Timestamp>> {
+        for (ts, edit_id) in Self::get_social_post_edits_tx(event_id, social_posts_edits_table)?
+            .into_iter()
+            .rev()
+        {
+            if Database::get_event_tx(edit_id, events_table)?
+                .is_none_or(|event| event.author() != author)
+            {
+                continue;
+            }
+            let Some(EventContentState::Present(content)) =
+                Database::get_event_content_tx(edit_id, events_content_table)?
+            else {
+                continue;
+            };
+            let Ok(edit) = content.deserialize_cbor::<content_kind::SocialPost>() else {
+                continue;
+            };
+            *social_post = SocialPost {
+                reply_to: social_post.reply_to,
+                persona: social_post.persona,
+                edit_of: social_post.edit_of,
+                ..edit
+            };
+            return Ok(Some(ts));
+        }
+        Ok(None)
+    }
+
     fn get_social_post_record_tx(
         events_table: &redb_bincode::ReadOnlyTable<ShortEventId, crate::EventRecord>,
         social_posts_table: &redb_bincode::ReadOnlyTable<ShortEventId, crate::SocialPostRecord>,
@@ -0,0 +1,175 @@
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+
+use redb::{ReadableTableMetadata as _, TableHandle as _};
+use redb_bincode::ReadTransaction;
+use rostra_core::event::EventExt as _;
+use rostra_core::id::RostraId;
+use serde::Serialize;
+
+use crate::event::EventContentState;
+use crate::{
+    Database, DbResult, api_tokens, db_reindex_pending, db_version, events, events_by_time,
+    events_content, events_content_missing, events_heads, events_missing, events_self,
+    ids_followees, ids_followers, ids_full, ids_nodes, ids_personas, ids_self, ids_unfollowed,
+    social_drafts, social_mentions, social_outbox, social_polls_votes, social_posts,
+    social_posts_by_time, social_posts_edits, social_posts_quotes, social_posts_quotes_missing,
+    social_posts_reactions, social_posts_replies, social_profiles, social_tags,
+};
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TableStats {
+    pub name: String,
+    pub rows: u64,
+    /// Bytes used by the keys and values
+    pub stored_bytes: u64,
+    /// Bytes used by the b-tree itself
+    pub metadata_bytes: u64,
+    /// Bytes allocated, but not used
+    pub fragmented_bytes: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct IdStats {
+    pub rostra_id: RostraId,
+    pub events: u64,
+    /// Total size of the stored (present or invalid) content
+    pub content_bytes: u64,
+    pub heads: u64,
+    pub missing_events: u64,
+}
+
+impl IdStats {
+    fn new(rostra_id: RostraId) -> Self {
+        Self {
+            rostra_id,
+            events: 0,
+            content_bytes: 0,
+            heads: 0,
+            missing_events: 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DbStats {
+    pub tables: Vec<TableStats>,
+    /// Per-identity stats, largest `content_bytes` first
+    pub ids: Vec<IdStats>,
+    pub events_missing: u64,
+    pub events_content_missing: u64,
+}
+
+impl Database {
+    /// Collect storage statistics of the whole database
+    ///
+    /// Requires a scan of all events, so can take a while on large databases.
+    pub async fn stats(&self) -> DbResult<DbStats> {
+        self.read_with(|tx| {
+            let tables = vec![
+                Self::table_stats_tx(tx, &db_version::TABLE)?,
+                Self::table_stats_tx(tx, &db_reindex_pending::TABLE)?,
+                Self::table_stats_tx(tx, &ids_self::TABLE)?,
+                Self::table_stats_tx(tx, &ids_full::TABLE)?,
+                Self::table_stats_tx(tx, &ids_nodes::TABLE)?,
+                Self::table_stats_tx(tx, &ids_followees::TABLE)?,
+                Self::table_stats_tx(tx, &ids_followers::TABLE)?,
+                Self::table_stats_tx(tx, &ids_unfollowed::TABLE)?,
+                Self::table_stats_tx(tx, &ids_personas::TABLE)?,
+                Self::table_stats_tx(tx, &events::TABLE)?,
+                Self::table_stats_tx(tx, &events_missing::TABLE)?,
+                Self::table_stats_tx(tx, &events_heads::TABLE)?,
+                Self::table_stats_tx(tx, &events_self::TABLE)?,
+                Self::table_stats_tx(tx, &events_content::TABLE)?,
+                Self::table_stats_tx(tx, &events_content_missing::TABLE)?,
+                Self::table_stats_tx(tx, &events_by_time::TABLE)?,
+                Self::table_stats_tx(tx, &social_profiles::TABLE)?,
+                Self::table_stats_tx(tx, &social_posts::TABLE)?,
+                Self::table_stats_tx(tx, &social_posts_replies::TABLE)?,
+                Self::table_stats_tx(tx, &social_posts_reactions::TABLE)?,
+                Self::table_stats_tx(tx, &social_posts_by_time::TABLE)?,
+                Self::table_stats_tx(tx, &social_posts_edits::TABLE)?,
+                Self::table_stats_tx(tx, &social_posts_quotes::TABLE)?,
+                Self::table_stats_tx(tx, &social_posts_quotes_missing::TABLE)?,
+                Self::table_stats_tx(tx, &social_tags::TABLE)?,
+                Self::table_stats_tx(tx, &social_mentions::TABLE)?,
+                Self::table_stats_tx(tx, &social_polls_votes::TABLE)?,
+                Self::table_stats_tx(tx, &social_outbox::TABLE)?,
+                Self::table_stats_tx(tx, &social_drafts::TABLE)?,
+                Self::table_stats_tx(tx, &api_tokens::TABLE)?,
+            ];
+
+            let events_tbl = tx.open_table(&events::TABLE)?;
+            let events_content_tbl = tx.open_table(&events_content::TABLE)?;
+            let events_heads_tbl = tx.open_table(&events_heads::TABLE)?;
+            let events_missing_tbl = tx.open_table(&events_missing::TABLE)?;
+            let events_content_missing_tbl = tx.open_table(&events_content_missing::TABLE)?;
+
+            let mut ids: BTreeMap<RostraId, IdStats> = BTreeMap::new();
+
+            for record in events_tbl.range(..)? {
+                let (k, v) = record?;
+                let event = v.value();
+                let id_stats = ids
+                    .entry(event.author())
+                    .or_insert_with(|| IdStats::new(event.author()));
+
+                id_stats.events += 1;
+                if let Some(EventContentState::Present(_) | EventContentState::Invalid(_)) =
+                    events_content_tbl.get(&k.value())?.map(|g| g.value())
+                {
+                    id_stats.content_bytes += u64::from(event.content_len());
+                }
+            }
+
+            for record in events_heads_tbl.range(..)? {
+                let (rostra_id, _) = record?.0.value();
+                ids.entry(rostra_id)
+                    .or_insert_with(|| IdStats::new(rostra_id))
+                    .heads += 1;
+            }
+
+            let mut events_missing = 0;
+            for record in events_missing_tbl.range(..)? {
+                let (rostra_id, _) = record?.0.value();
+                events_missing += 1;
+                ids.entry(rostra_id)
+                    .or_insert_with(|| IdStats::new(rostra_id))
+                    .missing_events += 1;
+            }
+
+            let mut events_content_missing = 0;
+            for record in events_content_missing_tbl.range(..)? {
+                record?;
+                events_content_missing += 1;
+            }
+
+            let mut ids: Vec<_> = ids.into_values().collect();
+            ids.sort_by_key(|id_stats| Reverse(id_stats.content_bytes));
+
+            Ok(DbStats {
+                tables,
+                ids,
+                events_missing,
+                events_content_missing,
+            })
+        })
+        .await
+    }
+
+    fn table_stats_tx<K, V>(
+        dbtx: &ReadTransaction,
+        def: &redb_bincode::TableDefinition<'_, K, V>,
+    ) -> DbResult<TableStats> {
+        let def = def.as_raw();
+        let tbl = dbtx.as_raw().open_table(def)?;
+        let stats = tbl.stats()?;
+        Ok(TableStats {
+            name: def.name().to_owned(),
+            rows: tbl.len()?,
+            stored_bytes: stats.stored_bytes(),
+            metadata_bytes: stats.metadata_bytes(),
+            fragmented_bytes: stats.fragmented_bytes(),
+        })
+    }
+}
@@ -1,6 +1,6 @@
 use redb_bincode::ReadTransaction;
 
-use crate::{Database, DbResult};
+use crate::{Database, DbResult, WriteTransactionCtx};
 
 impl Database {
     pub(crate) fn dump_table_dbtx<K, V>(
@@ -22,4 +22,54 @@ impl Database {
         }
         Ok(())
     }
+
+    /// Remove all records from a table
+    ///
+    /// Works on raw bytes, so can be used on tables with records in an
+    /// outdated format.
+    pub(crate) fn clear_table_tx<K, V>(
+        dbtx: &WriteTransactionCtx,
+        def: &redb_bincode::TableDefinition<'_, K, V>,
+    ) -> DbResult<()> {
+        dbtx.as_raw()
+            .open_table(def.as_raw())?
+            .retain(|_, _| false)?;
+        Ok(())
+    }
+
+    /// Convert all records of a table from an outdated format
+    ///
+    /// `old_def` and `new_def` must be definitions of the same table.
+    pub(crate) fn migrate_table_tx<K, OldV, NewV>(
+        dbtx: &WriteTransactionCtx,
+        old_def: &redb_bincode::TableDefinition<'_, K, OldV>,
+        new_def: &redb_bincode::TableDefinition<'_, K, NewV>,
+        f: impl Fn(OldV) -> NewV,
+    ) -> DbResult<()>
+    where
+        K: bincode::Decode<()> + bincode::Encode,
+        OldV: bincode::Decode<()> + bincode::Encode,
+        NewV: bincode::Decode<()> + bincode::Encode,
+    {
+        // Can't have the same table opened twice, so read everything first
+        let records = {
+            let old_table = dbtx.open_table(old_def)?;
+            old_table
+                .range(..)?
+                .map(|record| {
+                    let (k, v) = record?;
+                    Ok((k.value(), v.value()))
+                })
+                .collect::<DbResult<Vec<_>>>()?
+        };
+
+        // Don't let `insert` decode the old records as new ones
+        Self::clear_table_tx(dbtx, old_def)?;
+
+        let mut new_table = dbtx.open_table(new_def)?;
+        for (k, v) in records {
+            new_table.insert(&k, &f(v))?;
+        }
+        Ok(())
+    }
 }
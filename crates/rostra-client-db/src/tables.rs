@@ -1,15 +1,17 @@
+use std::collections::BTreeMap;
+
 use bincode::{Decode, Encode};
 pub use event::EventRecord;
 use event::EventsMissingRecord;
 use id_self::IdSelfAccountRecord;
-use ids::{
-    IdsFolloweesRecord, IdsFolloweesRecordV0, IdsFollowersRecord, IdsPersonaRecord,
-    IdsUnfollowedRecord,
+use ids::{IdsFolloweesRecord, IdsFollowersRecord, IdsPersonaRecord, IdsUnfollowedRecord};
+use rostra_core::event::{
+    ContentValidationResult, EventContentKind as _, IrohNodeId, PersonaId, SocialPost,
+    SocialProfileLink,
 };
-use rostra_core::event::{IrohNodeId, PersonaId};
 use rostra_core::id::{RestRostraId, RostraId, ShortRostraId};
-use rostra_core::{ShortEventId, Timestamp};
-use serde::Serialize;
+use rostra_core::{ExternalEventId, ShortEventId, Timestamp};
+use serde::{Deserialize, Serialize};
 
 pub use self::event::EventsHeadsTableRecord;
 pub(crate) mod event;
@@ -39,6 +41,13 @@ def_table! {
     db_version: () => u64
 }
 
+def_table! {
+    /// Set when derived tables need to be rebuilt from raw events
+    ///
+    /// See [`crate::Database::reindex`].
+    db_reindex_pending: () => ()
+}
+
 def_table! {
     /// Information about own account
     ids_self: () => IdSelfAccountRecord
@@ -52,7 +61,6 @@ def_table! {
     ids_full: ShortRostraId => RestRostraId
 }
 def_table!(ids_nodes: (RostraId, IrohNodeId) => IrohNodeRecord);
-def_table!(ids_followees_v0: (RostraId, RostraId) => IdsFolloweesRecordV0);
 def_table!(ids_followees: (RostraId, RostraId) => IdsFolloweesRecord);
 def_table!(ids_followers: (RostraId, RostraId) => IdsFollowersRecord);
 def_table!(ids_unfollowed: (RostraId, RostraId) => IdsUnfollowedRecord);
@@ -68,13 +76,72 @@ def_table!(events_content_missing: ShortEventId => ());
 def_table!(events_by_time: (Timestamp, ShortEventId) => ());
 
 // SOCIAL
-def_table!(social_profiles_v0: RostraId => Latest<IdSocialProfileRecordV0>);
 def_table!(social_profiles: RostraId => Latest<IdSocialProfileRecord>);
-def_table!(social_posts_v0: (ShortEventId)=> SocialPostRecordV0);
+/// [`social_profiles`] before db version 6, only used for migration
+pub(crate) mod social_profiles_v0 {
+    use super::*;
+    pub type Definition<'a> =
+        redb_bincode::TableDefinition<'a, RostraId, Latest<IdSocialProfileRecordV0>>;
+    pub const TABLE: Definition = redb_bincode::TableDefinition::new("social_profiles");
+}
 def_table!(social_posts: (ShortEventId)=> SocialPostRecord);
 def_table!(social_posts_replies: (ShortEventId, Timestamp, ShortEventId)=> SocialPostsRepliesRecord);
 def_table!(social_posts_reactions: (ShortEventId, Timestamp, ShortEventId)=> SocialPostsReactionsRecord);
 def_table!(social_posts_by_time: (Timestamp, ShortEventId) => ());
+def_table! {
+    /// New versions of posts: (original post, timestamp, edit)
+    ///
+    /// Not checked to be authored by the author of the original post, which
+    /// readers must do.
+    social_posts_edits: (ShortEventId, Timestamp, ShortEventId) => ()
+}
+def_table! {
+    /// Posts quoting other posts: (quoted post, timestamp, quoting post)
+    social_posts_quotes: (ShortEventId, Timestamp, ShortEventId) => ()
+}
+def_table! {
+    /// Quoted posts we don't have yet, to be fetched like missing events
+    ///
+    /// Unlike [`events_missing`], these are not parents of any known event.
+    social_posts_quotes_missing: (RostraId, ShortEventId) => ()
+}
+def_table! {
+    /// Posts by hashtag: (lowercased tag, timestamp, post)
+    ///
+    /// Only the tags of the original version of a post are indexed.
+    social_tags: (String, Timestamp, ShortEventId) => ()
+}
+def_table! {
+    /// Posts mentioning an identity: (mentioned id, timestamp, post)
+    ///
+    /// Only the mentions of the original version of a post are indexed.
+    social_mentions: (RostraId, Timestamp, ShortEventId) => ()
+}
+
+def_table! {
+    /// All poll votes: (poll, voter, timestamp, vote) => chosen option
+    ///
+    /// Only the latest vote of each voter cast before the poll closed counts.
+    social_polls_votes: (ShortEventId, RostraId, Timestamp, ShortEventId) => u8
+}
+
+// OUTBOX
+def_table! {
+    /// Own posts to sign and publish later, by a local id
+    social_outbox: u64 => SocialOutboxRecord
+}
+
+// DRAFTS
+def_table! {
+    /// Unfinished own posts, by a local id
+    social_drafts: u64 => SocialDraftRecord
+}
+
+// API
+def_table! {
+    /// API tokens, by the hash of the token
+    api_tokens: ApiTokenHash => ApiTokenRecord
+}
 
 #[derive(Debug, Encode, Decode, Clone)]
 pub struct Latest<T> {
@@ -88,33 +155,47 @@ pub struct SocialPostsRepliesRecord;
 pub struct SocialPostsReactionsRecord;
 
 #[derive(Debug, Encode, Decode, Clone)]
-pub struct IdSocialProfileRecordV0 {
+pub struct IdSocialProfileRecord {
     pub event_id: ShortEventId,
     pub display_name: String,
     pub bio: String,
-    pub img_mime: String,
-    pub img: Vec<u8>,
+    pub avatar: Option<(String, Vec<u8>)>,
+    pub links: Vec<SocialProfileLink>,
+    pub pronouns: Option<String>,
+    pub location: Option<String>,
+    pub header: Option<ExternalEventId>,
+    pub persona_descriptions: BTreeMap<PersonaId, String>,
 }
 
 #[derive(Debug, Encode, Decode, Clone)]
-pub struct IdSocialProfileRecord {
+pub struct IdSocialProfileRecordV0 {
     pub event_id: ShortEventId,
     pub display_name: String,
     pub bio: String,
     pub avatar: Option<(String, Vec<u8>)>,
 }
 
-#[derive(
-    Debug,
-    Encode,
-    Decode,
-    Clone,
-    // Note: needs to be default so we can track number of replies even before we get what was
-    // replied to
-    Default,
-)]
-pub struct SocialPostRecordV0 {
-    pub reply_count: u64,
+impl From<IdSocialProfileRecordV0> for IdSocialProfileRecord {
+    fn from(
+        IdSocialProfileRecordV0 {
+            event_id,
+            display_name,
+            bio,
+            avatar,
+        }: IdSocialProfileRecordV0,
+    ) -> Self {
+        Self {
+            event_id,
+            display_name,
+            bio,
+            avatar,
+            links: vec![],
+            pronouns: None,
+            location: None,
+            header: None,
+            persona_descriptions: BTreeMap::new(),
+        }
+    }
 }
 
 #[derive(
@@ -132,6 +213,95 @@ pub struct SocialPostRecord {
     pub reaction_count: u64,
 }
 
+pub type ApiTokenHash = [u8; 32];
+
+#[derive(Debug, Encode, Decode, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ApiTokenScope {
+    /// Only reading
+    ReadOnly,
+    /// Reading and publishing posts
+    PostOnly,
+    /// Everything a logged in user can do
+    Full,
+}
+
+impl ApiTokenScope {
+    pub fn can_post(self) -> bool {
+        matches!(self, Self::PostOnly | Self::Full)
+    }
+
+    pub fn can_sign(self) -> bool {
+        self != Self::ReadOnly
+    }
+}
+
+#[derive(Debug, Encode, Decode, Clone)]
+pub struct ApiTokenRecord {
+    pub name: String,
+    pub scope: ApiTokenScope,
+    pub created: Timestamp,
+    /// Secret key of the identity encrypted with a key derived from the
+    /// token, so it can only be recovered by the token holder
+    ///
+    /// Not set for scopes that can't sign anything.
+    pub wrapped_secret: Option<[u8; 32]>,
+}
+
+#[derive(Debug, Encode, Decode, Clone)]
+pub struct SocialOutboxRecord {
+    /// Time at which the post should be published
+    pub publish_at: Timestamp,
+    pub persona: PersonaId,
+    pub djot_content: String,
+    pub reply_to: Option<ExternalEventId>,
+    pub attachments: Vec<ExternalEventId>,
+    pub quote: Option<ExternalEventId>,
+    pub content_warning: Option<String>,
+    pub status: SocialOutboxStatus,
+}
+
+impl SocialOutboxRecord {
+    /// Check the post it will be published as, before it gets scheduled
+    pub fn validate(&self) -> ContentValidationResult<()> {
+        SocialPost {
+            persona: self.persona,
+            djot_content: Some(self.djot_content.clone()),
+            reply_to: self.reply_to,
+            reaction: None,
+            attachments: self.attachments.clone(),
+            edit_of: None,
+            quote: self.quote,
+            poll: None,
+            content_warning: self.content_warning.clone(),
+        }
+        .validate()
+    }
+}
+
+#[derive(Debug, Encode, Decode, Clone, PartialEq, Eq)]
+pub enum SocialOutboxStatus {
+    /// Waiting to be published
+    Pending,
+    /// Published as the event
+    Published(ShortEventId),
+    /// Publishing failed with an error, and will not be retried
+    Failed(String),
+    /// Claimed by the publisher, which is signing and publishing it
+    Publishing,
+}
+
+#[derive(Debug, Encode, Decode, Clone)]
+pub struct SocialDraftRecord {
+    /// Time of the last save
+    pub updated: Timestamp,
+    pub djot_content: String,
+    pub reply_to: Option<ExternalEventId>,
+    pub attachments: Vec<ExternalEventId>,
+    pub quote: Option<ExternalEventId>,
+    pub content_warning: Option<String>,
+}
+
 #[derive(Debug, Encode, Decode, Clone)]
 pub struct IrohNodeRecord {
     pub announcement_ts: Timestamp,
@@ -1,6 +1,6 @@
 use bincode::{Decode, Encode};
 use rostra_core::Timestamp;
-use rostra_core::event::{PersonaId, PersonaSelector};
+use rostra_core::event::PersonaSelector;
 use rostra_core::id::RestRostraId;
 
 #[derive(Debug, Encode, Decode, Clone, Copy)]
@@ -8,12 +8,6 @@ pub struct IdRecord {
     pub id_rest: RestRostraId,
 }
 
-#[derive(Debug, Encode, Decode, Clone)]
-pub struct IdsFolloweesRecordV0 {
-    pub ts: Timestamp,
-    pub persona: PersonaId,
-}
-
 #[derive(Debug, Encode, Decode, Clone)]
 pub struct IdsFolloweesRecord {
     pub ts: Timestamp,
@@ -1,6 +1,12 @@
-use rostra_core::EventId;
-use rostra_core::event::{Event, EventContent, EventKind, VerifiedEvent};
-use rostra_core::id::{RostraId, RostraIdSecretKey};
+use std::time::{Duration, UNIX_EPOCH};
+
+use rostra_core::event::content_kind::EventContentKind as _;
+use rostra_core::event::{
+    Event, EventContent, EventExt as _, EventKind, PersonaId, VerifiedEvent, VerifiedEventContent,
+    content_kind,
+};
+use rostra_core::id::{ExternalEventId, RostraId, RostraIdSecretKey};
+use rostra_core::{EventId, ShortEventId, Timestamp};
 use rostra_util_error::BoxedErrorResult;
 use snafu::ResultExt as _;
 use tempfile::{TempDir, tempdir};
@@ -8,8 +14,11 @@ use tracing::info;
 
 use crate::event::EventContentState;
 use crate::{
-    Database, events, events_by_time, events_content, events_content_missing, events_heads,
-    events_missing, ids_full,
+    ApiToken, ApiTokenScope, ArchiveDecodeError, Database, EventsHeadsTableRecord,
+    IdSocialProfileRecordV0, IdentityArchive, ImportArchiveError, IntegrityIssue, Latest,
+    SocialDraftRecord, SocialOutboxRecord, SocialOutboxStatus, SocialPostRecord, events,
+    events_by_time, events_content, events_content_missing, events_heads, events_missing, ids_full,
+    social_posts, social_posts_by_time, social_profiles_v0,
 };
 
 pub(crate) async fn temp_db_rng() -> BoxedErrorResult<(TempDir, super::Database)> {
@@ -232,3 +241,1488 @@ async fn test_store_deleted_event() -> BoxedErrorResult<()> {
 
     Ok(())
 }
+
+#[test_log::test(tokio::test(flavor = "multi_thread"))]
+async fn test_check_integrity() -> BoxedErrorResult<()> {
+    let id_secret = RostraIdSecretKey::generate();
+    let author = id_secret.id();
+    let (_dir, db) = temp_db(author).await?;
+
+    let event_a = build_test_event(id_secret, None);
+    let event_b = build_test_event(id_secret, event_a.event_id);
+    let event_c = build_test_event(id_secret, event_b.event_id);
+
+    // `event_b` is left missing on purpose
+    db.process_event(&event_a).await;
+    db.process_event(&event_c).await;
+    db.process_event_content(&VerifiedEventContent::verify(
+        event_a,
+        EventContent::new(vec![]),
+    )?)
+    .await;
+
+    let report = db.check_integrity(false).await?;
+    assert!(report.is_ok(), "{report:?}");
+    assert_eq!(report.events_checked, 2);
+    assert_eq!(report.contents_checked, 1);
+
+    db.write_with(|tx| {
+        let mut events_heads_table = tx.open_table(&events_heads::TABLE)?;
+        let mut events_missing_table = tx.open_table(&events_missing::TABLE)?;
+        let mut events_content_missing_table = tx.open_table(&events_content_missing::TABLE)?;
+        let mut social_posts_table = tx.open_table(&social_posts::TABLE)?;
+
+        events_heads_table.insert(&(author, event_b.event_id.into()), &EventsHeadsTableRecord)?;
+        events_missing_table.remove(&(author, event_b.event_id.into()))?;
+        events_content_missing_table.remove(&event_c.event_id.into())?;
+        social_posts_table.insert(
+            &event_a.event_id.into(),
+            &SocialPostRecord {
+                reply_count: 3,
+                reaction_count: 0,
+            },
+        )?;
+        Ok(())
+    })
+    .await?;
+
+    let report = db.check_integrity(false).await?;
+    info!(?report, "Corrupted");
+    assert!(!report.repaired);
+    assert_eq!(report.issues.len(), 4);
+    assert!(report.issues.iter().any(|issue| matches!(
+        issue,
+        IntegrityIssue::SocialPostCountersMismatch { recorded, .. } if recorded.reply_count == 3
+    )));
+
+    let report = db.check_integrity(true).await?;
+    assert!(report.repaired);
+    assert_eq!(report.issues.len(), 4);
+
+    // Counters are rebuilt by reindexing, which drops the bogus record
+    db.read_with(|tx| {
+        let social_posts_table = tx.open_table(&social_posts::TABLE)?;
+        assert!(social_posts_table.get(&event_a.event_id.into())?.is_none());
+        Ok(())
+    })
+    .await?;
+
+    let report = db.check_integrity(false).await?;
+    assert!(report.is_ok(), "{report:?}");
+    assert_eq!(
+        db.get_missing_events_for_id(author).await,
+        vec![event_b.event_id.into()]
+    );
+
+    Ok(())
+}
+
+fn build_social_post_event(
+    id_secret: RostraIdSecretKey,
+    parent: impl Into<Option<EventId>>,
+    post: content_kind::SocialPost,
+) -> VerifiedEventContent {
+    let parent = parent.into();
+
+    let content = post.serialize_cbor().expect("Valid content");
+    let author = id_secret.id();
+    let event = Event::builder()
+        .author(author)
+        .kind(EventKind::SOCIAL_POST)
+        .maybe_parent_prev(parent.map(Into::into))
+        .content(&content)
+        .singleton(false)
+        .build();
+
+    let signed_event = event.signed_by(id_secret);
+
+    VerifiedEventContent::verify(
+        VerifiedEvent::verify_signed(author, signed_event).expect("Valid event"),
+        content,
+    )
+    .expect("Valid content")
+}
+
+#[test_log::test(tokio::test(flavor = "multi_thread"))]
+async fn test_reindex() -> BoxedErrorResult<()> {
+    let id_secret = RostraIdSecretKey::generate();
+    let author = id_secret.id();
+    let (_dir, db) = temp_db(author).await?;
+
+    let post = build_social_post_event(
+        id_secret,
+        None,
+        content_kind::SocialPost {
+            persona: PersonaId(0),
+            djot_content: Some("Hello".into()),
+            reply_to: None,
+            reaction: None,
+            attachments: vec![],
+            edit_of: None,
+            quote: None,
+            poll: None,
+            content_warning: None,
+        },
+    );
+    let post_id = post.event_id();
+    let reply = build_social_post_event(
+        id_secret,
+        post_id,
+        content_kind::SocialPost {
+            persona: PersonaId(0),
+            djot_content: Some("Hi".into()),
+            reply_to: Some(ExternalEventId::new(author, post_id)),
+            reaction: None,
+            attachments: vec![],
+            edit_of: None,
+            quote: None,
+            poll: None,
+            content_warning: None,
+        },
+    );
+    let reaction = build_social_post_event(
+        id_secret,
+        reply.event_id(),
+        content_kind::SocialPost {
+            persona: PersonaId(0),
+            djot_content: None,
+            reply_to: Some(ExternalEventId::new(author, post_id)),
+            reaction: Some("👍".into()),
+            attachments: vec![],
+            edit_of: None,
+            quote: None,
+            poll: None,
+            content_warning: None,
+        },
+    );
+
+    // Insert out of order, to make sure reindex doesn't depend on it
+    for event_content in [&reaction, &post, &reply] {
+        db.process_event_with_content(event_content).await;
+    }
+
+    db.write_with(|tx| {
+        Database::clear_table_tx(tx, &social_posts::TABLE)?;
+        Database::clear_table_tx(tx, &social_posts_by_time::TABLE)?;
+        Ok(())
+    })
+    .await?;
+
+    let mut new_posts = db.new_posts_subscribe();
+    let progress = db.reindex(|progress| info!(?progress, "Reindex")).await?;
+    assert_eq!(progress.processed, 3);
+    assert_eq!(progress.total, 3);
+    // Replayed posts are not new
+    assert!(new_posts.try_recv().is_err());
+
+    let record = db
+        .read_with(|tx| {
+            Database::get_social_post_tx(post_id, &tx.open_table(&social_posts::TABLE)?)
+        })
+        .await?
+        .expect("Must have the record");
+    assert_eq!(record.reply_count, 1);
+    assert_eq!(record.reaction_count, 1);
+
+    let report = db.check_integrity(false).await?;
+    assert!(report.is_ok(), "{report:?}");
+
+    Ok(())
+}
+
+#[test_log::test(tokio::test(flavor = "multi_thread"))]
+async fn test_migrate_social_profiles() -> BoxedErrorResult<()> {
+    let (_dir, db) = temp_db_rng().await?;
+    let id = RostraIdSecretKey::generate().id();
+
+    db.write_with(|tx| {
+        tx.open_table(&social_profiles_v0::TABLE)?.insert(
+            &id,
+            &Latest {
+                ts: Timestamp(1),
+                inner: IdSocialProfileRecordV0 {
+                    event_id: ShortEventId::ZERO,
+                    display_name: "Alice".into(),
+                    bio: "Hi".into(),
+                    avatar: None,
+                },
+            },
+        )?;
+        Database::migrate_v5(tx)
+    })
+    .await?;
+
+    let profile = db
+        .get_social_profile(id)
+        .await
+        .expect("Must have the profile");
+    assert_eq!(profile.display_name, "Alice");
+    assert_eq!(profile.bio, "Hi");
+    assert!(profile.links.is_empty());
+    assert!(profile.persona_descriptions.is_empty());
+
+    Ok(())
+}
+
+#[test_log::test(tokio::test(flavor = "multi_thread"))]
+async fn test_export_import_archive() -> BoxedErrorResult<()> {
+    let id_secret = RostraIdSecretKey::generate();
+    let author = id_secret.id();
+    let (_dir, db) = temp_db(author).await?;
+
+    let post = build_social_post_event(
+        id_secret,
+        None,
+        content_kind::SocialPost {
+            persona: PersonaId(0),
+            djot_content: Some("Hello".into()),
+            reply_to: None,
+            reaction: None,
+            attachments: vec![],
+            edit_of: None,
+            quote: None,
+            poll: None,
+            content_warning: None,
+        },
+    );
+    let reply = build_social_post_event(
+        id_secret,
+        post.event_id(),
+        content_kind::SocialPost {
+            persona: PersonaId(0),
+            djot_content: Some("Hi".into()),
+            reply_to: Some(ExternalEventId::new(author, post.event_id())),
+            reaction: None,
+            attachments: vec![],
+            edit_of: None,
+            quote: None,
+            poll: None,
+            content_warning: None,
+        },
+    );
+    // Events of other identities are not exported
+    let other = build_social_post_event(
+        RostraIdSecretKey::generate(),
+        None,
+        content_kind::SocialPost {
+            persona: PersonaId(0),
+            djot_content: Some("Other".into()),
+            reply_to: None,
+            reaction: None,
+            attachments: vec![],
+            edit_of: None,
+            quote: None,
+            poll: None,
+            content_warning: None,
+        },
+    );
+    for event_content in [&post, &reply, &other] {
+        db.process_event_with_content(event_content).await;
+    }
+
+    let archive = db.export_identity(author).await?;
+    assert_eq!(archive.manifest.num_events, 2);
+    assert_eq!(archive.manifest.heads, vec![reply.event_id().into()]);
+    assert_eq!(
+        archive
+            .events
+            .iter()
+            .map(|event| event.signed.compute_short_id())
+            .collect::<Vec<_>>(),
+        vec![post.event_id().into(), reply.event_id().into()]
+    );
+
+    let mut encoded = archive.encode_to_vec();
+    let archive = IdentityArchive::decode_from_slice(&encoded)?;
+    encoded.push(0);
+    assert!(matches!(
+        IdentityArchive::decode_from_slice(&encoded),
+        Err(ArchiveDecodeError::TrailingBytes)
+    ));
+
+    let (_dir_other, db_other) = temp_db_rng().await?;
+    let outcome = db_other.import_archive(&archive).await?;
+    assert_eq!(outcome.num_events, 2);
+    assert_eq!(outcome.num_new_events, 2);
+
+    let outcome = db_other.import_archive(&archive).await?;
+    assert_eq!(outcome.num_new_events, 0);
+
+    assert_eq!(
+        db_other
+            .read_with(|tx| Database::get_heads_events_tx(
+                author,
+                &tx.open_table(&events_heads::TABLE)?
+            ))
+            .await?,
+        vec![reply.event_id().into()]
+    );
+    assert!(db_other.get_event_content(post.event_id()).await.is_some());
+
+    // An archive claiming events of a different author must be rejected
+    let mut tampered = archive.clone();
+    tampered.manifest.author = RostraIdSecretKey::generate().id();
+    assert!(matches!(
+        db_other.import_archive(&tampered).await,
+        Err(ImportArchiveError::InvalidEvent { .. })
+    ));
+
+    Ok(())
+}
+
+#[test_log::test(tokio::test(flavor = "multi_thread"))]
+async fn test_stats() -> BoxedErrorResult<()> {
+    let id_secret = RostraIdSecretKey::generate();
+    let author = id_secret.id();
+    let (_dir, db) = temp_db(author).await?;
+
+    let post = |text: &str| content_kind::SocialPost {
+        persona: PersonaId(0),
+        djot_content: Some(text.into()),
+        reply_to: None,
+        reaction: None,
+        attachments: vec![],
+        edit_of: None,
+        quote: None,
+        poll: None,
+        content_warning: None,
+    };
+
+    let first = build_social_post_event(id_secret, None, post("First"));
+    let second = build_social_post_event(id_secret, first.event_id(), post("Second"));
+
+    // The other identity's first event was never received
+    let other_id_secret = RostraIdSecretKey::generate();
+    let other = other_id_secret.id();
+    let other_older = build_social_post_event(other_id_secret, None, post("Older"));
+    let other_newer =
+        build_social_post_event(other_id_secret, other_older.event_id(), post("Newer"));
+
+    for event_content in [&first, &second, &other_newer] {
+        db.process_event_with_content(event_content).await;
+    }
+
+    let stats = db.stats().await?;
+
+    let table_rows = |name: &str| {
+        stats
+            .tables
+            .iter()
+            .find(|table| table.name == name)
+            .expect("Table must be listed")
+            .rows
+    };
+    assert_eq!(table_rows("events"), 3);
+    assert_eq!(table_rows("events_heads"), 2);
+    assert_eq!(table_rows("events_missing"), 1);
+    assert_eq!(stats.events_missing, 1);
+
+    assert_eq!(stats.ids.len(), 2);
+    let author_stats = stats
+        .ids
+        .iter()
+        .find(|id_stats| id_stats.rostra_id == author)
+        .expect("Must have author stats");
+    assert_eq!(author_stats.events, 2);
+    assert_eq!(author_stats.heads, 1);
+    assert_eq!(author_stats.missing_events, 0);
+    assert_eq!(
+        author_stats.content_bytes,
+        u64::from(first.content_len()) + u64::from(second.content_len())
+    );
+
+    let other_stats = stats
+        .ids
+        .iter()
+        .find(|id_stats| id_stats.rostra_id == other)
+        .expect("Must have other stats");
+    assert_eq!(other_stats.events, 1);
+    assert_eq!(other_stats.heads, 1);
+    assert_eq!(other_stats.missing_events, 1);
+    assert_eq!(
+        other_stats.content_bytes,
+        u64::from(other_newer.content_len())
+    );
+
+    Ok(())
+}
+
+#[test_log::test(tokio::test(flavor = "multi_thread"))]
+async fn test_snapshot_restore() -> BoxedErrorResult<()> {
+    let id_secret = RostraIdSecretKey::generate();
+    let author = id_secret.id();
+    let (dir, db) = temp_db(author).await?;
+
+    let post = build_social_post_event(
+        id_secret,
+        None,
+        content_kind::SocialPost {
+            persona: PersonaId(0),
+            djot_content: Some("Hello".into()),
+            reply_to: None,
+            reaction: None,
+            attachments: vec![],
+            edit_of: None,
+            quote: None,
+            poll: None,
+            content_warning: None,
+        },
+    );
+    db.process_event_with_content(&post).await;
+
+    let snapshot_path = dir.path().join("snapshot.redb");
+    db.snapshot_to(&snapshot_path).await?;
+
+    let restored_path = dir.path().join("restored.redb");
+    // A snapshot of a different identity must be rejected
+    assert!(
+        Database::restore_snapshot(
+            &snapshot_path,
+            &restored_path,
+            RostraIdSecretKey::generate().id()
+        )
+        .await
+        .is_err()
+    );
+    assert!(!restored_path.exists());
+
+    assert_eq!(
+        Database::restore_snapshot(&snapshot_path, &restored_path, author).await?,
+        None
+    );
+
+    let restored = Database::open(&restored_path, author).await.boxed()?;
+    assert!(restored.get_event_content(post.event_id()).await.is_some());
+    assert_eq!(
+        restored.get_head(author).await,
+        Some(post.event_id().into())
+    );
+    drop(restored);
+
+    // The replaced database is kept
+    let old_path = Database::restore_snapshot(&snapshot_path, &restored_path, author)
+        .await?
+        .expect("Must keep the replaced database");
+    assert!(old_path.exists());
+    assert!(restored_path.exists());
+
+    Ok(())
+}
+
+#[test_log::test(tokio::test(flavor = "multi_thread"))]
+async fn test_api_tokens() -> BoxedErrorResult<()> {
+    let id_secret = RostraIdSecretKey::generate();
+    let (dir, db) = temp_db(id_secret.id()).await?;
+
+    let read_only = db
+        .create_api_token("reader".into(), ApiTokenScope::ReadOnly, id_secret)
+        .await?;
+    let full = db
+        .create_api_token("script".into(), ApiTokenScope::Full, id_secret)
+        .await?;
+
+    let verified = db.verify_api_token(&read_only).await?.expect("Valid token");
+    assert_eq!(verified.scope, ApiTokenScope::ReadOnly);
+    assert!(verified.id_secret.is_none());
+
+    // The token round-trips through its string form and unwraps the secret
+    let parsed: ApiToken = full.to_string().parse().boxed()?;
+    let verified = db.verify_api_token(&parsed).await?.expect("Valid token");
+    assert_eq!(verified.id_secret, Some(id_secret));
+
+    // A token of another identity is not valid here
+    assert!(
+        db.verify_api_token(&ApiToken::generate(RostraIdSecretKey::generate().id()))
+            .await?
+            .is_none()
+    );
+
+    assert_eq!(db.list_api_tokens().await?.len(), 2);
+    assert!(db.revoke_api_token(full.hash()).await?);
+    assert!(!db.revoke_api_token(full.hash()).await?);
+    assert!(db.verify_api_token(&full).await?.is_none());
+    assert_eq!(db.list_api_tokens().await?.len(), 1);
+
+    // Same answers without opening the database
+    drop(db);
+    let db_path = dir.path().join("db.redb");
+    let verified = Database::verify_api_token_at(&db_path, &read_only)
+        .await?
+        .expect("Valid token");
+    assert_eq!(verified.scope, ApiTokenScope::ReadOnly);
+    assert!(
+        Database::verify_api_token_at(&db_path, &full)
+            .await?
+            .is_none()
+    );
+    let other_id = RostraIdSecretKey::generate().id();
+    assert!(
+        Database::verify_api_token_at(&db_path, &ApiToken::generate(other_id))
+            .await?
+            .is_none()
+    );
+
+    Ok(())
+}
+
+#[test_log::test(tokio::test(flavor = "multi_thread"))]
+async fn test_get_blob() -> BoxedErrorResult<()> {
+    let id_secret = RostraIdSecretKey::generate();
+    let author = id_secret.id();
+    let (_dir, db) = temp_db(author).await?;
+
+    let blob = content_kind::Blob {
+        mime: "image/png".into(),
+        data: vec![1, 2, 3],
+    };
+    let content = blob.serialize_cbor()?;
+    let event = Event::builder()
+        .author(author)
+        .kind(EventKind::BLOB)
+        .content(&content)
+        .singleton(false)
+        .build();
+    let blob_event = VerifiedEventContent::verify(
+        VerifiedEvent::verify_signed(author, event.signed_by(id_secret))?,
+        content,
+    )?;
+    let blob_id: ShortEventId = blob_event.event_id().into();
+
+    // Not available until the content is
+    db.process_event(&blob_event.event).await;
+    assert!(db.get_blob(author, blob_id).await.is_none());
+
+    db.process_event_content(&blob_event).await;
+    let (content_hash, stored) = db.get_blob(author, blob_id).await.expect("Stored");
+    assert_eq!(stored, blob);
+    assert_eq!(content_hash, blob_event.content_hash());
+
+    // Only blobs of the given author
+    assert!(
+        db.get_blob(RostraIdSecretKey::generate().id(), blob_id)
+            .await
+            .is_none()
+    );
+
+    // Only blob events
+    let post = build_social_post_event(
+        id_secret,
+        blob_event.event_id(),
+        content_kind::SocialPost {
+            persona: PersonaId(0),
+            djot_content: Some("Look".into()),
+            reply_to: None,
+            reaction: None,
+            attachments: vec![ExternalEventId::new(author, blob_id)],
+            edit_of: None,
+            quote: None,
+            poll: None,
+            content_warning: None,
+        },
+    );
+    db.process_event_with_content(&post).await;
+    assert!(db.get_blob(author, post.event_id().into()).await.is_none());
+
+    Ok(())
+}
+
+#[test_log::test(tokio::test(flavor = "multi_thread"))]
+async fn test_get_blob_max_size() -> BoxedErrorResult<()> {
+    let id_secret = RostraIdSecretKey::generate();
+    let author = id_secret.id();
+    let (_dir, db) = temp_db(author).await?;
+
+    // Way over the limit of other content kinds
+    let blob = content_kind::Blob {
+        mime: "image/png".into(),
+        data: vec![7; content_kind::Blob::MAX_SIZE],
+    };
+    let content = blob.serialize_cbor()?;
+    let event = Event::builder()
+        .author(author)
+        .kind(EventKind::BLOB)
+        .content(&content)
+        .singleton(false)
+        .build();
+    let blob_event = VerifiedEventContent::verify(
+        VerifiedEvent::verify_signed(author, event.signed_by(id_secret))?,
+        content,
+    )?;
+
+    db.process_event_with_content(&blob_event).await;
+    let (_, stored) = db
+        .get_blob(author, blob_event.event_id().into())
+        .await
+        .expect("Stored");
+    assert_eq!(stored, blob);
+
+    Ok(())
+}
+
+#[test_log::test(tokio::test(flavor = "multi_thread"))]
+async fn test_post_edits() -> BoxedErrorResult<()> {
+    let id_secret = RostraIdSecretKey::generate();
+    let author = id_secret.id();
+    let (_dir, db) = temp_db(author).await?;
+
+    let post = build_social_post_event(
+        id_secret,
+        None,
+        content_kind::SocialPost {
+            persona: PersonaId(1),
+            djot_content: Some("Helo".into()),
+            reply_to: None,
+            reaction: None,
+            attachments: vec![],
+            edit_of: None,
+            quote: None,
+            poll: None,
+            content_warning: None,
+        },
+    );
+    let post_id: ShortEventId = post.event_id().into();
+    let reply = build_social_post_event(
+        id_secret,
+        post.event_id(),
+        content_kind::SocialPost {
+            persona: PersonaId(0),
+            djot_content: Some("Hi".into()),
+            reply_to: Some(ExternalEventId::new(author, post_id)),
+            reaction: None,
+            attachments: vec![],
+            edit_of: None,
+            quote: None,
+            poll: None,
+            content_warning: None,
+        },
+    );
+    let edit = build_social_post_event(
+        id_secret,
+        reply.event_id(),
+        content_kind::SocialPost {
+            persona: PersonaId(0),
+            djot_content: Some("Hello".into()),
+            reply_to: None,
+            reaction: None,
+            attachments: vec![],
+            edit_of: Some(post_id),
+            quote: None,
+            poll: None,
+            content_warning: None,
+        },
+    );
+    // Only the author can edit their posts
+    let impostor_edit = build_social_post_event(
+        RostraIdSecretKey::generate(),
+        None,
+        content_kind::SocialPost {
+            persona: PersonaId(0),
+            djot_content: Some("Hijacked".into()),
+            reply_to: None,
+            reaction: None,
+            attachments: vec![],
+            edit_of: Some(post_id),
+            quote: None,
+            poll: None,
+            content_warning: None,
+        },
+    );
+
+    for event_content in [&post, &reply, &edit, &impostor_edit] {
+        db.process_event_with_content(event_content).await;
+    }
+
+    let record = db
+        .get_social_post(post_id)
+        .await
+        .expect("Must have the post");
+    assert_eq!(record.content.djot_content.as_deref(), Some("Hello"));
+    assert_eq!(record.content.persona, PersonaId(1));
+    assert_eq!(record.content.edit_of, None);
+    assert_eq!(record.edited, Some(edit.timestamp()));
+    assert_eq!(record.reply_count, 1);
+
+    // Edits are not posts on their own
+    let (posts, _) = db.paginate_social_posts_rev(None, 10, |_| true).await;
+    assert_eq!(posts.len(), 2);
+    let record = posts
+        .iter()
+        .find(|post| post.event_id == post_id)
+        .expect("Must have the post");
+    assert_eq!(record.content.djot_content.as_deref(), Some("Hello"));
+
+    let versions = db.get_social_post_versions(author, post_id).await;
+    assert_eq!(
+        versions
+            .iter()
+            .map(|version| version.content.djot_content.as_deref())
+            .collect::<Vec<_>>(),
+        vec![Some("Helo"), Some("Hello")]
+    );
+
+    Ok(())
+}
+
+#[test_log::test(tokio::test(flavor = "multi_thread"))]
+async fn test_deletion_propagates_to_replicas() -> BoxedErrorResult<()> {
+    let id_secret = RostraIdSecretKey::generate();
+    let author = id_secret.id();
+    // Author's own node, and two nodes replicating the author
+    let (_dir_a, node_a) = temp_db(author).await?;
+    let (_dir_b, node_b) = temp_db_rng().await?;
+    let (_dir_c, node_c) = temp_db_rng().await?;
+
+    let post = build_social_post_event(
+        id_secret,
+        None,
+        content_kind::SocialPost {
+            persona: PersonaId(0),
+            djot_content: Some("Oops".into()),
+            reply_to: None,
+            reaction: None,
+            attachments: vec![],
+            edit_of: None,
+            quote: None,
+            poll: None,
+            content_warning: None,
+        },
+    );
+    let post_id: ShortEventId = post.event_id().into();
+
+    let content = content_kind::SocialPost {
+        persona: PersonaId(0),
+        djot_content: None,
+        reply_to: None,
+        reaction: None,
+        attachments: vec![],
+        edit_of: None,
+        quote: None,
+        poll: None,
+        content_warning: None,
+    }
+    .serialize_cbor()?;
+    let event = Event::builder()
+        .author(author)
+        .kind(EventKind::SOCIAL_POST)
+        .parent_prev(post_id)
+        .delete(post_id)
+        .content(&content)
+        .singleton(false)
+        .build();
+    let delete = VerifiedEventContent::verify(
+        VerifiedEvent::verify_signed(author, event.signed_by(id_secret))?,
+        content,
+    )?;
+
+    // `a` and `b` had the post before it got deleted
+    for node in [&node_a, &node_b] {
+        node.process_event_with_content(&post).await;
+        assert!(node.get_social_post(post_id).await.is_some());
+        node.process_event_with_content(&delete).await;
+    }
+
+    // `c` gets the deletion first, and must not accept the content afterwards
+    node_c.process_event_with_content(&delete).await;
+    let (_, process_state) = node_c.process_event(&post.event).await;
+    assert!(!node_c.wants_content(post_id, process_state).await);
+    node_c.process_event_content(&post).await;
+
+    for node in [&node_a, &node_b, &node_c] {
+        assert!(node.get_event_content(post_id).await.is_none());
+        assert!(node.is_event_content_deleted(post_id).await);
+        assert!(node.get_social_post(post_id).await.is_none());
+        // Neither the post nor its empty replacement are displayed
+        let (posts, _) = node.paginate_social_posts_rev(None, 10, |_| true).await;
+        assert!(posts.is_empty());
+    }
+
+    Ok(())
+}
+
+#[test_log::test(tokio::test(flavor = "multi_thread"))]
+async fn test_quote_posts() -> BoxedErrorResult<()> {
+    let id_secret = RostraIdSecretKey::generate();
+    let author = id_secret.id();
+    let (_dir, db) = temp_db(author).await?;
+
+    // Not followed by anyone we know
+    let quoted_id_secret = RostraIdSecretKey::generate();
+    let quoted_author = quoted_id_secret.id();
+
+    let quoted = build_social_post_event(
+        quoted_id_secret,
+        None,
+        content_kind::SocialPost {
+            persona: PersonaId(0),
+            djot_content: Some("Hot take".into()),
+            reply_to: None,
+            reaction: None,
+            attachments: vec![],
+            edit_of: None,
+            quote: None,
+            poll: None,
+            content_warning: None,
+        },
+    );
+    let quoted_id: ShortEventId = quoted.event_id().into();
+    let quote = build_social_post_event(
+        id_secret,
+        None,
+        content_kind::SocialPost {
+            persona: PersonaId(0),
+            djot_content: Some("Indeed".into()),
+            reply_to: None,
+            reaction: None,
+            attachments: vec![],
+            edit_of: None,
+            quote: Some(ExternalEventId::new(quoted_author, quoted_id)),
+            poll: None,
+            content_warning: None,
+        },
+    );
+
+    db.process_event_with_content(&quote).await;
+
+    assert_eq!(db.count_social_post_quotes(quoted_id).await, 1);
+    assert_eq!(
+        db.get_missing_events_for_id(quoted_author).await,
+        vec![quoted_id]
+    );
+    let report = db.check_integrity(false).await?;
+    assert!(report.is_ok(), "{report:?}");
+
+    db.process_event_with_content(&quoted).await;
+
+    assert!(db.get_missing_events_for_id(quoted_author).await.is_empty());
+    assert!(db.get_social_post(quoted_id).await.is_some());
+    assert_eq!(db.count_social_post_quotes(quoted_id).await, 1);
+
+    // Quoting an already known post does not make it missing
+    let quote_again = build_social_post_event(
+        id_secret,
+        quote.event_id(),
+        content_kind::SocialPost {
+            persona: PersonaId(0),
+            djot_content: Some("Still true".into()),
+            reply_to: None,
+            reaction: None,
+            attachments: vec![],
+            edit_of: None,
+            quote: Some(ExternalEventId::new(quoted_author, quoted_id)),
+            poll: None,
+            content_warning: None,
+        },
+    );
+    db.process_event_with_content(&quote_again).await;
+    assert!(db.get_missing_events_for_id(quoted_author).await.is_empty());
+    assert_eq!(db.count_social_post_quotes(quoted_id).await, 2);
+
+    Ok(())
+}
+
+#[test_log::test(tokio::test(flavor = "multi_thread"))]
+async fn test_quote_unfollowed_author_fetches_one_event() -> BoxedErrorResult<()> {
+    let id_secret = RostraIdSecretKey::generate();
+    let (_dir, db) = temp_db(id_secret.id()).await?;
+
+    let quoted_id_secret = RostraIdSecretKey::generate();
+    let quoted_author = quoted_id_secret.id();
+    let post = |text: &str| content_kind::SocialPost {
+        persona: PersonaId(0),
+        djot_content: Some(text.into()),
+        reply_to: None,
+        reaction: None,
+        attachments: vec![],
+        edit_of: None,
+        quote: None,
+        poll: None,
+        content_warning: None,
+    };
+
+    // The quoted post has history we don't have, and don't care about
+    let older = build_social_post_event(quoted_id_secret, None, post("Older"));
+    let quoted = build_social_post_event(quoted_id_secret, older.event_id(), post("Hot take"));
+    let quoted_id: ShortEventId = quoted.event_id().into();
+    let quote = build_social_post_event(
+        id_secret,
+        None,
+        content_kind::SocialPost {
+            quote: Some(ExternalEventId::new(quoted_author, quoted_id)),
+            ..post("Indeed")
+        },
+    );
+
+    db.process_event_with_content(&quote).await;
+    assert_eq!(
+        db.get_missing_events_for_id(quoted_author).await,
+        vec![quoted_id]
+    );
+
+    // Once fetched, nothing else of the author is wanted
+    db.process_event_with_content(&quoted).await;
+    assert!(db.get_social_post(quoted_id).await.is_some());
+    assert!(db.get_missing_events_for_id(quoted_author).await.is_empty());
+
+    Ok(())
+}
+
+#[test_log::test(tokio::test(flavor = "multi_thread"))]
+async fn test_social_tags() -> BoxedErrorResult<()> {
+    let id_secret = RostraIdSecretKey::generate();
+    let author = id_secret.id();
+    let (_dir, db) = temp_db(author).await?;
+
+    let first = build_social_post_event(
+        id_secret,
+        None,
+        content_kind::SocialPost {
+            persona: PersonaId(0),
+            djot_content: Some("Learning #Rust".into()),
+            reply_to: None,
+            reaction: None,
+            attachments: vec![],
+            edit_of: None,
+            quote: None,
+            poll: None,
+            content_warning: None,
+        },
+    );
+    let second = build_social_post_event(
+        id_secret,
+        first.event_id(),
+        content_kind::SocialPost {
+            persona: PersonaId(0),
+            djot_content: Some("#rust and #p2p".into()),
+            reply_to: None,
+            reaction: None,
+            attachments: vec![],
+            edit_of: None,
+            quote: None,
+            poll: None,
+            content_warning: None,
+        },
+    );
+    let first_id: ShortEventId = first.event_id().into();
+    let second_id: ShortEventId = second.event_id().into();
+
+    db.process_event_with_content(&first).await;
+    db.process_event_with_content(&second).await;
+
+    let (posts, cursor) = db
+        .paginate_social_posts_by_tag_rev("rust".into(), None, 1, |_| true)
+        .await;
+    assert_eq!(posts.len(), 1);
+    let cursor = cursor.expect("More posts");
+    let (more_posts, cursor) = db
+        .paginate_social_posts_by_tag_rev("rust".into(), Some(cursor), 1, |_| true)
+        .await;
+    assert_eq!(more_posts.len(), 1);
+    assert!(cursor.is_none());
+    let mut ids = vec![posts[0].event_id, more_posts[0].event_id];
+    ids.sort();
+    let mut expected = vec![first_id, second_id];
+    expected.sort();
+    assert_eq!(ids, expected);
+
+    let (posts, _) = db
+        .paginate_social_posts_by_tag_rev("p2p".into(), None, 10, |_| true)
+        .await;
+    assert_eq!(
+        posts.iter().map(|post| post.event_id).collect::<Vec<_>>(),
+        vec![second_id]
+    );
+
+    let (posts, _) = db
+        .paginate_social_posts_by_tag_rev("rust".into(), None, 10, move |post| {
+            post.author != author
+        })
+        .await;
+    assert!(posts.is_empty());
+
+    Ok(())
+}
+
+#[test_log::test(tokio::test(flavor = "multi_thread"))]
+async fn test_social_mentions() -> BoxedErrorResult<()> {
+    let id_secret = RostraIdSecretKey::generate();
+    let author = id_secret.id();
+    let (_dir, db) = temp_db(author).await?;
+
+    let mentioned = RostraIdSecretKey::generate().id();
+    let post = build_social_post_event(
+        id_secret,
+        None,
+        content_kind::SocialPost {
+            persona: PersonaId(0),
+            djot_content: Some(format!("Hi <rostra:{mentioned}>!")),
+            reply_to: None,
+            reaction: None,
+            attachments: vec![],
+            edit_of: None,
+            quote: None,
+            poll: None,
+            content_warning: None,
+        },
+    );
+    let post_id: ShortEventId = post.event_id().into();
+
+    db.process_event_with_content(&post).await;
+
+    assert_eq!(
+        db.get_social_mentions(mentioned).await,
+        [post_id].into_iter().collect()
+    );
+    assert!(db.get_social_mentions(author).await.is_empty());
+
+    let (posts, _) = db
+        .paginate_social_notifications_rev(mentioned, None, 10)
+        .await;
+    assert_eq!(
+        posts.iter().map(|post| post.event_id).collect::<Vec<_>>(),
+        vec![post_id]
+    );
+    // Own posts are not notifications, even if mentioning oneself
+    let (posts, _) = db.paginate_social_notifications(author, None, 10).await;
+    assert!(posts.is_empty());
+
+    Ok(())
+}
+
+fn build_poll_vote_event(
+    id_secret: RostraIdSecretKey,
+    parent: Option<ShortEventId>,
+    delete: Option<ShortEventId>,
+    ts: u64,
+    vote: content_kind::PollVote,
+) -> VerifiedEventContent {
+    let content = vote.serialize_cbor().expect("Valid content");
+    let author = id_secret.id();
+    let event = Event::builder()
+        .author(author)
+        .kind(EventKind::SOCIAL_POLL_VOTE)
+        .maybe_parent_prev(parent)
+        .maybe_delete(delete)
+        .timestamp(UNIX_EPOCH + Duration::from_secs(ts))
+        .content(&content)
+        .singleton(false)
+        .build();
+
+    VerifiedEventContent::verify(
+        VerifiedEvent::verify_signed(author, event.signed_by(id_secret)).expect("Valid event"),
+        content,
+    )
+    .expect("Valid content")
+}
+
+#[test_log::test(tokio::test(flavor = "multi_thread"))]
+async fn test_social_polls() -> BoxedErrorResult<()> {
+    let poll_id_secret = RostraIdSecretKey::generate();
+    let voter_id_secret = RostraIdSecretKey::generate();
+    let other_voter_id_secret = RostraIdSecretKey::generate();
+    let (_dir, db) = temp_db(voter_id_secret.id()).await?;
+
+    let content = content_kind::SocialPoll {
+        question: "Tabs or spaces?".into(),
+        options: vec!["Tabs".into(), "Spaces".into()],
+        closes_at: Timestamp::MAX,
+    }
+    .serialize_cbor()?;
+    let event = Event::builder()
+        .author(poll_id_secret.id())
+        .kind(EventKind::SOCIAL_POLL)
+        .content(&content)
+        .singleton(false)
+        .build();
+    let poll_event = VerifiedEventContent::verify(
+        VerifiedEvent::verify_signed(poll_id_secret.id(), event.signed_by(poll_id_secret))?,
+        content,
+    )?;
+    let poll_id: ShortEventId = poll_event.event_id().into();
+    let poll = ExternalEventId::new(poll_id_secret.id(), poll_id);
+
+    let vote_tabs = build_poll_vote_event(
+        voter_id_secret,
+        None,
+        None,
+        110,
+        content_kind::PollVote {
+            poll,
+            option: Some(0),
+        },
+    );
+    let vote_tabs_id: ShortEventId = vote_tabs.event_id().into();
+    let vote_spaces = build_poll_vote_event(
+        voter_id_secret,
+        Some(vote_tabs_id),
+        None,
+        120,
+        content_kind::PollVote {
+            poll,
+            option: Some(1),
+        },
+    );
+    let vote_spaces_id: ShortEventId = vote_spaces.event_id().into();
+    let other_vote = build_poll_vote_event(
+        other_voter_id_secret,
+        None,
+        None,
+        115,
+        content_kind::PollVote {
+            poll,
+            option: Some(0),
+        },
+    );
+
+    // Order of arrival does not matter, the latest vote counts
+    for event_content in [&poll_event, &vote_spaces, &vote_tabs, &other_vote] {
+        db.process_event_with_content(event_content).await;
+    }
+    let record = db
+        .get_social_poll(poll_id)
+        .await
+        .expect("Must have the poll");
+    assert_eq!(record.tally, vec![1, 1]);
+    assert_eq!(record.self_vote, Some((vote_spaces_id, 1)));
+
+    // Deleting the latest vote makes the previous one count again
+    let withdraw_spaces = build_poll_vote_event(
+        voter_id_secret,
+        Some(vote_spaces_id),
+        Some(vote_spaces_id),
+        130,
+        content_kind::PollVote { poll, option: None },
+    );
+    db.process_event_with_content(&withdraw_spaces).await;
+    let record = db
+        .get_social_poll(poll_id)
+        .await
+        .expect("Must have the poll");
+    assert_eq!(record.tally, vec![2, 0]);
+    assert_eq!(record.self_vote, Some((vote_tabs_id, 0)));
+
+    let withdraw_tabs = build_poll_vote_event(
+        voter_id_secret,
+        Some(withdraw_spaces.event_id().into()),
+        Some(vote_tabs_id),
+        140,
+        content_kind::PollVote { poll, option: None },
+    );
+    db.process_event_with_content(&withdraw_tabs).await;
+    let record = db
+        .get_social_poll(poll_id)
+        .await
+        .expect("Must have the poll");
+    assert_eq!(record.tally, vec![1, 0]);
+    assert_eq!(record.self_vote, None);
+
+    // The tally is derived, so must survive a reindex
+    db.reindex(|_| {}).await?;
+    let record = db
+        .get_social_poll(poll_id)
+        .await
+        .expect("Must have the poll");
+    assert_eq!(record.tally, vec![1, 0]);
+
+    Ok(())
+}
+
+#[test_log::test(tokio::test(flavor = "multi_thread"))]
+async fn test_social_polls_invalid_votes() -> BoxedErrorResult<()> {
+    let poll_id_secret = RostraIdSecretKey::generate();
+    let voter_id_secret = RostraIdSecretKey::generate();
+    let late_voter_id_secret = RostraIdSecretKey::generate();
+    let (_dir, db) = temp_db(voter_id_secret.id()).await?;
+
+    let content = content_kind::SocialPoll {
+        question: "Tabs or spaces?".into(),
+        options: vec!["Tabs".into(), "Spaces".into()],
+        closes_at: Timestamp(200),
+    }
+    .serialize_cbor()?;
+    let event = Event::builder()
+        .author(poll_id_secret.id())
+        .kind(EventKind::SOCIAL_POLL)
+        .timestamp(UNIX_EPOCH + Duration::from_secs(100))
+        .content(&content)
+        .singleton(false)
+        .build();
+    let poll_event = VerifiedEventContent::verify(
+        VerifiedEvent::verify_signed(poll_id_secret.id(), event.signed_by(poll_id_secret))?,
+        content,
+    )?;
+    let poll_id: ShortEventId = poll_event.event_id().into();
+    let poll = ExternalEventId::new(poll_id_secret.id(), poll_id);
+
+    let vote_tabs = build_poll_vote_event(
+        voter_id_secret,
+        None,
+        None,
+        110,
+        content_kind::PollVote {
+            poll,
+            option: Some(0),
+        },
+    );
+    let vote_tabs_id: ShortEventId = vote_tabs.event_id().into();
+    // Options the poll doesn't have are not counted
+    let vote_missing_option = build_poll_vote_event(
+        voter_id_secret,
+        Some(vote_tabs_id),
+        None,
+        120,
+        content_kind::PollVote {
+            poll,
+            option: Some(2),
+        },
+    );
+    // Changing the vote after the poll closed is not counted
+    let vote_spaces_late = build_poll_vote_event(
+        voter_id_secret,
+        Some(vote_missing_option.event_id().into()),
+        None,
+        200,
+        content_kind::PollVote {
+            poll,
+            option: Some(1),
+        },
+    );
+    let late_vote = build_poll_vote_event(
+        late_voter_id_secret,
+        None,
+        None,
+        300,
+        content_kind::PollVote {
+            poll,
+            option: Some(1),
+        },
+    );
+
+    for event_content in [
+        &poll_event,
+        &vote_tabs,
+        &vote_missing_option,
+        &vote_spaces_late,
+        &late_vote,
+    ] {
+        db.process_event_with_content(event_content).await;
+    }
+    let record = db
+        .get_social_poll(poll_id)
+        .await
+        .expect("Must have the poll");
+    assert_eq!(record.tally, vec![1, 0]);
+    assert_eq!(record.self_vote, Some((vote_tabs_id, 0)));
+
+    Ok(())
+}
+
+#[test_log::test(tokio::test(flavor = "multi_thread"))]
+async fn test_social_outbox() -> BoxedErrorResult<()> {
+    let (_dir, db) = temp_db_rng().await?;
+
+    let record = |publish_at: u64, djot_content: &str| SocialOutboxRecord {
+        publish_at: Timestamp(publish_at),
+        persona: PersonaId(0),
+        djot_content: djot_content.to_owned(),
+        reply_to: None,
+        attachments: vec![],
+        quote: None,
+        content_warning: None,
+        status: SocialOutboxStatus::Pending,
+    };
+
+    let outbox_rx = db.social_outbox_subscribe();
+    let later = db.insert_social_outbox(record(20, "later"), None).await?;
+    let sooner = db.insert_social_outbox(record(10, "sooner"), None).await?;
+    assert_ne!(later, sooner);
+    assert!(outbox_rx.has_changed()?);
+
+    let list = db.list_social_outbox().await?;
+    assert_eq!(
+        list.iter().map(|(id, _)| *id).collect::<Vec<_>>(),
+        vec![sooner, later]
+    );
+    assert_eq!(
+        db.get_social_outbox_next_publish_at().await?,
+        Some(Timestamp(10))
+    );
+    assert!(db.get_social_outbox_due(Timestamp(5)).await?.is_empty());
+    assert_eq!(db.get_social_outbox_due(Timestamp(15)).await?.len(), 1);
+
+    // Edit the pending entry
+    assert!(db.update_social_outbox(later, record(30, "edited")).await?);
+    let edited = db.get_social_outbox(later).await?.expect("Exists");
+    assert_eq!(edited.djot_content, "edited");
+    assert_eq!(edited.publish_at, Timestamp(30));
+
+    // Published entries can't be edited and are no longer due
+    let event_id = ShortEventId::from_bytes([1; 16]);
+    db.set_social_outbox_status(sooner, SocialOutboxStatus::Published(event_id))
+        .await?;
+    assert!(
+        !db.update_social_outbox(sooner, record(10, "too late"))
+            .await?
+    );
+    assert!(db.get_social_outbox_due(Timestamp(15)).await?.is_empty());
+    assert_eq!(
+        db.get_social_outbox_next_publish_at().await?,
+        Some(Timestamp(30))
+    );
+
+    // Cancel
+    assert!(db.remove_social_outbox(later).await?);
+    assert!(!db.remove_social_outbox(later).await?);
+    assert_eq!(db.get_social_outbox_next_publish_at().await?, None);
+    assert_eq!(db.list_social_outbox().await?.len(), 1);
+
+    Ok(())
+}
+
+#[test_log::test(tokio::test(flavor = "multi_thread"))]
+async fn test_social_outbox_claim() -> BoxedErrorResult<()> {
+    let (_dir, db) = temp_db_rng().await?;
+
+    let record = |djot_content: &str| SocialOutboxRecord {
+        publish_at: Timestamp(10),
+        persona: PersonaId(0),
+        djot_content: djot_content.to_owned(),
+        reply_to: None,
+        attachments: vec![],
+        quote: None,
+        content_warning: None,
+        status: SocialOutboxStatus::Pending,
+    };
+
+    let id = db.insert_social_outbox(record("original"), None).await?;
+
+    // Not due yet
+    assert!(db.claim_social_outbox(id, Timestamp(5)).await?.is_none());
+
+    // Edits made before claiming are what gets published
+    assert!(db.update_social_outbox(id, record("edited")).await?);
+    let claimed = db
+        .claim_social_outbox(id, Timestamp(15))
+        .await?
+        .expect("Claimed");
+    assert_eq!(claimed.djot_content, "edited");
+
+    // Claimed once only, and no longer editable
+    assert!(db.claim_social_outbox(id, Timestamp(15)).await?.is_none());
+    assert!(!db.update_social_outbox(id, record("too late")).await?);
+    assert!(db.get_social_outbox_due(Timestamp(15)).await?.is_empty());
+
+    // A publisher that never finished leaves it failed, not pending
+    db.fail_interrupted_social_outbox().await?;
+    assert!(matches!(
+        db.get_social_outbox(id).await?.expect("Exists").status,
+        SocialOutboxStatus::Failed(_)
+    ));
+
+    Ok(())
+}
+
+#[test_log::test(tokio::test(flavor = "multi_thread"))]
+async fn test_social_drafts() -> BoxedErrorResult<()> {
+    let (_dir, db) = temp_db_rng().await?;
+
+    let record = |updated: u64, djot_content: &str| SocialDraftRecord {
+        updated: Timestamp(updated),
+        djot_content: djot_content.to_owned(),
+        reply_to: None,
+        attachments: vec![],
+        quote: None,
+        content_warning: None,
+    };
+
+    let first = db.save_social_draft(None, record(10, "first")).await?;
+    let second = db.save_social_draft(None, record(20, "second")).await?;
+    assert_ne!(first, second);
+
+    // Autosaving the same draft again keeps its id
+    assert_eq!(
+        db.save_social_draft(Some(first), record(30, "first edited"))
+            .await?,
+        first
+    );
+    assert_eq!(
+        db.get_social_draft(first)
+            .await?
+            .expect("Exists")
+            .djot_content,
+        "first edited"
+    );
+
+    let list = db.list_social_drafts().await?;
+    assert_eq!(
+        list.iter().map(|(id, _)| *id).collect::<Vec<_>>(),
+        vec![first, second]
+    );
+
+    assert!(db.remove_social_draft(first).await?);
+    assert!(!db.remove_social_draft(first).await?);
+    assert!(db.get_social_draft(first).await?.is_none());
+    assert_eq!(db.list_social_drafts().await?.len(), 1);
+
+    Ok(())
+}
+
+#[test_log::test(tokio::test(flavor = "multi_thread"))]
+async fn test_social_draft_removed_after_posting() -> BoxedErrorResult<()> {
+    let (_dir, db) = temp_db_rng().await?;
+
+    let draft_id = db
+        .save_social_draft(
+            None,
+            SocialDraftRecord {
+                updated: Timestamp(10),
+                djot_content: "scheduled".to_owned(),
+                reply_to: None,
+                attachments: vec![],
+                quote: None,
+                content_warning: None,
+            },
+        )
+        .await?;
+    let other_id = db
+        .save_social_draft(
+            None,
+            SocialDraftRecord {
+                updated: Timestamp(20),
+                djot_content: "other".to_owned(),
+                reply_to: None,
+                attachments: vec![],
+                quote: None,
+                content_warning: None,
+            },
+        )
+        .await?;
+
+    let outbox_id = db
+        .insert_social_outbox(
+            SocialOutboxRecord {
+                publish_at: Timestamp(30),
+                persona: PersonaId(0),
+                djot_content: "scheduled".to_owned(),
+                reply_to: None,
+                attachments: vec![],
+                quote: None,
+                content_warning: None,
+                status: SocialOutboxStatus::Pending,
+            },
+            Some(draft_id),
+        )
+        .await?;
+
+    assert!(db.get_social_outbox(outbox_id).await?.is_some());
+    assert!(db.get_social_draft(draft_id).await?.is_none());
+    assert_eq!(
+        db.list_social_drafts()
+            .await?
+            .into_iter()
+            .map(|(id, _)| id)
+            .collect::<Vec<_>>(),
+        vec![other_id]
+    );
+
+    Ok(())
+}
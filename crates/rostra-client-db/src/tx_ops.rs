@@ -45,6 +45,31 @@ impl Database {
             .map_ok(|(k, v)| (k.value().1, v.value())))
     }
 
+    /// Is `id` the `self_id`, followed by it, or by one of its followees
+    pub fn is_self_or_followee_extended_tx(
+        self_id: RostraId,
+        id: RostraId,
+        ids_followees_table: &impl ids_followees::ReadableTable,
+    ) -> DbResult<bool> {
+        if id == self_id {
+            return Ok(true);
+        }
+        for followee in Database::read_followees_tx_iter(self_id, ids_followees_table)? {
+            let (followee, record) = followee?;
+            if record.selector.is_none() {
+                continue;
+            }
+            if followee == id
+                || ids_followees_table
+                    .get(&(followee, id))?
+                    .is_some_and(|r| r.value().selector.is_some())
+            {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
     pub fn read_followers_tx(
         id: RostraId,
         ids_followers_table: &impl ids_followers::ReadableTable,
@@ -22,7 +22,7 @@ use rostra_core::event::{
     VerifiedEventContent, content_kind,
 };
 use rostra_core::id::{RostraId, RostraIdSecretKey, ToShort as _};
-use rostra_core::{ExternalEventId, ShortEventId};
+use rostra_core::{ExternalEventId, ShortEventId, Timestamp};
 use rostra_p2p::connection::{Connection, FeedEventResponse};
 use rostra_p2p::{ConnectionSnafu, RpcError};
 use rostra_p2p_api::ROSTRA_P2P_V0_ALPN;
@@ -38,13 +38,16 @@ use crate::LOG_TARGET;
 use crate::error::{
     ActivateResult, ConnectIrohSnafu, ConnectResult, IdResolveError, IdResolveResult,
     IdSecretReadResult, InitIrohClientSnafu, InitPkarrClientSnafu, InitResult, InvalidIdSnafu,
-    IoSnafu, IrohResult, MissingTicketSnafu, ParsingSnafu, PeerUnavailableSnafu, PkarrResolveSnafu,
-    PostResult, RRecordSnafu, ResolveSnafu, SecretMismatchSnafu,
+    IoSnafu, IrohResult, MissingTicketSnafu, NotOwnPostSnafu, ParsingSnafu, PeerUnavailableSnafu,
+    PkarrResolveSnafu, PollClosedSnafu, PollNotFoundSnafu, PollOptionInvalidSnafu, PostResult,
+    RRecordSnafu, ResolveSnafu, SecretMismatchSnafu,
 };
 use crate::id::{CompactTicket, IdPublishedData, IdResolvedData};
+use crate::task::db_backup::{DbBackup, DbBackupOpts};
 use crate::task::head_merger::HeadMerger;
 use crate::task::missing_event_content_fetcher::MissingEventContentFetcher;
 use crate::task::missing_event_fetcher::MissingEventFetcher;
+use crate::task::outbox_publisher::OutboxPublisher;
 use crate::task::pkarr_id_publisher::PkarrIdPublisher;
 use crate::task::request_handler::RequestHandler;
 
@@ -150,6 +153,8 @@ impl Client {
         #[builder(start_fn)] id: RostraId,
         #[builder(default = true)] start_request_handler: bool,
         db: Option<Database>,
+        /// Periodically snapshot the `db` (only used with `db` set)
+        backup: Option<DbBackupOpts>,
     ) -> InitResult<Arc<Self>> {
         debug!(target: LOG_TARGET, id = %id, "Starting Rostra client");
         let is_mode_full = db.is_some();
@@ -195,6 +200,9 @@ impl Client {
             client.start_head_update_broadcaster();
             client.start_missing_event_fetcher();
             client.start_missing_event_content_fetcher();
+            if let Some(backup) = backup {
+                client.start_db_backup(backup);
+            }
         }
 
         trace!(target: LOG_TARGET, %id, "Client complete");
@@ -214,6 +222,7 @@ impl Client {
         if !self.active.swap(true, SeqCst) {
             self.start_pkarr_id_publisher(id_secret);
             self.start_head_merger(id_secret);
+            self.start_outbox_publisher(id_secret);
         }
 
         let db = &self.db;
@@ -375,6 +384,10 @@ impl Client {
         tokio::spawn(HeadMerger::new(self, secret_id).run());
     }
 
+    pub(crate) fn start_outbox_publisher(&self, secret_id: RostraIdSecretKey) {
+        tokio::spawn(OutboxPublisher::new(self, secret_id).run());
+    }
+
     pub(crate) fn start_request_handler(&self) {
         tokio::spawn(RequestHandler::new(self, self.endpoint.clone()).run());
     }
@@ -391,6 +404,9 @@ impl Client {
     pub(crate) fn start_missing_event_content_fetcher(&self) {
         tokio::spawn(MissingEventContentFetcher::new(self).run());
     }
+    pub(crate) fn start_db_backup(&self, opts: DbBackupOpts) {
+        tokio::spawn(DbBackup::new(self, opts).run());
+    }
 
     pub(crate) async fn iroh_address(&self) -> IrohResult<NodeAddr> {
         pub(crate) fn sanitize_node_addr(node_addr: NodeAddr) -> NodeAddr {
@@ -610,13 +626,18 @@ impl Client {
         body: String,
         reply_to: Option<ExternalEventId>,
         persona: PersonaId,
+        attachments: Vec<ExternalEventId>,
+        quote: Option<ExternalEventId>,
+        content_warning: Option<String>,
     ) -> PostResult<VerifiedEvent> {
-        let (content, reaction) =
-            if let Some(reaction) = content_kind::SocialPost::is_reaction(&reply_to, &body) {
-                (None, Some(reaction.to_owned()))
-            } else {
-                (Some(body), None)
-            };
+        let (content, reaction) = if let Some(reaction) =
+            content_kind::SocialPost::is_reaction(&reply_to, &body)
+                .filter(|_| attachments.is_empty() && quote.is_none() && content_warning.is_none())
+        {
+            (None, Some(reaction.to_owned()))
+        } else {
+            (Some(body), None)
+        };
         self.publish_event(
             id_secret,
             content_kind::SocialPost {
@@ -624,36 +645,183 @@ impl Client {
                 persona,
                 reply_to,
                 reaction,
+                attachments,
+                edit_of: None,
+                quote,
+                poll: None,
+                content_warning,
             },
         )
         .call()
         .await
     }
-    pub async fn post_social_profile_update(
+
+    /// Publish a new version of an own post
+    ///
+    /// The persona, the post replied to, the quoted post and the poll are kept
+    /// from the original.
+    pub async fn edit_social_post(
         &self,
         id_secret: RostraIdSecretKey,
-        display_name: String,
-        bio: String,
-        avatar: Option<(String, Vec<u8>)>,
+        original: ShortEventId,
+        body: String,
+        attachments: Vec<ExternalEventId>,
+        content_warning: Option<String>,
     ) -> PostResult<VerifiedEvent> {
-        let existing = self
+        let original = self
             .db
-            .get_social_profile(self.rostra_id())
+            .get_social_post(original)
             .await
-            .map(|r| r.event_id);
+            .filter(|post| post.author == id_secret.id() && post.content.edit_of.is_none())
+            .context(NotOwnPostSnafu)?;
+        self.publish_event(
+            id_secret,
+            content_kind::SocialPost {
+                djot_content: Some(body),
+                persona: original.content.persona,
+                reply_to: original.reply_to,
+                reaction: None,
+                attachments,
+                edit_of: Some(original.event_id),
+                quote: original.content.quote,
+                poll: original.content.poll,
+                content_warning,
+            },
+        )
+        .call()
+        .await
+    }
+
+    /// Delete an own post, along with all its edits
+    ///
+    /// The content is replaced with an empty post, so all well-behaved nodes
+    /// drop it.
+    pub async fn delete_social_post(
+        &self,
+        id_secret: RostraIdSecretKey,
+        event_id: ShortEventId,
+    ) -> PostResult<()> {
+        let versions = self
+            .db
+            .get_social_post_versions(id_secret.id(), event_id)
+            .await;
+        ensure!(
+            versions
+                .first()
+                .is_some_and(|original| original.content.edit_of.is_none()),
+            NotOwnPostSnafu
+        );
+        for version in versions {
+            self.publish_event(
+                id_secret,
+                content_kind::SocialPost {
+                    persona: version.content.persona,
+                    djot_content: None,
+                    reply_to: None,
+                    reaction: None,
+                    attachments: vec![],
+                    edit_of: None,
+                    quote: None,
+                    poll: None,
+                    content_warning: None,
+                },
+            )
+            .replace(version.event_id)
+            .call()
+            .await?;
+        }
+        Ok(())
+    }
+
+    /// Publish a poll, and a post it is attached to
+    pub async fn social_poll(
+        &self,
+        id_secret: RostraIdSecretKey,
+        body: Option<String>,
+        persona: PersonaId,
+        poll: content_kind::SocialPoll,
+        content_warning: Option<String>,
+    ) -> PostResult<VerifiedEvent> {
+        let poll_event = self.publish_event(id_secret, poll).call().await?;
         self.publish_event(
             id_secret,
-            content_kind::SocialProfileUpdate {
-                display_name,
-                bio,
-                avatar,
+            content_kind::SocialPost {
+                persona,
+                djot_content: body,
+                reply_to: None,
+                reaction: None,
+                attachments: vec![],
+                edit_of: None,
+                quote: None,
+                poll: Some(poll_event.event_id.to_short()),
+                content_warning,
             },
         )
-        .maybe_replace(existing)
         .call()
         .await
     }
 
+    /// Vote in a poll, replacing the previous own vote
+    ///
+    /// `None` withdraws the vote.
+    pub async fn vote_social_poll(
+        &self,
+        id_secret: RostraIdSecretKey,
+        poll: ExternalEventId,
+        option: Option<u8>,
+    ) -> PostResult<()> {
+        let record = self
+            .db
+            .get_social_poll(poll.event_id())
+            .await
+            .filter(|record| record.author == poll.rostra_id())
+            .context(PollNotFoundSnafu)?;
+        ensure!(!record.content.is_closed(Timestamp::now()), PollClosedSnafu);
+        if let Some(option) = option {
+            ensure!(
+                usize::from(option) < record.content.options.len(),
+                PollOptionInvalidSnafu
+            );
+        }
+        if option.is_none() && record.self_vote.is_none() {
+            return Ok(());
+        }
+
+        self.publish_event(id_secret, content_kind::PollVote { poll, option })
+            .maybe_replace(record.self_vote.map(|(event_id, _)| event_id))
+            .call()
+            .await?;
+        Ok(())
+    }
+
+    /// Publish `data` as a [`content_kind::Blob`], to be attached to posts
+    pub async fn publish_blob(
+        &self,
+        id_secret: RostraIdSecretKey,
+        mime: String,
+        data: Vec<u8>,
+    ) -> PostResult<VerifiedEvent> {
+        self.publish_event(id_secret, content_kind::Blob { mime, data })
+            .call()
+            .await
+    }
+
+    pub async fn post_social_profile_update(
+        &self,
+        id_secret: RostraIdSecretKey,
+        profile: content_kind::SocialProfileUpdate,
+    ) -> PostResult<VerifiedEvent> {
+        let existing = self
+            .db
+            .get_social_profile(self.rostra_id())
+            .await
+            .map(|r| r.event_id);
+        self.publish_event(id_secret, profile)
+            .maybe_replace(existing)
+            .call()
+            .await
+    }
+
     pub async fn follow(
         &self,
         id_secret: RostraIdSecretKey,
@@ -775,3 +943,6 @@ impl Client {
         self.db.self_followers_subscribe()
     }
 }
+
+#[cfg(test)]
+mod tests;
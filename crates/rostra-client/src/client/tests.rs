@@ -0,0 +1,51 @@
+use rostra_core::ShortEventId;
+use rostra_core::event::PersonaId;
+use rostra_core::id::RostraIdSecretKey;
+use rostra_p2p::connection::Connection;
+use rostra_p2p_api::ROSTRA_P2P_V0_ALPN;
+use rostra_util_error::BoxedErrorResult;
+
+use super::Client;
+
+/// Connect `from` directly to the endpoint of `to`, without any discovery
+async fn connect_direct(from: &Client, to: &Client) -> BoxedErrorResult<Connection> {
+    let node_addr = to.endpoint.node_addr().await?;
+    Ok(from
+        .endpoint
+        .connect(node_addr, ROSTRA_P2P_V0_ALPN)
+        .await?
+        .into())
+}
+
+#[test_log::test(tokio::test(flavor = "multi_thread"))]
+async fn test_get_event_content_deleted() -> BoxedErrorResult<()> {
+    let id_secret = RostraIdSecretKey::generate();
+    let author = Client::builder(id_secret.id()).build().await?;
+    let peer = Client::builder(RostraIdSecretKey::generate().id())
+        .start_request_handler(false)
+        .build()
+        .await?;
+
+    let post = author
+        .social_post(
+            id_secret,
+            "Oops".into(),
+            None,
+            PersonaId(0),
+            vec![],
+            None,
+            None,
+        )
+        .await?;
+    let post_id = ShortEventId::from(post.event_id);
+    let conn = connect_direct(&peer, &author).await?;
+    assert!(conn.get_event_content(post).await?.is_some());
+
+    // The author refuses to serve it with an explicit code, which the peer
+    // handles like missing content, instead of failing
+    author.delete_social_post(id_secret, post_id).await?;
+    assert!(author.db().is_event_content_deleted(post_id).await);
+    assert!(conn.get_event_content(post).await?.is_none());
+
+    Ok(())
+}
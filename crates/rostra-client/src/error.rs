@@ -98,6 +98,18 @@ pub enum PostError {
     Encode { source: BoxedError },
     #[snafu(transparent)]
     Validation { source: ContentValidationError },
+    #[snafu(display("Post not found among own posts"))]
+    #[snafu(visibility(pub))]
+    NotOwnPost,
+    #[snafu(display("Poll not found"))]
+    #[snafu(visibility(pub))]
+    PollNotFound,
+    #[snafu(display("Poll is closed"))]
+    #[snafu(visibility(pub))]
+    PollClosed,
+    #[snafu(display("Poll has no such option"))]
+    #[snafu(visibility(pub))]
+    PollOptionInvalid,
 }
 
 pub type PostResult<T> = std::result::Result<T, PostError>;
@@ -0,0 +1,299 @@
+//! Passphrase protected keystore files
+//!
+//! A keystore holds the [`RostraIdSecretKey`] of an identity encrypted with a
+//! key derived from a passphrase with Argon2id, so the mnemonic doesn't have
+//! to be kept in a plaintext file or retyped on every unlock.
+
+use std::io;
+use std::path::{Path, PathBuf};
+use std::str::FromStr as _;
+
+use chacha20poly1305::aead::{Aead as _, KeyInit as _, Payload};
+use chacha20poly1305::{Key, XChaCha20Poly1305, XNonce};
+use data_encoding::HEXLOWER;
+use rand::Rng as _;
+use rostra_core::id::{RostraId, RostraIdSecretKey};
+use serde::{Deserialize, Serialize};
+use snafu::{OptionExt as _, ResultExt as _, Snafu, ensure};
+
+const KEYSTORE_DIR: &str = "keystore";
+const KEYSTORE_EXT: &str = "json";
+const KEYSTORE_VERSION: u32 = 1;
+
+#[derive(Debug, Snafu)]
+pub enum KeystoreError {
+    #[snafu(display("IO error: {source}"))]
+    Io { source: io::Error },
+    #[snafu(display("Invalid keystore format: {source}"))]
+    Format { source: serde_json::Error },
+    #[snafu(display("Invalid keystore field: {field}"))]
+    InvalidField { field: &'static str },
+    #[snafu(display("Unsupported keystore version: {version}"))]
+    UnsupportedVersion { version: u32 },
+    #[snafu(display("Key derivation failed: {msg}"))]
+    Kdf { msg: String },
+    #[snafu(display("Encryption failed"))]
+    Encrypt,
+    #[snafu(display("Wrong passphrase"))]
+    WrongPassphrase,
+    #[snafu(display("Keystore does not match its identity"))]
+    IdMismatch,
+}
+
+pub type KeystoreResult<T> = std::result::Result<T, KeystoreError>;
+
+/// Argon2id parameters
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct KdfParams {
+    /// Memory size in KiB
+    pub m_cost: u32,
+    pub t_cost: u32,
+    pub p_cost: u32,
+}
+
+impl KdfParams {
+    /// 1 GiB
+    const MAX_M_COST: u32 = 1024 * 1024;
+    const MAX_T_COST: u32 = 16;
+    const MAX_P_COST: u32 = 16;
+
+    /// Within bounds that can't make unlocking take forever or exhaust memory
+    ///
+    /// Keystore files are not trusted to be sane.
+    pub fn is_sane(&self) -> bool {
+        (1..=Self::MAX_P_COST).contains(&self.p_cost)
+            && (1..=Self::MAX_T_COST).contains(&self.t_cost)
+            && (8 * self.p_cost..=Self::MAX_M_COST).contains(&self.m_cost)
+    }
+}
+
+impl Default for KdfParams {
+    fn default() -> Self {
+        // OWASP recommended minimum for Argon2id
+        Self {
+            m_cost: 19 * 1024,
+            t_cost: 2,
+            p_cost: 1,
+        }
+    }
+}
+
+/// On-disk format, with binary fields hex encoded
+#[derive(Serialize, Deserialize)]
+struct KeystoreFile {
+    version: u32,
+    rostra_id: RostraId,
+    kdf: KdfParams,
+    salt: String,
+    nonce: String,
+    ciphertext: String,
+}
+
+/// Encrypted [`RostraIdSecretKey`]
+#[derive(Debug, Clone)]
+pub struct Keystore {
+    rostra_id: RostraId,
+    kdf: KdfParams,
+    salt: [u8; 16],
+    nonce: [u8; 24],
+    ciphertext: Vec<u8>,
+}
+
+impl Keystore {
+    /// Encrypt `id_secret` with `passphrase`
+    ///
+    /// Key derivation is deliberately slow, so avoid calling it on an async
+    /// executor thread.
+    pub fn encrypt(
+        id_secret: RostraIdSecretKey,
+        passphrase: &str,
+        kdf: KdfParams,
+    ) -> KeystoreResult<Self> {
+        ensure!(kdf.is_sane(), InvalidFieldSnafu { field: "kdf" });
+        let rostra_id = id_secret.id();
+        let salt: [u8; 16] = rand::thread_rng().r#gen();
+        let nonce: [u8; 24] = rand::thread_rng().r#gen();
+        let key = derive_key(passphrase, &salt, kdf)?;
+
+        let ciphertext = XChaCha20Poly1305::new(Key::from_slice(&key))
+            .encrypt(
+                XNonce::from_slice(&nonce),
+                Payload {
+                    msg: &id_secret.to_bytes(),
+                    aad: rostra_id.to_string().as_bytes(),
+                },
+            )
+            .map_err(|_| EncryptSnafu.build())?;
+
+        Ok(Self {
+            rostra_id,
+            kdf,
+            salt,
+            nonce,
+            ciphertext,
+        })
+    }
+
+    /// Decrypt the secret with `passphrase`
+    ///
+    /// See [`Self::encrypt`] about blocking.
+    pub fn decrypt(&self, passphrase: &str) -> KeystoreResult<RostraIdSecretKey> {
+        let key = derive_key(passphrase, &self.salt, self.kdf)?;
+
+        let plaintext = XChaCha20Poly1305::new(Key::from_slice(&key))
+            .decrypt(
+                XNonce::from_slice(&self.nonce),
+                Payload {
+                    msg: &self.ciphertext,
+                    aad: self.rostra_id.to_string().as_bytes(),
+                },
+            )
+            .map_err(|_| WrongPassphraseSnafu.build())?;
+        let id_secret =
+            RostraIdSecretKey::from_bytes(<[u8; 32]>::try_from(plaintext).ok().context(
+                InvalidFieldSnafu {
+                    field: "ciphertext",
+                },
+            )?);
+        ensure!(id_secret.id() == self.rostra_id, IdMismatchSnafu);
+        Ok(id_secret)
+    }
+
+    pub fn rostra_id(&self) -> RostraId {
+        self.rostra_id
+    }
+
+    /// Path of the keystore of `rostra_id` in `data_dir`
+    pub fn path(data_dir: &Path, rostra_id: RostraId) -> PathBuf {
+        data_dir
+            .join(KEYSTORE_DIR)
+            .join(format!("{rostra_id}.{KEYSTORE_EXT}"))
+    }
+
+    /// Identities that have a keystore in `data_dir`
+    pub async fn list(data_dir: &Path) -> KeystoreResult<Vec<RostraId>> {
+        let mut entries = match tokio::fs::read_dir(data_dir.join(KEYSTORE_DIR)).await {
+            Ok(entries) => entries,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(vec![]),
+            Err(source) => return Err(source).context(IoSnafu),
+        };
+
+        let mut ids = vec![];
+        while let Some(entry) = entries.next_entry().await.context(IoSnafu)? {
+            let path = entry.path();
+            if path.extension().and_then(|ext| ext.to_str()) != Some(KEYSTORE_EXT) {
+                continue;
+            }
+            if let Some(id) = path
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .and_then(|stem| RostraId::from_str(stem).ok())
+            {
+                ids.push(id);
+            }
+        }
+        ids.sort();
+        Ok(ids)
+    }
+
+    pub async fn read(path: &Path) -> KeystoreResult<Self> {
+        let content = tokio::fs::read(path).await.context(IoSnafu)?;
+        Self::decode(&content)
+    }
+
+    /// Write the keystore, replacing any existing one atomically
+    pub async fn write(&self, path: &Path) -> KeystoreResult<()> {
+        let content = self.encode();
+        let path = path.to_owned();
+        tokio::task::spawn_blocking(move || write_private_file(&path, &content))
+            .await
+            .expect("Keystore write panicked")
+            .context(IoSnafu)
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        serde_json::to_vec_pretty(&KeystoreFile {
+            version: KEYSTORE_VERSION,
+            rostra_id: self.rostra_id,
+            kdf: self.kdf,
+            salt: HEXLOWER.encode(&self.salt),
+            nonce: HEXLOWER.encode(&self.nonce),
+            ciphertext: HEXLOWER.encode(&self.ciphertext),
+        })
+        .expect("Can't fail")
+    }
+
+    pub fn decode(content: &[u8]) -> KeystoreResult<Self> {
+        let file: KeystoreFile = serde_json::from_slice(content).context(FormatSnafu)?;
+        ensure!(
+            file.version == KEYSTORE_VERSION,
+            UnsupportedVersionSnafu {
+                version: file.version
+            }
+        );
+
+        ensure!(file.kdf.is_sane(), InvalidFieldSnafu { field: "kdf" });
+
+        Ok(Self {
+            rostra_id: file.rostra_id,
+            kdf: file.kdf,
+            salt: decode_hex(&file.salt, "salt")?,
+            nonce: decode_hex(&file.nonce, "nonce")?,
+            ciphertext: HEXLOWER.decode(file.ciphertext.as_bytes()).ok().context(
+                InvalidFieldSnafu {
+                    field: "ciphertext",
+                },
+            )?,
+        })
+    }
+}
+
+fn decode_hex<const N: usize>(s: &str, field: &'static str) -> KeystoreResult<[u8; N]> {
+    HEXLOWER
+        .decode(s.as_bytes())
+        .ok()
+        .and_then(|bytes| <[u8; N]>::try_from(bytes).ok())
+        .context(InvalidFieldSnafu { field })
+}
+
+fn derive_key(passphrase: &str, salt: &[u8], kdf: KdfParams) -> KeystoreResult<[u8; 32]> {
+    let params =
+        argon2::Params::new(kdf.m_cost, kdf.t_cost, kdf.p_cost, Some(32)).map_err(|err| {
+            KdfSnafu {
+                msg: err.to_string(),
+            }
+            .build()
+        })?;
+    let mut key = [0u8; 32];
+    argon2::Argon2::new(argon2::Algorithm::Argon2id, argon2::Version::V0x13, params)
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|err| {
+            KdfSnafu {
+                msg: err.to_string(),
+            }
+            .build()
+        })?;
+    Ok(key)
+}
+
+/// Write a file readable only by the owner, via a temporary file and rename
+fn write_private_file(path: &Path, content: &[u8]) -> io::Result<()> {
+    use std::io::Write as _;
+
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let tmp_path = path.with_extension("tmp");
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+
+    let mut file = options.open(&tmp_path)?;
+    file.write_all(content)?;
+    file.sync_all()?;
+    std::fs::rename(&tmp_path, path)
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,81 @@
+use rostra_core::id::RostraIdSecretKey;
+
+use super::{KdfParams, Keystore, KeystoreError};
+
+/// Cheap parameters, to keep tests fast
+const TEST_KDF: KdfParams = KdfParams {
+    m_cost: 64,
+    t_cost: 1,
+    p_cost: 1,
+};
+
+#[test]
+fn keystore_roundtrip() {
+    let id_secret = RostraIdSecretKey::generate();
+    let keystore = Keystore::encrypt(id_secret, "correct horse", TEST_KDF).unwrap();
+
+    let decoded = Keystore::decode(&keystore.encode()).unwrap();
+    assert_eq!(decoded.rostra_id(), id_secret.id());
+    assert_eq!(decoded.decrypt("correct horse").unwrap(), id_secret);
+    assert!(matches!(
+        decoded.decrypt("wrong horse"),
+        Err(KeystoreError::WrongPassphrase)
+    ));
+}
+
+#[test]
+fn keystore_rejects_swapped_identity() {
+    let id_secret = RostraIdSecretKey::generate();
+    let mut keystore = Keystore::encrypt(id_secret, "pass", TEST_KDF).unwrap();
+    keystore.rostra_id = RostraIdSecretKey::generate().id();
+
+    assert!(keystore.decrypt("pass").is_err());
+}
+
+#[test]
+fn keystore_rejects_insane_kdf_params() {
+    let id_secret = RostraIdSecretKey::generate();
+    let keystore = Keystore::encrypt(id_secret, "pass", TEST_KDF).unwrap();
+
+    for kdf in [
+        KdfParams {
+            m_cost: u32::MAX,
+            ..TEST_KDF
+        },
+        KdfParams {
+            t_cost: 1_000_000,
+            ..TEST_KDF
+        },
+        KdfParams {
+            p_cost: 0,
+            ..TEST_KDF
+        },
+    ] {
+        assert!(Keystore::encrypt(id_secret, "pass", kdf).is_err());
+
+        let mut tampered = keystore.clone();
+        tampered.kdf = kdf;
+        assert!(matches!(
+            Keystore::decode(&tampered.encode()),
+            Err(KeystoreError::InvalidField { field: "kdf" })
+        ));
+    }
+}
+
+#[tokio::test]
+async fn keystore_list() {
+    let dir = tempfile::tempdir().unwrap();
+    assert!(Keystore::list(dir.path()).await.unwrap().is_empty());
+
+    let id_secret = RostraIdSecretKey::generate();
+    let keystore = Keystore::encrypt(id_secret, "pass", TEST_KDF).unwrap();
+    keystore
+        .write(&Keystore::path(dir.path(), id_secret.id()))
+        .await
+        .unwrap();
+
+    assert_eq!(
+        Keystore::list(dir.path()).await.unwrap(),
+        vec![id_secret.id()]
+    );
+}
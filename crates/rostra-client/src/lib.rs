@@ -6,6 +6,8 @@ pub mod multiclient;
 
 pub mod id;
 
+pub mod keystore;
+
 use std::str::FromStr;
 
 use error::{
@@ -23,6 +25,7 @@ const LOG_TARGET: &str = "rostra";
 
 mod client;
 pub use crate::client::*;
+pub use crate::task::db_backup::DbBackupOpts;
 
 fn get_rrecord_typed<T>(
     packet: &pkarr::SignedPacket,
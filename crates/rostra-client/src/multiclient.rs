@@ -3,7 +3,7 @@ use std::io;
 use std::path::PathBuf;
 use std::sync::Arc;
 
-use rostra_client_db::{Database, DbError};
+use rostra_client_db::{ApiToken, Database, DbError, VerifiedApiToken};
 use rostra_core::id::RostraId;
 use rostra_util_error::FmtCompact as _;
 use snafu::{ResultExt as _, Snafu};
@@ -11,7 +11,7 @@ use tokio::sync::RwLock;
 use tracing::warn;
 
 use crate::error::InitError;
-use crate::{Client, ClientHandle, LOG_TARGET};
+use crate::{Client, ClientHandle, DbBackupOpts, LOG_TARGET};
 
 #[derive(Debug, Snafu)]
 pub enum MultiClientError {
@@ -30,6 +30,7 @@ pub enum MultiClientError {
 pub type MultiClientResult<T> = std::result::Result<T, MultiClientError>;
 pub struct MultiClient {
     data_dir: PathBuf,
+    backup: Option<DbBackupOpts>,
     inner: tokio::sync::RwLock<HashMap<RostraId, Arc<Client>>>,
 }
 
@@ -37,9 +38,16 @@ impl MultiClient {
     pub fn new(data_dir: PathBuf) -> Self {
         Self {
             data_dir,
+            backup: None,
             inner: RwLock::new(Default::default()),
         }
     }
+
+    /// Periodically snapshot databases of all loaded clients
+    pub fn with_backup(mut self, backup: DbBackupOpts) -> Self {
+        self.backup = Some(backup);
+        self
+    }
 }
 
 impl MultiClient {
@@ -66,6 +74,7 @@ impl MultiClient {
         }
         let client = Client::builder(id)
             .db(db)
+            .maybe_backup(self.backup.clone())
             .build()
             .await
             .context(ClientInitSnafu)?;
@@ -74,7 +83,52 @@ impl MultiClient {
         Ok(client)
     }
 
+    /// Like [`Self::load`], but only if the database of `id` already exists
+    pub async fn load_existing(&self, id: RostraId) -> MultiClientResult<Option<Arc<Client>>> {
+        if !Database::mk_db_path(&self.data_dir, id).await?.exists() {
+            return Ok(None);
+        }
+        self.load(id).await.map(Some)
+    }
+
+    /// Look up an [`ApiToken`] in the database of the identity it belongs to
+    ///
+    /// Does not start a client: if none is loaded yet, the database is only
+    /// peeked at, and only if it already exists.
+    pub async fn verify_api_token(
+        &self,
+        token: &ApiToken,
+    ) -> MultiClientResult<Option<VerifiedApiToken>> {
+        // Held, so the database is not opened by `load` in the meantime
+        let read = self.inner.read().await;
+        if let Some(client) = read.get(&token.rostra_id()) {
+            return client
+                .db()
+                .verify_api_token(token)
+                .await
+                .context(DatabaseSnafu);
+        }
+
+        let db_path = Database::mk_db_path(&self.data_dir, token.rostra_id()).await?;
+        if !db_path.exists() {
+            return Ok(None);
+        }
+        Database::verify_api_token_at(&db_path, token)
+            .await
+            .context(DatabaseSnafu)
+    }
+
     pub async fn get(&self, id: RostraId) -> Option<ClientHandle> {
         self.inner.read().await.get(&id).map(|c| c.handle())
     }
+
+    /// Handles of all loaded clients
+    pub async fn all(&self) -> Vec<ClientHandle> {
+        self.inner
+            .read()
+            .await
+            .values()
+            .map(|c| c.handle())
+            .collect()
+    }
 }
@@ -1,8 +1,10 @@
 pub(crate) mod connection_cache;
+pub(crate) mod db_backup;
 pub(crate) mod followee_head_checker;
 pub(crate) mod head_merger;
 pub(crate) mod head_update_broadcaster;
 pub(crate) mod missing_event_content_fetcher;
 pub(crate) mod missing_event_fetcher;
+pub(crate) mod outbox_publisher;
 pub(crate) mod pkarr_id_publisher;
 pub(crate) mod request_handler;
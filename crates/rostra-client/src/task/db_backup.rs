@@ -0,0 +1,108 @@
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use rostra_core::id::RostraId;
+use rostra_util_error::FmtCompact as _;
+use tracing::{debug, info, instrument, warn};
+
+use crate::LOG_TARGET;
+use crate::client::Client;
+
+/// Settings of the periodic database snapshots
+#[derive(Debug, Clone)]
+pub struct DbBackupOpts {
+    /// Directory to keep the snapshots in
+    pub dir: PathBuf,
+    pub interval: Duration,
+    /// Number of most recent snapshots to keep
+    pub keep: NonZeroUsize,
+}
+
+pub struct DbBackup {
+    client: crate::client::ClientHandle,
+    self_id: RostraId,
+    opts: DbBackupOpts,
+}
+
+impl DbBackup {
+    pub fn new(client: &Client, opts: DbBackupOpts) -> Self {
+        debug!(target: LOG_TARGET, "Starting database backup task" );
+        Self {
+            client: client.handle(),
+            self_id: client.rostra_id(),
+            opts,
+        }
+    }
+
+    /// Run the thread
+    #[instrument(name = "db-backup", skip(self), ret)]
+    pub async fn run(self) {
+        let mut interval = tokio::time::interval(self.opts.interval);
+        // First tick completes immediately, and we don't want to snapshot right on
+        // start
+        interval.tick().await;
+
+        loop {
+            interval.tick().await;
+
+            let Ok(db) = self.client.db() else {
+                break;
+            };
+
+            if let Err(err) = tokio::fs::create_dir_all(&self.opts.dir).await {
+                warn!(target: LOG_TARGET, err = %err.fmt_compact(), "Failed to create backup dir");
+                continue;
+            }
+
+            let now = SystemTime::now()
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .expect("Time before epoch")
+                .as_secs();
+            let path = self
+                .opts
+                .dir
+                .join(format!("{}-{now:020}.redb", self.self_id));
+
+            if let Err(err) = db.snapshot_to(&path).await {
+                warn!(target: LOG_TARGET, err = %err.fmt_compact(), path = %path.display(), "Failed to write database snapshot");
+                continue;
+            }
+
+            if let Err(err) = self.remove_old_snapshots().await {
+                warn!(target: LOG_TARGET, err = %err.fmt_compact(), "Failed to remove old snapshots");
+            }
+        }
+    }
+
+    /// Remove all but the `keep` newest snapshots of `self_id`
+    async fn remove_old_snapshots(&self) -> std::io::Result<()> {
+        let mut snapshots = list_snapshots(&self.opts.dir, self.self_id).await?;
+        // Names embed a zero-padded timestamp, so they sort chronologically
+        snapshots.sort();
+
+        let num_to_remove = snapshots.len().saturating_sub(self.opts.keep.get());
+        for path in &snapshots[..num_to_remove] {
+            info!(target: LOG_TARGET, path = %path.display(), "Removing old database snapshot");
+            tokio::fs::remove_file(path).await?;
+        }
+        Ok(())
+    }
+}
+
+async fn list_snapshots(dir: &Path, self_id: RostraId) -> std::io::Result<Vec<PathBuf>> {
+    let prefix = format!("{self_id}-");
+    let mut snapshots = vec![];
+
+    let mut entries = tokio::fs::read_dir(dir).await?;
+    while let Some(entry) = entries.next_entry().await? {
+        let name = entry.file_name();
+        let Some(name) = name.to_str() else {
+            continue;
+        };
+        if name.starts_with(&prefix) && name.ends_with(".redb") {
+            snapshots.push(entry.path());
+        }
+    }
+    Ok(snapshots)
+}
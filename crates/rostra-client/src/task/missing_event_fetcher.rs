@@ -76,7 +76,9 @@ impl MissingEventFetcher {
 
             let mut connections = ConnectionCache::new();
 
-            for follower_id in followers.iter().chain([self.self_id].iter()) {
+            // The author itself too, as quoted posts can come from ids nobody we know
+            // follows
+            for follower_id in followers.iter().chain([author_id, self.self_id].iter()) {
                 let Ok(client) = self.client.client_ref().boxed() else {
                     break;
                 };
@@ -0,0 +1,126 @@
+use std::time::Duration;
+
+use rostra_client_db::{SocialOutboxRecord, SocialOutboxStatus};
+use rostra_core::Timestamp;
+use rostra_core::id::RostraIdSecretKey;
+use rostra_util_error::FmtCompact as _;
+use tokio::sync::watch;
+use tracing::{debug, instrument, trace, warn};
+
+use crate::client::Client;
+const LOG_TARGET: &str = "rostra::outbox_publisher";
+
+/// Signs and publishes scheduled posts from the outbox once they are due
+pub struct OutboxPublisher {
+    client: crate::client::ClientHandle,
+    outbox_rx: watch::Receiver<()>,
+    id_secret: RostraIdSecretKey,
+}
+
+impl OutboxPublisher {
+    pub fn new(client: &Client, id_secret: RostraIdSecretKey) -> Self {
+        debug!(target: LOG_TARGET, "Starting outbox publishing task" );
+        Self {
+            client: client.handle(),
+            outbox_rx: client.db().social_outbox_subscribe(),
+            id_secret,
+        }
+    }
+
+    /// Run the thread
+    #[instrument(skip(self), ret)]
+    pub async fn run(mut self) {
+        if let Ok(db) = self.client.db() {
+            if let Err(err) = db.fail_interrupted_social_outbox().await {
+                warn!(target: LOG_TARGET, err = %err.fmt_compact(), "Failed to clean up interrupted outbox entries");
+            }
+        }
+
+        loop {
+            trace!(target: LOG_TARGET, "Woke up" );
+            self.outbox_rx.mark_unchanged();
+
+            let Ok(db) = self.client.db() else {
+                break;
+            };
+
+            let due = match db.get_social_outbox_due(Timestamp::now()).await {
+                Ok(due) => due,
+                Err(err) => {
+                    warn!(target: LOG_TARGET, err = %err.fmt_compact(), "Failed to load due outbox entries");
+                    Vec::new()
+                }
+            };
+
+            for (id, _) in due {
+                // Re-read and claim it, so edits and cancellations made since
+                // listing are not lost, and it's never published twice
+                let record = match db.claim_social_outbox(id, Timestamp::now()).await {
+                    Ok(Some(record)) => record,
+                    Ok(None) => continue,
+                    Err(err) => {
+                        warn!(target: LOG_TARGET, err = %err.fmt_compact(), %id, "Failed to claim outbox entry");
+                        continue;
+                    }
+                };
+                let status = match self.publish(record).await {
+                    Ok(status) => status,
+                    Err(()) => return,
+                };
+                if let Err(err) = db.set_social_outbox_status(id, status).await {
+                    warn!(target: LOG_TARGET, err = %err.fmt_compact(), %id, "Failed to update outbox entry status");
+                }
+            }
+            // Setting the statuses notified us about our own changes
+            self.outbox_rx.mark_unchanged();
+
+            let next_publish_at = db.get_social_outbox_next_publish_at().await.ok().flatten();
+            drop(db);
+
+            let sleep = match next_publish_at {
+                Some(publish_at) => {
+                    Duration::from_secs(publish_at.0.saturating_sub(Timestamp::now().0))
+                }
+                None => Duration::MAX,
+            };
+
+            tokio::select! {
+                res = self.outbox_rx.changed() => {
+                    if res.is_err() {
+                        break;
+                    }
+                }
+                _ = tokio::time::sleep(sleep) => {}
+            }
+        }
+    }
+
+    /// Publish a single entry, returning `Err` only if the client is gone
+    async fn publish(&self, record: SocialOutboxRecord) -> Result<SocialOutboxStatus, ()> {
+        let client = self.client.client_ref().map_err(|_| ())?;
+
+        Ok(
+            match client
+                .social_post(
+                    self.id_secret,
+                    record.djot_content,
+                    record.reply_to,
+                    record.persona,
+                    record.attachments,
+                    record.quote,
+                    record.content_warning,
+                )
+                .await
+            {
+                Ok(event) => {
+                    debug!(target: LOG_TARGET, event_id = %event.event_id, "Published scheduled post");
+                    SocialOutboxStatus::Published(event.event_id.into())
+                }
+                Err(err) => {
+                    warn!(target: LOG_TARGET, err = %err.fmt_compact(), "Failed to publish scheduled post");
+                    SocialOutboxStatus::Failed(err.fmt_compact().to_string())
+                }
+            },
+        )
+    }
+}
@@ -298,6 +298,13 @@ impl RequestHandler {
 
         let content = db.get_event_content(event_id).await;
 
+        if content.is_none() && db.is_event_content_deleted(event_id).await {
+            Connection::write_return_code(&mut send, GetEventContentRequest::DELETED)
+                .await
+                .context(RpcSnafu)?;
+            return Ok(());
+        }
+
         Connection::write_success_return_code(&mut send)
             .await
             .context(RpcSnafu)?;
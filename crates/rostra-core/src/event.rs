@@ -273,6 +273,8 @@ impl EventKind {
     pub const NULL: Self = EventKind::from_u16(0);
     /// Unspecified binary data
     pub const RAW: Self = EventKind::from_u16(1);
+    /// Binary data with a mime type, e.g. an image attached to a post
+    pub const BLOB: Self = EventKind::from_u16(2);
 
     /// Control: Start following identity
     pub const FOLLOW: Self = EventKind::from_u16(0x10);
@@ -288,6 +290,8 @@ impl EventKind {
     pub const SOCIAL_LIKE: Self = EventKind::from_u16(0x21);
     pub const SOCIAL_REPOST: Self = EventKind::from_u16(0x22);
     pub const SOCIAL_PROFILE_UPDATE: Self = EventKind::from_u16(0x24);
+    pub const SOCIAL_POLL: Self = EventKind::from_u16(0x25);
+    pub const SOCIAL_POLL_VOTE: Self = EventKind::from_u16(0x26);
 
     pub const fn from_u16(value: u16) -> Self {
         Self(value.to_be_bytes())
@@ -299,6 +303,7 @@ impl fmt::Display for EventKind {
         let s = match *self {
             Self::NULL => "null",
             Self::RAW => "raw",
+            Self::BLOB => "blob",
             Self::FOLLOW => "follow",
             Self::UNFOLLOW => "unfollow",
             Self::PERSONA_UPDATE => "persona-update",
@@ -307,6 +312,8 @@ impl fmt::Display for EventKind {
             Self::SOCIAL_LIKE => "social-like",
             Self::SOCIAL_REPOST => "social-repost",
             Self::SOCIAL_PROFILE_UPDATE => "social-profile-update",
+            Self::SOCIAL_POLL => "social-poll",
+            Self::SOCIAL_POLL_VOTE => "social-poll-vote",
             v => {
                 f.write_fmt(format_args!("{v}"))?;
                 return Ok(());
@@ -1,4 +1,4 @@
-use std::collections::BTreeSet;
+use std::collections::{BTreeMap, BTreeSet};
 use std::str::FromStr as _;
 
 use snafu::Snafu;
@@ -7,7 +7,7 @@ use unicode_segmentation::UnicodeSegmentation as _;
 use super::{EventContent, EventKind, PersonaId};
 use crate::id::RostraId;
 use crate::{
-    ExternalEventId, ShortEventId, array_type_define, array_type_impl_base32_str,
+    ExternalEventId, ShortEventId, Timestamp, array_type_define, array_type_impl_base32_str,
     array_type_impl_serde,
 };
 
@@ -170,9 +170,88 @@ pub struct SocialPost {
     // "e" for "emoji"
     #[serde(rename = "e")]
     pub reaction: Option<String>,
+    /// [`Blob`]s attached to the post
+    #[serde(rename = "a", default, skip_serializing_if = "Vec::is_empty")]
+    pub attachments: Vec<ExternalEventId>,
+    /// Post (of the same author) this is a new version of
+    ///
+    /// Always the original post, not the previous version, so replies and
+    /// reactions keep referring to it.
+    #[serde(rename = "v", default, skip_serializing_if = "Option::is_none")]
+    pub edit_of: Option<ShortEventId>,
+    /// Post quoted (reposted) by this one, with the content as commentary
+    #[serde(rename = "q", default, skip_serializing_if = "Option::is_none")]
+    pub quote: Option<ExternalEventId>,
+    /// [`SocialPoll`] (of the same author) attached to the post
+    #[serde(rename = "o", default, skip_serializing_if = "Option::is_none")]
+    pub poll: Option<ShortEventId>,
+    /// Content warning, the content should be hidden behind it
+    #[serde(rename = "w", default, skip_serializing_if = "Option::is_none")]
+    pub content_warning: Option<String>,
 }
 
 impl SocialPost {
+    /// Maximum number of attachments of a single post
+    pub const MAX_ATTACHMENTS: usize = 8;
+    /// Maximum length of a content warning, in bytes
+    pub const MAX_CONTENT_WARNING_LEN: usize = 100;
+    /// Maximum length of a hashtag (without the `#`), in bytes
+    pub const MAX_HASHTAG_LEN: usize = 64;
+
+    /// Hashtags in `text`, as the offset of the `#` and the tag without it
+    ///
+    /// A hashtag starts a word and consists of letters, digits and `_`, with
+    /// at least one letter, so `#1` or `foo#bar` are not tags.
+    pub fn find_hashtags(text: &str) -> impl Iterator<Item = (usize, &str)> {
+        text.match_indices('#').filter_map(|(start, _)| {
+            if !text[..start]
+                .chars()
+                .next_back()
+                .is_none_or(|c| c.is_whitespace() || "([{\"'".contains(c))
+            {
+                return None;
+            }
+            let rest = &text[start + 1..];
+            let len = rest
+                .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+                .unwrap_or(rest.len());
+            let tag = &rest[..len];
+            (tag.chars().any(char::is_alphabetic) && tag.len() <= Self::MAX_HASHTAG_LEN)
+                .then_some((start, tag))
+        })
+    }
+
+    /// Ids mentioned in `text` with `rostra:<id>` links
+    pub fn find_mentions(text: &str) -> impl Iterator<Item = RostraId> {
+        text.match_indices("rostra:").filter_map(|(start, prefix)| {
+            let rest = &text[start + prefix.len()..];
+            let len = rest
+                .find(|c: char| !c.is_ascii_alphanumeric())
+                .unwrap_or(rest.len());
+            RostraId::from_str(&rest[..len]).ok()
+        })
+    }
+
+    /// Ids mentioned in the post content
+    pub fn mentions(&self) -> BTreeSet<RostraId> {
+        self.djot_content
+            .as_deref()
+            .map(|content| Self::find_mentions(content).collect())
+            .unwrap_or_default()
+    }
+
+    /// Lowercased hashtags of the post content
+    pub fn hashtags(&self) -> BTreeSet<String> {
+        self.djot_content
+            .as_deref()
+            .map(|content| {
+                Self::find_hashtags(content)
+                    .map(|(_, tag)| tag.to_lowercase())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
     pub fn is_reaction<'t>(
         reply_to: &'_ Option<ExternalEventId>,
         text: &'t str,
@@ -220,6 +299,125 @@ impl SocialPost {
 }
 impl EventContentKind for SocialPost {
     const KIND: EventKind = EventKind::SOCIAL_POST;
+
+    fn validate(&self) -> ContentValidationResult<()> {
+        if Self::MAX_ATTACHMENTS < self.attachments.len() {
+            return Err(ContentValidationError);
+        }
+        if self
+            .content_warning
+            .as_ref()
+            .is_some_and(|cw| Self::MAX_CONTENT_WARNING_LEN < cw.len())
+        {
+            return Err(ContentValidationError);
+        }
+        // Only posts with content can be edited
+        if self.edit_of.is_some() && self.djot_content.is_none() {
+            return Err(ContentValidationError);
+        }
+        Ok(())
+    }
+}
+
+/// A poll, usually attached to a [`SocialPost`]
+#[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub struct SocialPoll {
+    #[serde(rename = "q")]
+    pub question: String,
+    #[serde(rename = "o")]
+    pub options: Vec<String>,
+    /// Clients don't accept new votes after this time
+    #[serde(rename = "t")]
+    pub closes_at: Timestamp,
+}
+
+impl SocialPoll {
+    pub const MAX_OPTIONS: usize = 8;
+
+    pub fn is_closed(&self, now: Timestamp) -> bool {
+        self.closes_at <= now
+    }
+}
+
+impl EventContentKind for SocialPoll {
+    const KIND: EventKind = EventKind::SOCIAL_POLL;
+
+    fn validate(&self) -> ContentValidationResult<()> {
+        if self.question.is_empty() || 1000 < self.question.len() {
+            return Err(ContentValidationError);
+        }
+        if self.options.len() < 2 || Self::MAX_OPTIONS < self.options.len() {
+            return Err(ContentValidationError);
+        }
+        if self
+            .options
+            .iter()
+            .any(|option| option.is_empty() || 100 < option.len())
+        {
+            return Err(ContentValidationError);
+        }
+        Ok(())
+    }
+}
+
+/// A vote in a [`SocialPoll`]
+///
+/// Only the latest vote of each voter counts. To change or withdraw a vote,
+/// the previous one gets deleted, and replaced with a new one.
+#[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub struct PollVote {
+    #[serde(rename = "p")]
+    pub poll: ExternalEventId,
+    /// Index of the chosen option, `None` if the vote was withdrawn
+    #[serde(rename = "o")]
+    pub option: Option<u8>,
+}
+
+impl EventContentKind for PollVote {
+    const KIND: EventKind = EventKind::SOCIAL_POLL_VOTE;
+
+    fn validate(&self) -> ContentValidationResult<()> {
+        if self
+            .option
+            .is_some_and(|option| SocialPoll::MAX_OPTIONS <= usize::from(option))
+        {
+            return Err(ContentValidationError);
+        }
+        Ok(())
+    }
+}
+
+/// Binary data with a mime type, e.g. an image attached to a [`SocialPost`]
+///
+/// Published as a separate event, so it can be referenced and fetched
+/// independently of the events referring to it.
+#[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub struct Blob {
+    #[serde(rename = "m")]
+    pub mime: String,
+    #[serde(rename = "d", with = "serde_bytes")]
+    pub data: Vec<u8>,
+}
+
+impl Blob {
+    pub const MAX_SIZE: usize = 10_000_000;
+}
+
+impl EventContentKind for Blob {
+    const KIND: EventKind = EventKind::BLOB;
+
+    fn validate(&self) -> ContentValidationResult<()> {
+        if self.mime.is_empty() || 100 < self.mime.len() || !self.mime.contains('/') {
+            return Err(ContentValidationError);
+        }
+        if Self::MAX_SIZE < self.data.len() {
+            return Err(ContentValidationError);
+        }
+        Ok(())
+    }
 }
 
 #[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
@@ -251,7 +449,7 @@ pub struct SocialRepost {
 }
 
 #[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
-#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Default)]
 pub struct SocialProfileUpdate {
     #[serde(rename = "n")]
     pub display_name: String,
@@ -259,17 +457,45 @@ pub struct SocialProfileUpdate {
     pub bio: String,
     #[serde(rename = "a")]
     pub avatar: Option<(String, Vec<u8>)>,
+    /// Links to elsewhere, in display order
+    #[serde(rename = "l", default, skip_serializing_if = "Vec::is_empty")]
+    pub links: Vec<SocialProfileLink>,
+    #[serde(rename = "p", default, skip_serializing_if = "Option::is_none")]
+    pub pronouns: Option<String>,
+    #[serde(rename = "o", default, skip_serializing_if = "Option::is_none")]
+    pub location: Option<String>,
+    /// Header image, as a [`Blob`] event
+    #[serde(rename = "h", default, skip_serializing_if = "Option::is_none")]
+    pub header: Option<ExternalEventId>,
+    /// Descriptions of the personas the author posts as
+    #[serde(rename = "d", default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub persona_descriptions: BTreeMap<PersonaId, String>,
+}
+
+impl SocialProfileUpdate {
+    /// Maximum length of the display name, in bytes
+    pub const MAX_DISPLAY_NAME_LEN: usize = 100;
+    /// Maximum length of the bio, in bytes
+    pub const MAX_BIO_LEN: usize = 1000;
+    pub const MAX_LINKS: usize = 8;
+    /// Maximum length of the pronouns, in bytes
+    pub const MAX_PRONOUNS_LEN: usize = 50;
+    /// Maximum length of the location, in bytes
+    pub const MAX_LOCATION_LEN: usize = 100;
+    pub const MAX_PERSONA_DESCRIPTIONS: usize = 16;
+    /// Maximum length of a single persona description, in bytes
+    pub const MAX_PERSONA_DESCRIPTION_LEN: usize = 500;
 }
 
 impl EventContentKind for SocialProfileUpdate {
     const KIND: EventKind = EventKind::SOCIAL_PROFILE_UPDATE;
 
     fn validate(&self) -> ContentValidationResult<()> {
-        if 100 < self.display_name.len() {
+        if Self::MAX_DISPLAY_NAME_LEN < self.display_name.len() {
             return Err(ContentValidationError);
         }
 
-        if 1000 < self.display_name.len() {
+        if Self::MAX_BIO_LEN < self.bio.len() {
             return Err(ContentValidationError);
         }
 
@@ -285,10 +511,65 @@ impl EventContentKind for SocialProfileUpdate {
                 return Err(ContentValidationError);
             }
         }
+
+        if Self::MAX_LINKS < self.links.len() || !self.links.iter().all(SocialProfileLink::is_valid)
+        {
+            return Err(ContentValidationError);
+        }
+
+        if self
+            .pronouns
+            .as_ref()
+            .is_some_and(|pronouns| Self::MAX_PRONOUNS_LEN < pronouns.len())
+        {
+            return Err(ContentValidationError);
+        }
+
+        if self
+            .location
+            .as_ref()
+            .is_some_and(|location| Self::MAX_LOCATION_LEN < location.len())
+        {
+            return Err(ContentValidationError);
+        }
+
+        if Self::MAX_PERSONA_DESCRIPTIONS < self.persona_descriptions.len()
+            || self
+                .persona_descriptions
+                .values()
+                .any(|description| Self::MAX_PERSONA_DESCRIPTION_LEN < description.len())
+        {
+            return Err(ContentValidationError);
+        }
         Ok(())
     }
 }
 
+/// A link on a profile
+#[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
+#[cfg_attr(feature = "bincode", derive(::bincode::Encode, ::bincode::Decode))]
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub struct SocialProfileLink {
+    #[serde(rename = "l")]
+    pub label: String,
+    #[serde(rename = "u")]
+    pub url: String,
+}
+
+impl SocialProfileLink {
+    /// Maximum length of the label, in bytes
+    pub const MAX_LABEL_LEN: usize = 100;
+    /// Maximum length of the url, in bytes
+    pub const MAX_URL_LEN: usize = 500;
+
+    /// Whether the link is within limits and points to a web page
+    pub fn is_valid(&self) -> bool {
+        self.label.len() <= Self::MAX_LABEL_LEN
+            && self.url.len() <= Self::MAX_URL_LEN
+            && (self.url.starts_with("https://") || self.url.starts_with("http://"))
+    }
+}
+
 #[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
 #[cfg_attr(feature = "bincode", derive(::bincode::Encode, ::bincode::Decode))]
 #[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
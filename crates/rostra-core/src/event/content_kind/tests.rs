@@ -1,6 +1,11 @@
 use std::{cmp, fmt};
 
-use super::{IrohNodeId, NodeAnnouncement};
+use super::{
+    Blob, EventContentKind as _, IrohNodeId, NodeAnnouncement, PersonaId, PollVote, SocialPoll,
+    SocialPost, SocialProfileLink, SocialProfileUpdate,
+};
+use crate::id::RostraId;
+use crate::{ExternalEventId, ShortEventId, Timestamp};
 
 fn round_trip<T>(v: T)
 where
@@ -28,3 +33,215 @@ fn sanity_check_node_annocment() {
     let ann = NodeAnnouncement::Iroh { addr: node_id };
     round_trip(ann);
 }
+
+#[test]
+fn blob_round_trip() {
+    round_trip(Blob {
+        mime: "image/png".into(),
+        data: vec![0x89, b'P', b'N', b'G'],
+    });
+}
+
+#[test]
+fn social_post_without_attachments_is_backward_compatible() {
+    #[derive(::serde::Serialize)]
+    struct SocialPostV0 {
+        #[serde(rename = "p")]
+        persona: PersonaId,
+        #[serde(rename = "c")]
+        djot_content: Option<String>,
+        #[serde(rename = "r")]
+        reply_to: Option<ExternalEventId>,
+        #[serde(rename = "e")]
+        reaction: Option<String>,
+    }
+
+    let post = SocialPost {
+        persona: PersonaId(1),
+        djot_content: Some("Hello".into()),
+        reply_to: None,
+        reaction: None,
+        attachments: vec![],
+        edit_of: None,
+        quote: None,
+        poll: None,
+        content_warning: None,
+    };
+    let v0 = SocialPostV0 {
+        persona: PersonaId(1),
+        djot_content: Some("Hello".into()),
+        reply_to: None,
+        reaction: None,
+    };
+    let mut v0_bytes = vec![];
+    cbor4ii::serde::to_writer(&mut v0_bytes, &v0).expect("Can't fail");
+
+    let encoded = post.serialize_cbor().expect("Valid");
+    assert_eq!(encoded.as_ref(), v0_bytes.as_slice());
+    assert_eq!(
+        encoded.deserialize_cbor::<SocialPost>().expect("Valid"),
+        post
+    );
+}
+
+#[test]
+fn social_post_content_warning_validation() {
+    let post = SocialPost {
+        persona: PersonaId(1),
+        djot_content: Some("Hello".into()),
+        reply_to: None,
+        reaction: None,
+        attachments: vec![],
+        edit_of: None,
+        quote: None,
+        poll: None,
+        content_warning: Some("Spoilers".into()),
+    };
+    round_trip(post.clone());
+    assert!(post.validate().is_ok());
+
+    let too_long = SocialPost {
+        content_warning: Some("x".repeat(SocialPost::MAX_CONTENT_WARNING_LEN + 1)),
+        ..post
+    };
+    assert!(too_long.validate().is_err());
+}
+
+#[test]
+fn social_post_hashtags() {
+    assert_eq!(
+        SocialPost::find_hashtags("#rust is (#Fun), not foo#bar #1 or ##x #_a").collect::<Vec<_>>(),
+        vec![(0, "rust"), (10, "Fun"), (39, "_a")]
+    );
+
+    let post = SocialPost {
+        persona: PersonaId(1),
+        djot_content: Some("#Rust and #rust, #p2p_networks".into()),
+        reply_to: None,
+        reaction: None,
+        attachments: vec![],
+        edit_of: None,
+        quote: None,
+        poll: None,
+        content_warning: None,
+    };
+    assert_eq!(
+        post.hashtags().into_iter().collect::<Vec<_>>(),
+        vec!["p2p_networks".to_string(), "rust".to_string()]
+    );
+}
+
+#[test]
+fn social_post_mentions() {
+    let id = RostraId::ZERO;
+    let text = format!("Hi <rostra:{id}>, and rostra:{id}, but not rostra:nope");
+    assert_eq!(
+        SocialPost::find_mentions(&text).collect::<Vec<_>>(),
+        vec![id, id]
+    );
+}
+
+#[test]
+fn social_poll_validation() {
+    let poll = SocialPoll {
+        question: "Tabs or spaces?".into(),
+        options: vec!["Tabs".into(), "Spaces".into()],
+        closes_at: Timestamp(1000),
+    };
+    round_trip(poll.clone());
+    assert!(poll.validate().is_ok());
+    assert!(poll.is_closed(Timestamp(1000)));
+    assert!(!poll.is_closed(Timestamp(999)));
+
+    let single_option = SocialPoll {
+        options: vec!["Tabs".into()],
+        ..poll.clone()
+    };
+    assert!(single_option.validate().is_err());
+
+    let empty_option = SocialPoll {
+        options: vec!["Tabs".into(), "".into()],
+        ..poll
+    };
+    assert!(empty_option.validate().is_err());
+
+    let vote = PollVote {
+        poll: ExternalEventId::new(RostraId::ZERO, ShortEventId::ZERO),
+        option: Some(SocialPoll::MAX_OPTIONS as u8),
+    };
+    assert!(vote.validate().is_err());
+}
+
+#[test]
+fn social_profile_update_validation() {
+    #[derive(::serde::Serialize)]
+    struct SocialProfileUpdateV0 {
+        #[serde(rename = "n")]
+        display_name: String,
+        #[serde(rename = "b")]
+        bio: String,
+        #[serde(rename = "a")]
+        avatar: Option<(String, Vec<u8>)>,
+    }
+    let mut v0_bytes = vec![];
+    cbor4ii::serde::to_writer(
+        &mut v0_bytes,
+        &SocialProfileUpdateV0 {
+            display_name: "Alice".into(),
+            bio: "Hi".into(),
+            avatar: None,
+        },
+    )
+    .expect("Can't fail");
+    let v0: SocialProfileUpdate = cbor4ii::serde::from_slice(&v0_bytes).expect("Valid");
+    assert_eq!(
+        v0,
+        SocialProfileUpdate {
+            display_name: "Alice".into(),
+            bio: "Hi".into(),
+            ..Default::default()
+        }
+    );
+
+    let profile = SocialProfileUpdate {
+        display_name: "Alice".into(),
+        bio: "Hi".into(),
+        links: vec![SocialProfileLink {
+            label: "Blog".into(),
+            url: "https://example.com".into(),
+        }],
+        pronouns: Some("she/her".into()),
+        location: Some("Earth".into()),
+        header: Some(ExternalEventId::new(RostraId::ZERO, ShortEventId::ZERO)),
+        persona_descriptions: [(PersonaId(1), "Work stuff".into())].into_iter().collect(),
+        ..Default::default()
+    };
+    round_trip(profile.clone());
+    assert!(profile.validate().is_ok());
+
+    let long_bio = SocialProfileUpdate {
+        bio: "x".repeat(SocialProfileUpdate::MAX_BIO_LEN + 1),
+        ..profile.clone()
+    };
+    assert!(long_bio.validate().is_err());
+
+    let script_link = SocialProfileUpdate {
+        links: vec![SocialProfileLink {
+            label: "Click".into(),
+            url: "javascript:alert(1)".into(),
+        }],
+        ..profile.clone()
+    };
+    assert!(script_link.validate().is_err());
+
+    let long_description = SocialProfileUpdate {
+        persona_descriptions: [(
+            PersonaId(0),
+            "x".repeat(SocialProfileUpdate::MAX_PERSONA_DESCRIPTION_LEN + 1),
+        )]
+        .into_iter()
+        .collect(),
+        ..profile
+    };
+    assert!(long_description.validate().is_err());
+}
@@ -1,5 +1,6 @@
 use std::fmt;
 use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
 
 #[cfg(feature = "bincode")]
 pub mod bincode;
@@ -134,6 +135,15 @@ pub struct Timestamp(pub u64);
 impl Timestamp {
     pub const ZERO: Self = Self(0);
     pub const MAX: Self = Self(u64::MAX);
+
+    pub fn now() -> Self {
+        Self(
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .expect("Dates before Unix epoch are unsupported")
+                .as_secs(),
+        )
+    }
 }
 
 impl From<u64> for Timestamp {
@@ -21,8 +21,8 @@ use tracing::trace;
 
 use crate::{
     DecodingBaoSnafu, DecodingSnafu, EncodingBaoSnafu, EventVerificationSnafu, FailedSnafu,
-    LOG_TARGET, MessageTooLargeSnafu, ReadSnafu, RpcResult, StreamConnectionSnafu, TrailerSnafu,
-    WriteSnafu,
+    LOG_TARGET, MessageTooLargeSnafu, ReadSnafu, RpcError, RpcResult, StreamConnectionSnafu,
+    TrailerSnafu, WriteSnafu,
 };
 
 #[derive(Debug)]
@@ -200,6 +200,11 @@ define_rpc!(
     pub struct GetEventContentResponse(pub bool);
 );
 
+impl GetEventContentRequest {
+    /// The content was deleted by its author, and will not be served
+    pub const DELETED: u8 = 1;
+}
+
 impl FeedEventResponse {
     pub const RETURN_CODE_ALREADY_HAVE: u8 = 1;
     pub const RETURN_CODE_DOES_NOT_NEED: u8 = 2;
@@ -495,7 +500,7 @@ impl Connection {
         &self,
         event: VerifiedEvent,
     ) -> RpcResult<Option<VerifiedEventContent>> {
-        let (_resp, content) = self
+        let res = self
             .make_rpc_with_extra_data_recv(
                 &GetEventContentRequest(event.event_id.to_short()),
                 |recv, resp| {
@@ -516,7 +521,14 @@ impl Connection {
                     })
                 },
             )
-            .await?;
+            .await;
+        let (_resp, content) = match res {
+            Ok(res) => res,
+            Err(RpcError::Failed {
+                return_code: GetEventContentRequest::DELETED,
+            }) => return Ok(None),
+            Err(err) => return Err(err),
+        };
 
         let verified_content = content.map(|content| {
             VerifiedEventContent::verify(event, content)
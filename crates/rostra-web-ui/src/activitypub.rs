@@ -0,0 +1,830 @@
+//! ActivityPub (fediverse) bridge
+//!
+//! Exposes identities that have a database on this node as ActivityPub
+//! actors: WebFinger discovery, actor documents, and outboxes of
+//! `Create(Note)` activities derived from their posts. Fediverse followers
+//! are tracked, and new posts are pushed to their inboxes.
+//!
+//! Rostra identity keys never leave Rostra: all requests are signed (HTTP
+//! signatures, `rsa-sha256`) with a key held by the bridge, which is what
+//! actor documents publish.
+
+use std::collections::{BTreeMap, HashSet};
+use std::net::{IpAddr, SocketAddr};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+use std::{fmt, io};
+
+use axum::http::header::{ACCEPT, CONTENT_TYPE, DATE, HOST};
+use axum::http::{HeaderMap, HeaderName, HeaderValue, Method};
+use data_encoding::{BASE64, HEXLOWER};
+use rostra_client::ClientHandle;
+use rostra_client_db::{IdSocialProfileRecord, def_table};
+use rostra_core::event::{EventKind, SocialPost};
+use rostra_core::id::{RostraId, ToShort as _};
+use rostra_core::{ShortEventId, Timestamp};
+use rostra_util_error::FmtCompact as _;
+use rsa::pkcs1v15::{Signature, SigningKey, VerifyingKey};
+use rsa::pkcs8::{
+    DecodePrivateKey as _, DecodePublicKey as _, EncodePrivateKey as _, EncodePublicKey as _,
+    LineEnding,
+};
+use rsa::signature::{SignatureEncoding as _, Signer as _, Verifier as _};
+use rsa::{RsaPrivateKey, RsaPublicKey};
+use serde_json::{Value, json};
+use sha2::{Digest as _, Sha256};
+use snafu::{OptionExt as _, ResultExt as _, Snafu, ensure};
+use tracing::{debug, info, warn};
+use url::Url;
+
+use crate::LOG_TARGET;
+use crate::routes::content::render_djot;
+use crate::routes::feed::fmt_rfc3339;
+
+pub const AP_CONTENT_TYPE: &str = "application/activity+json";
+const AP_CONTEXT: &str = "https://www.w3.org/ns/activitystreams";
+const AP_PUBLIC: &str = "https://www.w3.org/ns/activitystreams#Public";
+const SECURITY_CONTEXT: &str = "https://w3id.org/security/v1";
+
+const KEY_FILE: &str = "activitypub.key";
+const DB_FILE: &str = "activitypub.redb";
+const KEY_BITS: usize = 2048;
+
+/// Headers covered by signatures of outgoing requests
+const SIGNED_HEADERS: &str = "(request-target) host date digest";
+/// Headers signatures of incoming requests must cover
+const REQUIRED_SIGNED_HEADERS: [&str; 4] = ["(request-target)", "host", "date", "digest"];
+/// Maximum difference between the `Date` of a signed request and now
+const MAX_DATE_SKEW: Duration = Duration::from_secs(60 * 60);
+
+const HTTP_TIMEOUT: Duration = Duration::from_secs(10);
+/// Maximum size of fetched actor documents
+const MAX_ACTOR_SIZE: usize = 1024 * 1024;
+
+def_table! {
+    /// Fediverse followers of bridged identities: follower actor id => inbox url
+    ap_followers: (RostraId, String) => String
+}
+
+#[derive(Debug, Snafu)]
+pub enum ActivityPubError {
+    #[snafu(display("IO error on {}", path.display()))]
+    Io { source: io::Error, path: PathBuf },
+    #[snafu(display("Invalid bridge key: {msg}"))]
+    Key { msg: String },
+    #[snafu(transparent)]
+    Database { source: redb::DatabaseError },
+    #[snafu(transparent)]
+    Transaction { source: redb::TransactionError },
+    #[snafu(transparent)]
+    Table { source: redb::TableError },
+    #[snafu(transparent)]
+    Storage { source: redb::StorageError },
+    #[snafu(transparent)]
+    Commit { source: redb::CommitError },
+    #[snafu(display("HTTP error: {source}"))]
+    Http { source: reqwest::Error },
+    #[snafu(display("Delivery to {inbox} failed: {status}"))]
+    Delivery {
+        inbox: Url,
+        status: reqwest::StatusCode,
+    },
+    #[snafu(display("Invalid remote actor: {msg}"))]
+    InvalidActor { msg: &'static str },
+    #[snafu(display("Invalid signature: {msg}"))]
+    InvalidSignature { msg: &'static str },
+}
+
+pub type ActivityPubResult<T> = std::result::Result<T, ActivityPubError>;
+
+/// The bridge: its key, followers and http client
+#[derive(Clone)]
+pub struct ApBridge {
+    /// Public url of this node, without a trailing slash
+    base_url: String,
+    key: Arc<RsaPrivateKey>,
+    public_key_pem: Arc<str>,
+    db: Arc<redb_bincode::Database>,
+    http: reqwest::Client,
+    /// Identities that already have a task pushing their new posts
+    publishers: Arc<Mutex<HashSet<RostraId>>>,
+}
+
+impl fmt::Debug for ApBridge {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Never print the key
+        f.debug_struct("ApBridge")
+            .field("base_url", &self.base_url)
+            .finish_non_exhaustive()
+    }
+}
+
+impl ApBridge {
+    /// Open (or create) the bridge state in `data_dir`, serving actors under
+    /// `base_url`
+    pub async fn open(data_dir: &Path, base_url: &Url) -> ActivityPubResult<Self> {
+        tokio::task::block_in_place(|| {
+            std::fs::create_dir_all(data_dir).context(IoSnafu { path: data_dir })?;
+            let key = load_or_create_key(&data_dir.join(KEY_FILE))?;
+            let db = redb::Database::create(data_dir.join(DB_FILE))?;
+            Self::new(base_url, key, db)
+        })
+    }
+
+    pub fn new(base_url: &Url, key: RsaPrivateKey, db: redb::Database) -> ActivityPubResult<Self> {
+        let db = redb_bincode::Database::from(db);
+        let dbtx = db.begin_write()?;
+        dbtx.open_table(&ap_followers::TABLE)?;
+        dbtx.commit()?;
+
+        let public_key_pem = RsaPublicKey::from(&key)
+            .to_public_key_pem(LineEnding::LF)
+            .map_err(|err| {
+                KeySnafu {
+                    msg: err.to_string(),
+                }
+                .build()
+            })?;
+
+        // Requests are made on behalf of remote (untrusted) servers, so they
+        // must not be able to make the bridge reach internal ones
+        let http = reqwest::Client::builder()
+            .timeout(HTTP_TIMEOUT)
+            .redirect(reqwest::redirect::Policy::none())
+            .no_proxy()
+            .dns_resolver(Arc::new(PublicResolver))
+            .build()
+            .context(HttpSnafu)?;
+
+        Ok(Self {
+            base_url: base_url.as_str().trim_end_matches('/').to_owned(),
+            key: Arc::new(key),
+            public_key_pem: public_key_pem.into(),
+            db: Arc::new(db),
+            http,
+            publishers: Default::default(),
+        })
+    }
+
+    pub fn public_key_pem(&self) -> &str {
+        &self.public_key_pem
+    }
+
+    /// Domain used in WebFinger (`acct:{rostra_id}@{domain}`) handles
+    pub fn domain(&self) -> &str {
+        self.base_url
+            .split_once("://")
+            .map(|(_, rest)| rest)
+            .unwrap_or(&self.base_url)
+    }
+
+    pub fn actor_url(&self, id: RostraId) -> String {
+        format!("{}/ap/{id}", self.base_url)
+    }
+
+    pub fn key_id(&self, id: RostraId) -> String {
+        format!("{}#main-key", self.actor_url(id))
+    }
+
+    fn note_url(&self, author: RostraId, event_id: ShortEventId) -> String {
+        format!("{}/post/{event_id}", self.actor_url(author))
+    }
+
+    /// WebFinger resource (JRD) of `id`
+    pub fn webfinger(&self, id: RostraId) -> Value {
+        json!({
+            "subject": format!("acct:{id}@{}", self.domain()),
+            "links": [{
+                "rel": "self",
+                "type": AP_CONTENT_TYPE,
+                "href": self.actor_url(id),
+            }],
+        })
+    }
+
+    pub fn actor(&self, id: RostraId, profile: Option<&IdSocialProfileRecord>) -> Value {
+        let actor_url = self.actor_url(id);
+        let name = profile
+            .map(|profile| profile.display_name.clone())
+            .unwrap_or_else(|| id.to_short().to_string());
+        json!({
+            "@context": [AP_CONTEXT, SECURITY_CONTEXT],
+            "id": actor_url,
+            "type": "Person",
+            "preferredUsername": id.to_string(),
+            "name": name,
+            "summary": profile.map(|profile| profile.bio.as_str()).unwrap_or_default(),
+            "url": format!("{}/ui/profile/{id}", self.base_url),
+            "inbox": format!("{actor_url}/inbox"),
+            "outbox": format!("{actor_url}/outbox"),
+            "followers": format!("{actor_url}/followers"),
+            "publicKey": {
+                "id": self.key_id(id),
+                "owner": actor_url,
+                "publicKeyPem": &*self.public_key_pem,
+            },
+        })
+    }
+
+    /// `Note` of a post
+    pub fn note(
+        &self,
+        author: RostraId,
+        event_id: ShortEventId,
+        ts: Timestamp,
+        post: &SocialPost,
+    ) -> Value {
+        let content = post
+            .djot_content
+            .as_deref()
+            .map(|djot| render_djot(djot).into_string())
+            .unwrap_or_default();
+        json!({
+            "id": self.note_url(author, event_id),
+            "type": "Note",
+            "attributedTo": self.actor_url(author),
+            "summary": post.content_warning,
+            "sensitive": post.content_warning.is_some(),
+            "content": content,
+            "published": fmt_rfc3339(ts),
+            "url": format!("{}/ui/post/{author}/{event_id}", self.base_url),
+            "to": [AP_PUBLIC],
+            "cc": [format!("{}/followers", self.actor_url(author))],
+            "inReplyTo": post.reply_to.map(|reply_to| {
+                self.note_url(reply_to.rostra_id(), reply_to.event_id())
+            }),
+        })
+    }
+
+    /// `Create` activity of a post
+    pub fn create(
+        &self,
+        author: RostraId,
+        event_id: ShortEventId,
+        ts: Timestamp,
+        post: &SocialPost,
+    ) -> Value {
+        let note = self.note(author, event_id, ts, post);
+        json!({
+            "@context": AP_CONTEXT,
+            "id": format!("{}/activity", self.note_url(author, event_id)),
+            "type": "Create",
+            "actor": self.actor_url(author),
+            "published": note["published"],
+            "to": note["to"],
+            "cc": note["cc"],
+            "object": note,
+        })
+    }
+
+    /// `OrderedCollection` of `items`, as used for outboxes and followers
+    pub fn collection(&self, id: String, total: usize, items: Vec<Value>) -> Value {
+        json!({
+            "@context": AP_CONTEXT,
+            "id": id,
+            "type": "OrderedCollection",
+            "totalItems": total,
+            "orderedItems": items,
+        })
+    }
+
+    /// Headers signing a request of `id` to `url` with `body`
+    pub fn sign_request(&self, id: RostraId, method: &Method, url: &Url, body: &[u8]) -> HeaderMap {
+        let host = match url.port() {
+            Some(port) => format!("{}:{port}", url.host_str().unwrap_or_default()),
+            None => url.host_str().unwrap_or_default().to_owned(),
+        };
+        let date = httpdate::fmt_http_date(SystemTime::now());
+        let digest = body_digest(body);
+        let target = match url.query() {
+            Some(query) => format!("{}?{query}", url.path()),
+            None => url.path().to_owned(),
+        };
+
+        let signing_string = format!(
+            "(request-target): {} {target}\nhost: {host}\ndate: {date}\ndigest: {digest}",
+            method.as_str().to_lowercase()
+        );
+        let signature = SigningKey::<Sha256>::new((*self.key).clone())
+            .sign(signing_string.as_bytes())
+            .to_bytes();
+
+        let mut headers = HeaderMap::new();
+        for (name, value) in [
+            (HOST, host),
+            (DATE, date),
+            (HeaderName::from_static("digest"), digest),
+            (
+                HeaderName::from_static("signature"),
+                format!(
+                    r#"keyId="{}",algorithm="rsa-sha256",headers="{SIGNED_HEADERS}",signature="{}""#,
+                    self.key_id(id),
+                    BASE64.encode(&signature)
+                ),
+            ),
+        ] {
+            headers.insert(
+                name,
+                HeaderValue::from_str(&value).expect("Only ascii characters"),
+            );
+        }
+        headers
+    }
+
+    /// Post `activity` of `from` to a remote `inbox`
+    pub async fn deliver(
+        &self,
+        from: RostraId,
+        inbox: &Url,
+        activity: &Value,
+    ) -> ActivityPubResult<()> {
+        let body = serde_json::to_vec(activity).expect("Can't fail");
+        let headers = self.sign_request(from, &Method::POST, inbox, &body);
+
+        let resp = self
+            .http
+            .post(inbox.clone())
+            .headers(headers)
+            .header(CONTENT_TYPE, AP_CONTENT_TYPE)
+            .body(body)
+            .send()
+            .await
+            .context(HttpSnafu)?;
+        ensure!(
+            resp.status().is_success(),
+            DeliverySnafu {
+                inbox: inbox.clone(),
+                status: resp.status(),
+            }
+        );
+        Ok(())
+    }
+
+    /// Fetch the document of a remote actor
+    pub async fn fetch_actor(&self, actor: &Url) -> ActivityPubResult<Value> {
+        ensure_public_url(actor)?;
+        let mut resp = self
+            .http
+            .get(actor.clone())
+            .header(ACCEPT, AP_CONTENT_TYPE)
+            .send()
+            .await
+            .context(HttpSnafu)?
+            .error_for_status()
+            .context(HttpSnafu)?;
+
+        let too_large = InvalidActorSnafu {
+            msg: "document too large",
+        };
+        ensure!(
+            resp.content_length()
+                .is_none_or(|len| usize::try_from(len).is_ok_and(|len| len <= MAX_ACTOR_SIZE)),
+            too_large
+        );
+        let mut body = vec![];
+        while let Some(chunk) = resp.chunk().await.context(HttpSnafu)? {
+            ensure!(body.len() + chunk.len() <= MAX_ACTOR_SIZE, too_large);
+            body.extend_from_slice(&chunk);
+        }
+        serde_json::from_slice(&body)
+            .ok()
+            .context(InvalidActorSnafu {
+                msg: "invalid document",
+            })
+    }
+
+    /// Verify the signature of an incoming request and return the document of
+    /// the actor that signed it
+    pub async fn verify_incoming(
+        &self,
+        method: &Method,
+        target: &str,
+        headers: &HeaderMap,
+        body: &[u8],
+    ) -> ActivityPubResult<Value> {
+        let key_id = parse_signature_header(headers)?
+            .remove("keyId")
+            .context(InvalidSignatureSnafu { msg: "no keyId" })?;
+        let actor_url = Url::parse(&key_id).ok().context(InvalidSignatureSnafu {
+            msg: "invalid keyId",
+        })?;
+
+        let actor = self.fetch_actor(&actor_url).await?;
+        let actor_id = actor["id"]
+            .as_str()
+            .context(InvalidActorSnafu { msg: "no id" })?;
+        ensure!(
+            Url::parse(actor_id).is_ok_and(|actor_id| actor_id.origin() == actor_url.origin()),
+            InvalidActorSnafu {
+                msg: "actor of a different origin than its key"
+            }
+        );
+        ensure!(
+            actor["publicKey"]["id"].as_str() == Some(key_id.as_str())
+                && actor["publicKey"]["owner"].as_str() == Some(actor_id),
+            InvalidActorSnafu {
+                msg: "key does not belong to actor"
+            }
+        );
+        let public_key = actor["publicKey"]["publicKeyPem"]
+            .as_str()
+            .and_then(|pem| RsaPublicKey::from_public_key_pem(pem).ok())
+            .context(InvalidActorSnafu {
+                msg: "invalid public key",
+            })?;
+
+        verify_signature(
+            &public_key,
+            method,
+            target,
+            headers,
+            body,
+            SystemTime::now(),
+        )?;
+        Ok(actor)
+    }
+
+    /// Handle an activity sent to the inbox of `id` by (verified) `actor`
+    pub async fn handle_inbox(
+        &self,
+        id: RostraId,
+        actor: &Value,
+        activity: &Value,
+    ) -> ActivityPubResult<()> {
+        let actor_id = actor["id"]
+            .as_str()
+            .context(InvalidActorSnafu { msg: "no id" })?;
+        ensure!(
+            activity["actor"].as_str() == Some(actor_id),
+            InvalidActorSnafu {
+                msg: "activity of a different actor"
+            }
+        );
+
+        match (
+            activity["type"].as_str(),
+            activity["object"]["type"].as_str(),
+        ) {
+            (Some("Follow"), _) => {
+                ensure!(
+                    activity["object"].as_str() == Some(self.actor_url(id).as_str()),
+                    InvalidActorSnafu {
+                        msg: "follow of a different actor"
+                    }
+                );
+                let inbox = actor["inbox"]
+                    .as_str()
+                    .and_then(|inbox| Url::parse(inbox).ok())
+                    .context(InvalidActorSnafu { msg: "no inbox" })?;
+                // The actor's origin was verified, so we only ever post back to it
+                ensure!(
+                    Url::parse(actor_id).is_ok_and(|actor_id| actor_id.origin() == inbox.origin()),
+                    InvalidActorSnafu {
+                        msg: "inbox of a different origin than the actor"
+                    }
+                );
+
+                self.insert_follower(id, actor_id, &inbox)?;
+                info!(target: LOG_TARGET, %id, follower = %actor_id, "New fediverse follower");
+
+                let accept = json!({
+                    "@context": AP_CONTEXT,
+                    "id": format!("{}#accepts/{}", self.actor_url(id), HEXLOWER.encode(&Sha256::digest(actor_id))),
+                    "type": "Accept",
+                    "actor": self.actor_url(id),
+                    "object": activity,
+                });
+                self.deliver(id, &inbox, &accept).await?;
+            }
+            (Some("Undo"), Some("Follow")) => {
+                self.remove_follower(id, actor_id)?;
+                info!(target: LOG_TARGET, %id, follower = %actor_id, "Fediverse follower left");
+            }
+            (kind, _) => {
+                debug!(target: LOG_TARGET, %id, ?kind, "Ignoring activity");
+            }
+        }
+        Ok(())
+    }
+
+    /// Followers of `id`: actor id => inbox url
+    pub fn followers(&self, id: RostraId) -> ActivityPubResult<BTreeMap<String, String>> {
+        tokio::task::block_in_place(|| {
+            let dbtx = self.db.begin_read()?;
+            let tbl = dbtx.open_table(&ap_followers::TABLE)?;
+            let mut followers = BTreeMap::new();
+            for record in tbl.range(&(id, String::new())..)? {
+                let (k, v) = record?;
+                let (follower_of, actor) = k.value();
+                if follower_of != id {
+                    break;
+                }
+                followers.insert(actor, v.value());
+            }
+            Ok(followers)
+        })
+    }
+
+    fn insert_follower(&self, id: RostraId, actor: &str, inbox: &Url) -> ActivityPubResult<()> {
+        tokio::task::block_in_place(|| {
+            let dbtx = self.db.begin_write()?;
+            dbtx.open_table(&ap_followers::TABLE)?
+                .insert(&(id, actor.to_owned()), &inbox.to_string())?;
+            dbtx.commit()?;
+            Ok(())
+        })
+    }
+
+    fn remove_follower(&self, id: RostraId, actor: &str) -> ActivityPubResult<()> {
+        tokio::task::block_in_place(|| {
+            let dbtx = self.db.begin_write()?;
+            dbtx.open_table(&ap_followers::TABLE)?
+                .remove(&(id, actor.to_owned()))?;
+            dbtx.commit()?;
+            Ok(())
+        })
+    }
+
+    /// Push new posts of `client`'s identity to its fediverse followers
+    ///
+    /// Does nothing if already started for this identity.
+    pub fn start_publisher(&self, client: ClientHandle) {
+        let Ok(client_ref) = client.client_ref() else {
+            return;
+        };
+        let id = client_ref.rostra_id();
+        if !self.publishers.lock().expect("Locking failed").insert(id) {
+            return;
+        }
+        let mut new_posts = client_ref.new_posts_subscribe();
+        drop(client_ref);
+
+        let bridge = self.clone();
+        tokio::spawn(async move {
+            loop {
+                let (event_content, post) = match new_posts.recv().await {
+                    Ok(new_post) => new_post,
+                    Err(tokio::sync::broadcast::error::RecvError::Closed) => break,
+                    Err(tokio::sync::broadcast::error::RecvError::Lagged(_)) => continue,
+                };
+                let event = &event_content.event.event;
+                if event.author != id
+                    || event.kind != EventKind::SOCIAL_POST
+                    || post.reaction.is_some()
+                    || post.djot_content.is_none()
+                {
+                    continue;
+                }
+                let activity = bridge.create(
+                    id,
+                    event_content.event.event_id.into(),
+                    event.timestamp.into(),
+                    &post,
+                );
+                bridge.deliver_to_followers(id, &activity).await;
+            }
+            bridge
+                .publishers
+                .lock()
+                .expect("Locking failed")
+                .remove(&id);
+        });
+    }
+
+    async fn deliver_to_followers(&self, id: RostraId, activity: &Value) {
+        let followers = match self.followers(id) {
+            Ok(followers) => followers,
+            Err(err) => {
+                warn!(target: LOG_TARGET, err = %err.fmt_compact(), "Failed to load followers");
+                return;
+            }
+        };
+        // Shared inboxes are not supported, but avoid posting twice to the same inbox
+        let inboxes: HashSet<_> = followers.into_values().collect();
+        for inbox in inboxes {
+            let Ok(inbox) = Url::parse(&inbox) else {
+                continue;
+            };
+            if let Err(err) = self.deliver(id, &inbox, activity).await {
+                warn!(target: LOG_TARGET, err = %err.fmt_compact(), %inbox, "Failed to deliver activity");
+            }
+        }
+    }
+}
+
+/// `Digest` header value of `body`
+fn body_digest(body: &[u8]) -> String {
+    format!("SHA-256={}", BASE64.encode(&Sha256::digest(body)))
+}
+
+/// Parse the `Signature` header into its `key="value"` parameters
+fn parse_signature_header(headers: &HeaderMap) -> ActivityPubResult<BTreeMap<String, String>> {
+    let header = headers
+        .get("signature")
+        .and_then(|value| value.to_str().ok())
+        .context(InvalidSignatureSnafu {
+            msg: "no signature header",
+        })?;
+    Ok(header
+        .split(',')
+        .filter_map(|param| param.split_once('='))
+        .map(|(k, v)| (k.trim().to_owned(), v.trim().trim_matches('"').to_owned()))
+        .collect())
+}
+
+/// Verify a signed request (`target` being its path and query) against
+/// `public_key`, including the digest of its `body`, and its `Date` being
+/// close to `now`
+pub fn verify_signature(
+    public_key: &RsaPublicKey,
+    method: &Method,
+    target: &str,
+    headers: &HeaderMap,
+    body: &[u8],
+    now: SystemTime,
+) -> ActivityPubResult<()> {
+    let params = parse_signature_header(headers)?;
+    let signed_headers = params.get("headers").map(String::as_str).unwrap_or("date");
+    ensure!(
+        REQUIRED_SIGNED_HEADERS
+            .iter()
+            .all(|required| signed_headers.split(' ').any(|name| name == *required)),
+        InvalidSignatureSnafu {
+            msg: "required header not signed"
+        }
+    );
+    let date = headers
+        .get(DATE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| httpdate::parse_http_date(value).ok())
+        .context(InvalidSignatureSnafu {
+            msg: "invalid date",
+        })?;
+    let skew = now
+        .duration_since(date)
+        .unwrap_or_else(|err| err.duration());
+    ensure!(
+        skew <= MAX_DATE_SKEW,
+        InvalidSignatureSnafu {
+            msg: "date too far from now"
+        }
+    );
+    ensure!(
+        headers.get("digest").and_then(|v| v.to_str().ok()) == Some(body_digest(body).as_str()),
+        InvalidSignatureSnafu {
+            msg: "digest mismatch"
+        }
+    );
+
+    let mut lines = vec![];
+    for name in signed_headers.split(' ') {
+        if name == "(request-target)" {
+            lines.push(format!(
+                "(request-target): {} {target}",
+                method.as_str().to_lowercase()
+            ));
+        } else {
+            let value = headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .context(InvalidSignatureSnafu {
+                    msg: "signed header missing",
+                })?;
+            lines.push(format!("{name}: {value}"));
+        }
+    }
+
+    let signature = params
+        .get("signature")
+        .and_then(|sig| BASE64.decode(sig.as_bytes()).ok())
+        .and_then(|sig| Signature::try_from(sig.as_slice()).ok())
+        .context(InvalidSignatureSnafu {
+            msg: "invalid signature encoding",
+        })?;
+    VerifyingKey::<Sha256>::new(public_key.clone())
+        .verify(lines.join("\n").as_bytes(), &signature)
+        .ok()
+        .context(InvalidSignatureSnafu {
+            msg: "signature mismatch",
+        })
+}
+
+/// Reject urls that are not http(s), or have a non-public ip address as a host
+///
+/// Host names are checked when resolved, see [`PublicResolver`].
+fn ensure_public_url(url: &Url) -> ActivityPubResult<()> {
+    let is_public = matches!(url.scheme(), "http" | "https")
+        && match url.host() {
+            Some(url::Host::Domain(_)) => true,
+            Some(url::Host::Ipv4(ip)) => is_public_ip(ip.into()),
+            Some(url::Host::Ipv6(ip)) => is_public_ip(ip.into()),
+            None => false,
+        };
+    ensure!(
+        is_public,
+        InvalidActorSnafu {
+            msg: "not a public url"
+        }
+    );
+    Ok(())
+}
+
+/// Whether `ip` is (likely) reachable over the public internet
+fn is_public_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, ..] = ip.octets();
+            !(ip.is_unspecified()
+                || ip.is_loopback()
+                || ip.is_private()
+                || ip.is_link_local()
+                || ip.is_broadcast()
+                || ip.is_documentation()
+                || ip.is_multicast()
+                // "This network", shared address space, benchmarking and reserved
+                || a == 0
+                || (a == 100 && (b & 0xc0) == 64)
+                || (a == 198 && (b & 0xfe) == 18)
+                || 240 <= a)
+        }
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => is_public_ip(ip.into()),
+            None => {
+                let [a, b, ..] = ip.segments();
+                !(ip.is_unspecified()
+                    || ip.is_loopback()
+                    || ip.is_multicast()
+                    // Unique local, link local and documentation
+                    || (a & 0xfe00) == 0xfc00
+                    || (a & 0xffc0) == 0xfe80
+                    || (a == 0x2001 && b == 0x0db8))
+            }
+        },
+    }
+}
+
+/// DNS resolver only returning public addresses
+struct PublicResolver;
+
+impl reqwest::dns::Resolve for PublicResolver {
+    fn resolve(&self, name: reqwest::dns::Name) -> reqwest::dns::Resolving {
+        let host = name.as_str().to_owned();
+        Box::pin(async move {
+            let addrs: Vec<SocketAddr> = tokio::net::lookup_host((host.as_str(), 0))
+                .await?
+                .filter(|addr| is_public_ip(addr.ip()))
+                .collect();
+            if addrs.is_empty() {
+                return Err(format!("No public address of {host}").into());
+            }
+            let addrs: reqwest::dns::Addrs = Box::new(addrs.into_iter());
+            Ok(addrs)
+        })
+    }
+}
+
+/// Load the bridge key, generating it on first use
+fn load_or_create_key(path: &Path) -> ActivityPubResult<RsaPrivateKey> {
+    match std::fs::read_to_string(path) {
+        Ok(pem) => RsaPrivateKey::from_pkcs8_pem(&pem).map_err(|err| {
+            KeySnafu {
+                msg: err.to_string(),
+            }
+            .build()
+        }),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => {
+            let key = RsaPrivateKey::new(&mut rand::thread_rng(), KEY_BITS).map_err(|err| {
+                KeySnafu {
+                    msg: err.to_string(),
+                }
+                .build()
+            })?;
+            let pem = key.to_pkcs8_pem(LineEnding::LF).map_err(|err| {
+                KeySnafu {
+                    msg: err.to_string(),
+                }
+                .build()
+            })?;
+            write_key_file(path, pem.as_bytes()).context(IoSnafu { path })?;
+            Ok(key)
+        }
+        Err(source) => Err(source).context(IoSnafu { path }),
+    }
+}
+
+fn write_key_file(path: &Path, pem: &[u8]) -> io::Result<()> {
+    use std::io::Write as _;
+
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+
+    let mut file = options.open(path)?;
+    file.write_all(pem)?;
+    file.sync_all()
+}
+
+#[cfg(test)]
+mod tests;
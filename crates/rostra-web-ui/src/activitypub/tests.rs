@@ -0,0 +1,244 @@
+use std::time::{Duration, SystemTime};
+
+use axum::Router;
+use axum::body::Bytes;
+use axum::extract::State;
+use axum::http::{HeaderMap, HeaderValue, Method, StatusCode, Uri};
+use axum::routing::post;
+use rostra_core::event::{PersonaId, SocialPost};
+use rostra_core::id::RostraIdSecretKey;
+use rostra_core::{ShortEventId, Timestamp};
+use rsa::{RsaPrivateKey, RsaPublicKey};
+use serde_json::json;
+use tokio::sync::mpsc;
+use url::Url;
+
+use super::{ActivityPubError, ApBridge, verify_signature};
+
+fn test_bridge(base_url: &str) -> ApBridge {
+    // Small key, as generating a real one is slow in debug builds
+    let key = RsaPrivateKey::new(&mut rand::thread_rng(), 1024).unwrap();
+    let db = redb::Database::builder()
+        .create_with_backend(redb::backends::InMemoryBackend::new())
+        .unwrap();
+    ApBridge::new(&Url::parse(base_url).unwrap(), key, db).unwrap()
+}
+
+type Received = (Uri, HeaderMap, Bytes);
+
+/// Local inbox recording all requests it receives
+async fn mock_inbox() -> (Url, mpsc::UnboundedReceiver<Received>) {
+    async fn inbox(
+        State(tx): State<mpsc::UnboundedSender<Received>>,
+        uri: Uri,
+        headers: HeaderMap,
+        body: Bytes,
+    ) -> StatusCode {
+        tx.send((uri, headers, body)).unwrap();
+        StatusCode::ACCEPTED
+    }
+
+    let (tx, rx) = mpsc::unbounded_channel();
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::serve(
+            listener,
+            Router::new().route("/inbox", post(inbox)).with_state(tx),
+        )
+        .await
+        .unwrap();
+    });
+    (Url::parse(&format!("http://{addr}/inbox")).unwrap(), rx)
+}
+
+fn test_post(content: &str) -> SocialPost {
+    SocialPost {
+        persona: PersonaId(0),
+        djot_content: Some(content.to_owned()),
+        reply_to: None,
+        reaction: None,
+        attachments: vec![],
+        edit_of: None,
+        quote: None,
+        poll: None,
+        content_warning: None,
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn deliveries_are_signed() {
+    let bridge = test_bridge("https://rostra.example/");
+    let id = RostraIdSecretKey::generate().id();
+    let (inbox, mut received) = mock_inbox().await;
+
+    let activity = bridge.create(id, ShortEventId::ZERO, Timestamp(0), &test_post("Hello"));
+    bridge.deliver(id, &inbox, &activity).await.unwrap();
+
+    let (uri, headers, body) = received.recv().await.unwrap();
+    assert_eq!(
+        serde_json::from_slice::<serde_json::Value>(&body).unwrap(),
+        activity
+    );
+    assert!(headers["signature"].to_str().unwrap().contains(&format!(
+        r#"keyId="https://rostra.example/ap/{id}#main-key""#
+    )));
+
+    let public_key = RsaPublicKey::from(&*bridge.key);
+    let now = SystemTime::now();
+    let verify = |public_key, headers, body| {
+        verify_signature(public_key, &Method::POST, uri.path(), headers, body, now)
+    };
+    verify(&public_key, &headers, &body).unwrap();
+
+    // Tampered body, or a signature of another key
+    assert!(verify(&public_key, &headers, b"{}").is_err());
+    let other_key = RsaPublicKey::from(&*test_bridge("https://other.example/").key);
+    assert!(verify(&other_key, &headers, &body).is_err());
+
+    // Replayed much later
+    assert!(
+        verify_signature(
+            &public_key,
+            &Method::POST,
+            uri.path(),
+            &headers,
+            &body,
+            now + Duration::from_secs(2 * 60 * 60)
+        )
+        .is_err()
+    );
+
+    // Signature not covering the required headers
+    let mut partial = headers.clone();
+    let signature = partial["signature"].to_str().unwrap().replace(
+        r#"headers="(request-target) host date digest""#,
+        r#"headers="digest""#,
+    );
+    partial.insert("signature", HeaderValue::from_str(&signature).unwrap());
+    assert!(verify(&public_key, &partial, &body).is_err());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn non_public_actors_are_not_fetched() {
+    let bridge = test_bridge("https://rostra.example");
+
+    for url in [
+        "http://127.0.0.1/actor",
+        "http://[::1]/actor",
+        "http://10.0.0.1/actor",
+        "http://169.254.169.254/latest/meta-data",
+        "http://[::ffff:192.168.0.1]/actor",
+        "file:///etc/passwd",
+    ] {
+        assert!(matches!(
+            bridge.fetch_actor(&Url::parse(url).unwrap()).await,
+            Err(ActivityPubError::InvalidActor { .. })
+        ));
+    }
+
+    // Host names resolving to internal addresses are rejected as well
+    assert!(
+        bridge
+            .fetch_actor(&Url::parse("http://localhost/actor").unwrap())
+            .await
+            .is_err()
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn follow_with_inbox_elsewhere_is_rejected() {
+    let bridge = test_bridge("https://rostra.example");
+    let id = RostraIdSecretKey::generate().id();
+    let actor = json!({
+        "id": "https://fedi.example/users/alice",
+        "inbox": "https://internal.example/inbox",
+    });
+    let follow = json!({
+        "type": "Follow",
+        "actor": "https://fedi.example/users/alice",
+        "object": bridge.actor_url(id),
+    });
+
+    assert!(matches!(
+        bridge.handle_inbox(id, &actor, &follow).await,
+        Err(ActivityPubError::InvalidActor { .. })
+    ));
+    assert!(bridge.followers(id).unwrap().is_empty());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn create_note_from_post() {
+    let bridge = test_bridge("https://rostra.example");
+    let id = RostraIdSecretKey::generate().id();
+
+    let activity = bridge.create(
+        id,
+        ShortEventId::ZERO,
+        Timestamp(1_700_000_000),
+        &test_post("_Hello_"),
+    );
+
+    assert_eq!(activity["type"], "Create");
+    assert_eq!(activity["actor"], format!("https://rostra.example/ap/{id}"));
+    assert_eq!(activity["object"]["type"], "Note");
+    assert_eq!(activity["object"]["attributedTo"], activity["actor"]);
+    assert_eq!(activity["object"]["published"], "2023-11-14T22:13:20Z");
+    assert!(
+        activity["object"]["content"]
+            .as_str()
+            .unwrap()
+            .contains("<em>Hello</em>")
+    );
+    assert_eq!(activity["object"]["inReplyTo"], json!(null));
+    assert_eq!(activity["object"]["summary"], json!(null));
+    assert_eq!(activity["object"]["sensitive"], false);
+
+    let activity = bridge.create(
+        id,
+        ShortEventId::ZERO,
+        Timestamp(1_700_000_000),
+        &SocialPost {
+            content_warning: Some("Spoilers".into()),
+            ..test_post("_Hello_")
+        },
+    );
+    assert_eq!(activity["object"]["summary"], "Spoilers");
+    assert_eq!(activity["object"]["sensitive"], true);
+
+    let actor = bridge.actor(id, None);
+    assert_eq!(actor["id"], activity["actor"]);
+    assert_eq!(actor["publicKey"]["publicKeyPem"], bridge.public_key_pem());
+    assert_eq!(
+        bridge.webfinger(id)["subject"],
+        format!("acct:{id}@rostra.example")
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn followers_per_identity() {
+    let bridge = test_bridge("https://rostra.example");
+    let id = RostraIdSecretKey::generate().id();
+    let other_id = RostraIdSecretKey::generate().id();
+    let inbox = Url::parse("https://fedi.example/users/alice/inbox").unwrap();
+
+    bridge
+        .insert_follower(id, "https://fedi.example/users/alice", &inbox)
+        .unwrap();
+    bridge
+        .insert_follower(other_id, "https://fedi.example/users/bob", &inbox)
+        .unwrap();
+
+    let followers = bridge.followers(id).unwrap();
+    assert_eq!(followers.len(), 1);
+    assert_eq!(
+        followers["https://fedi.example/users/alice"],
+        inbox.to_string()
+    );
+
+    bridge
+        .remove_follower(id, "https://fedi.example/users/alice")
+        .unwrap();
+    assert!(bridge.followers(id).unwrap().is_empty());
+    assert_eq!(bridge.followers(other_id).unwrap().len(), 1);
+}
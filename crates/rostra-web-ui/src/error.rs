@@ -4,12 +4,14 @@ use axum::http::{HeaderName, HeaderValue, StatusCode};
 use axum::response::{IntoResponse, Redirect, Response};
 use rostra_client::ClientRefError;
 use rostra_client::error::{ActivateError, InitError, PostError};
+use rostra_client::keystore::KeystoreError;
 use rostra_client::multiclient::MultiClientError;
 use rostra_client_db::DbError;
 use rostra_util_error::BoxedError;
 use serde::Serialize;
 use snafu::Snafu;
 use tracing::info;
+use utoipa::ToSchema;
 
 use super::routes::AppJson;
 use crate::UiStateClientError;
@@ -20,6 +22,8 @@ pub enum UserRequestError {
     SomethingNotFound,
     #[snafu(visibility(pub(crate)))]
     InvalidData,
+    Unauthorized,
+    Forbidden,
 }
 
 impl IntoResponse for &UserRequestError {
@@ -27,13 +31,15 @@ impl IntoResponse for &UserRequestError {
         let (status_code, message) = match self {
             UserRequestError::SomethingNotFound => (StatusCode::NOT_FOUND, self.to_string()),
             UserRequestError::InvalidData => (StatusCode::BAD_REQUEST, self.to_string()),
+            UserRequestError::Unauthorized => (StatusCode::UNAUTHORIZED, self.to_string()),
+            UserRequestError::Forbidden => (StatusCode::FORBIDDEN, self.to_string()),
         };
         (status_code, AppJson(UserErrorResponse { message })).into_response()
     }
 }
 
 // How we want user errors responses to be serialized
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
 pub struct UserErrorResponse {
     pub message: String,
 }
@@ -44,6 +50,17 @@ pub enum UnlockError {
     PublicKeyMissing,
     #[snafu(visibility(pub(crate)))]
     IdMismatch,
+    #[snafu(visibility(pub(crate)), display("{source}"))]
+    Keystore {
+        source: KeystoreError,
+    },
+    #[snafu(
+        visibility(pub(crate)),
+        display("Too many failed attempts, try again in {retry_in_secs}s")
+    )]
+    TooManyAttempts {
+        retry_in_secs: u64,
+    },
     #[snafu(transparent)]
     Io {
         source: io::Error,
@@ -0,0 +1,413 @@
+//! Static HTML export of an identity's public posts
+//!
+//! Renders a profile and its posts with the same markup and stylesheet as the
+//! web UI into a self-contained directory that any static file host can
+//! serve. All links between exported pages are relative.
+//!
+//! Image attachments are exported along with the posts. Other attachments, and
+//! ones whose data is not available, are left out.
+
+use std::collections::HashMap;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use maud::{DOCTYPE, Markup, html};
+use rostra_client_db::Database;
+use rostra_client_db::social::SocialPostRecord;
+use rostra_core::Timestamp;
+use rostra_core::event::{PersonaId, SocialPost};
+use rostra_core::id::{ExternalEventId, RostraId, ToShort as _};
+use serde::Serialize;
+use snafu::{ResultExt as _, Snafu};
+use time::OffsetDateTime;
+use tracing::warn;
+
+use crate::LOG_TARGET;
+use crate::routes::content::render_djot;
+use crate::routes::post::render_content_warning;
+
+/// Number of posts fetched from the database at a time
+const EXPORT_PAGE_SIZE: usize = 100;
+
+const POSTS_DIR: &str = "posts";
+const ATTACHMENTS_DIR: &str = "attachments";
+const ASSETS_DIR: &str = "assets";
+const DEFAULT_AVATAR: &str = "icons/circle-user.svg";
+
+#[derive(Debug, Snafu)]
+pub enum ExportSiteError {
+    #[snafu(display("IO error on {}: {source}", path.display()))]
+    Io { source: io::Error, path: PathBuf },
+}
+
+pub type ExportSiteResult<T> = std::result::Result<T, ExportSiteError>;
+
+#[derive(Debug, Serialize)]
+pub struct ExportSiteSummary {
+    pub path: PathBuf,
+    pub posts: usize,
+    pub attachments: usize,
+    /// Attachments left out, as they are not images or not available
+    pub skipped_attachments: usize,
+}
+
+/// Export the profile and posts of `author` (optionally only of `persona`)
+/// from `db` into `out_dir`
+///
+/// Assets (stylesheet and icons) are copied from `assets_dir`, defaulting to
+/// the ones the web UI is using.
+pub async fn export_site(
+    db: &Database,
+    author: RostraId,
+    persona: Option<PersonaId>,
+    assets_dir: Option<&Path>,
+    out_dir: &Path,
+) -> ExportSiteResult<ExportSiteSummary> {
+    let assets_dir = assets_dir
+        .map(ToOwned::to_owned)
+        .unwrap_or_else(crate::default_rostra_assets_dir);
+
+    create_dir(out_dir).await?;
+    create_dir(&out_dir.join(POSTS_DIR)).await?;
+    create_dir(&out_dir.join(ATTACHMENTS_DIR)).await?;
+    copy_assets(&assets_dir, &out_dir.join(ASSETS_DIR)).await?;
+
+    let profile = db.get_social_profile(author).await;
+    let display_name = profile
+        .as_ref()
+        .map(|profile| profile.display_name.clone())
+        .unwrap_or_else(|| author.to_short().to_string());
+    let bio = profile
+        .as_ref()
+        .map(|profile| profile.bio.clone())
+        .unwrap_or_default();
+    let personas = db.get_personas_for_id(author).await;
+
+    let avatar = match profile.and_then(|profile| profile.avatar) {
+        Some((mime, bytes)) if image_ext(&mime).is_some() => {
+            let file = format!("avatar.{}", image_ext(&mime).expect("Just checked"));
+            write_file(&out_dir.join(&file), bytes).await?;
+            file
+        }
+        _ => format!("{ASSETS_DIR}/{DEFAULT_AVATAR}"),
+    };
+
+    let posts = get_all_posts(db, author, persona).await;
+    let (attachments, skipped_attachments) = export_attachments(db, &posts, out_dir).await?;
+
+    let site = Site {
+        author,
+        display_name: &display_name,
+        avatar: &avatar,
+        attachments: &attachments,
+    };
+
+    for post in &posts {
+        let persona_name = personas.get(&post.content.persona).map(String::as_str);
+        let page = site.render_page(
+            "../",
+            &format!("{display_name}: {}", fmt_ts(post.ts)),
+            html! {
+                (site.render_profile_header("../", &bio))
+                (site.render_post("../", post, persona_name))
+                a ."o-exportSite__backLink" href="../index.html" { "All posts" }
+            },
+        );
+        write_file(
+            &out_dir
+                .join(POSTS_DIR)
+                .join(format!("{}.html", post.event_id)),
+            page.into_string(),
+        )
+        .await?;
+    }
+
+    let title = match persona.and_then(|persona| personas.get(&persona)) {
+        Some(persona_name) => format!("{display_name} ({persona_name})"),
+        None => display_name.clone(),
+    };
+    let index = site.render_page(
+        "",
+        &title,
+        html! {
+            (site.render_profile_header("", &bio))
+            div ."o-mainBarTimeline" {
+                @for post in &posts {
+                    div ."o-mainBarTimeline__item -post" {
+                        (site.render_post("", post, personas.get(&post.content.persona).map(String::as_str)))
+                    }
+                }
+                @if posts.is_empty() {
+                    div ."o-mainBarTimeline__item -empty" { "No posts" }
+                }
+            }
+        },
+    );
+    write_file(&out_dir.join("index.html"), index.into_string()).await?;
+
+    Ok(ExportSiteSummary {
+        path: out_dir.to_owned(),
+        posts: posts.len(),
+        attachments: attachments.len(),
+        skipped_attachments,
+    })
+}
+
+/// Shared parts of all exported pages
+struct Site<'a> {
+    author: RostraId,
+    display_name: &'a str,
+    /// Path of the avatar, relative to the site root
+    avatar: &'a str,
+    /// Paths of the exported attachments, relative to the site root
+    attachments: &'a HashMap<ExternalEventId, String>,
+}
+
+impl Site<'_> {
+    /// Full page, with `root` being the relative path to the site root
+    fn render_page(&self, root: &str, title: &str, content: Markup) -> Markup {
+        html! {
+            (DOCTYPE)
+            html lang="en" {
+                head {
+                    meta charset="utf-8";
+                    meta name="viewport" content="width=device-width, initial-scale=1.0";
+                    link rel="stylesheet" type="text/css" href=(format!("{root}{ASSETS_DIR}/style.css"));
+                    link rel="icon" type="image/png" href=(format!("{root}{ASSETS_DIR}/favicon.png"));
+                    title { (title) }
+                }
+                body ."o-body" {
+                    div ."o-pageLayout" {
+                        main ."o-mainBar" { (content) }
+                    }
+                }
+            }
+        }
+    }
+
+    fn render_profile_header(&self, root: &str, bio: &str) -> Markup {
+        html! {
+            div ."m-profileSummary" {
+                img ."m-profileSummary__userImage u-userImage"
+                    src=(format!("{root}{}", self.avatar))
+                    alt=(format!("{}'s avatar", self.display_name))
+                    width="32pt"
+                    height="32pt"
+                    { }
+
+                div ."m-profileSummary__content" {
+                    a ."m-profileSummary__displayName" href=(format!("{root}index.html")) {
+                        (self.display_name)
+                    }
+                    p ."m-profileSummary__bio" { (bio) }
+                    p ."m-profileSummary__id" { code { (self.author) } }
+                }
+            }
+        }
+    }
+
+    fn render_post(
+        &self,
+        root: &str,
+        post: &SocialPostRecord<SocialPost>,
+        persona_name: Option<&str>,
+    ) -> Markup {
+        let content = post.content.djot_content.as_deref().map(render_djot);
+        html! {
+            article #(format!("post-{}", post.event_id)) ."m-postOverview" {
+                div ."m-postOverview__main" {
+                    img ."m-postOverview__userImage u-userImage"
+                        src=(format!("{root}{}", self.avatar))
+                        alt=(format!("{}'s avatar", self.display_name))
+                        width="32pt"
+                        height="32pt"
+                        loading="lazy"
+                    { }
+
+                    div ."m-postOverview__contentSide" {
+                        header ."m-postOverview__header" {
+                            span ."m-postOverview__userHandle" {
+                                (self.display_name)
+                                @if let Some(persona_name) = persona_name {
+                                    span ."m-postOverview__personaDisplayName" {
+                                        (format!("({persona_name})"))
+                                    }
+                                }
+                            }
+                            a ."m-postOverview__postAnchor"
+                                href=(format!("{root}{POSTS_DIR}/{}.html", post.event_id))
+                            {
+                                (fmt_ts(post.ts))
+                            }
+                        }
+
+                        (render_content_warning(post.content.content_warning.as_deref(), html! {
+                            div ."m-postOverview__content -present" {
+                                @if let Some(content) = content {
+                                    (content)
+                                }
+                            }
+                            (self.render_attachments(root, &post.content.attachments))
+                        }))
+                    }
+                }
+            }
+        }
+    }
+
+    fn render_attachments(&self, root: &str, attachments: &[ExternalEventId]) -> Markup {
+        let files: Vec<_> = attachments
+            .iter()
+            .filter_map(|attachment| self.attachments.get(attachment))
+            .collect();
+        html! {
+            @if !files.is_empty() {
+                div ."m-postOverview__attachments" {
+                    @for file in files {
+                        a ."m-postOverview__attachment" href=(format!("{root}{file}")) {
+                            img ."m-postOverview__attachmentImage"
+                                src=(format!("{root}{file}"))
+                                alt="Attachment"
+                                loading="lazy"
+                                {}
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Write the image attachments of `posts` into `out_dir`
+///
+/// Returns the paths of the written ones, relative to `out_dir`, and the
+/// number of attachments left out.
+async fn export_attachments(
+    db: &Database,
+    posts: &[SocialPostRecord<SocialPost>],
+    out_dir: &Path,
+) -> ExportSiteResult<(HashMap<ExternalEventId, String>, usize)> {
+    let mut files = HashMap::new();
+    let mut skipped = 0;
+    for attachment in posts.iter().flat_map(|post| &post.content.attachments) {
+        if files.contains_key(attachment) {
+            continue;
+        }
+        let Some((_, blob)) = db
+            .get_blob(attachment.rostra_id(), attachment.event_id())
+            .await
+        else {
+            warn!(target: LOG_TARGET, %attachment, "Attachment not available, not exporting it");
+            skipped += 1;
+            continue;
+        };
+        let Some(ext) = image_ext(&blob.mime) else {
+            warn!(target: LOG_TARGET, %attachment, mime = %blob.mime, "Attachment not an image, not exporting it");
+            skipped += 1;
+            continue;
+        };
+        let file = format!("{ATTACHMENTS_DIR}/{}.{ext}", attachment.event_id());
+        write_file(&out_dir.join(&file), blob.data).await?;
+        files.insert(*attachment, file);
+    }
+    Ok((files, skipped))
+}
+
+/// All posts of `author` (excluding reactions), newest first
+async fn get_all_posts(
+    db: &Database,
+    author: RostraId,
+    persona: Option<PersonaId>,
+) -> Vec<SocialPostRecord<SocialPost>> {
+    let mut posts = vec![];
+    let mut cursor = None;
+    loop {
+        let (page, next) = db
+            .paginate_social_posts_rev(cursor, EXPORT_PAGE_SIZE, move |post| {
+                post.author == author
+                    && post.content.reaction.is_none()
+                    && post.content.djot_content.is_some()
+                    && persona.is_none_or(|persona| persona == post.content.persona)
+            })
+            .await;
+        posts.extend(page);
+        match next {
+            Some(next) => cursor = Some(next),
+            None => return posts,
+        }
+    }
+}
+
+/// Copy the stylesheet, favicon and icons used by the exported pages
+async fn copy_assets(src: &Path, dst: &Path) -> ExportSiteResult<()> {
+    create_dir(&dst.join("icons")).await?;
+    copy_file(&src.join("favicon.png"), &dst.join("favicon.png")).await?;
+
+    // The stylesheet refers to icons by absolute paths, which would not work
+    // when the site is not hosted at the root of a domain
+    let style_path = src.join("style.css");
+    let style = tokio::fs::read_to_string(&style_path)
+        .await
+        .context(IoSnafu { path: &style_path })?;
+    write_file(
+        &dst.join("style.css"),
+        style.replace("url('/assets/", "url('"),
+    )
+    .await?;
+
+    let icons_dir = src.join("icons");
+    let mut entries = tokio::fs::read_dir(&icons_dir)
+        .await
+        .context(IoSnafu { path: &icons_dir })?;
+    while let Some(entry) = entries
+        .next_entry()
+        .await
+        .context(IoSnafu { path: &icons_dir })?
+    {
+        copy_file(&entry.path(), &dst.join("icons").join(entry.file_name())).await?;
+    }
+    Ok(())
+}
+
+async fn create_dir(path: &Path) -> ExportSiteResult<()> {
+    tokio::fs::create_dir_all(path)
+        .await
+        .context(IoSnafu { path })
+}
+
+async fn copy_file(src: &Path, dst: &Path) -> ExportSiteResult<()> {
+    tokio::fs::copy(src, dst)
+        .await
+        .context(IoSnafu { path: src })?;
+    Ok(())
+}
+
+async fn write_file(path: &Path, content: impl AsRef<[u8]>) -> ExportSiteResult<()> {
+    tokio::fs::write(path, content)
+        .await
+        .context(IoSnafu { path })
+}
+
+fn image_ext(mime: &str) -> Option<&'static str> {
+    Some(match mime {
+        "image/png" => "png",
+        "image/jpeg" => "jpg",
+        "image/gif" => "gif",
+        "image/webp" => "webp",
+        "image/svg+xml" => "svg",
+        _ => return None,
+    })
+}
+
+fn fmt_ts(ts: Timestamp) -> String {
+    let Some(dt) = i64::try_from(ts.0)
+        .ok()
+        .and_then(|secs| OffsetDateTime::from_unix_timestamp(secs).ok())
+    else {
+        return String::new();
+    };
+    format!("{} {:02}:{:02} UTC", dt.date(), dt.hour(), dt.minute())
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,174 @@
+use std::path::Path;
+
+use rostra_client_db::Database;
+use rostra_core::event::content_kind::{Blob, EventContentKind};
+use rostra_core::event::{Event, PersonaId, SocialPost, VerifiedEvent, VerifiedEventContent};
+use rostra_core::id::{ExternalEventId, RostraIdSecretKey};
+use rostra_core::{EventId, ShortEventId};
+
+use super::export_site;
+
+fn build_event<C: EventContentKind>(
+    id_secret: RostraIdSecretKey,
+    parent: Option<EventId>,
+    content: &C,
+) -> VerifiedEventContent {
+    let author = id_secret.id();
+    let content = content.serialize_cbor().unwrap();
+    let event = Event::builder()
+        .author(author)
+        .kind(C::KIND)
+        .maybe_parent_prev(parent.map(Into::into))
+        .content(&content)
+        .singleton(false)
+        .build();
+    VerifiedEventContent::verify(
+        VerifiedEvent::verify_signed(author, event.signed_by(id_secret)).unwrap(),
+        content,
+    )
+    .unwrap()
+}
+
+fn test_post(persona: u8, content: &str, attachments: Vec<ExternalEventId>) -> SocialPost {
+    SocialPost {
+        persona: PersonaId(persona),
+        djot_content: Some(content.to_owned()),
+        reply_to: None,
+        reaction: None,
+        attachments,
+        edit_of: None,
+        quote: None,
+        poll: None,
+        content_warning: None,
+    }
+}
+
+/// Relative `href`s and `src`s in `html`
+fn relative_links(html: &str) -> Vec<&str> {
+    ["href=\"", "src=\""]
+        .into_iter()
+        .flat_map(|attr| html.split(attr).skip(1))
+        .filter_map(|rest| rest.split('"').next())
+        .filter(|link| !link.contains("://") && !link.starts_with('#'))
+        .collect()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn export_site_without_posts() {
+    let dir = tempfile::tempdir().unwrap();
+    let id = RostraIdSecretKey::generate().id();
+    let db = Database::new_in_memory(id).await.unwrap();
+
+    let assets_dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("assets");
+    let summary = export_site(&db, id, None, Some(&assets_dir), dir.path())
+        .await
+        .unwrap();
+    assert_eq!(summary.posts, 0);
+
+    let index = std::fs::read_to_string(dir.path().join("index.html")).unwrap();
+    assert!(index.contains(&id.to_string()));
+    assert!(index.contains("assets/icons/circle-user.svg"));
+    assert!(dir.path().join("assets/icons/circle-user.svg").exists());
+
+    let style = std::fs::read_to_string(dir.path().join("assets/style.css")).unwrap();
+    assert!(!style.contains("url('/assets/"));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn export_site_with_posts() {
+    let dir = tempfile::tempdir().unwrap();
+    let id_secret = RostraIdSecretKey::generate();
+    let id = id_secret.id();
+    let db = Database::new_in_memory(id).await.unwrap();
+
+    let image = build_event(
+        id_secret,
+        None,
+        &Blob {
+            mime: "image/png".into(),
+            data: vec![1, 2, 3],
+        },
+    );
+    let document = build_event(
+        id_secret,
+        Some(image.event_id()),
+        &Blob {
+            mime: "application/pdf".into(),
+            data: vec![4, 5, 6],
+        },
+    );
+    let post = build_event(
+        id_secret,
+        Some(document.event_id()),
+        &test_post(
+            0,
+            "Hello *world*",
+            vec![
+                ExternalEventId::new(id, image.event_id()),
+                ExternalEventId::new(id, document.event_id()),
+            ],
+        ),
+    );
+    let sensitive_post = build_event(
+        id_secret,
+        Some(post.event_id()),
+        &SocialPost {
+            content_warning: Some("Spoilers".into()),
+            ..test_post(0, "The ending", vec![])
+        },
+    );
+    let other_persona_post = build_event(
+        id_secret,
+        Some(sensitive_post.event_id()),
+        &test_post(1, "Elsewhere", vec![]),
+    );
+    for event_content in [
+        &image,
+        &document,
+        &post,
+        &sensitive_post,
+        &other_persona_post,
+    ] {
+        db.process_event_with_content(event_content).await;
+    }
+
+    let assets_dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("assets");
+    let summary = export_site(&db, id, Some(PersonaId(0)), Some(&assets_dir), dir.path())
+        .await
+        .unwrap();
+    assert_eq!(summary.posts, 2);
+    assert_eq!(summary.attachments, 1);
+    assert_eq!(summary.skipped_attachments, 1);
+
+    let posts_dir = dir.path().join("posts");
+    let post_file = |event_id: EventId| format!("{}.html", ShortEventId::from(event_id));
+    assert!(
+        !posts_dir
+            .join(post_file(other_persona_post.event_id()))
+            .exists()
+    );
+    let page = std::fs::read_to_string(posts_dir.join(post_file(post.event_id()))).unwrap();
+    assert!(page.contains("<p>Hello <strong>world</strong></p>"));
+    assert!(!page.contains("<p><p>"));
+    assert!(!page.contains("m-postOverview__contentWarning"));
+
+    let sensitive_page =
+        std::fs::read_to_string(posts_dir.join(post_file(sensitive_post.event_id()))).unwrap();
+    let (warning, content) = sensitive_page
+        .split_once("m-postOverview__contentWarningContent")
+        .unwrap();
+    assert!(warning.contains("Spoilers"));
+    assert!(content.contains("The ending"));
+
+    let index = std::fs::read_to_string(dir.path().join("index.html")).unwrap();
+    assert!(!index.contains("Elsewhere"));
+
+    // All links between exported files resolve
+    for (base, html) in [(posts_dir.as_path(), &page), (dir.path(), &index)] {
+        let links = relative_links(html);
+        assert!(links.iter().any(|link| link.contains("attachments/")));
+        for link in links {
+            assert!(base.join(link).exists(), "Broken link: {link}");
+        }
+    }
+}
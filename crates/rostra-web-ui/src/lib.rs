@@ -1,11 +1,15 @@
+pub mod activitypub;
 pub(crate) mod asset_cache;
 mod error;
+pub mod export;
 mod fragment;
 pub mod html_utils;
 pub mod is_htmx;
 mod routes;
 // TODO: move to own crate
 mod serde_util;
+pub mod session_store;
+pub mod thumbnail;
 
 use std::net::{AddrParseError, SocketAddr};
 use std::path::{Path, PathBuf};
@@ -14,21 +18,26 @@ use std::sync::Arc;
 use std::time::Duration;
 use std::{io, result};
 
+use activitypub::{ActivityPubError, ApBridge};
 use asset_cache::AssetCache;
-use axum::http::header::{ACCEPT, CONTENT_TYPE};
+use axum::http::header::{ACCEPT, AUTHORIZATION, CONTENT_TYPE};
 use axum::http::{HeaderName, HeaderValue, Method};
 use axum::routing::get;
 use axum::{Router, middleware};
-use error::{IdMismatchSnafu, UnlockError, UnlockResult};
+use error::{IdMismatchSnafu, OtherSnafu, RequestResult, UnlockError, UnlockResult};
 use listenfd::ListenFd;
 use rostra_client::error::IdSecretReadError;
 use rostra_client::multiclient::MultiClient;
 use rostra_client::{ClientHandle, ClientRefError};
+use rostra_client_db::{ApiToken, VerifiedApiToken};
 use rostra_core::id::{RostraId, RostraIdSecretKey};
 use rostra_util::is_rostra_dev_mode_set;
 use rostra_util_error::WhateverResult;
+use routes::unlock::backoff::UnlockBackoff;
 use routes::{cache_control, get_static_asset};
+use session_store::{RedbSessionStore, SessionStoreInitError};
 use snafu::{ResultExt as _, Snafu, Whatever, ensure};
+use thumbnail::{ThumbnailCache, ThumbnailError};
 use tokio::net::{TcpListener, TcpSocket};
 use tokio::signal;
 use tower_cookies::CookieManagerLayer;
@@ -37,8 +46,9 @@ use tower_http::compression::CompressionLayer;
 use tower_http::compression::predicate::SizeAbove;
 use tower_http::cors::CorsLayer;
 use tower_http::services::ServeDir;
-use tower_sessions::{Expiry, MemoryStore, SessionManagerLayer};
+use tower_sessions::{Expiry, SessionManagerLayer};
 use tracing::info;
+use url::Url;
 
 pub const UI_ROOT_PATH: &str = "/ui";
 
@@ -89,6 +99,16 @@ pub struct Opts {
     assets_dir: PathBuf,
     pub reuseport: bool,
     pub data_dir: PathBuf,
+    /// Log out sessions inactive for this long
+    pub session_expiry: Duration,
+    /// Public url of this node, enabling the ActivityPub bridge
+    pub activitypub_url: Option<Url>,
+    /// Public url of this node, for absolute links (e.g. in feeds)
+    ///
+    /// Defaults to [`Self::activitypub_url`].
+    pub public_url: Option<Url>,
+    /// Offer identities with a keystore on this node on the unlock page
+    pub list_keystores: bool,
 }
 
 impl Opts {
@@ -99,6 +119,7 @@ impl Opts {
         reuseport: bool,
         data_dir: PathBuf,
         secret_file: Option<PathBuf>,
+        session_expiry: Duration,
     ) -> Self {
         Self {
             listen,
@@ -107,8 +128,31 @@ impl Opts {
             reuseport,
             data_dir,
             secret_file,
+            session_expiry,
+            activitypub_url: None,
+            public_url: None,
+            list_keystores: false,
         }
     }
+
+    /// Expose identities to the fediverse, see [`activitypub`]
+    pub fn with_activitypub_url(mut self, url: Url) -> Self {
+        self.activitypub_url = Some(url);
+        self
+    }
+
+    /// Absolute links to this node, see [`Self::public_url`]
+    pub fn with_public_url(mut self, url: Url) -> Self {
+        self.public_url = Some(url);
+        self
+    }
+
+    /// List keystore identities on the unlock page, see
+    /// [`Self::list_keystores`]
+    pub fn with_list_keystores(mut self, list_keystores: bool) -> Self {
+        self.list_keystores = list_keystores;
+        self
+    }
 }
 
 impl Opts {
@@ -129,7 +173,15 @@ pub type UiStateClientResult<T> = result::Result<T, UiStateClientError>;
 
 pub struct UiState {
     clients: MultiClient,
+    sessions: RedbSessionStore,
+    data_dir: PathBuf,
     assets: Option<Arc<AssetCache>>,
+    activitypub: Option<ApBridge>,
+    /// [`Opts::public_url`], without a trailing slash
+    public_url: Option<String>,
+    thumbnails: ThumbnailCache,
+    list_keystores: bool,
+    unlock_backoff: UnlockBackoff,
 }
 
 impl UiState {
@@ -140,21 +192,61 @@ impl UiState {
         }
     }
 
+    /// Client with a database replicating `id`, without requiring a login
+    ///
+    /// Prefers the database of `id` itself, if it exists on this node.
+    pub async fn replicating_client(&self, id: RostraId) -> RequestResult<Option<ClientHandle>> {
+        if let Some(client) = self
+            .clients
+            .load_existing(id)
+            .await
+            .boxed()
+            .context(OtherSnafu)?
+        {
+            return Ok(Some(client.handle()));
+        }
+        for client in self.clients.all().await {
+            let Ok(db) = client.db() else {
+                continue;
+            };
+            if db.get_head(id).await.is_some() {
+                return Ok(Some(client));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Look up an [`ApiToken`] in the database of the identity it belongs to
+    ///
+    /// No client is started just to check the token.
+    pub async fn verify_api_token(
+        &self,
+        token: &ApiToken,
+    ) -> RequestResult<Option<VerifiedApiToken>> {
+        self.clients
+            .verify_api_token(token)
+            .await
+            .boxed()
+            .context(OtherSnafu)
+    }
+
     pub async fn unlock(
         &self,
         rostra_id: RostraId,
         secret_id: Option<RostraIdSecretKey>,
     ) -> UnlockResult<Option<RostraIdSecretKey>> {
-        let res = if let Some(secret_id) = secret_id {
+        let (client, res) = if let Some(secret_id) = secret_id {
             ensure!(secret_id.id() == rostra_id, IdMismatchSnafu);
             let client = self.clients.load(secret_id.id()).await?;
             client.unlock_active(secret_id).await?;
 
-            Some(secret_id)
+            (client, Some(secret_id))
         } else {
-            self.clients.load(rostra_id).await?;
-            None
+            (self.clients.load(rostra_id).await?, None)
         };
+        if let Some(bridge) = self.activitypub.as_ref() {
+            bridge.start_publisher(client.handle());
+        }
         Ok(res)
     }
 }
@@ -183,6 +275,18 @@ pub enum WebUiServerError {
         source: UnlockError,
     },
 
+    SessionStore {
+        source: SessionStoreInitError,
+    },
+
+    ActivityPub {
+        source: ActivityPubError,
+    },
+
+    Thumbnails {
+        source: ThumbnailError,
+    },
+
     ListenAddr {
         source: AddrParseError,
     },
@@ -216,9 +320,34 @@ impl Server {
                     .context(AssetsLoadSnafu)?,
             ))
         };
+        let sessions = RedbSessionStore::open(&opts.data_dir)
+            .await
+            .context(SessionStoreSnafu)?;
+        let activitypub = match opts.activitypub_url.as_ref() {
+            Some(url) => Some(
+                ApBridge::open(&opts.data_dir, url)
+                    .await
+                    .context(ActivityPubSnafu)?,
+            ),
+            None => None,
+        };
+        let thumbnails = ThumbnailCache::open(&opts.data_dir)
+            .await
+            .context(ThumbnailsSnafu)?;
         let state = Arc::new(UiState {
             clients,
+            sessions,
+            data_dir: opts.data_dir.clone(),
             assets: assets.clone(),
+            activitypub,
+            public_url: opts
+                .public_url
+                .as_ref()
+                .or(opts.activitypub_url.as_ref())
+                .map(|url| url.as_str().trim_end_matches('/').to_owned()),
+            thumbnails,
+            list_keystores: opts.list_keystores,
+            unlock_backoff: UnlockBackoff::default(),
         });
 
         info!("Listening on {}", listener.local_addr()?);
@@ -283,9 +412,11 @@ impl Server {
             }
         }
 
-        let session_store = MemoryStore::default();
-        let session_layer = SessionManagerLayer::new(session_store)
-            .with_expiry(Expiry::OnInactivity(time::Duration::minutes(2 * 24 * 60)));
+        let session_layer = SessionManagerLayer::new(self.state.sessions.clone()).with_expiry(
+            Expiry::OnInactivity(
+                time::Duration::try_from(self.opts.session_expiry).unwrap_or(time::Duration::MAX),
+            ),
+        );
 
         axum::serve(
             self.listener,
@@ -319,7 +450,12 @@ fn compression_layer() -> CompressionLayer<SizeAbove> {
 fn cors_layer(opts: &Opts, listen: SocketAddr) -> ServerResult<CorsLayer> {
     Ok(CorsLayer::new()
         .allow_credentials(true)
-        .allow_headers([ACCEPT, CONTENT_TYPE, HeaderName::from_static("csrf-token")])
+        .allow_headers([
+            ACCEPT,
+            AUTHORIZATION,
+            CONTENT_TYPE,
+            HeaderName::from_static("csrf-token"),
+        ])
         .max_age(Duration::from_secs(86400))
         .allow_origin(opts.cors_origin_url_header(listen).context(CorsSnafu)?)
         .allow_methods([
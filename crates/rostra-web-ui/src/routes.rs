@@ -1,19 +1,28 @@
+mod activitypub;
 mod add_followee;
+mod api;
+mod api_tokens;
 mod avatar;
-mod content;
+mod blob;
+pub(crate) mod content;
 mod cookies;
+mod diagnostics;
+pub(crate) mod feed;
 mod new_post;
-mod post;
+mod outbox;
+mod poll;
+pub(crate) mod post;
 mod profile;
 mod profile_self;
+mod thumbnail;
 mod timeline;
-mod unlock;
+pub(crate) mod unlock;
 
 use std::sync::Arc;
 
 use axum::Router;
 use axum::body::Body;
-use axum::extract::{FromRequest, Path, Request, State};
+use axum::extract::{DefaultBodyLimit, FromRequest, Path, Request, State};
 use axum::http::header::{self, ACCEPT_ENCODING, CONTENT_ENCODING, CONTENT_TYPE};
 use axum::http::{HeaderMap, HeaderValue, StatusCode};
 use axum::middleware::{self, Next};
@@ -109,11 +118,20 @@ pub async fn get_static_asset(
     (resp_headers, content).into_response()
 }
 
+/// `Cache-Control` of responses that can never change, e.g. because they are
+/// addressed by an event id
+pub const IMMUTABLE_CACHE_CONTROL: &str = "public, max-age=31536000, immutable";
+
 pub async fn cache_control(request: Request, next: Next) -> Response {
     let mut response = next.run(request).await;
 
+    // Handlers know better
+    if response.headers().contains_key(header::CACHE_CONTROL) {
+        return response;
+    }
+
     if let Some(content_type) = response.headers().get(CONTENT_TYPE) {
-        const NON_CACHEABLE_CONTENT_TYPES: &[&str] = &["text/html"];
+        const NON_CACHEABLE_CONTENT_TYPES: &[&str] = &["text/html", "application/json"];
         const SHORT_CACHE_CONTENT_TYPES: &[&str] = &["text/css"];
 
         let cache_duration_secs = if SHORT_CACHE_CONTENT_TYPES
@@ -159,6 +177,7 @@ pub fn route_handler(state: SharedState) -> Router<Arc<UiState>> {
         .route("/ui/followees", get(timeline::get_followees))
         .route("/ui/network", get(timeline::get_network))
         .route("/ui/notifications", get(timeline::get_notifications))
+        .route("/ui/tag/{tag}", get(timeline::get_tag))
         .route("/ui/profile/{id}", get(profile::get_profile))
         .route(
             "/ui/profile/{id}/follow",
@@ -166,7 +185,10 @@ pub fn route_handler(state: SharedState) -> Router<Arc<UiState>> {
         )
         .route("/ui/avatar/{id}", get(avatar::get))
         .route("/ui/updates", get(timeline::get_updates))
-        .route("/ui/post/{author}/{event}", get(post::get_single_post))
+        .route(
+            "/ui/post/{author}/{event}",
+            get(post::get_single_post).delete(post::delete_post),
+        )
         .route("/ui/post", post(new_post::post_new_post))
         .route("/ui/post/preview", post(new_post::get_post_preview))
         .route(
@@ -174,15 +196,63 @@ pub fn route_handler(state: SharedState) -> Router<Arc<UiState>> {
             post(new_post::get_post_preview_dialog),
         )
         .route("/ui/post/reply_to", get(new_post::get_reply_to))
+        .route("/ui/post/quote", get(new_post::get_quote))
+        .route("/ui/post/mentions", get(new_post::get_mention_suggestions))
+        .route("/ui/post/edit", get(new_post::get_edit_post_form))
+        .route("/ui/post/draft", post(new_post::post_draft))
+        .route("/ui/post/drafts", get(new_post::get_drafts))
+        .route(
+            "/ui/post/draft/{id}",
+            get(new_post::get_draft).delete(new_post::delete_draft),
+        )
+        .route("/ui/poll/{author}/{event}/vote", post(poll::post_vote))
+        .route(
+            "/ui/post/{author}/{event}/history",
+            get(post::get_post_history),
+        )
+        .route(
+            "/ui/blob",
+            post(blob::post_blob).layer(DefaultBodyLimit::max(blob::UPLOAD_BODY_LIMIT)),
+        )
+        .route("/ui/blob/{author}/{event}", get(blob::get_blob))
+        .route(
+            "/ui/thumbnail/{author}/{event}",
+            get(thumbnail::get_thumbnail),
+        )
         .route("/ui/followee", post(add_followee::add_followee))
         .route("/ui/unlock", get(unlock::get).post(unlock::post_unlock))
         .route("/ui/unlock/logout", get(unlock::get).post(unlock::logout))
+        .route("/ui/unlock/logout_all", post(unlock::logout_all))
         .route("/ui/unlock/random", get(unlock::get_random))
+        .route("/ui/unlock/keystore", post(unlock::post_unlock_keystore))
         .route("/ui/comments/{event_id}", get(timeline::get_post_comments))
+        .route(
+            "/ui/settings/content_warnings",
+            post(timeline::post_content_warnings_setting),
+        )
+        .route("/ui/diagnostics", get(diagnostics::get_diagnostics))
+        .route(
+            "/ui/tokens",
+            get(api_tokens::get_api_tokens).post(api_tokens::post_api_token),
+        )
+        .route(
+            "/ui/tokens/{hash}/revoke",
+            post(api_tokens::post_revoke_api_token),
+        )
+        .route("/ui/outbox", get(outbox::get_outbox))
+        .route("/ui/outbox/{id}", post(outbox::post_outbox_edit))
+        .route("/ui/outbox/{id}/cancel", post(outbox::post_outbox_cancel))
         .route(
             "/ui/self/edit",
-            get(profile_self::get_self_account_edit).post(profile_self::post_self_account_edit),
+            get(profile_self::get_self_account_edit)
+                // Avatars are shrunk before publishing, so allow uploading large pictures
+                .post(profile_self::post_self_account_edit)
+                .layer(DefaultBodyLimit::max(blob::UPLOAD_BODY_LIMIT)),
         )
+        .route("/feed/{file}", get(feed::get_feed))
+        .route("/.well-known/webfinger", get(activitypub::get_webfinger))
+        .nest("/ap", activitypub::route_handler())
+        .nest("/api/v1", api::route_handler())
         // .route("/a/", put(account_new))
         // .route("/t/", put(token_new))
         // .route("/m/", put(metric_new).get(metric_find))
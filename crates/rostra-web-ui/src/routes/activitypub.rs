@@ -0,0 +1,197 @@
+//! ActivityPub endpoints of the bridge, see [`crate::activitypub`]
+
+use std::sync::Arc;
+
+use axum::Router;
+use axum::body::Bytes;
+use axum::extract::{Path, Query, State};
+use axum::http::header::CONTENT_TYPE;
+use axum::http::{HeaderMap, Method, Uri};
+use axum::response::IntoResponse;
+use axum::routing::{get, post};
+use rostra_client::ClientHandle;
+use rostra_core::ShortEventId;
+use rostra_core::id::RostraId;
+use serde::Deserialize;
+use serde_json::Value;
+use snafu::ResultExt as _;
+use tracing::debug;
+
+use crate::activitypub::{AP_CONTENT_TYPE, ActivityPubError, ApBridge};
+use crate::error::{OtherSnafu, RequestResult, UserRequestError, UserSnafu};
+use crate::{LOG_TARGET, SharedState, UiState};
+
+/// Number of most recent posts in outboxes
+const OUTBOX_SIZE: usize = 50;
+
+pub fn route_handler() -> Router<Arc<UiState>> {
+    Router::new()
+        .route("/{id}", get(get_actor))
+        .route("/{id}/outbox", get(get_outbox))
+        .route("/{id}/followers", get(get_followers))
+        .route("/{id}/inbox", post(post_inbox))
+        .route("/{id}/post/{event_id}", get(get_note))
+}
+
+#[derive(Deserialize)]
+pub struct WebFingerQuery {
+    resource: String,
+}
+
+/// `/.well-known/webfinger?resource=acct:{rostra_id}@{domain}`
+pub async fn get_webfinger(
+    state: State<SharedState>,
+    Query(query): Query<WebFingerQuery>,
+) -> RequestResult<impl IntoResponse> {
+    let Some(id) = query
+        .resource
+        .strip_prefix("acct:")
+        .and_then(|acct| acct.split_once('@'))
+        .and_then(|(user, _domain)| user.parse::<RostraId>().ok())
+    else {
+        return Err(UserRequestError::SomethingNotFound).context(UserSnafu);
+    };
+    let (bridge, _client) = state.bridged_client(id).await?;
+
+    Ok((
+        [(CONTENT_TYPE, "application/jrd+json")],
+        bridge.webfinger(id).to_string(),
+    ))
+}
+
+async fn get_actor(
+    state: State<SharedState>,
+    Path(id): Path<RostraId>,
+) -> RequestResult<impl IntoResponse> {
+    let (bridge, client) = state.bridged_client(id).await?;
+    let profile = client.db()?.get_social_profile(id).await;
+
+    Ok(ap_json(bridge.actor(id, profile.as_ref())))
+}
+
+async fn get_outbox(
+    state: State<SharedState>,
+    Path(id): Path<RostraId>,
+) -> RequestResult<impl IntoResponse> {
+    let (bridge, client) = state.bridged_client(id).await?;
+    let (posts, _) = client
+        .db()?
+        .paginate_social_posts_rev(None, OUTBOX_SIZE, move |post| {
+            post.author == id
+                && post.content.reaction.is_none()
+                && post.content.djot_content.is_some()
+        })
+        .await;
+
+    let items: Vec<_> = posts
+        .iter()
+        .map(|post| bridge.create(id, post.event_id, post.ts, &post.content))
+        .collect();
+    Ok(ap_json(bridge.collection(
+        format!("{}/outbox", bridge.actor_url(id)),
+        items.len(),
+        items,
+    )))
+}
+
+async fn get_followers(
+    state: State<SharedState>,
+    Path(id): Path<RostraId>,
+) -> RequestResult<impl IntoResponse> {
+    let (bridge, _client) = state.bridged_client(id).await?;
+    let followers = bridge.followers(id).boxed().context(OtherSnafu)?;
+
+    // Only the count, followers are not public
+    Ok(ap_json(bridge.collection(
+        format!("{}/followers", bridge.actor_url(id)),
+        followers.len(),
+        vec![],
+    )))
+}
+
+async fn get_note(
+    state: State<SharedState>,
+    Path((id, event_id)): Path<(RostraId, ShortEventId)>,
+) -> RequestResult<impl IntoResponse> {
+    let (bridge, client) = state.bridged_client(id).await?;
+    let Some(post) = client
+        .db()?
+        .get_social_post(event_id)
+        .await
+        .filter(|post| post.author == id && post.content.djot_content.is_some())
+    else {
+        return Err(UserRequestError::SomethingNotFound).context(UserSnafu);
+    };
+
+    let mut note = bridge.note(id, post.event_id, post.ts, &post.content);
+    note["@context"] = Value::from("https://www.w3.org/ns/activitystreams");
+    Ok(ap_json(note))
+}
+
+async fn post_inbox(
+    state: State<SharedState>,
+    Path(id): Path<RostraId>,
+    uri: Uri,
+    headers: HeaderMap,
+    body: Bytes,
+) -> RequestResult<impl IntoResponse> {
+    let (bridge, _client) = state.bridged_client(id).await?;
+    let target = uri
+        .path_and_query()
+        .map(|path| path.as_str())
+        .unwrap_or_else(|| uri.path());
+
+    let actor = match bridge
+        .verify_incoming(&Method::POST, target, &headers, &body)
+        .await
+    {
+        Ok(actor) => actor,
+        Err(
+            err @ (ActivityPubError::InvalidSignature { .. }
+            | ActivityPubError::InvalidActor { .. }),
+        ) => {
+            debug!(target: LOG_TARGET, %err, "Rejected inbox request");
+            return Err(UserRequestError::Unauthorized).context(UserSnafu);
+        }
+        Err(err) => return Err(err).boxed().context(OtherSnafu),
+    };
+    let Ok(activity) = serde_json::from_slice::<Value>(&body) else {
+        return Err(UserRequestError::InvalidData).context(UserSnafu);
+    };
+
+    bridge
+        .handle_inbox(id, &actor, &activity)
+        .await
+        .boxed()
+        .context(OtherSnafu)?;
+
+    Ok(axum::http::StatusCode::ACCEPTED)
+}
+
+fn ap_json(value: Value) -> impl IntoResponse {
+    ([(CONTENT_TYPE, AP_CONTENT_TYPE)], value.to_string())
+}
+
+impl UiState {
+    /// The bridge and client of `id`, if the bridge is enabled and `id` has a
+    /// database on this node
+    ///
+    /// Makes sure new posts of `id` get pushed to its followers.
+    async fn bridged_client(&self, id: RostraId) -> RequestResult<(&ApBridge, ClientHandle)> {
+        let Some(bridge) = self.activitypub.as_ref() else {
+            return Err(UserRequestError::SomethingNotFound).context(UserSnafu);
+        };
+        let Some(client) = self
+            .clients
+            .load_existing(id)
+            .await
+            .boxed()
+            .context(OtherSnafu)?
+        else {
+            return Err(UserRequestError::SomethingNotFound).context(UserSnafu);
+        };
+        let client = client.handle();
+        bridge.start_publisher(client.clone());
+        Ok((bridge, client))
+    }
+}
@@ -0,0 +1,506 @@
+//! Versioned JSON API
+//!
+//! Mirrors the capabilities of the HTML UI for scripting against a running
+//! node. The OpenAPI description is served under `/api/v1/openapi.json`.
+
+pub mod model;
+
+use std::sync::Arc;
+
+use axum::Router;
+use axum::extract::{FromRequestParts, Path, Query, State};
+use axum::http::{header, request};
+use axum::routing::{get, post, put};
+use model::{
+    ApiCursor, ApiFollow, ApiFollowee, ApiNewPost, ApiPaginationQuery, ApiPersonaSelector,
+    ApiPersonaSelectorMode, ApiPost, ApiPostsPage, ApiProfile, ApiProfileLink, ApiProfileUpdate,
+    ApiPublished,
+};
+use rostra_client_db::{ApiToken, ApiTokenScope};
+use rostra_core::ShortEventId;
+use rostra_core::event::{EventContentKind as _, SocialProfileUpdate};
+use rostra_core::id::{RostraId, RostraIdSecretKey};
+use serde::Deserialize;
+use snafu::ResultExt as _;
+use utoipa::openapi::security::{HttpAuthScheme, HttpBuilder, SecurityScheme};
+use utoipa::{Modify, OpenApi, ToSchema};
+
+use super::AppJson;
+use super::timeline::TimelineMode;
+use super::unlock::session::UserSession;
+use crate::error::{
+    ReadOnlyModeSnafu, RequestError, RequestResult, UserErrorResponse, UserRequestError, UserSnafu,
+};
+use crate::{SharedState, UiState};
+
+/// Number of comments returned per page
+const COMMENTS_PAGE_SIZE: usize = 100;
+
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "Rostra API", version = "1"),
+    modifiers(&BearerSecurity),
+    security(("bearer" = [])),
+    paths(
+        get_timeline,
+        get_profile,
+        get_profile_posts,
+        get_post,
+        get_post_comments,
+        post_post,
+        get_followees,
+        put_followee,
+        delete_followee,
+        put_self_profile,
+    ),
+    components(schemas(
+        ApiCursor,
+        ApiPost,
+        ApiPostsPage,
+        ApiProfile,
+        ApiPersonaSelectorMode,
+        ApiPersonaSelector,
+        ApiFollowee,
+        ApiNewPost,
+        ApiFollow,
+        ApiProfileUpdate,
+        ApiProfileLink,
+        ApiPublished,
+        ApiTimeline,
+        UserErrorResponse,
+    ))
+)]
+pub struct ApiDoc;
+
+/// Declares the `Authorization: Bearer` [`ApiToken`] scheme
+struct BearerSecurity;
+
+impl Modify for BearerSecurity {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        openapi
+            .components
+            .get_or_insert_with(Default::default)
+            .add_security_scheme(
+                "bearer",
+                SecurityScheme::Http(HttpBuilder::new().scheme(HttpAuthScheme::Bearer).build()),
+            );
+    }
+}
+
+pub fn route_handler() -> Router<Arc<UiState>> {
+    Router::new()
+        .route("/openapi.json", get(get_openapi))
+        .route("/timeline/{mode}", get(get_timeline))
+        .route("/profile/{id}", get(get_profile))
+        .route("/profile/{id}/posts", get(get_profile_posts))
+        .route("/post", post(post_post))
+        .route("/post/{author}/{event_id}", get(get_post))
+        .route("/post/{author}/{event_id}/comments", get(get_post_comments))
+        .route("/followees", get(get_followees))
+        .route("/followees/{id}", put(put_followee).delete(delete_followee))
+        .route("/self/profile", put(put_self_profile))
+}
+
+/// Caller of an API request
+///
+/// Authenticated either with an `Authorization: Bearer` [`ApiToken`], or with
+/// the [`UserSession`] of the web UI, which has the [`ApiTokenScope::Full`]
+/// scope. Unlike [`UserSession`], responds with `401` instead of redirecting
+/// to the unlock page.
+pub struct ApiSession {
+    id: RostraId,
+    id_secret: Option<RostraIdSecretKey>,
+    scope: ApiTokenScope,
+}
+
+impl ApiSession {
+    pub fn id(&self) -> RostraId {
+        self.id
+    }
+
+    /// Secret to sign posts with
+    fn post_id_secret(&self) -> RequestResult<RostraIdSecretKey> {
+        if !self.scope.can_post() {
+            return Err(UserRequestError::Forbidden).context(UserSnafu);
+        }
+        self.id_secret.ok_or_else(|| ReadOnlyModeSnafu.build())
+    }
+
+    /// Secret to sign any other event with
+    fn full_id_secret(&self) -> RequestResult<RostraIdSecretKey> {
+        if self.scope != ApiTokenScope::Full {
+            return Err(UserRequestError::Forbidden).context(UserSnafu);
+        }
+        self.id_secret.ok_or_else(|| ReadOnlyModeSnafu.build())
+    }
+}
+
+impl FromRequestParts<SharedState> for ApiSession {
+    type Rejection = RequestError;
+
+    async fn from_request_parts(
+        req: &mut request::Parts,
+        state: &SharedState,
+    ) -> Result<Self, Self::Rejection> {
+        if let Some(auth) = req.headers.get(header::AUTHORIZATION) {
+            let Some(token) = auth
+                .to_str()
+                .ok()
+                .and_then(|auth| auth.strip_prefix("Bearer "))
+                .and_then(|token| token.trim().parse::<ApiToken>().ok())
+            else {
+                return Err(UserRequestError::Unauthorized).context(UserSnafu);
+            };
+            let Some(verified) = state.verify_api_token(&token).await? else {
+                return Err(UserRequestError::Unauthorized).context(UserSnafu);
+            };
+            return Ok(Self {
+                id: token.rostra_id(),
+                id_secret: verified.id_secret,
+                scope: verified.scope,
+            });
+        }
+
+        match UserSession::from_request_parts(req, state).await {
+            Ok(session) => Ok(Self {
+                id: session.id(),
+                id_secret: session.id_secret().ok(),
+                scope: ApiTokenScope::Full,
+            }),
+            Err(RequestError::LoginRequired) => {
+                Err(UserRequestError::Unauthorized).context(UserSnafu)
+            }
+            Err(err) => Err(err),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ApiTimeline {
+    /// Posts of followed identities
+    Followees,
+    /// Posts of the whole known network
+    Network,
+    /// Replies to own posts
+    Notifications,
+}
+
+impl From<ApiTimeline> for TimelineMode {
+    fn from(timeline: ApiTimeline) -> Self {
+        match timeline {
+            ApiTimeline::Followees => TimelineMode::Followees,
+            ApiTimeline::Network => TimelineMode::Network,
+            ApiTimeline::Notifications => TimelineMode::Notifications,
+        }
+    }
+}
+
+async fn get_openapi() -> AppJson<utoipa::openapi::OpenApi> {
+    AppJson(ApiDoc::openapi())
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/timeline/{mode}",
+    params(("mode" = ApiTimeline, Path), ApiPaginationQuery),
+    responses((status = 200, body = ApiPostsPage))
+)]
+async fn get_timeline(
+    state: State<SharedState>,
+    session: ApiSession,
+    Path(mode): Path<ApiTimeline>,
+    Query(query): Query<ApiPaginationQuery>,
+) -> RequestResult<AppJson<ApiPostsPage>> {
+    Ok(AppJson(
+        state
+            .api_posts_page(&session, TimelineMode::from(mode), query)
+            .await?,
+    ))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/profile/{id}",
+    params(("id" = String, Path)),
+    responses(
+        (status = 200, body = ApiProfile),
+        (status = 404, body = UserErrorResponse)
+    )
+)]
+async fn get_profile(
+    state: State<SharedState>,
+    session: ApiSession,
+    Path(id): Path<RostraId>,
+) -> RequestResult<AppJson<ApiProfile>> {
+    let client = state.client(session.id()).await?;
+    let Some(profile) = state
+        .get_social_profile_opt(id, &client.client_ref()?)
+        .await
+    else {
+        return Err(UserRequestError::SomethingNotFound).context(UserSnafu);
+    };
+    Ok(AppJson(ApiProfile::new(id, profile)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/profile/{id}/posts",
+    params(("id" = String, Path), ApiPaginationQuery),
+    responses((status = 200, body = ApiPostsPage))
+)]
+async fn get_profile_posts(
+    state: State<SharedState>,
+    session: ApiSession,
+    Path(id): Path<RostraId>,
+    Query(query): Query<ApiPaginationQuery>,
+) -> RequestResult<AppJson<ApiPostsPage>> {
+    Ok(AppJson(
+        state
+            .api_posts_page(&session, TimelineMode::Profile(id), query)
+            .await?,
+    ))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/post/{author}/{event_id}",
+    params(("author" = String, Path), ("event_id" = String, Path)),
+    responses(
+        (status = 200, body = ApiPost),
+        (status = 404, body = UserErrorResponse)
+    )
+)]
+async fn get_post(
+    state: State<SharedState>,
+    session: ApiSession,
+    Path((author, event_id)): Path<(RostraId, ShortEventId)>,
+) -> RequestResult<AppJson<ApiPost>> {
+    let post = state
+        .client(session.id())
+        .await?
+        .db()?
+        .get_social_post(event_id)
+        .await
+        .filter(|post| post.author == author);
+    let Some(post) = post else {
+        return Err(UserRequestError::SomethingNotFound).context(UserSnafu);
+    };
+    Ok(AppJson(post.into()))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/post/{author}/{event_id}/comments",
+    params(("author" = String, Path), ("event_id" = String, Path), ApiPaginationQuery),
+    responses((status = 200, body = ApiPostsPage))
+)]
+async fn get_post_comments(
+    state: State<SharedState>,
+    session: ApiSession,
+    Path((_author, event_id)): Path<(RostraId, ShortEventId)>,
+    Query(query): Query<ApiPaginationQuery>,
+) -> RequestResult<AppJson<ApiPostsPage>> {
+    let (posts, next) = state
+        .client(session.id())
+        .await?
+        .db()?
+        .paginate_social_post_comments_rev(event_id, query.cursor(), COMMENTS_PAGE_SIZE)
+        .await;
+    Ok(AppJson(ApiPostsPage {
+        posts: posts.into_iter().map(Into::into).collect(),
+        next: next.map(Into::into),
+    }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/post",
+    request_body = ApiNewPost,
+    responses((status = 200, body = ApiPublished))
+)]
+async fn post_post(
+    state: State<SharedState>,
+    session: ApiSession,
+    AppJson(input): AppJson<ApiNewPost>,
+) -> RequestResult<AppJson<ApiPublished>> {
+    let event = state
+        .client(session.id())
+        .await?
+        .client_ref()?
+        .social_post(
+            session.post_id_secret()?,
+            input.content,
+            input.reply_to,
+            input.persona.unwrap_or_default(),
+            input.attachments,
+            input.quote,
+            input.content_warning,
+        )
+        .await?;
+    Ok(AppJson(ApiPublished {
+        event_id: event.event_id.into(),
+    }))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/followees",
+    responses((status = 200, body = Vec<ApiFollowee>))
+)]
+async fn get_followees(
+    state: State<SharedState>,
+    session: ApiSession,
+) -> RequestResult<AppJson<Vec<ApiFollowee>>> {
+    let followees = state
+        .client(session.id())
+        .await?
+        .db()?
+        .get_followees(session.id())
+        .await;
+    Ok(AppJson(
+        followees
+            .into_iter()
+            .map(|(rostra_id, selector)| ApiFollowee {
+                rostra_id,
+                selector: selector.into(),
+            })
+            .collect(),
+    ))
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/v1/followees/{id}",
+    params(("id" = String, Path)),
+    request_body = ApiFollow,
+    responses((status = 200, body = ApiPublished))
+)]
+async fn put_followee(
+    state: State<SharedState>,
+    session: ApiSession,
+    Path(id): Path<RostraId>,
+    AppJson(input): AppJson<ApiFollow>,
+) -> RequestResult<AppJson<ApiPublished>> {
+    let event = state
+        .client(session.id())
+        .await?
+        .client_ref()?
+        .follow(session.full_id_secret()?, id, input.selector.into())
+        .await?;
+    Ok(AppJson(ApiPublished {
+        event_id: event.event_id.into(),
+    }))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/v1/followees/{id}",
+    params(("id" = String, Path)),
+    responses((status = 200, body = ApiPublished))
+)]
+async fn delete_followee(
+    state: State<SharedState>,
+    session: ApiSession,
+    Path(id): Path<RostraId>,
+) -> RequestResult<AppJson<ApiPublished>> {
+    let event = state
+        .client(session.id())
+        .await?
+        .client_ref()?
+        .unfollow(session.full_id_secret()?, id)
+        .await?;
+    Ok(AppJson(ApiPublished {
+        event_id: event.event_id.into(),
+    }))
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/v1/self/profile",
+    request_body = ApiProfileUpdate,
+    responses((status = 200, body = ApiPublished))
+)]
+async fn put_self_profile(
+    state: State<SharedState>,
+    session: ApiSession,
+    AppJson(input): AppJson<ApiProfileUpdate>,
+) -> RequestResult<AppJson<ApiPublished>> {
+    let client = state.client(session.id()).await?;
+    let client_ref = client.client_ref()?;
+    if input
+        .header
+        .flatten()
+        .is_some_and(|header| header.rostra_id() != session.id())
+    {
+        return Err(UserRequestError::InvalidData).context(UserSnafu);
+    }
+    let existing = client_ref.db().get_social_profile(session.id()).await;
+
+    let profile = SocialProfileUpdate {
+        display_name: input.display_name,
+        bio: input.bio,
+        // Avatar uploads are not supported over the API, so keep the existing one
+        avatar: existing
+            .as_ref()
+            .and_then(|existing| existing.avatar.clone()),
+        links: match input.links {
+            Some(links) => links.into_iter().map(Into::into).collect(),
+            None => existing
+                .as_ref()
+                .map(|existing| existing.links.clone())
+                .unwrap_or_default(),
+        },
+        pronouns: match input.pronouns {
+            Some(pronouns) => Some(pronouns).filter(|pronouns| !pronouns.is_empty()),
+            None => existing
+                .as_ref()
+                .and_then(|existing| existing.pronouns.clone()),
+        },
+        location: match input.location {
+            Some(location) => Some(location).filter(|location| !location.is_empty()),
+            None => existing
+                .as_ref()
+                .and_then(|existing| existing.location.clone()),
+        },
+        header: match input.header {
+            Some(header) => header,
+            None => existing.as_ref().and_then(|existing| existing.header),
+        },
+        persona_descriptions: match input.persona_descriptions {
+            Some(persona_descriptions) => persona_descriptions,
+            None => existing
+                .map(|existing| existing.persona_descriptions)
+                .unwrap_or_default(),
+        },
+    };
+    if profile.validate().is_err() {
+        return Err(UserRequestError::InvalidData).context(UserSnafu);
+    }
+
+    let event = client_ref
+        .post_social_profile_update(session.full_id_secret()?, profile)
+        .await?;
+    Ok(AppJson(ApiPublished {
+        event_id: event.event_id.into(),
+    }))
+}
+
+impl UiState {
+    async fn api_posts_page(
+        &self,
+        session: &ApiSession,
+        mode: TimelineMode,
+        query: ApiPaginationQuery,
+    ) -> RequestResult<ApiPostsPage> {
+        let client = self.client(session.id()).await?;
+        let (posts, next) = mode.get_posts(&client.client_ref()?, query.cursor()).await;
+        Ok(ApiPostsPage {
+            posts: posts.into_iter().map(Into::into).collect(),
+            next: next.map(Into::into),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests;
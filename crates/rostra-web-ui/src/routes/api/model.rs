@@ -0,0 +1,287 @@
+//! JSON types of the `/api/v1` endpoints
+
+use std::collections::BTreeMap;
+
+use rostra_client_db::IdSocialProfileRecord;
+use rostra_client_db::social::{EventPaginationCursor, SocialPostRecord};
+use rostra_core::event::{PersonaId, PersonaSelector, SocialPost, SocialProfileLink};
+use rostra_core::id::RostraId;
+use rostra_core::{ExternalEventId, ShortEventId, Timestamp};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::serde_util::null_as_some_none;
+
+/// Pagination cursor, as returned in [`ApiPostsPage::next`]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, ToSchema)]
+pub struct ApiCursor {
+    #[schema(value_type = u64)]
+    pub ts: Timestamp,
+    #[schema(value_type = String)]
+    pub event_id: ShortEventId,
+}
+
+impl From<EventPaginationCursor> for ApiCursor {
+    fn from(cursor: EventPaginationCursor) -> Self {
+        Self {
+            ts: cursor.ts,
+            event_id: cursor.event_id,
+        }
+    }
+}
+
+impl From<ApiCursor> for EventPaginationCursor {
+    fn from(cursor: ApiCursor) -> Self {
+        Self {
+            ts: cursor.ts,
+            event_id: cursor.event_id,
+        }
+    }
+}
+
+/// Optional pagination query parameters
+///
+/// Both have to be set for the cursor to be used.
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ApiPaginationQuery {
+    #[param(value_type = Option<u64>)]
+    pub ts: Option<Timestamp>,
+    #[param(value_type = Option<String>)]
+    pub event_id: Option<ShortEventId>,
+}
+
+impl ApiPaginationQuery {
+    pub fn cursor(&self) -> Option<EventPaginationCursor> {
+        self.ts.and_then(|ts| {
+            self.event_id
+                .map(|event_id| EventPaginationCursor { ts, event_id })
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ApiPost {
+    #[schema(value_type = String)]
+    pub author: RostraId,
+    #[schema(value_type = String)]
+    pub event_id: ShortEventId,
+    #[schema(value_type = u64)]
+    pub ts: Timestamp,
+    #[schema(value_type = u8)]
+    pub persona: PersonaId,
+    /// Djot markup
+    pub content: Option<String>,
+    /// Emoji, if the post is a reaction to `reply_to`
+    pub reaction: Option<String>,
+    #[schema(value_type = Option<String>)]
+    pub reply_to: Option<ExternalEventId>,
+    /// Attached blobs, served at `/ui/blob/{author}/{event_id}`
+    #[schema(value_type = Vec<String>)]
+    pub attachments: Vec<ExternalEventId>,
+    #[schema(value_type = Option<String>)]
+    pub quote: Option<ExternalEventId>,
+    /// Attached poll, by the same author
+    #[schema(value_type = Option<String>)]
+    pub poll: Option<ShortEventId>,
+    pub content_warning: Option<String>,
+    pub reply_count: u64,
+    /// Time of the latest edit, whose content is returned
+    #[schema(value_type = Option<u64>)]
+    pub edited: Option<Timestamp>,
+}
+
+impl From<SocialPostRecord<SocialPost>> for ApiPost {
+    fn from(record: SocialPostRecord<SocialPost>) -> Self {
+        Self {
+            author: record.author,
+            event_id: record.event_id,
+            ts: record.ts,
+            persona: record.content.persona,
+            content: record.content.djot_content,
+            reaction: record.content.reaction,
+            reply_to: record.reply_to,
+            attachments: record.content.attachments,
+            quote: record.content.quote,
+            poll: record.content.poll,
+            content_warning: record.content.content_warning,
+            reply_count: record.reply_count,
+            edited: record.edited,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ApiPostsPage {
+    pub posts: Vec<ApiPost>,
+    /// Cursor to pass to get the next page, if there is one
+    pub next: Option<ApiCursor>,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ApiProfile {
+    #[schema(value_type = String)]
+    pub rostra_id: RostraId,
+    pub display_name: String,
+    pub bio: String,
+    /// Available under `/ui/avatar/{rostra_id}` if set
+    pub avatar_mime_type: Option<String>,
+    pub links: Vec<ApiProfileLink>,
+    pub pronouns: Option<String>,
+    pub location: Option<String>,
+    /// Header image blob, served at `/ui/blob/{author}/{event_id}`
+    #[schema(value_type = Option<String>)]
+    pub header: Option<ExternalEventId>,
+    /// Descriptions of personas, by persona id
+    #[schema(value_type = Object)]
+    pub persona_descriptions: BTreeMap<PersonaId, String>,
+}
+
+impl ApiProfile {
+    pub fn new(rostra_id: RostraId, record: IdSocialProfileRecord) -> Self {
+        Self {
+            rostra_id,
+            display_name: record.display_name,
+            bio: record.bio,
+            avatar_mime_type: record.avatar.map(|(mime, _)| mime),
+            links: record.links.into_iter().map(Into::into).collect(),
+            pronouns: record.pronouns,
+            location: record.location,
+            header: record.header,
+            persona_descriptions: record.persona_descriptions,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ApiProfileLink {
+    pub label: String,
+    pub url: String,
+}
+
+impl From<SocialProfileLink> for ApiProfileLink {
+    fn from(link: SocialProfileLink) -> Self {
+        Self {
+            label: link.label,
+            url: link.url,
+        }
+    }
+}
+
+impl From<ApiProfileLink> for SocialProfileLink {
+    fn from(link: ApiProfileLink) -> Self {
+        Self {
+            label: link.label,
+            url: link.url,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ApiPersonaSelectorMode {
+    /// Follow only the listed personas
+    Only,
+    /// Follow all but the listed personas
+    Except,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ApiPersonaSelector {
+    pub mode: ApiPersonaSelectorMode,
+    #[schema(value_type = Vec<u8>)]
+    pub personas: Vec<PersonaId>,
+}
+
+impl Default for ApiPersonaSelector {
+    fn default() -> Self {
+        Self {
+            mode: ApiPersonaSelectorMode::Except,
+            personas: vec![],
+        }
+    }
+}
+
+impl From<PersonaSelector> for ApiPersonaSelector {
+    fn from(selector: PersonaSelector) -> Self {
+        match selector {
+            PersonaSelector::Only { ids } => Self {
+                mode: ApiPersonaSelectorMode::Only,
+                personas: ids,
+            },
+            PersonaSelector::Except { ids } => Self {
+                mode: ApiPersonaSelectorMode::Except,
+                personas: ids,
+            },
+        }
+    }
+}
+
+impl From<ApiPersonaSelector> for PersonaSelector {
+    fn from(selector: ApiPersonaSelector) -> Self {
+        match selector.mode {
+            ApiPersonaSelectorMode::Only => Self::Only {
+                ids: selector.personas,
+            },
+            ApiPersonaSelectorMode::Except => Self::Except {
+                ids: selector.personas,
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ApiFollowee {
+    #[schema(value_type = String)]
+    pub rostra_id: RostraId,
+    pub selector: ApiPersonaSelector,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ApiNewPost {
+    /// Djot markup, or a single emoji for a reaction to `reply_to`
+    pub content: String,
+    #[schema(value_type = Option<String>)]
+    pub reply_to: Option<ExternalEventId>,
+    #[schema(value_type = Option<u8>)]
+    pub persona: Option<PersonaId>,
+    /// Blobs to attach
+    #[serde(default)]
+    #[schema(value_type = Vec<String>)]
+    pub attachments: Vec<ExternalEventId>,
+    /// Post to quote
+    #[schema(value_type = Option<String>)]
+    pub quote: Option<ExternalEventId>,
+    /// Content warning to hide the post behind
+    pub content_warning: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize, ToSchema)]
+pub struct ApiFollow {
+    /// Defaults to following all personas
+    #[serde(default)]
+    pub selector: ApiPersonaSelector,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ApiProfileUpdate {
+    pub display_name: String,
+    pub bio: String,
+    /// Fields below are kept unchanged if omitted
+    pub links: Option<Vec<ApiProfileLink>>,
+    pub pronouns: Option<String>,
+    pub location: Option<String>,
+    /// Own blob to use as the header image, `null` to remove it
+    #[serde(default, deserialize_with = "null_as_some_none")]
+    #[schema(value_type = Option<String>)]
+    pub header: Option<Option<ExternalEventId>>,
+    #[schema(value_type = Option<Object>)]
+    pub persona_descriptions: Option<BTreeMap<PersonaId, String>>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, ToSchema)]
+pub struct ApiPublished {
+    /// Id of the published event
+    #[schema(value_type = String)]
+    pub event_id: ShortEventId,
+}
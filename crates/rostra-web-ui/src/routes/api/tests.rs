@@ -0,0 +1,65 @@
+use rostra_core::event::{PersonaId, PersonaSelector};
+use utoipa::OpenApi as _;
+
+use super::ApiDoc;
+use super::model::{ApiPersonaSelector, ApiProfileUpdate};
+
+#[test]
+fn openapi_describes_all_routes() {
+    let doc = serde_json::to_value(ApiDoc::openapi()).unwrap();
+    let paths = doc["paths"].as_object().unwrap();
+
+    for path in [
+        "/api/v1/timeline/{mode}",
+        "/api/v1/profile/{id}",
+        "/api/v1/profile/{id}/posts",
+        "/api/v1/post",
+        "/api/v1/post/{author}/{event_id}",
+        "/api/v1/post/{author}/{event_id}/comments",
+        "/api/v1/followees",
+        "/api/v1/followees/{id}",
+        "/api/v1/self/profile",
+    ] {
+        assert!(paths.contains_key(path), "Missing {path}");
+    }
+    assert!(doc["components"]["schemas"]["ApiPost"].is_object());
+    assert_eq!(
+        doc["components"]["securitySchemes"]["bearer"]["scheme"],
+        "bearer"
+    );
+}
+
+#[test]
+fn persona_selector_roundtrip() {
+    for selector in [
+        PersonaSelector::Only {
+            ids: vec![PersonaId(1)],
+        },
+        PersonaSelector::Except { ids: vec![] },
+    ] {
+        assert_eq!(
+            PersonaSelector::from(ApiPersonaSelector::from(selector.clone())),
+            selector
+        );
+    }
+
+    let selector: ApiPersonaSelector =
+        serde_json::from_str(r#"{"mode":"only","personas":[3]}"#).unwrap();
+    assert_eq!(
+        PersonaSelector::from(selector),
+        PersonaSelector::Only {
+            ids: vec![PersonaId(3)]
+        }
+    );
+}
+
+#[test]
+fn profile_update_header_null_clears() {
+    let update: ApiProfileUpdate =
+        serde_json::from_str(r#"{"display_name":"a","bio":""}"#).unwrap();
+    assert_eq!(update.header, None);
+
+    let update: ApiProfileUpdate =
+        serde_json::from_str(r#"{"display_name":"a","bio":"","header":null}"#).unwrap();
+    assert_eq!(update.header, Some(None));
+}
@@ -0,0 +1,212 @@
+use axum::Form;
+use axum::extract::{Path, State};
+use axum::response::IntoResponse;
+use maud::{Markup, html};
+use rostra_client_db::{ApiToken, ApiTokenHash, ApiTokenRecord, ApiTokenScope};
+use rostra_core::Timestamp;
+use serde::Deserialize;
+use snafu::ResultExt as _;
+use time::OffsetDateTime;
+
+use super::Maud;
+use super::unlock::session::UserSession;
+use crate::error::{OtherSnafu, RequestResult, UserRequestError, UserSnafu};
+use crate::{SharedState, UiState};
+
+#[derive(Deserialize)]
+pub struct NewTokenInput {
+    name: String,
+    scope: ApiTokenScope,
+}
+
+pub async fn get_api_tokens(
+    state: State<SharedState>,
+    session: UserSession,
+) -> RequestResult<impl IntoResponse> {
+    let navbar = state.render_navbar(session.id(), &session).await?;
+    let tokens = state.render_api_tokens(&session, None).await?;
+    Ok(Maud(
+        state
+            .render_html_page(
+                "Rostra: API Tokens",
+                html! {
+                    (navbar)
+                    main ."o-mainBar" {
+                        (tokens)
+                    }
+                },
+            )
+            .await?,
+    ))
+}
+
+pub async fn post_api_token(
+    state: State<SharedState>,
+    session: UserSession,
+    Form(form): Form<NewTokenInput>,
+) -> RequestResult<impl IntoResponse> {
+    let name = form.name.trim().to_owned();
+    if name.is_empty() {
+        return Err(UserRequestError::InvalidData).context(UserSnafu);
+    }
+
+    let token = state
+        .client(session.id())
+        .await?
+        .db()?
+        .create_api_token(name, form.scope, session.id_secret()?)
+        .await
+        .boxed()
+        .context(OtherSnafu)?;
+
+    Ok(Maud(state.render_api_tokens(&session, Some(token)).await?))
+}
+
+pub async fn post_revoke_api_token(
+    state: State<SharedState>,
+    session: UserSession,
+    Path(hash): Path<String>,
+) -> RequestResult<impl IntoResponse> {
+    let Some(hash) = data_encoding::HEXLOWER
+        .decode(hash.as_bytes())
+        .ok()
+        .and_then(|hash| ApiTokenHash::try_from(hash).ok())
+    else {
+        return Err(UserRequestError::InvalidData).context(UserSnafu);
+    };
+    // Revoking requires the same access as issuing
+    session.id_secret()?;
+
+    state
+        .client(session.id())
+        .await?
+        .db()?
+        .revoke_api_token(hash)
+        .await
+        .boxed()
+        .context(OtherSnafu)?;
+
+    Ok(Maud(state.render_api_tokens(&session, None).await?))
+}
+
+impl UiState {
+    /// List of API tokens, with the `new_token` displayed once after issuing
+    async fn render_api_tokens(
+        &self,
+        session: &UserSession,
+        new_token: Option<ApiToken>,
+    ) -> RequestResult<Markup> {
+        let tokens = self
+            .client(session.id())
+            .await?
+            .db()?
+            .list_api_tokens()
+            .await
+            .boxed()
+            .context(OtherSnafu)?;
+
+        Ok(html! {
+            div ."o-apiTokens" {
+                h2 { "API Tokens" }
+                p {
+                    "Tokens give scripts access to the "
+                    a href="/api/v1/openapi.json" { "API" }
+                    " of this node, passed as an "
+                    code { "Authorization: Bearer" }
+                    " header."
+                }
+
+                @if let Some(token) = new_token {
+                    div ."o-apiTokens__newToken" {
+                        p { "Copy the new token now, it will not be shown again:" }
+                        code ."o-apiTokens__newTokenValue" { (token) }
+                    }
+                }
+
+                table ."o-apiTokens__table" {
+                    thead {
+                        tr {
+                            th { "Name" }
+                            th { "Scope" }
+                            th { "Created" }
+                            th {}
+                        }
+                    }
+                    tbody {
+                        @for (hash, record) in &tokens {
+                            (self.render_api_token_row(session, hash, record))
+                        }
+                    }
+                }
+
+                form ."o-apiTokens__form"
+                    hx-post="/ui/tokens"
+                    hx-target=".o-apiTokens"
+                    hx-swap="outerHTML"
+                {
+                    input ."o-apiTokens__nameInput"
+                        type="text"
+                        name="name"
+                        placeholder="Name"
+                        autocomplete="off"
+                        required
+                        disabled[session.ro_mode().to_disabled()]
+                        {}
+                    select ."o-apiTokens__scopeSelect" name="scope" {
+                        option value="read_only" { (scope_label(ApiTokenScope::ReadOnly)) }
+                        option value="post_only" { (scope_label(ApiTokenScope::PostOnly)) }
+                        option value="full" { (scope_label(ApiTokenScope::Full)) }
+                    }
+                    button ."o-apiTokens__createButton u-button"
+                        type="submit"
+                        disabled[session.ro_mode().to_disabled()]
+                    {
+                        "Create"
+                    }
+                }
+            }
+        })
+    }
+
+    fn render_api_token_row(
+        &self,
+        session: &UserSession,
+        hash: &ApiTokenHash,
+        record: &ApiTokenRecord,
+    ) -> Markup {
+        html! {
+            tr {
+                td { (record.name) }
+                td { (scope_label(record.scope)) }
+                td { (fmt_date(record.created)) }
+                td {
+                    button ."o-apiTokens__revokeButton u-button"
+                        hx-post=(format!("/ui/tokens/{}/revoke", data_encoding::HEXLOWER.encode(hash)))
+                        hx-target=".o-apiTokens"
+                        hx-swap="outerHTML"
+                        hx-confirm=(format!("Revoke token \"{}\"?", record.name))
+                        disabled[session.ro_mode().to_disabled()]
+                    {
+                        "Revoke"
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn scope_label(scope: ApiTokenScope) -> &'static str {
+    match scope {
+        ApiTokenScope::ReadOnly => "Read only",
+        ApiTokenScope::PostOnly => "Post only",
+        ApiTokenScope::Full => "Full",
+    }
+}
+
+fn fmt_date(ts: Timestamp) -> String {
+    i64::try_from(ts.0)
+        .ok()
+        .and_then(|secs| OffsetDateTime::from_unix_timestamp(secs).ok())
+        .map(|dt| dt.date().to_string())
+        .unwrap_or_default()
+}
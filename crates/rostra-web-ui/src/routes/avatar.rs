@@ -1,63 +1,57 @@
-use axum::extract::{Path, State};
-use axum::http::{HeaderMap, HeaderValue, header};
-use axum::response::{IntoResponse, Redirect};
+use axum::extract::{Path, Query, State};
+use axum::http::HeaderMap;
+use axum::response::{IntoResponse, Redirect, Response};
+use rostra_core::event::EventExt as _;
 use rostra_core::id::RostraId;
 
 use super::get_static_asset;
+use super::thumbnail::ThumbnailQuery;
 use super::unlock::session::UserSession;
 use crate::SharedState;
 use crate::error::RequestResult;
+use crate::thumbnail::ThumbnailKind;
 
 pub async fn get(
     state: State<SharedState>,
     session: UserSession,
     req_headers: HeaderMap,
     Path(avatar_id): Path<RostraId>,
+    Query(query): Query<ThumbnailQuery>,
 ) -> RequestResult<impl IntoResponse> {
-    let not_found_redirect = Redirect::temporary("/assets/icons/circle-user.svg").into_response();
-    let Some(profile) = state
-        .client(session.id())
-        .await?
-        .client_ref()?
-        .db()
-        .get_social_profile(avatar_id)
-        .await
-    else {
-        if state.assets.is_some() {
-            return Ok(get_static_asset(
-                state,
-                Path("icons/circle-user.svg".to_owned()),
-                req_headers,
-            )
-            .await);
-        } else {
-            return Ok(not_found_redirect);
-        }
+    let client = state.client(session.id()).await?;
+    let client_ref = client.client_ref()?;
+    let db = client_ref.db();
+
+    let Some(profile) = db.get_social_profile(avatar_id).await else {
+        return Ok(default_avatar(state, req_headers).await);
     };
     let Some(avatar) = profile.avatar else {
-        if state.assets.is_some() {
-            return Ok(get_static_asset(
-                state,
-                Path("icons/circle-user.svg".to_owned()),
-                req_headers,
-            )
-            .await);
-        } else {
-            return Ok(not_found_redirect);
-        }
+        return Ok(default_avatar(state, req_headers).await);
+    };
+    let Some(event) = db.get_event(profile.event_id).await else {
+        return Ok(default_avatar(state, req_headers).await);
     };
 
-    let mut resp_headers = HeaderMap::new();
-    let etag = profile.event_id.to_string();
-
-    // Handle ETag and conditional request
-    if let Some(response) = crate::handle_etag(&req_headers, &etag, &mut resp_headers) {
-        return Ok(response);
+    let response = state
+        .thumbnail_response(
+            &req_headers,
+            HeaderMap::new(),
+            event.content_hash(),
+            ThumbnailKind::Avatar(query.size),
+            &avatar.1,
+        )
+        .await?;
+    match response {
+        Some(response) => Ok(response),
+        // Avatars that can't be rendered are treated like missing ones
+        None => Ok(default_avatar(state, req_headers).await),
     }
+}
 
-    let Ok(mime) = HeaderValue::from_str(&avatar.0) else {
-        return Ok(not_found_redirect);
-    };
-    resp_headers.insert(header::CONTENT_TYPE, mime);
-    Ok((resp_headers, avatar.1).into_response())
+async fn default_avatar(state: State<SharedState>, req_headers: HeaderMap) -> Response {
+    if state.assets.is_some() {
+        get_static_asset(state, Path("icons/circle-user.svg".to_owned()), req_headers).await
+    } else {
+        Redirect::temporary("/assets/icons/circle-user.svg").into_response()
+    }
 }
@@ -0,0 +1,181 @@
+use axum::extract::{Multipart, Path, State};
+use axum::http::{HeaderMap, HeaderValue, header};
+use axum::response::IntoResponse;
+use maud::{Markup, html};
+use rostra_core::event::content_kind::Blob;
+use rostra_core::id::RostraId;
+use rostra_core::{ExternalEventId, ShortEventId};
+use snafu::{OptionExt as _, ResultExt as _};
+
+use super::unlock::session::UserSession;
+use super::{IMMUTABLE_CACHE_CONTROL, Maud};
+use crate::error::{InvalidDataSnafu, RequestResult, UserRequestError, UserSnafu};
+use crate::{SharedState, UiState};
+
+/// Name of the file field of uploads
+pub const UPLOAD_FIELD: &str = "attachment";
+
+/// Name of the hidden inputs of uploaded attachments in the new post form
+pub const ATTACHMENTS_FIELD: &str = "attachments";
+
+/// Max. size of upload requests, leaving some room for the multipart encoding
+pub const UPLOAD_BODY_LIMIT: usize = Blob::MAX_SIZE + 64 * 1024;
+
+pub async fn get_blob(
+    state: State<SharedState>,
+    session: UserSession,
+    req_headers: HeaderMap,
+    Path((author, event_id)): Path<(RostraId, ShortEventId)>,
+) -> RequestResult<impl IntoResponse> {
+    let Some((content_hash, blob)) = state
+        .client(session.id())
+        .await?
+        .client_ref()?
+        .db()
+        .get_blob(author, event_id)
+        .await
+    else {
+        return Err(UserRequestError::SomethingNotFound).context(UserSnafu);
+    };
+
+    let mut resp_headers = HeaderMap::new();
+    // Blobs are addressed by event id, so never change
+    resp_headers.insert(
+        header::CACHE_CONTROL,
+        HeaderValue::from_static(IMMUTABLE_CACHE_CONTROL),
+    );
+    if let Some(response) =
+        crate::handle_etag(&req_headers, &content_hash.to_string(), &mut resp_headers)
+    {
+        return Ok(response);
+    }
+
+    insert_untrusted_content_headers(&mut resp_headers, &blob.mime)?;
+
+    Ok((resp_headers, blob.data).into_response())
+}
+
+/// Publish uploaded images as blobs, returning their attachment previews
+pub async fn post_blob(
+    state: State<SharedState>,
+    session: UserSession,
+    mut multipart: Multipart,
+) -> RequestResult<impl IntoResponse> {
+    let client_handle = state.client(session.id()).await?;
+    let client_ref = client_handle.client_ref()?;
+
+    let mut attachments = vec![];
+    while let Some(field) = multipart
+        .next_field()
+        .await
+        .ok()
+        .context(InvalidDataSnafu)
+        .context(UserSnafu)?
+    {
+        if field.name() != Some(UPLOAD_FIELD) {
+            continue;
+        }
+        let Some(mime) = field
+            .content_type()
+            .filter(|mime| is_image(mime))
+            .map(ToOwned::to_owned)
+        else {
+            return Err(UserRequestError::InvalidData).context(UserSnafu);
+        };
+        let data = field
+            .bytes()
+            .await
+            .ok()
+            .context(InvalidDataSnafu)
+            .context(UserSnafu)?;
+        if data.is_empty() {
+            continue;
+        }
+
+        let event = client_ref
+            .publish_blob(session.id_secret()?, mime, data.to_vec())
+            .await?;
+        attachments.push(ExternalEventId::new(session.id(), event.event_id));
+    }
+
+    Ok(Maud(html! {
+        @for attachment in attachments {
+            (state.render_new_post_attachment(attachment))
+        }
+    }))
+}
+
+impl UiState {
+    pub fn blob_url(&self, blob: ExternalEventId) -> String {
+        format!("/ui/blob/{}/{}", blob.rostra_id(), blob.event_id())
+    }
+
+    /// Attachment preview in the new post form, submitted along with the post
+    pub fn render_new_post_attachment(&self, attachment: ExternalEventId) -> Markup {
+        html! {
+            div ."m-newPostForm__attachment" {
+                img ."m-newPostForm__attachmentImage"
+                    src=(self.thumbnail_url(attachment.rostra_id(), attachment.event_id()))
+                    alt="Attachment"
+                    {}
+                input type="hidden" name=(ATTACHMENTS_FIELD) value=(attachment) {}
+                button ."m-newPostForm__attachmentRemoveButton u-button"
+                    type="button"
+                    title="Remove"
+                    onclick="this.parentNode.remove()"
+                {
+                    span ."m-newPostForm__attachmentRemoveButtonIcon u-buttonIcon" width="1rem" height="1rem" {}
+                }
+            }
+        }
+    }
+
+    pub fn render_post_attachments(&self, attachments: &[ExternalEventId]) -> Markup {
+        html! {
+            @if !attachments.is_empty() {
+                div ."m-postOverview__attachments" {
+                    @for attachment in attachments {
+                        a ."m-postOverview__attachment"
+                            href=(self.blob_url(*attachment))
+                            target="_blank"
+                        {
+                            img ."m-postOverview__attachmentImage"
+                                src=(self.thumbnail_url(attachment.rostra_id(), attachment.event_id()))
+                                alt="Attachment"
+                                loading="lazy"
+                                {}
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Headers for serving content published by others, making sure it can't
+/// run any scripts
+pub fn insert_untrusted_content_headers(headers: &mut HeaderMap, mime: &str) -> RequestResult<()> {
+    let Ok(content_type) = HeaderValue::from_str(mime) else {
+        return Err(UserRequestError::InvalidData).context(UserSnafu);
+    };
+    headers.insert(header::CONTENT_TYPE, content_type);
+    headers.insert(
+        header::X_CONTENT_TYPE_OPTIONS,
+        HeaderValue::from_static("nosniff"),
+    );
+    headers.insert(
+        header::CONTENT_SECURITY_POLICY,
+        HeaderValue::from_static("sandbox"),
+    );
+    if !is_image(mime) {
+        headers.insert(
+            header::CONTENT_DISPOSITION,
+            HeaderValue::from_static("attachment"),
+        );
+    }
+    Ok(())
+}
+
+pub(crate) fn is_image(mime: &str) -> bool {
+    mime.starts_with("image/")
+}
@@ -1,165 +1,240 @@
 use std::borrow::Cow;
+use std::collections::HashMap;
 use std::str::FromStr as _;
 
 use jotdown::{Attributes, Container, Event};
 use maud::{Markup, PreEscaped};
 use rostra_client::ClientRef;
+use rostra_core::event::SocialPost;
 use rostra_core::id::RostraId;
 use url::Url;
 
 use crate::UiState;
 
 impl UiState {
-    pub(crate) async fn render_content(&self, _client: &ClientRef<'_>, content: &str) -> Markup {
-        let sanitized = jotdown::Parser::new(content).map(|e| match e {
-            Event::Start(Container::RawBlock { format }, _attrs) if format == "html" => {
-                Event::Start(Container::CodeBlock { language: format }, Attributes::new())
-            }
-            Event::End(Container::RawBlock { format }) if format == "html" => {
-                Event::End(Container::CodeBlock { language: format })
-            }
-            Event::Start(Container::RawInline { format }, _attr) if format == "html" => {
-                Event::Start(Container::CodeBlock { language: format }, Attributes::new())
+    pub(crate) async fn render_content(&self, client: &ClientRef<'_>, content: &str) -> Markup {
+        // Rendering can't be async, so look up all the mentioned profiles upfront
+        let display_names = client
+            .db()
+            .get_social_profiles(SocialPost::find_mentions(content))
+            .await
+            .into_iter()
+            .map(|(id, profile)| (id, profile.display_name))
+            .collect();
+        render_djot_with_display_names(content, &display_names)
+    }
+
+    /// Extra rostra id from a link `s`
+    pub(crate) fn extra_rostra_id_link(s: &str) -> Option<RostraId> {
+        if let Some(s) = s.strip_prefix("rostra:") {
+            RostraId::from_str(s).ok()
+        } else {
+            None
+        }
+    }
+}
+
+/// Render djot `content` to html, with raw html and attributes stripped
+pub(crate) fn render_djot(content: &str) -> Markup {
+    render_djot_with_display_names(content, &HashMap::new())
+}
+
+/// Like [`render_djot`], with mentions of ids in `display_names` displayed
+/// using their display names
+pub(crate) fn render_djot_with_display_names(
+    content: &str,
+    display_names: &HashMap<RostraId, String>,
+) -> Markup {
+    let sanitized = jotdown::Parser::new(content).map(|e| match e {
+        Event::Start(Container::RawBlock { format }, _attrs) if format == "html" => {
+            Event::Start(Container::CodeBlock { language: format }, Attributes::new())
+        }
+        Event::End(Container::RawBlock { format }) if format == "html" => {
+            Event::End(Container::CodeBlock { language: format })
+        }
+        Event::Start(Container::RawInline { format }, _attr) if format == "html" => {
+            Event::Start(Container::CodeBlock { language: format }, Attributes::new())
+        }
+        Event::End(Container::RawInline { format }) if format == "html" => {
+            Event::End(Container::CodeBlock { language: format })
+        }
+        Event::Start(container, _attr) => Event::Start(container, Attributes::new()),
+        e => e,
+    });
+
+    let mut in_profile_link = vec![];
+    let mut in_img_to_raw_html = vec![];
+    let mut in_img_to_img = vec![];
+    // Depth of containers in which hashtags are not turned into links
+    let mut in_no_hashtags = 0usize;
+    let out = jotdown::html::render_to_string(sanitized.flat_map(|event| {
+        match &event {
+            Event::Start(container, _) if is_no_hashtags_container(container) => {
+                in_no_hashtags += 1;
             }
-            Event::End(Container::RawInline { format }) if format == "html" => {
-                Event::End(Container::CodeBlock { language: format })
+            Event::End(container) if is_no_hashtags_container(container) => {
+                in_no_hashtags = in_no_hashtags.saturating_sub(1);
             }
-            Event::Start(container, _attr) => Event::Start(container, Attributes::new()),
-            e => e,
-        });
-
-        let mut in_profile_link = vec![];
-        let mut in_img_to_raw_html = vec![];
-        let mut in_img_to_img = vec![];
-        let out = jotdown::html::render_to_string(sanitized.flat_map(|event| {
-            match event {
-                Event::Start(Container::Link(s, jotdown::LinkType::AutoLink), attr) => {
-                    if let Some(rostra_id) = Self::extra_rostra_id_link(&s) {
-                        // TODO: blocked on https://github.com/hellux/jotdown/issues/86
-                        // let x = client
-                        //     .db()
-                        //     .get_social_profile(rostra_id)
-                        //     .await
-                        //     .map(|record| record.display_name)
-                        //     .unwrap_or_else(|| rostra_id.to_string());
-                        in_profile_link.push(rostra_id);
-                        vec![Event::Start(
-                            Container::Link(
-                                format!("/ui/profile/{rostra_id}").into(),
-                                jotdown::LinkType::Span(jotdown::SpanLinkType::Inline),
-                            ),
-                            attr,
-                        )]
-                    } else {
-                        vec![Event::Start(
-                            Container::Link(s, jotdown::LinkType::AutoLink),
-                            attr,
-                        )]
-                    }
-                }
-                Event::End(Container::Link(s, jotdown::LinkType::AutoLink)) => {
-                    if let Some(rostra_id) = Self::extra_rostra_id_link(&s) {
-                        in_profile_link.pop();
-                        vec![Event::End(Container::Link(
+            _ => {}
+        }
+        match event {
+            Event::Start(Container::Link(s, jotdown::LinkType::AutoLink), attr) => {
+                if let Some(rostra_id) = UiState::extra_rostra_id_link(&s) {
+                    in_profile_link.push(rostra_id);
+                    vec![Event::Start(
+                        Container::Link(
                             format!("/ui/profile/{rostra_id}").into(),
                             jotdown::LinkType::Span(jotdown::SpanLinkType::Inline),
-                        ))]
-                    } else {
-                        vec![Event::End(Container::Link(s, jotdown::LinkType::AutoLink))]
-                    }
-                }
-                Event::Start(Container::Image(s, _link_type), _attr) => {
-                    if let Some(html) = maybe_embed_media_html(&s) {
-                        in_img_to_raw_html.push((html, String::new()));
-                    } else {
-                        in_img_to_img.push(String::new());
-                    };
+                        ),
+                        attr,
+                    )]
+                } else {
                     vec![Event::Start(
-                        Container::Div {
-                            class: "lazyload-wrapper",
-                        },
-                        jotdown::Attributes::try_from(
-                            "{ onclick=\"this.classList.add('-expanded')\" }",
-                        )
-                        .expect("Can't fail"),
+                        Container::Link(s, jotdown::LinkType::AutoLink),
+                        attr,
                     )]
                 }
-                Event::End(Container::Image(s, link_type)) => [
-                    if let Some((html, alt)) = in_img_to_raw_html.pop() {
-                        let alt = alt.trim();
-                        let load_msg = if alt.is_empty() {
-                            format!("Load: {}", s).into()
-                        } else {
-                            format!("Load “{}”: {}", alt, s).into()
-                        };
-                        vec![
-                            Event::Start(Container::Paragraph, Attributes::new()),
-                            Event::Str(load_msg),
-                            Event::End(Container::Paragraph),
-                            Event::Start(
-                                Container::RawInline { format: "html" },
-                                Attributes::try_from("{ loading=lazy }").expect("Can't fail"),
-                            ),
-                            Event::Str(html.into()),
-                            Event::End(Container::RawInline { format: "html" }),
-                        ]
-                    } else if let Some(alt) = in_img_to_img.pop() {
-                        let alt = alt.trim();
-                        let load_msg = if alt.is_empty() {
-                            format!("Load: {}", s).into()
-                        } else {
-                            format!("Load “{}”: {}", alt, s).into()
-                        };
-                        vec![
-                            Event::Start(Container::Paragraph, Attributes::new()),
-                            Event::Str(load_msg),
-                            Event::End(Container::Paragraph),
-                            Event::Start(
-                                Container::Image(s.clone(), link_type),
-                                Attributes::try_from("{ loading=lazy }").expect("Can't fail"),
-                            ),
-                            Event::Str(alt.to_string().into()),
-                            Event::End(Container::Image(s, link_type)),
-                        ]
-                    } else {
-                        panic!("Can't be here")
+            }
+            Event::End(Container::Link(s, jotdown::LinkType::AutoLink)) => {
+                if let Some(rostra_id) = UiState::extra_rostra_id_link(&s) {
+                    in_profile_link.pop();
+                    vec![Event::End(Container::Link(
+                        format!("/ui/profile/{rostra_id}").into(),
+                        jotdown::LinkType::Span(jotdown::SpanLinkType::Inline),
+                    ))]
+                } else {
+                    vec![Event::End(Container::Link(s, jotdown::LinkType::AutoLink))]
+                }
+            }
+            Event::Start(Container::Image(s, _link_type), _attr) => {
+                if let Some(html) = maybe_embed_media_html(&s) {
+                    in_img_to_raw_html.push((html, String::new()));
+                } else {
+                    in_img_to_img.push(String::new());
+                };
+                vec![Event::Start(
+                    Container::Div {
+                        class: "lazyload-wrapper",
                     },
-                    vec![Event::End(Container::Div {
-                        class: "img-wrapper",
-                    })],
-                ]
-                .concat(),
-                Event::Str(s) => {
-                    if !in_profile_link.is_empty() {
-                        let profile = in_profile_link.last().expect("Not empty just checked");
-                        vec![Event::Str(format!("@{profile}").into())]
-                    } else if let Some(last) = in_img_to_raw_html.last_mut() {
-                        last.1 = s.to_string();
-                        // skip the img alt tag
-                        vec![]
-                    } else if let Some(last) = in_img_to_img.last_mut() {
-                        *last = s.to_string();
-                        // skip the img alt tag
-                        vec![]
+                    jotdown::Attributes::try_from(
+                        "{ onclick=\"this.classList.add('-expanded')\" }",
+                    )
+                    .expect("Can't fail"),
+                )]
+            }
+            Event::End(Container::Image(s, link_type)) => [
+                if let Some((html, alt)) = in_img_to_raw_html.pop() {
+                    let alt = alt.trim();
+                    let load_msg = if alt.is_empty() {
+                        format!("Load: {}", s).into()
+                    } else {
+                        format!("Load “{}”: {}", alt, s).into()
+                    };
+                    vec![
+                        Event::Start(Container::Paragraph, Attributes::new()),
+                        Event::Str(load_msg),
+                        Event::End(Container::Paragraph),
+                        Event::Start(
+                            Container::RawInline { format: "html" },
+                            Attributes::try_from("{ loading=lazy }").expect("Can't fail"),
+                        ),
+                        Event::Str(html.into()),
+                        Event::End(Container::RawInline { format: "html" }),
+                    ]
+                } else if let Some(alt) = in_img_to_img.pop() {
+                    let alt = alt.trim();
+                    let load_msg = if alt.is_empty() {
+                        format!("Load: {}", s).into()
+                    } else {
+                        format!("Load “{}”: {}", alt, s).into()
+                    };
+                    vec![
+                        Event::Start(Container::Paragraph, Attributes::new()),
+                        Event::Str(load_msg),
+                        Event::End(Container::Paragraph),
+                        Event::Start(
+                            Container::Image(s.clone(), link_type),
+                            Attributes::try_from("{ loading=lazy }").expect("Can't fail"),
+                        ),
+                        Event::Str(alt.to_string().into()),
+                        Event::End(Container::Image(s, link_type)),
+                    ]
+                } else {
+                    panic!("Can't be here")
+                },
+                vec![Event::End(Container::Div {
+                    class: "img-wrapper",
+                })],
+            ]
+            .concat(),
+            Event::Str(s) => {
+                if !in_profile_link.is_empty() {
+                    let profile = in_profile_link.last().expect("Not empty just checked");
+                    if let Some(display_name) = display_names.get(profile) {
+                        vec![Event::Str(format!("@{display_name}").into())]
                     } else {
-                        vec![Event::Str(s)]
+                        vec![Event::Str(format!("@{profile}").into())]
                     }
+                } else if let Some(last) = in_img_to_raw_html.last_mut() {
+                    last.1 = s.to_string();
+                    // skip the img alt tag
+                    vec![]
+                } else if let Some(last) = in_img_to_img.last_mut() {
+                    *last = s.to_string();
+                    // skip the img alt tag
+                    vec![]
+                } else if in_no_hashtags == 0 {
+                    link_hashtags(s)
+                } else {
+                    vec![Event::Str(s)]
                 }
-                event => vec![event],
             }
-        }));
+            event => vec![event],
+        }
+    }));
 
-        PreEscaped(out)
-    }
+    PreEscaped(out)
+}
 
-    /// Extra rostra id from a link `s`
-    pub(crate) fn extra_rostra_id_link(s: &str) -> Option<RostraId> {
-        if let Some(s) = s.strip_prefix("rostra:") {
-            RostraId::from_str(s).ok()
-        } else {
-            None
+/// Containers whose text can't contain hashtag links
+fn is_no_hashtags_container(container: &Container) -> bool {
+    matches!(
+        container,
+        Container::Link(..)
+            | Container::Image(..)
+            | Container::Verbatim
+            | Container::Math { .. }
+            | Container::CodeBlock { .. }
+            | Container::RawBlock { .. }
+            | Container::RawInline { .. }
+    )
+}
+
+/// Split text `s` into text and links to the pages of the hashtags in it
+fn link_hashtags(s: jotdown::CowStr) -> Vec<Event> {
+    let mut events = vec![];
+    let mut last = 0;
+    for (start, tag) in SocialPost::find_hashtags(&s) {
+        if last < start {
+            events.push(Event::Str(s[last..start].to_owned().into()));
         }
+        let link = format!("/ui/tag/{}", tag.to_lowercase());
+        let link_type = jotdown::LinkType::Span(jotdown::SpanLinkType::Inline);
+        events.push(Event::Start(
+            Container::Link(link.clone().into(), link_type),
+            Attributes::new(),
+        ));
+        events.push(Event::Str(format!("#{tag}").into()));
+        events.push(Event::End(Container::Link(link.into(), link_type)));
+        last = start + 1 + tag.len();
+    }
+    if events.is_empty() {
+        return vec![Event::Str(s)];
+    }
+    if last < s.len() {
+        events.push(Event::Str(s[last..].to_owned().into()));
     }
+    events
 }
 
 enum ExternalMedia<'s> {
@@ -13,3 +13,31 @@ fn extract_rostra_id_link() {
         Some(RostraId::from_str("rse1okfyp4yj75i6riwbz86mpmbgna3f7qr66aj1njceqoigjabegy").unwrap())
     );
 }
+
+#[test]
+fn render_hashtags_as_links() {
+    let html = super::render_djot("Learning #Rust, see [#docs](https://example.com) and `#code`")
+        .into_string();
+    assert!(
+        html.contains(r##"<a href="/ui/tag/rust">#Rust</a>"##),
+        "{html}"
+    );
+    assert!(!html.contains("/ui/tag/docs"), "{html}");
+    assert!(!html.contains("/ui/tag/code"), "{html}");
+}
+
+#[test]
+fn render_mentions_with_display_names() {
+    let id = RostraId::from_str("rse1okfyp4yj75i6riwbz86mpmbgna3f7qr66aj1njceqoigjabegy").unwrap();
+    let content = format!("Hi <rostra:{id}>");
+
+    let html = super::render_djot(&content).into_string();
+    assert!(html.contains(&format!("@{id}")), "{html}");
+
+    let display_names = [(id, "Alice".to_string())].into_iter().collect();
+    let html = super::render_djot_with_display_names(&content, &display_names).into_string();
+    assert!(
+        html.contains(&format!(r#"<a href="/ui/profile/{id}">@Alice</a>"#)),
+        "{html}"
+    );
+}
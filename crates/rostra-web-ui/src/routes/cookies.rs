@@ -8,6 +8,7 @@ use crate::LOG_TARGET;
 
 const NOTIFICATIONS_LAST_SEEN_COOKIE_NAME: &str = "notifications-last-seen";
 const PERSONA_COOKIE_NAME: &str = "persona";
+const EXPAND_CONTENT_WARNINGS_COOKIE_NAME: &str = "expand-content-warnings";
 
 pub(crate) trait CookiesExt {
     fn get_last_seen(&self, self_id: impl Into<ShortRostraId>) -> Option<EventPaginationCursor>;
@@ -21,6 +22,10 @@ pub(crate) trait CookiesExt {
     fn get_persona(&self, self_id: impl Into<ShortRostraId>) -> Option<u8>;
 
     fn save_persona(&mut self, self_id: impl Into<ShortRostraId>, persona_id: u8);
+
+    fn get_expand_content_warnings(&self, self_id: impl Into<ShortRostraId>) -> bool;
+
+    fn save_expand_content_warnings(&mut self, self_id: impl Into<ShortRostraId>, expand: bool);
 }
 
 impl CookiesExt for Cookies {
@@ -78,4 +83,24 @@ impl CookiesExt for Cookies {
         cookie.set_max_age(time::Duration::weeks(50));
         self.add(cookie);
     }
+
+    fn get_expand_content_warnings(&self, self_id: impl Into<ShortRostraId>) -> bool {
+        let self_id = self_id.into();
+        self.get(&format!(
+            "{self_id}-{}",
+            EXPAND_CONTENT_WARNINGS_COOKIE_NAME
+        ))
+        .is_some_and(|s| s.value() == "true")
+    }
+
+    fn save_expand_content_warnings(&mut self, self_id: impl Into<ShortRostraId>, expand: bool) {
+        let self_id = self_id.into();
+        let mut cookie = Cookie::new(
+            format!("{self_id}-{}", EXPAND_CONTENT_WARNINGS_COOKIE_NAME),
+            expand.to_string(),
+        );
+        cookie.set_path("/ui");
+        cookie.set_max_age(time::Duration::weeks(50));
+        self.add(cookie);
+    }
 }
@@ -0,0 +1,116 @@
+use axum::extract::State;
+use axum::response::IntoResponse;
+use maud::{Markup, html};
+use rostra_client_db::DbStats;
+use rostra_core::id::ToShort as _;
+use snafu::ResultExt as _;
+
+use super::Maud;
+use super::unlock::session::UserSession;
+use crate::error::{OtherSnafu, RequestResult};
+use crate::{SharedState, UiState};
+
+pub async fn get_diagnostics(
+    state: State<SharedState>,
+    session: UserSession,
+) -> RequestResult<impl IntoResponse> {
+    let stats = state
+        .client(session.id())
+        .await?
+        .db()?
+        .stats()
+        .await
+        .boxed()
+        .context(OtherSnafu)?;
+
+    let navbar = state.render_navbar(session.id(), &session).await?;
+    Ok(Maud(
+        state
+            .render_html_page(
+                "Rostra: Diagnostics",
+                html! {
+                    (navbar)
+                    main ."o-mainBar" {
+                        (state.render_diagnostics(&stats))
+                    }
+                },
+            )
+            .await?,
+    ))
+}
+
+impl UiState {
+    fn render_diagnostics(&self, stats: &DbStats) -> Markup {
+        html! {
+            div ."o-diagnostics" {
+                h2 { "Database" }
+                p {
+                    "Missing events: " (stats.events_missing)
+                    ", missing content: " (stats.events_content_missing)
+                }
+                table ."o-diagnostics__table" {
+                    thead {
+                        tr {
+                            th { "Table" }
+                            th { "Rows" }
+                            th { "Stored" }
+                            th { "Metadata" }
+                            th { "Fragmented" }
+                        }
+                    }
+                    tbody {
+                        @for table in &stats.tables {
+                            tr {
+                                td { (table.name) }
+                                td { (table.rows) }
+                                td { (fmt_bytes(table.stored_bytes)) }
+                                td { (fmt_bytes(table.metadata_bytes)) }
+                                td { (fmt_bytes(table.fragmented_bytes)) }
+                            }
+                        }
+                    }
+                }
+
+                h2 { "Identities" }
+                table ."o-diagnostics__table" {
+                    thead {
+                        tr {
+                            th { "Identity" }
+                            th { "Events" }
+                            th { "Content" }
+                            th { "Heads" }
+                            th { "Missing" }
+                        }
+                    }
+                    tbody {
+                        @for id in &stats.ids {
+                            tr {
+                                td {
+                                    a href=(format!("/ui/profile/{}", id.rostra_id)) {
+                                        (id.rostra_id.to_short())
+                                    }
+                                }
+                                td { (id.events) }
+                                td { (fmt_bytes(id.content_bytes)) }
+                                td { (id.heads) }
+                                td { (id.missing_events) }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn fmt_bytes(bytes: u64) -> String {
+    const UNITS: &[&str] = &["B", "KiB", "MiB", "GiB", "TiB"];
+
+    let mut value = bytes;
+    let mut unit = 0;
+    while 1024 <= value && unit + 1 < UNITS.len() {
+        value /= 1024;
+        unit += 1;
+    }
+    format!("{value} {}", UNITS[unit])
+}
@@ -0,0 +1,172 @@
+//! Atom feeds of identities, for following them from a regular feed reader
+
+use std::str::FromStr as _;
+
+use axum::extract::{Path, Query, State};
+use axum::http::header::CONTENT_TYPE;
+use axum::response::IntoResponse;
+use maud::{PreEscaped, html};
+use rostra_core::Timestamp;
+use rostra_core::event::PersonaId;
+use rostra_core::id::RostraId;
+use serde::Deserialize;
+use snafu::ResultExt as _;
+use time::OffsetDateTime;
+
+use crate::SharedState;
+use crate::error::{RequestResult, UserRequestError, UserSnafu};
+
+/// Number of most recent posts included in a feed
+const FEED_SIZE: usize = 50;
+
+/// Length (in chars) of entry titles
+const ENTRY_TITLE_LEN: usize = 80;
+
+#[derive(Deserialize)]
+pub struct FeedQuery {
+    /// Only include posts of this persona
+    persona: Option<u8>,
+}
+
+/// `/feed/{rostra_id}.atom`
+pub async fn get_feed(
+    state: State<SharedState>,
+    Path(file): Path<String>,
+    Query(query): Query<FeedQuery>,
+) -> RequestResult<impl IntoResponse> {
+    let Some(author) = file
+        .strip_suffix(".atom")
+        .and_then(|id| RostraId::from_str(id).ok())
+    else {
+        return Err(UserRequestError::SomethingNotFound).context(UserSnafu);
+    };
+    let Some(client) = state.replicating_client(author).await? else {
+        return Err(UserRequestError::SomethingNotFound).context(UserSnafu);
+    };
+    let client_ref = client.client_ref()?;
+    let persona = query.persona.map(PersonaId);
+
+    let (posts, _) = client_ref
+        .db()
+        .paginate_social_posts_rev(None, FEED_SIZE, move |post| {
+            post.author == author
+                && post.content.reaction.is_none()
+                && post.content.djot_content.is_some()
+                && persona.is_none_or(|persona| persona == post.content.persona)
+        })
+        .await;
+
+    let profile = state.get_social_profile(author, &client_ref).await;
+    let title = match persona {
+        Some(persona) => {
+            let persona_name = client_ref
+                .db()
+                .get_personas_for_id(author)
+                .await
+                .remove(&persona)
+                .unwrap_or_else(|| persona.0.to_string());
+            format!("{} ({persona_name})", profile.display_name)
+        }
+        None => profile.display_name.clone(),
+    };
+
+    // Without a configured public url, links are relative to the feed
+    let base = state.public_url.as_deref().unwrap_or_default();
+    let feed_url = match persona {
+        Some(persona) => format!("{base}/feed/{author}.atom?persona={}", persona.0),
+        None => format!("{base}/feed/{author}.atom"),
+    };
+    let updated = posts
+        .first()
+        .map(|post| post.ts)
+        .unwrap_or_else(Timestamp::now);
+
+    let mut entries = Vec::with_capacity(posts.len());
+    for post in posts {
+        let djot = post.content.djot_content.as_deref().unwrap_or_default();
+        let content = state.render_content(&client_ref, djot).await;
+        // Feed readers don't know about content warnings, so use it as the title
+        // and hide the content behind it
+        let (post_title, content) = match post.content.content_warning {
+            Some(content_warning) => (
+                format!("CW: {content_warning}"),
+                html! {
+                    details {
+                        summary { (content_warning) }
+                        (content)
+                    }
+                },
+            ),
+            None => (entry_title(djot), content),
+        };
+        entries.push((post.event_id, post.ts, post_title, content));
+    }
+
+    let feed = html! {
+        (PreEscaped(r#"<?xml version="1.0" encoding="utf-8"?>"#))
+        feed xmlns="http://www.w3.org/2005/Atom" xml:base=[state.public_url.as_deref()] {
+            id { (feed_url) }
+            title { (title) }
+            @if !profile.bio.is_empty() {
+                subtitle { (profile.bio) }
+            }
+            updated { (fmt_rfc3339(updated)) }
+            link rel="self" href=(feed_url) {}
+            link rel="alternate" type="text/html" href=(format!("{base}/ui/profile/{author}")) {}
+            author { name { (profile.display_name) } }
+            @for (event_id, ts, entry_title, content) in entries {
+                @let permalink = format!("{base}/ui/post/{author}/{event_id}");
+                entry {
+                    id { (permalink) }
+                    title { (entry_title) }
+                    published { (fmt_rfc3339(ts)) }
+                    updated { (fmt_rfc3339(ts)) }
+                    link rel="alternate" type="text/html" href=(permalink) {}
+                    // Escaped html, as required by `type="html"`
+                    content type="html" { (content.into_string()) }
+                }
+            }
+        }
+    };
+
+    Ok((
+        [(CONTENT_TYPE, "application/atom+xml; charset=utf-8")],
+        feed.into_string(),
+    ))
+}
+
+/// Plain text title of a post: its first non-empty line, shortened
+fn entry_title(djot: &str) -> String {
+    let line = djot
+        .lines()
+        .map(|line| line.trim().trim_start_matches(['#', '>', ' ']))
+        .find(|line| !line.is_empty())
+        .unwrap_or_default();
+
+    if line.chars().count() <= ENTRY_TITLE_LEN {
+        line.to_owned()
+    } else {
+        let mut title: String = line.chars().take(ENTRY_TITLE_LEN).collect();
+        title.push('…');
+        title
+    }
+}
+
+pub(crate) fn fmt_rfc3339(ts: Timestamp) -> String {
+    let dt = i64::try_from(ts.0)
+        .ok()
+        .and_then(|secs| OffsetDateTime::from_unix_timestamp(secs).ok())
+        .unwrap_or(OffsetDateTime::UNIX_EPOCH);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        dt.year(),
+        u8::from(dt.month()),
+        dt.day(),
+        dt.hour(),
+        dt.minute(),
+        dt.second()
+    )
+}
+
+#[cfg(test)]
+mod tests;
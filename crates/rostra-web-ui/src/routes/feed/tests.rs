@@ -0,0 +1,23 @@
+use rostra_core::Timestamp;
+
+use super::{entry_title, fmt_rfc3339};
+
+#[test]
+fn entry_title_uses_first_line() {
+    assert_eq!(entry_title("\n# Hello *world*\n\nMore"), "Hello *world*");
+    assert_eq!(entry_title(""), "");
+
+    let long = "x".repeat(100);
+    let title = entry_title(&long);
+    assert_eq!(title.chars().count(), 81);
+    assert!(title.ends_with('…'));
+}
+
+#[test]
+fn rfc3339_timestamps() {
+    assert_eq!(fmt_rfc3339(Timestamp(0)), "1970-01-01T00:00:00Z");
+    assert_eq!(
+        fmt_rfc3339(Timestamp(1_700_000_000)),
+        "2023-11-14T22:13:20Z"
+    );
+}
@@ -1,17 +1,22 @@
-use axum::Form;
-use axum::extract::{Query, State};
+use axum::extract::{Path, Query, State};
 use axum::response::IntoResponse;
+use axum_extra::extract::Form;
 use maud::{Markup, PreEscaped, html};
-use rostra_core::ExternalEventId;
-use rostra_core::event::PersonaId;
-use rostra_core::id::ToShort as _;
+use rostra_client_db::social::SocialPostRecord;
+use rostra_client_db::{SocialDraftRecord, SocialOutboxRecord, SocialOutboxStatus};
+use rostra_core::event::{PersonaId, SocialPoll, SocialPost};
+use rostra_core::id::{RostraId, ToShort as _};
+use rostra_core::{ExternalEventId, ShortEventId, Timestamp};
 use serde::Deserialize;
+use snafu::ResultExt as _;
 use tower_cookies::Cookies;
 
 use super::super::SharedState;
-use super::super::error::RequestResult;
+use super::super::error::{OtherSnafu, RequestResult, UserRequestError, UserSnafu};
 use super::Maud;
+use super::blob::{ATTACHMENTS_FIELD, UPLOAD_FIELD};
 use super::cookies::CookiesExt as _;
+use super::poll::{POLL_DURATIONS_HOURS, POLL_OPTIONS_FIELD};
 use super::unlock::session::{RoMode, UserSession};
 use crate::UiState;
 use crate::html_utils::{re_typeset_mathjax, submit_on_ctrl_enter};
@@ -21,6 +26,91 @@ pub struct PostInput {
     reply_to: Option<ExternalEventId>,
     content: String,
     persona: Option<u8>,
+    #[serde(default)]
+    attachments: Vec<ExternalEventId>,
+    /// Post being edited
+    edit_of: Option<ShortEventId>,
+    /// Post being quoted
+    quote: Option<ExternalEventId>,
+    /// Question of the poll to attach, if any
+    poll_question: Option<String>,
+    #[serde(default)]
+    poll_options: Vec<String>,
+    /// Hours until the poll closes
+    poll_hours: Option<u64>,
+    content_warning: Option<String>,
+    /// Unix time to publish at, instead of right away
+    publish_at: Option<u64>,
+    /// Draft the post was written in, if it was autosaved
+    draft_id: Option<u64>,
+}
+
+impl PostInput {
+    /// The time to publish the post at, if it is scheduled for later
+    fn publish_at(&self) -> Option<Timestamp> {
+        self.publish_at
+            .map(Timestamp)
+            .filter(|publish_at| Timestamp::now() < *publish_at)
+    }
+
+    /// The content warning to attach, if one was entered
+    fn content_warning(&self) -> Option<String> {
+        self.content_warning
+            .as_deref()
+            .map(str::trim)
+            .filter(|content_warning| !content_warning.is_empty())
+            .map(ToOwned::to_owned)
+    }
+
+    /// The poll to attach, if the poll part of the form was filled
+    fn poll(&self) -> Option<SocialPoll> {
+        let question = self.poll_question.as_ref()?.trim();
+        if question.is_empty() {
+            return None;
+        }
+        Some(SocialPoll {
+            question: question.to_owned(),
+            options: self
+                .poll_options
+                .iter()
+                .map(|option| option.trim())
+                .filter(|option| !option.is_empty())
+                .map(ToOwned::to_owned)
+                .collect(),
+            closes_at: Timestamp(
+                Timestamp::now()
+                    .0
+                    .saturating_add(self.poll_hours.unwrap_or(24).saturating_mul(60 * 60)),
+            ),
+        })
+    }
+}
+
+/// Draft to continue in the new post form
+struct NewPostDraft<'a> {
+    id: u64,
+    record: &'a SocialDraftRecord,
+    /// Display name of the author of the post replied to or quoted
+    context_display_name: Option<String>,
+}
+
+/// Autosaves the new post form as a draft whenever it changes
+fn render_draft_status(draft_id: Option<u64>, saved: bool) -> Markup {
+    html! {
+        div ."m-newPostForm__draft"
+            hx-post="/ui/post/draft"
+            hx-include="closest form"
+            hx-trigger="input delay:2s from:closest form"
+            hx-swap="outerHTML"
+        {
+            @if let Some(draft_id) = draft_id {
+                input type="hidden" name="draft_id" value=(draft_id) {}
+            }
+            @if saved {
+                span ."m-newPostForm__draftStatus" { "Draft saved" }
+            }
+        }
+    }
 }
 
 fn focus_on_new_post_content_input() -> Markup {
@@ -44,6 +134,52 @@ fn focus_on_new_post_content_input() -> Markup {
         }
     }
 }
+/// Suggest followees when typing an `@mention`, and insert the picked one
+fn suggest_mentions() -> Markup {
+    html! {
+        script {
+            (PreEscaped(r#"
+                (function() {
+                    const content = document.querySelector('.m-newPostForm__content');
+                    const suggestions = document.querySelector('.m-newPostForm__mentionSuggestions');
+                    if (content == null || suggestions == null) {
+                        return;
+                    }
+                    const mentionBeforeCursor = () => content.value
+                        .slice(0, content.selectionStart)
+                        .match(/(^|\s)@([\p{L}\p{N}_]*)$/u);
+
+                    content.addEventListener('input', () => {
+                        const mention = mentionBeforeCursor();
+                        if (mention == null) {
+                            suggestions.innerHTML = '';
+                            return;
+                        }
+                        htmx.ajax('GET', '/ui/post/mentions?q=' + encodeURIComponent(mention[2]), {
+                            target: suggestions,
+                            swap: 'innerHTML',
+                        });
+                    });
+
+                    suggestions.addEventListener('click', (e) => {
+                        const button = e.target.closest('.m-newPostForm__mentionSuggestion');
+                        const mention = mentionBeforeCursor();
+                        if (button == null || mention == null) {
+                            return;
+                        }
+                        const end = content.selectionStart;
+                        const start = end - mention[2].length - 1;
+                        content.setRangeText(button.dataset.mention + ' ', start, end, 'end');
+                        suggestions.innerHTML = '';
+                        content.focus();
+                        content.dispatchEvent(new Event('input', { bubbles: true }));
+                    });
+                })()
+            "#))
+        }
+    }
+}
+
 fn scroll_preview_into_view() -> Markup {
     html! {
         script {
@@ -75,14 +211,140 @@ pub async fn post_new_post(
         cookies.save_persona(client_ref.rostra_id(), persona_id);
     }
 
-    let event = client_ref
-        .social_post(
-            session.id_secret()?,
-            form.content.clone(),
-            form.reply_to,
-            PersonaId(form.persona.unwrap_or_default()),
-        )
-        .await?;
+    if let Some(edit_of) = form.edit_of {
+        client_ref
+            .edit_social_post(
+                session.id_secret()?,
+                edit_of,
+                form.content.clone(),
+                form.attachments.clone(),
+                form.content_warning(),
+            )
+            .await?;
+        let Some(post) = client_ref.db().get_social_post(edit_of).await else {
+            return Err(UserRequestError::SomethingNotFound).context(UserSnafu);
+        };
+
+        return Ok(Maud(html! {
+            (state.new_post_form(
+                html! {
+                    div {
+                        span { "Edited!" }
+                    }
+                },
+                session.ro_mode(),
+            ))
+
+            div ."o-previewDialog -empty" hx-swap-oob="outerHTML:.o-previewDialog" {}
+
+            div ."o-mainBarTimeline__item -preview -empty"
+                hx-swap-oob="outerHTML: .o-mainBarTimeline__item.-preview"
+            { }
+
+            // Replace the displayed post with its new version
+            (state.render_post_overview(&client_ref, post.author)
+                .event_id(post.event_id)
+                .maybe_content(post.content.djot_content.as_deref())
+                .attachments(&post.content.attachments)
+                .reply_count(post.reply_count)
+                .maybe_edited(post.edited)
+                .maybe_quote(post.content.quote)
+                .maybe_poll(post.content.poll)
+                .maybe_content_warning(post.content.content_warning.as_deref())
+                .ro(session.ro_mode())
+                .is_comment(post.reply_to.is_some())
+                .swap_oob(true)
+                .call()
+                .await?)
+            (re_typeset_mathjax())
+        }));
+    }
+
+    if let Some(publish_at) = form.publish_at() {
+        // Scheduling requires the same access as posting
+        session.id_secret()?;
+        if form.poll().is_some() {
+            return Err(UserRequestError::InvalidData).context(UserSnafu);
+        }
+        let record = SocialOutboxRecord {
+            publish_at,
+            persona: PersonaId(form.persona.unwrap_or_default()),
+            djot_content: form.content.clone(),
+            reply_to: form.reply_to,
+            attachments: form.attachments.clone(),
+            quote: form.quote,
+            content_warning: form.content_warning(),
+            status: SocialOutboxStatus::Pending,
+        };
+        // Catch what would fail to publish now, not at `publish_at`
+        if record.validate().is_err() {
+            return Err(UserRequestError::InvalidData).context(UserSnafu);
+        }
+        client_ref
+            .db()
+            .insert_social_outbox(record, form.draft_id)
+            .await
+            .boxed()
+            .context(OtherSnafu)?;
+
+        return Ok(Maud(html! {
+            (state.new_post_form(
+                html! {
+                    div {
+                        span { "Scheduled! " }
+                        a href="/ui/outbox" { "See scheduled posts" }
+                    }
+                },
+                session.ro_mode(),
+            ))
+
+            div ."o-previewDialog -empty" hx-swap-oob="outerHTML:.o-previewDialog" {}
+
+            div ."o-mainBarTimeline__item -preview -empty"
+                hx-swap-oob="outerHTML: .o-mainBarTimeline__item.-preview"
+            { }
+        }));
+    }
+
+    let (event, poll) = if let Some(poll) = form.poll() {
+        let event = client_ref
+            .social_poll(
+                session.id_secret()?,
+                Some(form.content.clone()).filter(|content| !content.trim().is_empty()),
+                PersonaId(form.persona.unwrap_or_default()),
+                poll,
+                form.content_warning(),
+            )
+            .await?;
+        let poll = client_ref
+            .db()
+            .get_social_post(event.event_id.to_short())
+            .await
+            .and_then(|post| post.content.poll);
+        (event, poll)
+    } else {
+        let event = client_ref
+            .social_post(
+                session.id_secret()?,
+                form.content.clone(),
+                form.reply_to,
+                PersonaId(form.persona.unwrap_or_default()),
+                form.attachments.clone(),
+                form.quote,
+                form.content_warning(),
+            )
+            .await?;
+        (event, None)
+    };
+
+    if let Some(draft_id) = form.draft_id {
+        client_ref
+            .db()
+            .remove_social_draft(draft_id)
+            .await
+            .boxed()
+            .context(OtherSnafu)?;
+    }
 
     // Clear the form content after posting
     let clean_form = state.new_post_form(
@@ -135,6 +397,10 @@ pub async fn post_new_post(
                     .maybe_reply_to(reply_to)
                     .event_id(event.event_id.to_short())
                     .content(&form.content)
+                    .attachments(&form.attachments)
+                    .maybe_quote(form.quote)
+                    .maybe_poll(poll)
+                    .maybe_content_warning(form.content_warning().as_deref())
                     .ro( session.ro_mode())
                     .call()
                 .await?)
@@ -154,7 +420,7 @@ pub async fn get_post_preview_dialog(
     let client_ref = client.client_ref()?;
     let self_id = client_ref.rostra_id();
 
-    if form.content.is_empty() {
+    if form.content.is_empty() && form.attachments.is_empty() {
         return Ok(Maud(html! {
             div ."o-previewDialog -empty" {}
         }));
@@ -173,6 +439,9 @@ pub async fn get_post_preview_dialog(
                         self_id
                         )
                         .content(&form.content)
+                        .attachments(&form.attachments)
+                        .maybe_quote(form.quote)
+                        .maybe_content_warning(form.content_warning().as_deref())
                         .ro(session.ro_mode())
                         .call().await?
                     )
@@ -188,6 +457,30 @@ pub async fn get_post_preview_dialog(
                         @if let Some(reply_to) = form.reply_to {
                             input type="hidden" name="reply_to" value=(reply_to) {}
                         }
+                        @if let Some(edit_of) = form.edit_of {
+                            input type="hidden" name="edit_of" value=(edit_of) {}
+                        }
+                        @if let Some(quote) = form.quote {
+                            input type="hidden" name="quote" value=(quote) {}
+                        }
+                        @if let Some(content_warning) = form.content_warning() {
+                            input type="hidden" name="content_warning" value=(content_warning) {}
+                        }
+                        @if let Some(draft_id) = form.draft_id {
+                            input type="hidden" name="draft_id" value=(draft_id) {}
+                        }
+                        @if let Some(poll_question) = form.poll_question.as_ref() {
+                            input type="hidden" name="poll_question" value=(poll_question) {}
+                        }
+                        @for poll_option in &form.poll_options {
+                            input type="hidden" name=(POLL_OPTIONS_FIELD) value=(poll_option) {}
+                        }
+                        @if let Some(poll_hours) = form.poll_hours {
+                            input type="hidden" name="poll_hours" value=(poll_hours) {}
+                        }
+                        @for attachment in &form.attachments {
+                            input type="hidden" name=(ATTACHMENTS_FIELD) value=(attachment) {}
+                        }
 
                         div ."o-previewDialog__actionContainer" {
                             div ."o-previewDialog__personaContainer" {
@@ -205,6 +498,19 @@ pub async fn get_post_preview_dialog(
                                 }
                             }
 
+                            @if form.edit_of.is_none() && form.poll().is_none() {
+                                div ."o-previewDialog__scheduleContainer" {
+                                    label ."o-previewDialog__scheduleLabel" for="publish-at-input" { "Publish at" }
+                                    input ."o-previewDialog__scheduleInput"
+                                        id="publish-at-input"
+                                        type="datetime-local"
+                                        // Local time is only known to the browser, so it converts it
+                                        onchange="this.form.publish_at.disabled = !this.value; this.form.publish_at.value = this.value ? Math.floor(new Date(this.value).getTime() / 1000) : ''"
+                                        {}
+                                    input type="hidden" name="publish_at" disabled {}
+                                }
+                            }
+
                             div ."o-previewDialog__actionButtons" {
                                 button ."o-previewDialog__cancelButton u-button"
                                     type="button"
@@ -218,7 +524,7 @@ pub async fn get_post_preview_dialog(
                                 button ."o-previewDialog__submitButton u-button" type="submit" {
                                     span ."o-previewDialog__submitButtonIcon u-buttonIcon"
                                         width="1rem" height="1rem" {}
-                                    "Post"
+                                    @if form.edit_of.is_some() { "Save" } @else { "Post" }
                                 }
                             }
                         }
@@ -238,7 +544,7 @@ pub async fn get_post_preview(
     let client = state.client(session.id()).await?;
     let self_id = client.client_ref()?.rostra_id();
     Ok(Maud(html! {
-        @if !form.content.is_empty() {
+        @if !form.content.is_empty() || !form.attachments.is_empty() {
             div ."o-mainBarTimeline__item -preview"
                 ."-reply"[form.reply_to.is_some()]
                 ."-post"[form.reply_to.is_none()]
@@ -248,6 +554,9 @@ pub async fn get_post_preview(
                     self_id
                     )
                     .content(&form.content)
+                    .attachments(&form.attachments)
+                    .maybe_quote(form.quote)
+                    .maybe_content_warning(form.content_warning().as_deref())
                     .ro(session.ro_mode())
                     .call().await?
                 )
@@ -261,6 +570,40 @@ pub async fn get_post_preview(
     }))
 }
 
+#[derive(Deserialize)]
+pub struct EditPostInput {
+    edit_of: Option<ShortEventId>,
+}
+
+/// New post form prefilled with an own post to edit, or a clean one
+pub async fn get_edit_post_form(
+    state: State<SharedState>,
+    session: UserSession,
+    Query(form): Query<EditPostInput>,
+) -> RequestResult<impl IntoResponse> {
+    let Some(edit_of) = form.edit_of else {
+        return Ok(Maud(state.new_post_form(None, session.ro_mode())));
+    };
+    let client_handle = state.client(session.id()).await?;
+    let client_ref = client_handle.client_ref()?;
+
+    let Some(post) = client_ref
+        .db()
+        .get_social_post(edit_of)
+        .await
+        .filter(|post| post.author == session.id())
+    else {
+        return Err(UserRequestError::SomethingNotFound).context(UserSnafu);
+    };
+
+    Ok(Maud(state.render_new_post_form(
+        None,
+        session.ro_mode(),
+        Some(&post),
+        None,
+    )))
+}
+
 #[derive(Deserialize)]
 pub struct ReplyToInput {
     reply_to: Option<ExternalEventId>,
@@ -287,6 +630,273 @@ pub async fn get_reply_to(
         state.render_reply_to_line(form.reply_to, display_name),
     ))
 }
+#[derive(Deserialize)]
+pub struct QuoteInput {
+    quote: Option<ExternalEventId>,
+}
+
+pub async fn get_quote(
+    state: State<SharedState>,
+    session: UserSession,
+    Query(form): Query<QuoteInput>,
+) -> RequestResult<impl IntoResponse> {
+    let client_handle = state.client(session.id()).await?;
+    let client_ref = client_handle.client_ref()?;
+
+    let Some(quote) = form.quote else {
+        return Ok(Maud(state.render_reply_to_line(None, None)));
+    };
+    let display_name = client_ref
+        .db()
+        .get_social_profile(quote.rostra_id())
+        .await
+        .map(|p| p.display_name);
+    Ok(Maud(state.render_quote_line(quote, display_name)))
+}
+
+/// Maximum number of followees suggested when typing a mention
+const MAX_MENTION_SUGGESTIONS: usize = 8;
+
+#[derive(Deserialize)]
+pub struct MentionSuggestionsInput {
+    q: Option<String>,
+}
+
+/// Followees matching the `@mention` being typed in the new post form
+pub async fn get_mention_suggestions(
+    state: State<SharedState>,
+    session: UserSession,
+    Query(form): Query<MentionSuggestionsInput>,
+) -> RequestResult<impl IntoResponse> {
+    let client_handle = state.client(session.id()).await?;
+    let client_ref = client_handle.client_ref()?;
+
+    let query = form.q.unwrap_or_default().to_lowercase();
+    let followees = client_ref.db().get_followees(session.id()).await;
+    let profiles = client_ref
+        .db()
+        .get_social_profiles(followees.iter().map(|(id, _)| *id))
+        .await;
+    let mut suggestions: Vec<(RostraId, String)> = followees
+        .into_iter()
+        .map(|(id, _)| {
+            let display_name = profiles
+                .get(&id)
+                .map(|profile| profile.display_name.clone())
+                .unwrap_or_else(|| id.to_short().to_string());
+            (id, display_name)
+        })
+        .filter(|(_, display_name)| display_name.to_lowercase().contains(&query))
+        .collect();
+    suggestions.sort_by(|(_, a), (_, b)| a.cmp(b));
+    suggestions.truncate(MAX_MENTION_SUGGESTIONS);
+
+    Ok(Maud(html! {
+        @for (id, display_name) in suggestions {
+            button ."m-newPostForm__mentionSuggestion u-button"
+                type="button"
+                data-mention=(format!("<rostra:{id}>"))
+            {
+                (display_name)
+            }
+        }
+    }))
+}
+
+/// Number of characters of a draft shown in the list of drafts
+const DRAFT_EXCERPT_LEN: usize = 200;
+
+/// Save the new post form as a draft
+pub async fn post_draft(
+    state: State<SharedState>,
+    session: UserSession,
+    Form(form): Form<PostInput>,
+) -> RequestResult<impl IntoResponse> {
+    session.id_secret()?;
+    let db = state.client(session.id()).await?.db()?;
+
+    if form.content.trim().is_empty() && form.attachments.is_empty() {
+        // Nothing worth keeping anymore
+        if let Some(draft_id) = form.draft_id {
+            db.remove_social_draft(draft_id)
+                .await
+                .boxed()
+                .context(OtherSnafu)?;
+        }
+        return Ok(Maud(render_draft_status(None, false)));
+    }
+
+    let draft_id = db
+        .save_social_draft(
+            form.draft_id,
+            SocialDraftRecord {
+                updated: Timestamp::now(),
+                djot_content: form.content.clone(),
+                reply_to: form.reply_to,
+                attachments: form.attachments.clone(),
+                quote: form.quote,
+                content_warning: form.content_warning(),
+            },
+        )
+        .await
+        .boxed()
+        .context(OtherSnafu)?;
+
+    Ok(Maud(render_draft_status(Some(draft_id), true)))
+}
+
+pub async fn get_drafts(
+    state: State<SharedState>,
+    session: UserSession,
+) -> RequestResult<impl IntoResponse> {
+    // Drafts are private, only show them to whoever can post
+    session.id_secret()?;
+
+    let client_handle = state.client(session.id()).await?;
+    let client_ref = client_handle.client_ref()?;
+
+    let drafts = client_ref
+        .db()
+        .list_social_drafts()
+        .await
+        .boxed()
+        .context(OtherSnafu)?;
+    let profiles = client_ref
+        .db()
+        .get_social_profiles(
+            drafts
+                .iter()
+                .filter_map(|(_, draft)| draft.quote.or(draft.reply_to))
+                .map(|context| context.rostra_id()),
+        )
+        .await;
+    let render_context = |label: &str, context: ExternalEventId| {
+        html! {
+            (label) " "
+            a href=(format!("/ui/post/{}/{}", context.rostra_id(), context.event_id())) {
+                (profiles
+                    .get(&context.rostra_id())
+                    .map(|profile| profile.display_name.clone())
+                    .unwrap_or_else(|| context.rostra_id().to_short().to_string()))
+            }
+        }
+    };
+
+    Ok(Maud(html! {
+        div ."m-newPostForm__draftsList" {
+            div ."m-newPostForm__draftsHeader" {
+                span { "Drafts" }
+                button ."m-newPostForm__draftsCloseButton u-button"
+                    type="button"
+                    onclick="document.querySelector('.m-newPostForm__drafts').innerHTML = ''"
+                {
+                    "Close"
+                }
+            }
+            @if drafts.is_empty() {
+                p ."m-newPostForm__draftsEmpty" { "No drafts." }
+            }
+            @for (id, draft) in &drafts {
+                div ."m-newPostForm__draftsItem" {
+                    @if let Some(quote) = draft.quote {
+                        div ."m-newPostForm__draftsItemContext" {
+                            (render_context("Quoting", quote))
+                        }
+                    } @else if let Some(reply_to) = draft.reply_to {
+                        div ."m-newPostForm__draftsItemContext" {
+                            (render_context("Reply to", reply_to))
+                        }
+                    }
+                    p ."m-newPostForm__draftsItemContent" dir="auto" {
+                        (draft.djot_content.chars().take(DRAFT_EXCERPT_LEN).collect::<String>())
+                    }
+                    div ."m-newPostForm__draftsItemActions" {
+                        button ."m-newPostForm__draftsContinueButton u-button"
+                            type="button"
+                            hx-get=(format!("/ui/post/draft/{id}"))
+                            hx-target=".m-newPostForm"
+                            hx-swap="outerHTML"
+                        {
+                            "Continue"
+                        }
+                        button ."m-newPostForm__draftsDeleteButton u-button"
+                            type="button"
+                            hx-delete=(format!("/ui/post/draft/{id}"))
+                            hx-target="closest .m-newPostForm__draftsItem"
+                            hx-swap="outerHTML"
+                            hx-confirm="Delete this draft?"
+                        {
+                            "Delete"
+                        }
+                    }
+                }
+            }
+        }
+    }))
+}
+
+/// New post form prefilled with a draft
+pub async fn get_draft(
+    state: State<SharedState>,
+    session: UserSession,
+    Path(id): Path<u64>,
+) -> RequestResult<impl IntoResponse> {
+    // Drafts are private, only show them to whoever can post
+    session.id_secret()?;
+
+    let client_handle = state.client(session.id()).await?;
+    let client_ref = client_handle.client_ref()?;
+
+    let Some(record) = client_ref
+        .db()
+        .get_social_draft(id)
+        .await
+        .boxed()
+        .context(OtherSnafu)?
+    else {
+        return Err(UserRequestError::SomethingNotFound).context(UserSnafu);
+    };
+    let context_display_name = if let Some(context) = record.quote.or(record.reply_to) {
+        client_ref
+            .db()
+            .get_social_profile(context.rostra_id())
+            .await
+            .map(|p| p.display_name)
+    } else {
+        None
+    };
+
+    Ok(Maud(state.render_new_post_form(
+        None,
+        session.ro_mode(),
+        None,
+        Some(NewPostDraft {
+            id,
+            record: &record,
+            context_display_name,
+        }),
+    )))
+}
+
+pub async fn delete_draft(
+    state: State<SharedState>,
+    session: UserSession,
+    Path(id): Path<u64>,
+) -> RequestResult<impl IntoResponse> {
+    session.id_secret()?;
+
+    state
+        .client(session.id())
+        .await?
+        .db()?
+        .remove_social_draft(id)
+        .await
+        .boxed()
+        .context(OtherSnafu)?;
+
+    Ok(Maud(html! {}))
+}
+
 impl UiState {
     fn render_reply_to_line(
         &self,
@@ -316,14 +926,104 @@ impl UiState {
         }
     }
 
+    fn render_quote_line(
+        &self,
+        quote: ExternalEventId,
+        quote_display_name: Option<String>,
+    ) -> Markup {
+        html! {
+            div ."m-newPostForm__replyToLine" {
+                p ."m-newPostForm__replyToLabel" {
+                    span ."m-newPostForm__replyToText" { "Quoting: " }
+                    (quote_display_name.unwrap_or_else(
+                        || quote.rostra_id().to_short().to_string()
+                    ))
+                }
+                input type="hidden" name="quote" value=(quote) {}
+                (focus_on_new_post_content_input())
+            }
+        }
+    }
+
+    fn render_edit_line(&self, edit_of: ShortEventId) -> Markup {
+        html! {
+            div ."m-newPostForm__replyToLine" {
+                p ."m-newPostForm__replyToLabel" {
+                    span ."m-newPostForm__replyToText" { "Editing post" }
+                    button ."m-newPostForm__cancelEditButton u-button"
+                        type="button"
+                        hx-get="/ui/post/edit"
+                        hx-target="closest .m-newPostForm"
+                        hx-swap="outerHTML"
+                    {
+                        "Cancel"
+                    }
+                }
+                input type="hidden" name="edit_of" value=(edit_of) {}
+                (focus_on_new_post_content_input())
+            }
+        }
+    }
+
     pub fn new_post_form(&self, notification: impl Into<Option<Markup>>, ro: RoMode) -> Markup {
+        self.render_new_post_form(notification, ro, None, None)
+    }
+
+    /// New post form, prefilled with `edit` if editing an existing post, or
+    /// with a `draft` to continue
+    fn render_new_post_form(
+        &self,
+        notification: impl Into<Option<Markup>>,
+        ro: RoMode,
+        edit: Option<&SocialPostRecord<SocialPost>>,
+        draft: Option<NewPostDraft<'_>>,
+    ) -> Markup {
         let notification = notification.into();
+        let content_warning = edit
+            .and_then(|edit| edit.content.content_warning.as_deref())
+            .or(draft
+                .as_ref()
+                .and_then(|draft| draft.record.content_warning.as_deref()));
+        let content = edit
+            .and_then(|edit| edit.content.djot_content.as_deref())
+            .or(draft
+                .as_ref()
+                .map(|draft| draft.record.djot_content.as_str()));
+        let attachments = edit
+            .map(|edit| edit.content.attachments.as_slice())
+            .or(draft
+                .as_ref()
+                .map(|draft| draft.record.attachments.as_slice()))
+            .unwrap_or_default();
         html! {
             form ."m-newPostForm"
                 hx-post="/ui/post/preview_dialog"
                 hx-swap="none"
             {
-                (self.render_reply_to_line(None, None))
+                @if let Some(edit) = edit {
+                    (self.render_edit_line(edit.event_id))
+                } @else if let Some(draft) = draft.as_ref() {
+                    @if let Some(quote) = draft.record.quote {
+                        (self.render_quote_line(quote, draft.context_display_name.clone()))
+                    } @else {
+                        (self.render_reply_to_line(
+                            draft.record.reply_to,
+                            draft.context_display_name.clone(),
+                        ))
+                    }
+                } @else {
+                    (self.render_reply_to_line(None, None))
+                }
+                input ."m-newPostForm__contentWarning"
+                    type="text"
+                    name="content_warning"
+                    placeholder="Content warning (optional)"
+                    maxlength=(SocialPost::MAX_CONTENT_WARNING_LEN)
+                    dir="auto"
+                    autocomplete="off"
+                    disabled[ro.to_disabled()]
+                    value=[content_warning]
+                    {}
                 textarea
                     ."m-newPostForm__content"
                     placeholder=(
@@ -343,12 +1043,100 @@ impl UiState {
                     autocomplete="off"
                     autofocus
                     disabled[ro.to_disabled()]
-                    {}
+                    {
+                        @if let Some(content) = content {
+                            (content)
+                        }
+                    }
+                div ."m-newPostForm__mentionSuggestions" {}
+                div ."m-newPostForm__attachments" {
+                    @for attachment in attachments {
+                        (self.render_new_post_attachment(*attachment))
+                    }
+                }
+                @if edit.is_none() && !ro.to_disabled() {
+                    (render_draft_status(draft.as_ref().map(|draft| draft.id), false))
+                }
+                @if edit.is_none() {
+                    div ."m-newPostForm__poll -hidden" {
+                        input ."m-newPostForm__pollQuestion"
+                            type="text"
+                            name="poll_question"
+                            placeholder="Poll question"
+                            autocomplete="off"
+                            disabled[ro.to_disabled()]
+                            {}
+                        @for i in 0..4 {
+                            input ."m-newPostForm__pollOption"
+                                type="text"
+                                name=(POLL_OPTIONS_FIELD)
+                                placeholder=(format!("Option {}", i + 1))
+                                autocomplete="off"
+                                disabled[ro.to_disabled()]
+                                {}
+                        }
+                        label ."m-newPostForm__pollHoursLabel" {
+                            "Closes in "
+                            select name="poll_hours" disabled[ro.to_disabled()] {
+                                @for hours in POLL_DURATIONS_HOURS {
+                                    option value=(hours) selected[hours == 24] {
+                                        @match hours {
+                                            1 => { "1 hour" }
+                                            24 => { "1 day" }
+                                            hours if hours < 24 => { (format!("{hours} hours")) }
+                                            hours => { (format!("{} days", hours / 24)) }
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
                 div ."m-newPostForm__footer" {
                     @if let Some(n) = notification {
                         (n)
                     }
+                    label ."m-newPostForm__attachButton u-button"
+                        ."-disabled"[ro.to_disabled()]
+                        title="Attach image"
+                    {
+                        span ."m-newPostForm__attachButtonIcon u-buttonIcon" width="1rem" height="1rem" {}
+                        input ."m-newPostForm__attachInput"
+                            type="file"
+                            name=(UPLOAD_FIELD)
+                            accept="image/*"
+                            hx-post="/ui/blob"
+                            hx-encoding="multipart/form-data"
+                            hx-params=(UPLOAD_FIELD)
+                            hx-trigger="change"
+                            hx-target=".m-newPostForm__attachments"
+                            hx-swap="beforeend"
+                            hx-on::after-request="this.value=''"
+                            disabled[ro.to_disabled()]
+                            style="display: none;"
+                            {}
+                    }
+                    @if edit.is_none() {
+                        button ."m-newPostForm__pollButton u-button"
+                            type="button"
+                            disabled[ro.to_disabled()]
+                            onclick="this.closest('.m-newPostForm').querySelector('.m-newPostForm__poll').classList.toggle('-hidden')"
+                        {
+                            "Poll"
+                        }
+                    }
                     a href="https://htmlpreview.github.io/?https://github.com/jgm/djot/blob/master/doc/syntax.html" target="_blank" { "Formatting" }
+                    @if edit.is_none() {
+                        button ."m-newPostForm__draftsButton u-button"
+                            type="button"
+                            hx-get="/ui/post/drafts"
+                            hx-target=".m-newPostForm__drafts"
+                            hx-swap="innerHTML"
+                        {
+                            "Drafts"
+                        }
+                    }
+                    a href="/ui/outbox" title="Scheduled posts" { "Scheduled" }
                     a
                         ."m-newPostForm__emojiButton"
                         href="#"
@@ -361,6 +1149,7 @@ impl UiState {
                         "Preview"
                     }
                 }
+                div ."m-newPostForm__drafts" {}
                 div
                     ."m-newPostForm__emojiBar -hidden"
                     role="tooltip" {
@@ -396,6 +1185,7 @@ impl UiState {
 
             }
             (submit_on_ctrl_enter(".m-newPostForm", ".m-newPostForm__content"))
+            (suggest_mentions())
         }
     }
 }
@@ -0,0 +1,233 @@
+use axum::Form;
+use axum::extract::{Path, State};
+use axum::response::IntoResponse;
+use maud::{Markup, html};
+use rostra_client_db::{SocialOutboxRecord, SocialOutboxStatus};
+use rostra_core::Timestamp;
+use serde::Deserialize;
+use snafu::ResultExt as _;
+use time::OffsetDateTime;
+
+use super::Maud;
+use super::unlock::session::UserSession;
+use crate::error::{OtherSnafu, RequestResult, UserRequestError, UserSnafu};
+use crate::{SharedState, UiState};
+
+#[derive(Deserialize)]
+pub struct EditOutboxInput {
+    content: String,
+    content_warning: Option<String>,
+    /// Unix time to publish at, if it should be changed
+    publish_at: Option<u64>,
+}
+
+pub async fn get_outbox(
+    state: State<SharedState>,
+    session: UserSession,
+) -> RequestResult<impl IntoResponse> {
+    // Scheduled posts are private, only show them to whoever can post
+    session.id_secret()?;
+
+    let navbar = state.render_navbar(session.id(), &session).await?;
+    let outbox = state.render_outbox(&session).await?;
+    Ok(Maud(
+        state
+            .render_html_page(
+                "Rostra: Scheduled Posts",
+                html! {
+                    (navbar)
+                    main ."o-mainBar" {
+                        (outbox)
+                    }
+                },
+            )
+            .await?,
+    ))
+}
+
+pub async fn post_outbox_edit(
+    state: State<SharedState>,
+    session: UserSession,
+    Path(id): Path<u64>,
+    Form(form): Form<EditOutboxInput>,
+) -> RequestResult<impl IntoResponse> {
+    // Editing requires the same access as posting
+    session.id_secret()?;
+
+    let db = state.client(session.id()).await?.db()?;
+    let Some(record) = db.get_social_outbox(id).await.boxed().context(OtherSnafu)? else {
+        return Err(UserRequestError::SomethingNotFound).context(UserSnafu);
+    };
+
+    let publish_at = form.publish_at.map(Timestamp).unwrap_or(record.publish_at);
+    if publish_at < Timestamp::now() {
+        return Err(UserRequestError::InvalidData).context(UserSnafu);
+    }
+
+    let record = SocialOutboxRecord {
+        publish_at,
+        djot_content: form.content,
+        content_warning: form
+            .content_warning
+            .map(|content_warning| content_warning.trim().to_owned())
+            .filter(|content_warning| !content_warning.is_empty()),
+        ..record
+    };
+    if record.validate().is_err() {
+        return Err(UserRequestError::InvalidData).context(UserSnafu);
+    }
+
+    let updated = db
+        .update_social_outbox(id, record)
+        .await
+        .boxed()
+        .context(OtherSnafu)?;
+    if !updated {
+        // Got published in the meantime
+        return Err(UserRequestError::SomethingNotFound).context(UserSnafu);
+    }
+
+    Ok(Maud(state.render_outbox(&session).await?))
+}
+
+pub async fn post_outbox_cancel(
+    state: State<SharedState>,
+    session: UserSession,
+    Path(id): Path<u64>,
+) -> RequestResult<impl IntoResponse> {
+    session.id_secret()?;
+
+    state
+        .client(session.id())
+        .await?
+        .db()?
+        .remove_social_outbox(id)
+        .await
+        .boxed()
+        .context(OtherSnafu)?;
+
+    Ok(Maud(state.render_outbox(&session).await?))
+}
+
+impl UiState {
+    /// List of scheduled posts, with the pending ones editable
+    async fn render_outbox(&self, session: &UserSession) -> RequestResult<Markup> {
+        let entries = self
+            .client(session.id())
+            .await?
+            .db()?
+            .list_social_outbox()
+            .await
+            .boxed()
+            .context(OtherSnafu)?;
+
+        Ok(html! {
+            div ."o-outbox" {
+                h2 { "Scheduled Posts" }
+                p {
+                    "Posts scheduled from the new post form are published at the set time, "
+                    "as long as this node is running and unlocked."
+                }
+
+                @if entries.is_empty() {
+                    p ."o-outbox__empty" { "Nothing scheduled." }
+                }
+
+                @for (id, record) in &entries {
+                    (self.render_outbox_entry(session, *id, record))
+                }
+            }
+        })
+    }
+
+    fn render_outbox_entry(
+        &self,
+        session: &UserSession,
+        id: u64,
+        record: &SocialOutboxRecord,
+    ) -> Markup {
+        let is_pending = record.status == SocialOutboxStatus::Pending;
+        html! {
+            div ."o-outbox__entry"
+                ."-pending"[is_pending]
+                ."-failed"[matches!(record.status, SocialOutboxStatus::Failed(_))]
+            {
+                div ."o-outbox__entryStatus" {
+                    @match &record.status {
+                        SocialOutboxStatus::Pending => {
+                            "Publishing at " (fmt_date_time(record.publish_at))
+                        }
+                        SocialOutboxStatus::Published(event_id) => {
+                            a href=(format!("/ui/post/{}/{event_id}", session.id())) {
+                                "Published"
+                            }
+                            " at " (fmt_date_time(record.publish_at))
+                        }
+                        SocialOutboxStatus::Failed(err) => {
+                            "Failed to publish: " (err)
+                        }
+                        SocialOutboxStatus::Publishing => {
+                            "Publishing..."
+                        }
+                    }
+                }
+
+                form ."o-outbox__entryForm"
+                    hx-post=(format!("/ui/outbox/{id}"))
+                    hx-target=".o-outbox"
+                    hx-swap="outerHTML"
+                {
+                    input ."o-outbox__entryContentWarning"
+                        type="text"
+                        name="content_warning"
+                        placeholder="Content warning"
+                        maxlength="100"
+                        value=[record.content_warning.as_deref()]
+                        disabled[!is_pending]
+                        {}
+                    textarea ."o-outbox__entryContent"
+                        name="content"
+                        rows="4"
+                        disabled[!is_pending]
+                    { (record.djot_content) }
+
+                    div ."o-outbox__entryActions" {
+                        @if is_pending {
+                            input ."o-outbox__entryPublishAt"
+                                type="datetime-local"
+                                title="New publish time"
+                                onchange="this.form.publish_at.disabled = !this.value; this.form.publish_at.value = this.value ? Math.floor(new Date(this.value).getTime() / 1000) : ''"
+                                disabled[session.ro_mode().to_disabled()]
+                                {}
+                            input type="hidden" name="publish_at" disabled {}
+                            button ."o-outbox__saveButton u-button"
+                                type="submit"
+                                disabled[session.ro_mode().to_disabled()]
+                            {
+                                "Save"
+                            }
+                        }
+                        button ."o-outbox__cancelButton u-button"
+                            type="button"
+                            hx-post=(format!("/ui/outbox/{id}/cancel"))
+                            hx-target=".o-outbox"
+                            hx-swap="outerHTML"
+                            hx-confirm=[is_pending.then_some("Cancel this scheduled post?")]
+                            disabled[session.ro_mode().to_disabled()]
+                        {
+                            @if is_pending { "Cancel" } @else { "Remove" }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn fmt_date_time(ts: Timestamp) -> String {
+    i64::try_from(ts.0)
+        .ok()
+        .and_then(|secs| OffsetDateTime::from_unix_timestamp(secs).ok())
+        .map(|dt| format!("{} {:02}:{:02} UTC", dt.date(), dt.hour(), dt.minute()))
+        .unwrap_or_default()
+}
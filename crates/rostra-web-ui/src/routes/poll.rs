@@ -0,0 +1,117 @@
+use axum::extract::{Path, State};
+use axum::response::IntoResponse;
+use axum_extra::extract::Form;
+use maud::{Markup, html};
+use rostra_client::ClientRef;
+use rostra_core::id::RostraId;
+use rostra_core::{ExternalEventId, ShortEventId, Timestamp};
+use serde::Deserialize;
+
+use super::Maud;
+use super::feed::fmt_rfc3339;
+use super::unlock::session::{RoMode, UserSession};
+use crate::error::RequestResult;
+use crate::{SharedState, UiState};
+
+/// Name of the fields of the poll options in the new post form
+pub const POLL_OPTIONS_FIELD: &str = "poll_options";
+
+/// Closing times offered in the new post form, in hours
+pub const POLL_DURATIONS_HOURS: [u64; 4] = [1, 24, 72, 168];
+
+#[derive(Deserialize)]
+pub struct VoteInput {
+    /// Option to vote for, none to withdraw the vote
+    option: Option<u8>,
+}
+
+pub async fn post_vote(
+    state: State<SharedState>,
+    session: UserSession,
+    Path((author, event_id)): Path<(RostraId, ShortEventId)>,
+    Form(form): Form<VoteInput>,
+) -> RequestResult<impl IntoResponse> {
+    let client_handle = state.client(session.id()).await?;
+    let client_ref = client_handle.client_ref()?;
+
+    client_ref
+        .vote_social_poll(
+            session.id_secret()?,
+            ExternalEventId::new(author, event_id),
+            form.option,
+        )
+        .await?;
+
+    Ok(Maud(
+        state
+            .render_poll(&client_ref, author, event_id, session.ro_mode())
+            .await,
+    ))
+}
+
+impl UiState {
+    /// Poll attached to a post, with buttons to vote if it's still open
+    pub async fn render_poll(
+        &self,
+        client: &ClientRef<'_>,
+        author: RostraId,
+        event_id: ShortEventId,
+        ro: RoMode,
+    ) -> Markup {
+        let Some(poll) = client
+            .db()
+            .get_social_poll(event_id)
+            .await
+            .filter(|poll| poll.author == author)
+        else {
+            return html! {
+                div ."m-postOverview__poll -missing" { "Poll not available yet" }
+            };
+        };
+
+        let is_closed = poll.content.is_closed(Timestamp::now());
+        let total: u64 = poll.tally.iter().sum();
+        let self_option = poll.self_vote.map(|(_, option)| option);
+        let vote_url = format!("/ui/poll/{author}/{event_id}/vote");
+
+        html! {
+            div ."m-postOverview__poll" ."-closed"[is_closed] {
+                p ."m-postOverview__pollQuestion" { (poll.content.question) }
+                @for (i, (option, count)) in poll.content.options.iter().zip(&poll.tally).enumerate() {
+                    @let percent = if total == 0 { 0 } else { count * 100 / total };
+                    @let is_own = self_option.is_some_and(|own| usize::from(own) == i);
+                    button ."m-postOverview__pollOption" ."-voted"[is_own]
+                        disabled[is_closed || ro.to_disabled()]
+                        hx-post=(vote_url)
+                        hx-vals=(format!(r#"{{"option": {i}}}"#))
+                        hx-target="closest .m-postOverview__poll"
+                        hx-swap="outerHTML"
+                        style=(format!("--poll-percent: {percent}%"))
+                    {
+                        span ."m-postOverview__pollOptionText" { (option) }
+                        span ."m-postOverview__pollOptionCount" { (format!("{percent}% ({count})")) }
+                    }
+                }
+                p ."m-postOverview__pollFooter" {
+                    @if total == 1 { "1 vote" } @else { (format!("{total} votes")) }
+                    " · "
+                    @if is_closed { "Closed " } @else { "Closes " }
+                    time datetime=(fmt_rfc3339(poll.content.closes_at)) {
+                        (fmt_rfc3339(poll.content.closes_at))
+                    }
+                    @if self_option.is_some() && !is_closed {
+                        " · "
+                        button ."m-postOverview__pollWithdraw"
+                            disabled[ro.to_disabled()]
+                            hx-post=(vote_url)
+                            hx-target="closest .m-postOverview__poll"
+                            hx-swap="outerHTML"
+                        {
+                            "Withdraw vote"
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
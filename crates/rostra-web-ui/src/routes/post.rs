@@ -9,13 +9,16 @@ use rostra_client_db::IdSocialProfileRecord;
 use rostra_client_db::social::SocialPostRecord;
 use rostra_core::event::SocialPost;
 use rostra_core::id::{RostraId, ToShort as _};
-use rostra_core::{ExternalEventId, ShortEventId};
+use rostra_core::{ExternalEventId, ShortEventId, Timestamp};
+use snafu::ResultExt as _;
 use tower_cookies::Cookies;
 
 use super::Maud;
+use super::feed::fmt_rfc3339;
 use super::timeline::TimelineMode;
 use super::unlock::session::{RoMode, UserSession};
-use crate::error::RequestResult;
+use crate::error::{RequestResult, UserRequestError, UserSnafu};
+use crate::html_utils::re_typeset_mathjax;
 use crate::{SharedState, UiState};
 
 pub async fn get_single_post(
@@ -38,6 +41,66 @@ pub async fn get_single_post(
     ))
 }
 
+pub async fn delete_post(
+    state: State<SharedState>,
+    session: UserSession,
+    Path((author, event_id)): Path<(RostraId, ShortEventId)>,
+) -> RequestResult<impl IntoResponse> {
+    if author != session.id() {
+        return Err(UserRequestError::SomethingNotFound).context(UserSnafu);
+    }
+    state
+        .client(session.id())
+        .await?
+        .client_ref()?
+        .delete_social_post(session.id_secret()?, event_id)
+        .await?;
+
+    Ok(Maud(html! {}))
+}
+
+/// All versions of an edited post, oldest first
+pub async fn get_post_history(
+    state: State<SharedState>,
+    session: UserSession,
+    Path((author, event_id)): Path<(RostraId, ShortEventId)>,
+) -> RequestResult<impl IntoResponse> {
+    let client = state.client(session.id()).await?;
+    let client_ref = client.client_ref()?;
+    let versions = client_ref
+        .db()
+        .get_social_post_versions(author, event_id)
+        .await;
+
+    let mut versions_html = vec![];
+    for (i, version) in versions.iter().enumerate() {
+        let Some(djot_content) = version.content.djot_content.as_ref() else {
+            continue;
+        };
+        versions_html.push(html! {
+            div ."m-postOverview__historyItem" {
+                p ."m-postOverview__historyTime" {
+                    @if i == 0 { "Original, " } @else { "Edited, " }
+                    time datetime=(fmt_rfc3339(version.ts)) { (fmt_rfc3339(version.ts)) }
+                }
+                div ."m-postOverview__historyContent" {
+                    (state.render_content(&client_ref, djot_content).await)
+                }
+                (state.render_post_attachments(&version.content.attachments))
+            }
+        });
+    }
+
+    Ok(Maud(html! {
+        div ."m-postOverview__history" {
+            @for version_html in versions_html {
+                (version_html)
+            }
+            (re_typeset_mathjax())
+        }
+    }))
+}
+
 #[bon::bon]
 impl UiState {
     #[allow(clippy::too_many_arguments)]
@@ -50,13 +113,26 @@ impl UiState {
         reply_to: Option<(RostraId, Option<&SocialPostRecord<SocialPost>>)>,
         event_id: Option<ShortEventId>,
         content: Option<&str>,
+        attachments: Option<&[ExternalEventId]>,
         reply_count: Option<u64>,
+        // Time of the latest edit, if the post was edited
+        edited: Option<Timestamp>,
+        // Post quoted by this one, rendered as an embedded card
+        quote: Option<ExternalEventId>,
+        // Number of posts quoting this one, if it should be displayed
+        quote_count: Option<u64>,
+        // Poll (of the same author) attached to the post
+        poll: Option<ShortEventId>,
+        // Hide the body of the post behind this warning
+        content_warning: Option<&str>,
         ro: RoMode,
         // Render the post including a comment, right away
         comment: Option<Markup>,
         // Is the post loaded as a comment to an existing post (already being
         // displayed)
         #[builder(default = false)] is_comment: bool,
+        // Replace the already displayed post (e.g. after editing it)
+        #[builder(default = false)] swap_oob: bool,
     ) -> RequestResult<Markup> {
         let external_event_id = event_id.map(|e| ExternalEventId::new(author, e));
         let user_profile = self.get_social_profile_opt(author, client).await;
@@ -118,16 +194,30 @@ impl UiState {
             None
         };
 
+        let poll_html = if let Some(poll) = poll {
+            Some(self.render_poll(client, author, poll, ro).await)
+        } else {
+            None
+        };
+
+        let quote_html = if let Some(quote) = quote {
+            Some(self.render_post_quote(client, quote).await)
+        } else {
+            None
+        };
+
         let display_name = if let Some(ref profile) = user_profile {
             profile.display_name.clone()
         } else {
             author.to_short().to_string()
         };
+        let avatar_url = self.profile_avatar_url(author, user_profile.as_ref());
         let post_main = html! {
             div ."m-postOverview__main"
             {
                 img ."m-postOverview__userImage u-userImage"
-                    src=(self.avatar_url(author))
+                    src=(avatar_url)
+                    srcset=(self.avatar_srcset(&avatar_url))
                     alt=(format!("{display_name}'s avatar"))
                     width="32pt"
                     height="32pt"
@@ -146,22 +236,53 @@ impl UiState {
                                 }
                             }
                         }
+                        @if let (Some(event_id), Some(edited)) = (event_id, edited) {
+                            button ."m-postOverview__editedButton"
+                                title=(format!("Edited at {}", fmt_rfc3339(edited)))
+                                hx-get=(format!("/ui/post/{author}/{event_id}/history"))
+                                hx-target="next .m-postOverview__history"
+                                hx-swap="outerHTML"
+                            {
+                                "(edited)"
+                            }
+                        }
                         @if let Some(event_id) = event_id {
                             a ."m-postOverview__postAnchor" href=(format!("/ui/post/{}/{}", author, event_id)) { "#" }
                         }
                     }
 
-                    div ."m-postOverview__content"
-                     ."-missing"[post_content_rendered.is_none()]
-                     ."-present"[post_content_rendered.is_some()]
-                    {
-                        p {
-                            @if let Some(post_content_rendered) = post_content_rendered {
-                                (post_content_rendered)
-                            } @else {
-                                "Post missing"
+                    (render_content_warning(content_warning, html! {
+                        // A post with a poll doesn't need any content of its own
+                        @if post_content_rendered.is_some() || poll_html.is_none() {
+                            div ."m-postOverview__content"
+                             ."-missing"[post_content_rendered.is_none()]
+                             ."-present"[post_content_rendered.is_some()]
+                            {
+                                p {
+                                    @if let Some(post_content_rendered) = post_content_rendered {
+                                        (post_content_rendered)
+                                    } @else {
+                                        "Post missing"
+                                    }
+                                }
                             }
                         }
+
+                        @if let Some(poll_html) = poll_html {
+                            (poll_html)
+                        }
+
+                        @if let Some(attachments) = attachments {
+                            (self.render_post_attachments(attachments))
+                        }
+
+                        @if let Some(quote_html) = quote_html {
+                            (quote_html)
+                        }
+                    }))
+
+                    @if edited.is_some() {
+                        div ."m-postOverview__history -empty" {}
                     }
                 }
 
@@ -202,6 +323,47 @@ impl UiState {
                             span ."m-postOverview__replyToButtonIcon u-buttonIcon" width="1rem" height="1rem" {}
                             "Reply"
                         }
+                        @if let Some(quote_count) = quote_count {
+                            @if quote_count > 0 {
+                                span ."m-postOverview__quoteCount" {
+                                    @if quote_count == 1 {
+                                        ("1 Quote".to_string())
+                                    } @else {
+                                        (format!("{} Quotes", quote_count))
+                                    }
+                                }
+                            }
+                        }
+                        button ."m-postOverview__quoteButton u-button"
+                            disabled[ro.to_disabled()]
+                            hx-get={"/ui/post/quote?quote="(ext_event_id)}
+                            hx-target=".m-newPostForm__replyToLine"
+                            hx-swap="outerHTML"
+                        {
+                            span ."m-postOverview__quoteButtonIcon u-buttonIcon" width="1rem" height="1rem" {}
+                            "Quote"
+                        }
+                        @if author == client.rostra_id() {
+                            button ."m-postOverview__editButton u-button"
+                                disabled[ro.to_disabled()]
+                                hx-get={"/ui/post/edit?edit_of="(ext_event_id.event_id().to_short())}
+                                hx-target=".m-newPostForm"
+                                hx-swap="outerHTML"
+                            {
+                                span ."m-postOverview__editButtonIcon u-buttonIcon" width="1rem" height="1rem" {}
+                                "Edit"
+                            }
+                            button ."m-postOverview__deleteButton u-button"
+                                disabled[ro.to_disabled()]
+                                hx-delete=(format!("/ui/post/{author}/{}", ext_event_id.event_id().to_short()))
+                                hx-confirm="Delete this post?"
+                                hx-target="closest .m-postOverview"
+                                hx-swap="delete"
+                            {
+                                span ."m-postOverview__deleteButtonIcon u-buttonIcon" width="1rem" height="1rem" {}
+                                "Delete"
+                            }
+                        }
                     }
                 }
             }
@@ -216,6 +378,7 @@ impl UiState {
 
         let post = html! {
             article #(post_id)
+                hx-swap-oob=[swap_oob.then_some("true")]
                 ."m-postOverview"
                 ."-response"[reply_to.is_some() || is_comment]
                 ."-reply-parent"[comment.is_some()]
@@ -239,13 +402,18 @@ impl UiState {
         Ok(html! {
             @if let Some((reply_to_author, reply_to_post)) = reply_to {
                 @if let Some(reply_to_post) = reply_to_post {
-                    @if let Some(djot_content) = reply_to_post.content.djot_content.as_ref() {
+                    @if reply_to_post.content.djot_content.is_some() || reply_to_post.content.poll.is_some() {
                         (Box::pin(self.render_post_overview(
                             client,
                             reply_to_post.author
                             )
                             .event_id(reply_to_post.event_id)
-                            .content(djot_content)
+                            .maybe_content(reply_to_post.content.djot_content.as_deref())
+                            .attachments(&reply_to_post.content.attachments)
+                            .maybe_edited(reply_to_post.edited)
+                            .maybe_quote(reply_to_post.content.quote)
+                            .maybe_poll(reply_to_post.content.poll)
+                            .maybe_content_warning(reply_to_post.content.content_warning.as_deref())
                             .ro(ro)
                             .comment(post)
                             .call()
@@ -266,4 +434,65 @@ impl UiState {
             }
         })
     }
+
+    /// Embedded card of a quoted post
+    async fn render_post_quote(&self, client: &ClientRef<'_>, quote: ExternalEventId) -> Markup {
+        let post = client
+            .db()
+            .get_social_post(quote.event_id())
+            .await
+            .filter(|post| post.author == quote.rostra_id());
+        let Some((post, Some(djot_content))) = post
+            .as_ref()
+            .map(|post| (post, post.content.djot_content.as_ref()))
+        else {
+            return html! {
+                div ."m-postOverview__quote -missing" {
+                    "Quoted post not available yet"
+                }
+            };
+        };
+
+        let display_name = self
+            .get_social_profile_opt(quote.rostra_id(), client)
+            .await
+            .map(|profile| profile.display_name)
+            .unwrap_or_else(|| quote.rostra_id().to_short().to_string());
+
+        html! {
+            div ."m-postOverview__quote" {
+                a ."m-postOverview__quoteAuthor"
+                    href=(format!("/ui/post/{}/{}", quote.rostra_id(), quote.event_id().to_short()))
+                {
+                    (display_name)
+                }
+                (render_content_warning(post.content.content_warning.as_deref(), html! {
+                    div ."m-postOverview__quoteContent" {
+                        (self.render_content(client, djot_content).await)
+                    }
+                    (self.render_post_attachments(&post.content.attachments))
+                }))
+            }
+        }
+    }
+}
+
+/// Hide `body` behind a button revealing it, if there's a content warning
+pub(crate) fn render_content_warning(content_warning: Option<&str>, body: Markup) -> Markup {
+    let Some(content_warning) = content_warning else {
+        return body;
+    };
+    html! {
+        div ."m-postOverview__contentWarning" {
+            button ."m-postOverview__contentWarningButton u-button"
+                type="button"
+                onclick="this.parentNode.classList.toggle('-revealed')"
+            {
+                span ."m-postOverview__contentWarningText" { (content_warning) }
+            }
+            div ."m-postOverview__contentWarningContent" {
+                (body)
+            }
+        }
+    }
 }
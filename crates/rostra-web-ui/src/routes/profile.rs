@@ -1,7 +1,10 @@
+use std::collections::BTreeMap;
+
 use axum::Form;
 use axum::extract::{Path, Query, State};
 use axum::response::IntoResponse;
 use maud::{Markup, PreEscaped, html};
+use rostra_client_db::IdSocialProfileRecord;
 use rostra_client_db::social::EventPaginationCursor;
 use rostra_core::event::PersonaId;
 use rostra_core::id::RostraId;
@@ -219,6 +222,7 @@ impl UiState {
             .await
             .iter()
             .any(|(id, _)| id == &profile_id);
+        let personas = client.db()?.get_personas_for_id(profile_id).await;
         Ok(html! {
             div ."m-profileSummary" {
                 script {
@@ -251,6 +255,7 @@ impl UiState {
                 }
                 img ."m-profileSummary__userImage u-userImage"
                     src=(self.avatar_url(profile_id))
+                    srcset=(self.avatar_srcset(&self.avatar_url(profile_id)))
                     alt=(format!("{}'s avatar", profile.display_name))
                     width="32pt"
                     height="32pt"
@@ -258,6 +263,13 @@ impl UiState {
                     { }
 
                 div ."m-profileSummary__content" {
+                    @if let Some(header) = profile.header {
+                        img ."m-profileSummary__header"
+                            src=(self.blob_url(header))
+                            alt=(format!("{}'s header image", profile.display_name))
+                            loading="lazy"
+                            { }
+                    }
                     a ."m-profileSummary__displayName"
                         href=(format!("/ui/profile/{}", profile_id))
                     {
@@ -291,9 +303,55 @@ impl UiState {
                         }
                     }
                     p ."m-profileSummary__bio" { (profile.bio) }
+                    (self.render_profile_fields(&profile, &personas))
                 }
             }
 
         })
     }
+
+    /// Profile fields beyond the display name and bio
+    fn render_profile_fields(
+        &self,
+        profile: &IdSocialProfileRecord,
+        personas: &BTreeMap<PersonaId, String>,
+    ) -> Markup {
+        html! {
+            @if profile.pronouns.is_some() || profile.location.is_some() {
+                p ."m-profileSummary__details" {
+                    @if let Some(pronouns) = profile.pronouns.as_ref() {
+                        span ."m-profileSummary__pronouns" { (pronouns) }
+                    }
+                    @if let Some(location) = profile.location.as_ref() {
+                        span ."m-profileSummary__location" { (location) }
+                    }
+                }
+            }
+            @if !profile.links.is_empty() {
+                ul ."m-profileSummary__links" {
+                    // Never trust others to have validated the urls
+                    @for link in profile.links.iter().filter(|link| link.is_valid()) {
+                        li {
+                            a href=(link.url) target="_blank" rel="noopener noreferrer me" {
+                                (link.label)
+                            }
+                        }
+                    }
+                }
+            }
+            @if !profile.persona_descriptions.is_empty() {
+                dl ."m-profileSummary__personas" {
+                    @for (persona_id, description) in &profile.persona_descriptions {
+                        dt {
+                            (personas
+                                .get(persona_id)
+                                .cloned()
+                                .unwrap_or_else(|| format!("Persona {persona_id}")))
+                        }
+                        dd { (description) }
+                    }
+                }
+            }
+        }
+    }
 }
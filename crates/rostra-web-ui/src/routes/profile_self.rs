@@ -5,14 +5,23 @@ use axum::response::IntoResponse;
 use maud::{Markup, PreEscaped, html};
 use rostra_client::ClientRef;
 use rostra_client_db::IdSocialProfileRecord;
-use rostra_core::ShortEventId;
+use rostra_core::event::{
+    EventContentKind as _, EventExt as _, SocialProfileLink, SocialProfileUpdate,
+};
 use rostra_core::id::{RostraId, ToShort as _};
+use rostra_core::{ExternalEventId, ShortEventId};
+use rostra_util_error::FmtCompact as _;
+use snafu::{OptionExt as _, ResultExt as _};
+use tracing::debug;
 
+use self::extractor::PERSONA_DESCRIPTION_FIELD_PREFIX;
 use super::Maud;
+use super::blob::is_image;
 use super::unlock::session::{RoMode, UserSession};
-use crate::error::RequestResult;
+use crate::error::{InvalidDataSnafu, RequestResult, UserRequestError, UserSnafu};
 use crate::html_utils::submit_on_ctrl_enter;
-use crate::{SharedState, UiState};
+use crate::thumbnail::{AvatarSize, ThumbnailKind, normalize_avatar};
+use crate::{LOG_TARGET, SharedState, UiState};
 
 pub async fn get_self_account_edit(
     state: State<SharedState>,
@@ -34,18 +43,71 @@ pub async fn post_self_account_edit(
         .get_social_profile(session.id())
         .await;
 
-    state
-        .client(session.id())
-        .await?
-        .client_ref()?
-        .post_social_profile_update(
-            session.id_secret()?,
-            form.name,
-            form.bio,
-            form.avatar.or_else(|| existing.and_then(|e| e.avatar)),
-        )
+    // Browsers send an empty file if no new avatar was picked
+    let avatars = match form.avatar.filter(|(_, data)| !data.is_empty()) {
+        Some((_, data)) => tokio::task::block_in_place(|| normalize_avatar(&data))
+            .ok()
+            .context(InvalidDataSnafu)
+            .context(UserSnafu)?,
+        None => vec![],
+    };
+    let avatar = match avatars.iter().find(|(size, _)| *size == AvatarSize::Large) {
+        Some((_, avatar)) => Some((avatar.mime.clone(), avatar.data.clone())),
+        None => existing.as_ref().and_then(|e| e.avatar.clone()),
+    };
+
+    let mut profile = SocialProfileUpdate {
+        display_name: form.name,
+        bio: form.bio,
+        avatar,
+        links: parse_links(&form.links),
+        pronouns: non_empty(&form.pronouns),
+        location: non_empty(&form.location),
+        header: None,
+        persona_descriptions: form
+            .persona_descriptions
+            .iter()
+            .filter_map(|(persona_id, description)| Some((*persona_id, non_empty(description)?)))
+            .collect(),
+    };
+    // Check before publishing the header image, which only references a blob
+    if profile.validate().is_err() {
+        return Err(UserRequestError::InvalidData).context(UserSnafu);
+    }
+
+    let client_handle = state.client(session.id()).await?;
+    let client_ref = client_handle.client_ref()?;
+
+    // Browsers send an empty file if no new header was picked
+    profile.header = match form.header.filter(|(_, data)| !data.is_empty()) {
+        Some((mime, data)) => {
+            if !is_image(&mime) {
+                return Err(UserRequestError::InvalidData).context(UserSnafu);
+            }
+            let event = client_ref
+                .publish_blob(session.id_secret()?, mime, data)
+                .await?;
+            Some(ExternalEventId::new(session.id(), event.event_id))
+        }
+        None if form.remove_header => None,
+        None => existing.and_then(|e| e.header),
+    };
+
+    let event = client_ref
+        .post_social_profile_update(session.id_secret()?, profile)
         .await?;
 
+    // Other sizes are only served from the thumbnail cache, so put them there
+    for (size, avatar) in avatars {
+        if let Err(err) = state
+            .thumbnails
+            .insert(event.content_hash(), ThumbnailKind::Avatar(size), &avatar)
+            .await
+        {
+            debug!(target: LOG_TARGET, err = %err.fmt_compact(), "Could not cache avatar thumbnail");
+        }
+    }
+
     Ok(Maud(
         state
             .render_self_profile_summary(&session, session.ro_mode())
@@ -53,6 +115,43 @@ pub async fn post_self_account_edit(
     ))
 }
 
+fn non_empty(s: &str) -> Option<String> {
+    Some(s.trim().to_owned()).filter(|s| !s.is_empty())
+}
+
+/// Links entered one per line, as `[label] url`
+fn parse_links(text: &str) -> Vec<SocialProfileLink> {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(|line| match line.rsplit_once(char::is_whitespace) {
+            Some((label, url)) => SocialProfileLink {
+                label: label.trim().to_owned(),
+                url: url.to_owned(),
+            },
+            None => SocialProfileLink {
+                label: line.to_owned(),
+                url: line.to_owned(),
+            },
+        })
+        .collect()
+}
+
+/// Links in the format accepted by [`parse_links`]
+fn format_links(links: &[SocialProfileLink]) -> String {
+    links
+        .iter()
+        .map(|link| {
+            if link.label == link.url {
+                link.url.clone()
+            } else {
+                format!("{} {}", link.label, link.url)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
 impl UiState {
     pub async fn get_social_profile(
         &self,
@@ -65,6 +164,11 @@ impl UiState {
                 display_name: id.to_short().to_string(),
                 bio: "".into(),
                 avatar: None,
+                links: vec![],
+                pronouns: None,
+                location: None,
+                header: None,
+                persona_descriptions: Default::default(),
             }
         })
     }
@@ -81,6 +185,29 @@ impl UiState {
         format!("/ui/avatar/{}", id)
     }
 
+    /// `srcset` of a (32pt) avatar image at `url`, picking the smallest size
+    /// that is sharp enough
+    pub fn avatar_srcset(&self, url: &str) -> String {
+        format!(
+            "{url}?size={} 1x, {url}?size={} 2x",
+            AvatarSize::Small.as_str(),
+            AvatarSize::Medium.as_str()
+        )
+    }
+
+    /// Like [`Self::avatar_url`], but pointing at the (long-lived) thumbnail
+    /// of the current avatar, if `profile` has one
+    pub fn profile_avatar_url(
+        &self,
+        id: RostraId,
+        profile: Option<&IdSocialProfileRecord>,
+    ) -> String {
+        match profile {
+            Some(profile) if profile.avatar.is_some() => self.thumbnail_url(id, profile.event_id),
+            _ => self.avatar_url(id),
+        }
+    }
+
     pub async fn render_self_profile_summary(
         &self,
         user: &UserSession,
@@ -111,6 +238,7 @@ impl UiState {
                 }
                 img ."m-profileSummary__userImage u-userImage"
                     src=(self.avatar_url(self_id))
+                    srcset=(self.avatar_srcset(&self.avatar_url(self_id)))
                     alt="Self avatar"
                     width="32pt"
                     height="32pt"
@@ -147,6 +275,14 @@ impl UiState {
                                 span ."m-profileSummary__logoutButtonIcon u-buttonIcon" width="1rem" height="1rem" {}
                                 "Logout"
                             }
+                        button
+                            ."m-profileSummary__logoutAllButton u-button"
+                            hx-post="/ui/unlock/logout_all"
+                            hx-confirm="Log out all sessions of this identity?"
+                            {
+                                span ."m-profileSummary__logoutButtonIcon u-buttonIcon" width="1rem" height="1rem" {}
+                                "Logout everywhere"
+                            }
                     }
                 }
             }
@@ -159,6 +295,10 @@ impl UiState {
         let self_profile = self
             .get_social_profile(client_ref.rostra_id(), &client_ref)
             .await;
+        let personas = client_ref
+            .db()
+            .get_personas_for_id(client_ref.rostra_id())
+            .await;
         Ok(html! {
             form ."m-profileSummary -edit"
                 hx-post="/ui/self/edit"
@@ -201,6 +341,54 @@ impl UiState {
                         name="bio" {
                         {(self_profile.bio)}
                     }
+                    input ."m-profileSummary__pronounsInput"
+                        type="text"
+                        name="pronouns"
+                        placeholder="Pronouns"
+                        maxlength=(SocialProfileUpdate::MAX_PRONOUNS_LEN)
+                        value=[self_profile.pronouns.as_deref()]
+                        {}
+                    input ."m-profileSummary__locationInput"
+                        type="text"
+                        name="location"
+                        placeholder="Location"
+                        maxlength=(SocialProfileUpdate::MAX_LOCATION_LEN)
+                        value=[self_profile.location.as_deref()]
+                        {}
+                    textarea ."m-profileSummary__linksInput"
+                        placeholder="Links, one per line: Label https://..."
+                        dir="auto"
+                        name="links"
+                        rows="3"
+                    {
+                        (format_links(&self_profile.links))
+                    }
+                    label ."m-profileSummary__headerLabel" {
+                        "Header image "
+                        input ."m-profileSummary__headerInput"
+                            type="file"
+                            name="header"
+                            accept="image/*"
+                            {}
+                    }
+                    @if self_profile.header.is_some() {
+                        label ."m-profileSummary__headerLabel" {
+                            input type="checkbox" name="remove_header" {}
+                            " Remove header image"
+                        }
+                    }
+                    @for (persona_id, persona_name) in &personas {
+                        textarea ."m-profileSummary__personaDescriptionInput"
+                            placeholder=(format!("Description of the {persona_name} persona"))
+                            dir="auto"
+                            name=(format!("{PERSONA_DESCRIPTION_FIELD_PREFIX}{persona_id}"))
+                            maxlength=(SocialProfileUpdate::MAX_PERSONA_DESCRIPTION_LEN)
+                        {
+                            @if let Some(description) = self_profile.persona_descriptions.get(persona_id) {
+                                (description)
+                            }
+                        }
+                    }
 
                     div ."m-profileSummary__buttons" {
                         button
@@ -1,17 +1,65 @@
+use std::collections::BTreeMap;
+
 use axum::body::Bytes;
+use axum::extract::multipart::Field;
 use axum::extract::{FromRequest, Multipart, Request};
 use axum::http::StatusCode;
 use axum::http::header::CONTENT_TYPE;
+use rostra_core::event::PersonaId;
+
+/// Prefix of the names of the persona description fields, followed by the
+/// persona id
+pub const PERSONA_DESCRIPTION_FIELD_PREFIX: &str = "persona_description_";
 
 pub struct InputForm {
     pub name: String,
     pub bio: String,
     pub avatar: Option<(String, Vec<u8>)>,
+    /// One link per line, as `[label] url`
+    pub links: String,
+    pub pronouns: String,
+    pub location: String,
+    pub header: Option<(String, Vec<u8>)>,
+    pub remove_header: bool,
+    pub persona_descriptions: BTreeMap<PersonaId, String>,
 }
 struct InputFormPart {
     pub name: Option<String>,
     pub bio: Option<String>,
     pub avatar: Option<(String, Vec<u8>)>,
+    pub links: Option<String>,
+    pub pronouns: Option<String>,
+    pub location: Option<String>,
+    pub header: Option<(String, Vec<u8>)>,
+    pub remove_header: bool,
+    pub persona_descriptions: BTreeMap<PersonaId, String>,
+}
+
+async fn field_text(field: Field<'_>) -> Result<String, (StatusCode, &'static str)> {
+    let v = field
+        .bytes()
+        .await
+        .map_err(|_| (StatusCode::BAD_REQUEST, "Failed to parse multipart field"))?;
+    String::from_utf8(v.to_vec()).map_err(|_| (StatusCode::BAD_REQUEST, "Invalid encoding"))
+}
+
+async fn field_file(field: Field<'_>) -> Result<(String, Vec<u8>), (StatusCode, &'static str)> {
+    let Some(mime) = field.content_type().map(ToOwned::to_owned) else {
+        return Err((StatusCode::BAD_REQUEST, "Missing file mime type"));
+    };
+    let v = field
+        .bytes()
+        .await
+        .map_err(|_| (StatusCode::BAD_REQUEST, "Failed to parse multipart field"))?;
+    Ok((mime, v.to_vec()))
+}
+
+/// Set a field that must appear only once
+fn set_once<T>(part: &mut Option<T>, v: T) -> Result<(), (StatusCode, &'static str)> {
+    if part.replace(v).is_some() {
+        return Err((StatusCode::BAD_REQUEST, "Failed to parse multipart field"));
+    }
+    Ok(())
 }
 
 impl<S> FromRequest<S> for InputForm
@@ -38,6 +86,12 @@ where
             name: None,
             bio: None,
             avatar: None,
+            links: None,
+            pronouns: None,
+            location: None,
+            header: None,
+            remove_header: false,
+            persona_descriptions: BTreeMap::new(),
         };
 
         loop {
@@ -50,35 +104,25 @@ where
             };
 
             match field.name() {
-                Some("bio") => {
-                    let v = field.bytes().await.map_err(|_| {
-                        (StatusCode::BAD_REQUEST, "Failed to parse multipart field")
-                    })?;
-                    let s = String::from_utf8(v.to_vec())
-                        .map_err(|_| (StatusCode::BAD_REQUEST, "Invalid encoding"))?;
-                    if parts.bio.replace(s).is_some() {
-                        return Err((StatusCode::BAD_REQUEST, "Failed to parse multipart field"));
-                    }
-                }
-                Some("name") => {
-                    let v = field.bytes().await.map_err(|_| {
-                        (StatusCode::BAD_REQUEST, "Failed to parse multipart field")
-                    })?;
-                    let s = String::from_utf8(v.to_vec())
-                        .map_err(|_| (StatusCode::BAD_REQUEST, "Invalid encoding"))?;
-                    if parts.name.replace(s).is_some() {
-                        return Err((StatusCode::BAD_REQUEST, "Failed to parse multipart field"));
-                    }
-                }
-                Some("avatar") => {
-                    let Some(mime) = field.content_type().map(ToOwned::to_owned) else {
-                        return Err((StatusCode::BAD_REQUEST, "Missing avatar mime type"));
-                    };
-                    let v = field.bytes().await.map_err(|_| {
-                        (StatusCode::BAD_REQUEST, "Failed to parse multipart field")
-                    })?;
-
-                    if parts.avatar.replace((mime, v.to_vec())).is_some() {
+                Some("bio") => set_once(&mut parts.bio, field_text(field).await?)?,
+                Some("name") => set_once(&mut parts.name, field_text(field).await?)?,
+                Some("avatar") => set_once(&mut parts.avatar, field_file(field).await?)?,
+                Some("links") => set_once(&mut parts.links, field_text(field).await?)?,
+                Some("pronouns") => set_once(&mut parts.pronouns, field_text(field).await?)?,
+                Some("location") => set_once(&mut parts.location, field_text(field).await?)?,
+                Some("header") => set_once(&mut parts.header, field_file(field).await?)?,
+                Some("remove_header") => parts.remove_header = true,
+                Some(name) if name.starts_with(PERSONA_DESCRIPTION_FIELD_PREFIX) => {
+                    let persona_id = name[PERSONA_DESCRIPTION_FIELD_PREFIX.len()..]
+                        .parse()
+                        .map(PersonaId)
+                        .map_err(|_| (StatusCode::BAD_REQUEST, "Invalid persona id"))?;
+                    let description = field_text(field).await?;
+                    if parts
+                        .persona_descriptions
+                        .insert(persona_id, description)
+                        .is_some()
+                    {
                         return Err((StatusCode::BAD_REQUEST, "Failed to parse multipart field"));
                     }
                 }
@@ -96,6 +140,12 @@ where
                 .bio
                 .ok_or((StatusCode::BAD_REQUEST, "Failed to parse multipart field"))?,
             avatar: parts.avatar,
+            links: parts.links.unwrap_or_default(),
+            pronouns: parts.pronouns.unwrap_or_default(),
+            location: parts.location.unwrap_or_default(),
+            header: parts.header,
+            remove_header: parts.remove_header,
+            persona_descriptions: parts.persona_descriptions,
         })
     }
 }
@@ -0,0 +1,120 @@
+use axum::extract::{Path, Query, State};
+use axum::http::{HeaderMap, HeaderValue, header};
+use axum::response::{IntoResponse, Response};
+use rostra_core::event::{EventExt as _, EventKind, SocialProfileUpdate};
+use rostra_core::id::RostraId;
+use rostra_core::{ContentHash, ShortEventId};
+use rostra_util_error::FmtCompact as _;
+use serde::Deserialize;
+use snafu::ResultExt as _;
+use tracing::debug;
+
+use super::IMMUTABLE_CACHE_CONTROL;
+use super::blob::insert_untrusted_content_headers;
+use super::unlock::session::UserSession;
+use crate::error::{RequestResult, UserRequestError, UserSnafu};
+use crate::thumbnail::{AvatarSize, ThumbnailKind};
+use crate::{LOG_TARGET, SharedState, UiState};
+
+#[derive(Deserialize)]
+pub struct ThumbnailQuery {
+    /// Only used for avatars
+    #[serde(default)]
+    pub size: AvatarSize,
+}
+
+/// Thumbnail of a blob, or of the avatar of a profile update
+pub async fn get_thumbnail(
+    state: State<SharedState>,
+    session: UserSession,
+    req_headers: HeaderMap,
+    Path((author, event_id)): Path<(RostraId, ShortEventId)>,
+    Query(query): Query<ThumbnailQuery>,
+) -> RequestResult<impl IntoResponse> {
+    let client = state.client(session.id()).await?;
+    let client_ref = client.client_ref()?;
+    let db = client_ref.db();
+
+    let Some(event) = db
+        .get_event(event_id)
+        .await
+        .filter(|event| event.author() == author)
+    else {
+        return Err(UserRequestError::SomethingNotFound).context(UserSnafu);
+    };
+    let source = match event.kind() {
+        EventKind::BLOB => db
+            .get_blob(author, event_id)
+            .await
+            .map(|(_, blob)| (ThumbnailKind::Attachment, blob.mime, blob.data)),
+        EventKind::SOCIAL_PROFILE_UPDATE => db
+            .get_event_content(event_id)
+            .await
+            .and_then(|content| content.deserialize_cbor::<SocialProfileUpdate>().ok())
+            .and_then(|profile| profile.avatar)
+            .map(|(mime, data)| (ThumbnailKind::Avatar(query.size), mime, data)),
+        _ => None,
+    };
+    let Some((kind, mime, data)) = source else {
+        return Err(UserRequestError::SomethingNotFound).context(UserSnafu);
+    };
+
+    let mut resp_headers = HeaderMap::new();
+    // Addressed by event id, so never changes
+    resp_headers.insert(
+        header::CACHE_CONTROL,
+        HeaderValue::from_static(IMMUTABLE_CACHE_CONTROL),
+    );
+    let Some(response) = state
+        .thumbnail_response(
+            &req_headers,
+            resp_headers,
+            event.content_hash(),
+            kind,
+            &data,
+        )
+        .await?
+    else {
+        return Err(UserRequestError::SomethingNotFound).context(UserSnafu);
+    };
+    Ok(response)
+}
+
+impl UiState {
+    pub fn thumbnail_url(&self, author: RostraId, event_id: ShortEventId) -> String {
+        format!("/ui/thumbnail/{author}/{event_id}")
+    }
+
+    /// Respond with a (cached) thumbnail of `data`
+    ///
+    /// `None` if it can't be rendered (e.g. unsupported image formats), as the
+    /// original is never served in its place.
+    pub async fn thumbnail_response(
+        &self,
+        req_headers: &HeaderMap,
+        mut resp_headers: HeaderMap,
+        content_hash: ContentHash,
+        kind: ThumbnailKind,
+        data: &[u8],
+    ) -> RequestResult<Option<Response>> {
+        let etag = format!("{content_hash}-{}", kind.size());
+        if let Some(response) = crate::handle_etag(req_headers, &etag, &mut resp_headers) {
+            return Ok(Some(response));
+        }
+
+        let thumbnail = match self
+            .thumbnails
+            .get_or_render(content_hash, kind, data)
+            .await
+        {
+            Ok(thumbnail) => thumbnail,
+            Err(err) => {
+                debug!(target: LOG_TARGET, %content_hash, err = %err.fmt_compact(), "Could not render thumbnail");
+                return Ok(None);
+            }
+        };
+        insert_untrusted_content_headers(&mut resp_headers, &thumbnail.mime)?;
+
+        Ok(Some((resp_headers, thumbnail.data).into_response()))
+    }
+}
@@ -78,6 +78,31 @@ pub async fn get_network(
     ))
 }
 
+pub async fn get_tag(
+    state: State<SharedState>,
+    session: UserSession,
+    mut cookies: Cookies,
+    Path(tag): Path<String>,
+    Form(form): Form<TimelinePaginationInput>,
+) -> RequestResult<impl IntoResponse> {
+    let pagination = form.ts.and_then(|ts| {
+        form.event_id
+            .map(|event_id| EventPaginationCursor { ts, event_id })
+    });
+    let navbar = state.timeline_common_navbar(&session).await?;
+    Ok(Maud(
+        state
+            .render_timeline_page(
+                navbar,
+                pagination,
+                &session,
+                &mut cookies,
+                TimelineMode::Tag(tag.to_lowercase()),
+            )
+            .await?,
+    ))
+}
+
 pub async fn get_notifications(
     state: State<SharedState>,
     session: UserSession,
@@ -102,6 +127,21 @@ pub async fn get_notifications(
     ))
 }
 
+#[derive(Deserialize)]
+pub struct ContentWarningsSettingInput {
+    #[serde(default)]
+    expand: bool,
+}
+
+/// Remember whether posts with content warnings are displayed expanded
+pub async fn post_content_warnings_setting(
+    session: UserSession,
+    mut cookies: Cookies,
+    Form(form): Form<ContentWarningsSettingInput>,
+) -> impl IntoResponse {
+    cookies.save_expand_content_warnings(session.id(), form.expand);
+}
+
 pub async fn get_updates(
     state: State<SharedState>,
     ws: WebSocketUpgrade,
@@ -205,18 +245,20 @@ impl UiState {
         let client = self.client(session.id()).await?;
         let client_ref = client.client_ref()?;
         let pending_notifications = self
-            .handle_notification_cookies(&client_ref, pagination, cookies, mode)
+            .handle_notification_cookies(&client_ref, pagination, cookies, &mode)
             .await?;
+        let expand_content_warnings = cookies.get_expand_content_warnings(session.id());
 
         let content = html! {
 
             (navbar)
 
-            main ."o-mainBar" {
+            main ."o-mainBar" ."-expandContentWarnings"[expand_content_warnings] {
                 (self.render_new_posts_alert(false, 0))
                 (self.render_main_bar_timeline(session, mode)
                     .maybe_pagination(pagination)
                     .maybe_pending_notifications(pending_notifications)
+                    .expand_content_warnings(expand_content_warnings)
                     .call()
                     .await?)
 
@@ -236,7 +278,7 @@ impl UiState {
         client: &ClientRef<'_>,
         pagination: Option<EventPaginationCursor>,
         cookies: &mut Cookies,
-        mode: TimelineMode,
+        mode: &TimelineMode,
     ) -> RequestResult<Option<usize>> {
         // If this is a non-first page, we don't need to do anything
         if pagination.is_some() {
@@ -244,8 +286,8 @@ impl UiState {
         }
 
         match mode {
-            TimelineMode::Profile(_) | TimelineMode::ProfileSingle(_, _) => {
-                // We're not displaying notifications on profile timelines
+            TimelineMode::Profile(_) | TimelineMode::ProfileSingle(_, _) | TimelineMode::Tag(_) => {
+                // We're not displaying notifications on profile and tag timelines
                 Ok(None)
             }
             TimelineMode::Notifications => {
@@ -270,10 +312,10 @@ impl UiState {
             TimelineMode::Followees | TimelineMode::Network => {
                 let pending_len = client
                     .db()
-                    .paginate_social_posts(
+                    .paginate_social_notifications(
+                        client.rostra_id(),
                         cookies.get_last_seen(client.rostra_id()),
                         10,
-                        TimelineMode::Notifications.to_filter_fn(client).await,
                     )
                     .await
                     .0
@@ -310,7 +352,11 @@ impl UiState {
                                 comment.author
                                 ).event_id(comment.event_id)
                                 .content(djot_content)
+                                .attachments(&comment.content.attachments)
                                 .reply_count(comment.reply_count)
+                                .maybe_edited(comment.edited)
+                                .maybe_quote(comment.content.quote)
+                                .maybe_content_warning(comment.content.content_warning.as_deref())
                                 .ro(session.ro_mode())
                                 .is_comment(true)
                                 .call().await?)
@@ -348,6 +394,7 @@ impl UiState {
         #[builder(start_fn)] mode: TimelineMode,
         pagination: Option<EventPaginationCursor>,
         pending_notifications: Option<usize>,
+        #[builder(default)] expand_content_warnings: bool,
     ) -> RequestResult<Markup> {
         let pending_notifications = pending_notifications.unwrap_or_default();
         let client = self.client(session.id()).await?;
@@ -378,6 +425,16 @@ impl UiState {
 
         let author_personas = client.db()?.get_personas(author_personas.into_iter()).await;
 
+        // Only the page of a single post shows how many times it was quoted
+        let quote_count = if let TimelineMode::ProfileSingle(_, event_id) = mode {
+            Some((
+                event_id,
+                client.db()?.count_social_post_quotes(event_id).await,
+            ))
+        } else {
+            None
+        };
+
         Ok(html! {
             div ."o-mainBarTimeline" {
                 div ."o-mainBarTimeline__tabs" {
@@ -389,6 +446,11 @@ impl UiState {
                             href=(mode.to_path())
                         { "Profile" }
 
+                    } @else if let TimelineMode::Tag(tag) = &mode {
+                        a ."o-mainBarTimeline__tag -active"
+                            href=(mode.to_path())
+                        { "#" (tag) }
+
                     } @else {
 
                         a ."o-mainBarTimeline__followees"
@@ -424,10 +486,26 @@ impl UiState {
                         { }
                         span class="slider round" { }
                     }
+
+                    label ."o-mainBarTimeline__contentWarningsLabel" for="expand-content-warnings" { "Expand CWs" }
+                    label ."o-mainBarTimeline__contentWarningsToggle switch" {
+                        input id="expand-content-warnings"
+                        ."o-mainBarTimeline__expandContentWarnings"
+                        type="checkbox"
+                        name="expand"
+                        value="true"
+                        checked[expand_content_warnings]
+                        hx-post="/ui/settings/content_warnings"
+                        hx-trigger="change"
+                        hx-swap="none"
+                            onclick="this.closest('.o-mainBar').classList.toggle('-expandContentWarnings', this.checked)"
+                        { }
+                        span class="slider round" { }
+                    }
                 }
                 div ."o-mainBarTimeline__item -preview -empty" { }
                 @for post in &filtered_posts {
-                    @if let Some(djot_content) = post.content.djot_content.as_ref() {
+                    @if post.content.djot_content.is_some() || post.content.poll.is_some() {
                         div ."o-mainBarTimeline__item"
                         ."-reply"[post.reply_to.is_some()]
                         ."-post"[post.reply_to.is_none()]
@@ -444,8 +522,18 @@ impl UiState {
                                         .map(|reply_to| (reply_to.rostra_id(), parents.get(&reply_to.event_id().to_short())))
                                     )
                                     .event_id(post.event_id)
-                                    .content(djot_content)
+                                    .maybe_content(post.content.djot_content.as_deref())
+                                    .attachments(&post.content.attachments)
                                     .reply_count(post.reply_count)
+                                    .maybe_edited(post.edited)
+                                    .maybe_quote(post.content.quote)
+                                    .maybe_poll(post.content.poll)
+                                    .maybe_content_warning(post.content.content_warning.as_deref())
+                                    .maybe_quote_count(
+                                        quote_count
+                                            .filter(|(event_id, _)| *event_id == post.event_id)
+                                            .map(|(_, count)| count)
+                                    )
                                     .ro(session.ro_mode())
                                     .call().await?)
                         }
@@ -505,23 +593,26 @@ impl UiState {
     }
 }
 
-#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
 pub(crate) enum TimelineMode {
     Followees,
     Network,
     Notifications,
     Profile(RostraId),
     ProfileSingle(RostraId, ShortEventId),
+    /// Posts with a (lowercased) hashtag, from followees and extended network
+    Tag(String),
 }
 
 impl TimelineMode {
-    fn to_path(self) -> String {
+    fn to_path(&self) -> String {
         match self {
             TimelineMode::Followees => "/ui/followees".to_string(),
             TimelineMode::Network => "/ui/network".to_string(),
             TimelineMode::Notifications => "/ui/notifications".to_string(),
             TimelineMode::Profile(rostra_id) => format!("/ui/profile/{rostra_id}"),
             TimelineMode::ProfileSingle(rostra_id, _) => format!("/ui/profile/{rostra_id}"),
+            TimelineMode::Tag(tag) => format!("/ui/tag/{tag}"),
         }
     }
 
@@ -538,15 +629,15 @@ impl TimelineMode {
     fn is_profile(&self) -> bool {
         matches!(self, TimelineMode::Profile(_))
     }
-    async fn get_posts(
-        self,
+    pub(crate) async fn get_posts(
+        &self,
         client: &ClientRef<'_>,
         pagination: Option<EventPaginationCursor>,
     ) -> (
         Vec<SocialPostRecord<SocialPost>>,
         Option<EventPaginationCursor>,
     ) {
-        if let Self::ProfileSingle(_author, event_id) = self {
+        if let Self::ProfileSingle(_author, event_id) = *self {
             (
                 client
                     .db()
@@ -556,6 +647,18 @@ impl TimelineMode {
                     .collect(),
                 None,
             )
+        } else if let Self::Tag(tag) = self {
+            let filter_fn = self.to_filter_fn(client).await;
+
+            client
+                .db()
+                .paginate_social_posts_by_tag_rev(tag.clone(), pagination, 20, filter_fn)
+                .await
+        } else if self.is_notifications() {
+            client
+                .db()
+                .paginate_social_notifications_rev(client.rostra_id(), pagination, 20)
+                .await
         } else {
             let filter_fn = self.to_filter_fn(&client).await;
 
@@ -568,7 +671,7 @@ impl TimelineMode {
 
     #[allow(clippy::type_complexity)]
     async fn to_filter_fn(
-        self,
+        &self,
         client: &ClientRef<'_>,
     ) -> Box<dyn Fn(&SocialPostRecord<SocialPost>) -> bool + Send + Sync + 'static> {
         let self_id = client.rostra_id();
@@ -591,12 +694,22 @@ impl TimelineMode {
                 // TODO: actually verify against extended followees
                 move |post| post.author != self_id,
             ),
-            TimelineMode::Notifications => Box::new(move |post| {
-                post.author != self_id
-                    && post.reply_to.map(|ext_id| ext_id.rostra_id()) == Some(self_id)
-            }),
-            TimelineMode::Profile(rostra_id) => Box::new(move |post| post.author == rostra_id),
-            TimelineMode::ProfileSingle(_, _) => {
+            TimelineMode::Profile(rostra_id) => {
+                let rostra_id = *rostra_id;
+                Box::new(move |post| post.author == rostra_id)
+            }
+            TimelineMode::Tag(_) => {
+                let (followees, extended) = client.db().get_followees_extended(self_id).await;
+                Box::new(move |post: &SocialPostRecord<SocialPost>| {
+                    post.author == self_id
+                        || extended.contains(&post.author)
+                        || followees
+                            .get(&post.author)
+                            .is_some_and(|selector| selector.matches(post.content.persona))
+                })
+            }
+            // Handled separately in `get_posts`
+            TimelineMode::Notifications | TimelineMode::ProfileSingle(_, _) => {
                 warn!(target: LOG_TARGET, "Should not be here");
                 Box::new(move |_post| false)
             }
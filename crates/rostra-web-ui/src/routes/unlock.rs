@@ -1,10 +1,14 @@
+pub mod backoff;
 pub mod session;
 
+use std::time::Instant;
+
 use axum::Form;
 use axum::extract::State;
 use axum::http::{HeaderName, HeaderValue, StatusCode};
 use axum::response::{IntoResponse, Response};
 use maud::{Markup, html};
+use rostra_client::keystore::Keystore;
 use rostra_core::id::{RostraId, RostraIdSecretKey};
 use serde::Deserialize;
 use session::{SESSION_KEY, UserSession};
@@ -13,7 +17,8 @@ use tower_sessions::Session;
 
 use super::Maud;
 use crate::error::{
-    LoginRequiredSnafu, OtherSnafu, PublicKeyMissingSnafu, RequestResult, UnlockResult, UnlockSnafu,
+    KeystoreSnafu, LoginRequiredSnafu, OtherSnafu, PublicKeyMissingSnafu, RequestResult,
+    TooManyAttemptsSnafu, UnlockResult, UnlockSnafu,
 };
 use crate::is_htmx::IsHtmx;
 use crate::serde_util::empty_string_as_none;
@@ -79,16 +84,7 @@ pub async fn post_unlock(
                     .map(|secret| secret.id())
                     .or(form.rostra_id)
                     .ok_or_else(|| LoginRequiredSnafu.build())?;
-                session
-                    .insert(SESSION_KEY, &UserSession::new(rostra_id, secret_key_opt))
-                    .await
-                    .boxed()
-                    .context(OtherSnafu)?;
-                let headers = [(
-                    HeaderName::from_static("hx-redirect"),
-                    HeaderValue::from_static("/ui"),
-                )];
-                (StatusCode::SEE_OTHER, headers).into_response()
+                start_session(&session, rostra_id, secret_key_opt).await?
             }
             Err(e) => Maud(
                 state
@@ -106,6 +102,57 @@ pub async fn post_unlock(
     )
 }
 
+#[derive(Deserialize)]
+pub struct KeystoreInput {
+    rostra_id: RostraId,
+    passphrase: String,
+}
+
+/// Unlock with the passphrase of a keystore in the data dir
+pub async fn post_unlock_keystore(
+    state: State<SharedState>,
+    session: Session,
+    Form(form): Form<KeystoreInput>,
+) -> RequestResult<Response> {
+    Ok(
+        match state
+            .unlock_keystore(form.rostra_id, &form.passphrase)
+            .await
+        {
+            Ok(secret_key) => start_session(&session, form.rostra_id, Some(secret_key)).await?,
+            Err(e) => Maud(
+                state
+                    .unlock_page(
+                        Some(form.rostra_id),
+                        None,
+                        html! {
+                            span ."o-unlockScreen_notice" { (e)}
+                        },
+                    )
+                    .await?,
+            )
+            .into_response(),
+        },
+    )
+}
+
+async fn start_session(
+    session: &Session,
+    rostra_id: RostraId,
+    secret_key: Option<RostraIdSecretKey>,
+) -> RequestResult<Response> {
+    session
+        .insert(SESSION_KEY, &UserSession::new(rostra_id, secret_key))
+        .await
+        .boxed()
+        .context(OtherSnafu)?;
+    let headers = [(
+        HeaderName::from_static("hx-redirect"),
+        HeaderValue::from_static("/ui"),
+    )];
+    Ok((StatusCode::SEE_OTHER, headers).into_response())
+}
+
 pub async fn logout(session: Session) -> RequestResult<impl IntoResponse> {
     session.delete().await.boxed().context(OtherSnafu)?;
 
@@ -116,7 +163,53 @@ pub async fn logout(session: Session) -> RequestResult<impl IntoResponse> {
     Ok((StatusCode::SEE_OTHER, headers).into_response())
 }
 
+/// Log out all sessions of the current identity
+pub async fn logout_all(
+    state: State<SharedState>,
+    user: UserSession,
+    session: Session,
+) -> RequestResult<impl IntoResponse> {
+    state
+        .sessions
+        .delete_by_rostra_id(user.id())
+        .await
+        .boxed()
+        .context(OtherSnafu)?;
+    session.delete().await.boxed().context(OtherSnafu)?;
+
+    let headers = [(
+        HeaderName::from_static("hx-redirect"),
+        HeaderValue::from_static("/ui"),
+    )];
+    Ok((StatusCode::SEE_OTHER, headers).into_response())
+}
+
 impl UiState {
+    async fn unlock_keystore(
+        &self,
+        rostra_id: RostraId,
+        passphrase: &str,
+    ) -> UnlockResult<RostraIdSecretKey> {
+        // Only identities with a keystore are tracked by the backoff
+        let keystore = Keystore::read(&Keystore::path(&self.data_dir, rostra_id))
+            .await
+            .context(KeystoreSnafu)?;
+        if let Err(retry_in) = self.unlock_backoff.attempt(rostra_id, Instant::now()) {
+            return TooManyAttemptsSnafu {
+                retry_in_secs: retry_in.as_secs().max(1),
+            }
+            .fail();
+        }
+        let passphrase = passphrase.to_owned();
+        let secret_key = tokio::task::spawn_blocking(move || keystore.decrypt(&passphrase))
+            .await
+            .expect("Keystore decrypt panicked")
+            .context(KeystoreSnafu)?;
+        self.unlock_backoff.succeeded(rostra_id);
+        self.unlock(rostra_id, Some(secret_key)).await?;
+        Ok(secret_key)
+    }
+
     async fn unlock_page(
         &self,
         current_rostra_id: Option<RostraId>,
@@ -127,6 +220,13 @@ impl UiState {
         let random_mnemonic = random_rostra_id_secret.to_string();
         let random_rostra_id = random_rostra_id_secret.id().to_string();
         let notification = notification.into();
+        let keystores = Keystore::list(&self.data_dir).await.unwrap_or_default();
+        // Don't reveal which identities are on this node, unless asked to
+        let keystore_ids = if self.list_keystores {
+            keystores.as_slice()
+        } else {
+            &[]
+        };
         let content = html! {
             div ."o-unlockScreen" {
 
@@ -200,6 +300,54 @@ impl UiState {
                             }
                     }
                 }
+
+                @if !keystores.is_empty() {
+                    form ."o-unlockScreen__form -keystore"
+                        method="post"
+                    {
+                        div ."o-unlockScreen__header" {
+                            h4 { "Unlock with passphrase" }
+                            p { "Identities with a keystore on this node (see `rostra keystore`)." }
+                        }
+                        div ."o-unlockScreen__idLine" {
+                            @if keystore_ids.is_empty() {
+                                input ."o-unlockScreen__keystoreId"
+                                    type="text"
+                                    name="rostra_id"
+                                    placeholder="Id (Public Key)"
+                                    autocomplete="username"
+                                    value=(current_rostra_id.map(|id| id.to_string()).unwrap_or_default())
+                                    required
+                                    {}
+                            } @else {
+                                select ."o-unlockScreen__keystoreId" name="rostra_id" {
+                                    @for id in keystore_ids {
+                                        option
+                                            value=(id)
+                                            selected[current_rostra_id == Some(*id)]
+                                        { (id) }
+                                    }
+                                }
+                            }
+                        }
+                        div ."o-unlockScreen__mnemonicLine" {
+                            input ."o-unlockScreen__passphrase"
+                                type="password"
+                                name="passphrase"
+                                autocomplete="current-password"
+                                placeholder="Keystore passphrase"
+                                required
+                                { }
+                            button ."o-unlockScreen__unlockButton u-button"
+                                type="submit"
+                                hx-target="closest .o-unlockScreen"
+                                hx-post="/ui/unlock/keystore" {
+                                    span ."o-unlockScreen__unlockButtonIcon u-buttonIcon" width="1rem" height="1rem" {}
+                                    "Unlock"
+                                }
+                        }
+                    }
+                }
             }
         };
         self.render_html_page("Sign in", content).await
@@ -0,0 +1,63 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use rostra_core::id::RostraId;
+
+/// Failed attempts allowed before having to wait
+const FREE_ATTEMPTS: u32 = 3;
+const MAX_DELAY: Duration = Duration::from_secs(15 * 60);
+/// Failures are forgotten after this long without new ones
+const FORGET_AFTER: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Exponential backoff of failed keystore unlock attempts, per identity
+///
+/// Passphrases are guessable, unlike mnemonics, so online guessing must be
+/// slowed down.
+#[derive(Default)]
+pub struct UnlockBackoff {
+    failures: Mutex<HashMap<RostraId, Failures>>,
+}
+
+#[derive(Clone, Copy)]
+struct Failures {
+    count: u32,
+    last: Instant,
+}
+
+impl UnlockBackoff {
+    /// Register an attempt to unlock `id` at `now`
+    ///
+    /// Returns how long to wait instead, if it's too soon after the previous
+    /// failures. The attempt counts as a failure until
+    /// [`Self::succeeded`], so concurrent attempts can't bypass it.
+    pub fn attempt(&self, id: RostraId, now: Instant) -> Result<(), Duration> {
+        let mut failures = self.failures.lock().expect("Locking failed");
+        failures.retain(|_, failures| now.saturating_duration_since(failures.last) < FORGET_AFTER);
+        let entry = failures.entry(id).or_insert(Failures {
+            count: 0,
+            last: now,
+        });
+        let allowed_at = entry.last + Self::delay(entry.count);
+        if now < allowed_at {
+            return Err(allowed_at - now);
+        }
+        entry.count = entry.count.saturating_add(1);
+        entry.last = now;
+        Ok(())
+    }
+
+    pub fn succeeded(&self, id: RostraId) {
+        self.failures.lock().expect("Locking failed").remove(&id);
+    }
+
+    fn delay(count: u32) -> Duration {
+        match count.checked_sub(FREE_ATTEMPTS) {
+            None => Duration::ZERO,
+            Some(over) => Duration::from_secs(1u64 << over.min(10)).min(MAX_DELAY),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests;
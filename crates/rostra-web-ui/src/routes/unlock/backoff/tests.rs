@@ -0,0 +1,47 @@
+use std::time::{Duration, Instant};
+
+use rostra_core::id::RostraIdSecretKey;
+
+use super::UnlockBackoff;
+
+#[test]
+fn backoff_after_free_attempts() {
+    let backoff = UnlockBackoff::default();
+    let id = RostraIdSecretKey::generate().id();
+    let other_id = RostraIdSecretKey::generate().id();
+    let now = Instant::now();
+
+    for _ in 0..3 {
+        assert!(backoff.attempt(id, now).is_ok());
+    }
+    assert_eq!(backoff.attempt(id, now), Err(Duration::from_secs(1)));
+    assert!(backoff.attempt(other_id, now).is_ok());
+
+    // Waiting allows another attempt, after which the delay doubles
+    let now = now + Duration::from_secs(1);
+    assert!(backoff.attempt(id, now).is_ok());
+    assert_eq!(backoff.attempt(id, now), Err(Duration::from_secs(2)));
+
+    backoff.succeeded(id);
+    assert!(backoff.attempt(id, now).is_ok());
+}
+
+#[test]
+fn old_failures_are_forgotten() {
+    let backoff = UnlockBackoff::default();
+    let id = RostraIdSecretKey::generate().id();
+    let now = Instant::now();
+
+    for _ in 0..3 {
+        assert!(backoff.attempt(id, now).is_ok());
+    }
+    assert!(backoff.attempt(id, now).is_err());
+
+    let other_id = RostraIdSecretKey::generate().id();
+    assert!(
+        backoff
+            .attempt(other_id, now + Duration::from_secs(24 * 60 * 60))
+            .is_ok()
+    );
+    assert_eq!(backoff.failures.lock().expect("Locking failed").len(), 1);
+}
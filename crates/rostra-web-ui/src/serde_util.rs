@@ -30,3 +30,14 @@ where
 
     T::deserialize(s.into_deserializer())
 }
+
+/// Distinguish an explicit `null` (`Some(None)`) from an omitted field
+///
+/// Needs `#[serde(default)]` on the field, so omitting it yields `None`.
+pub(crate) fn null_as_some_none<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
@@ -0,0 +1,279 @@
+//! Persistent [`SessionStore`] encrypting sessions at rest
+//!
+//! Sessions (including the [`RostraIdSecretKey`] of unlocked identities) are
+//! kept in a redb database under the data dir, encrypted with a per-install
+//! key, so they survive restarts without storing secrets in plain text.
+//!
+//! [`RostraIdSecretKey`]: rostra_core::id::RostraIdSecretKey
+
+use std::fmt;
+use std::io::{self, Write as _};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use bincode::{Decode, Encode};
+use chacha20poly1305::aead::{Aead as _, KeyInit as _, Payload};
+use chacha20poly1305::{Key, XChaCha20Poly1305, XNonce};
+use rand::Rng as _;
+use rostra_client_db::def_table;
+use rostra_core::id::RostraId;
+use snafu::{ResultExt as _, Snafu};
+use time::OffsetDateTime;
+use tower_sessions::session::{Id, Record};
+use tower_sessions::session_store::{self, SessionStore};
+use tracing::info;
+
+use crate::LOG_TARGET;
+use crate::routes::unlock::session::{SESSION_KEY, UserSession};
+
+const SESSIONS_DB_FILE: &str = "sessions.redb";
+const SESSIONS_KEY_FILE: &str = "sessions.key";
+
+def_table! {
+    /// Encrypted sessions, by session id
+    sessions: i128 => SessionRecord
+}
+
+#[derive(Debug, Encode, Decode, Clone)]
+pub struct SessionRecord {
+    /// Identity the session is logged in as, to log it out everywhere
+    rostra_id: Option<RostraId>,
+    /// Unix timestamp
+    expiry: i64,
+    nonce: [u8; 24],
+    /// Encrypted json of the [`Record`]
+    ciphertext: Vec<u8>,
+}
+
+#[derive(Debug, Snafu)]
+pub enum SessionStoreInitError {
+    #[snafu(display("IO error on {}", path.display()))]
+    Io { source: io::Error, path: PathBuf },
+    #[snafu(display("Session key file {} is corrupted", path.display()))]
+    KeyFileCorrupted { path: PathBuf },
+    #[snafu(transparent)]
+    Database { source: redb::DatabaseError },
+    #[snafu(transparent)]
+    Transaction { source: redb::TransactionError },
+    #[snafu(transparent)]
+    Table { source: redb::TableError },
+    #[snafu(transparent)]
+    Storage { source: redb::StorageError },
+    #[snafu(transparent)]
+    Commit { source: redb::CommitError },
+}
+
+pub type SessionStoreInitResult<T> = std::result::Result<T, SessionStoreInitError>;
+
+/// Redb backed, encrypted [`SessionStore`]
+#[derive(Clone)]
+pub struct RedbSessionStore {
+    db: Arc<redb_bincode::Database>,
+    cipher: Arc<XChaCha20Poly1305>,
+}
+
+impl fmt::Debug for RedbSessionStore {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Never print the key
+        f.debug_struct("RedbSessionStore").finish_non_exhaustive()
+    }
+}
+
+fn backend_err(err: impl fmt::Display) -> session_store::Error {
+    session_store::Error::Backend(err.to_string())
+}
+
+impl RedbSessionStore {
+    /// Open (or create) the store in `data_dir`
+    ///
+    /// Removes all expired sessions.
+    pub async fn open(data_dir: &Path) -> SessionStoreInitResult<Self> {
+        let store = tokio::task::block_in_place(|| {
+            std::fs::create_dir_all(data_dir).context(IoSnafu { path: data_dir })?;
+            let key = load_or_create_key(&data_dir.join(SESSIONS_KEY_FILE))?;
+            let db = redb_bincode::Database::from(redb::Database::create(
+                data_dir.join(SESSIONS_DB_FILE),
+            )?);
+
+            let dbtx = db.begin_write()?;
+            dbtx.open_table(&sessions::TABLE)?;
+            dbtx.commit()?;
+
+            Ok::<_, SessionStoreInitError>(Self {
+                db: Arc::new(db),
+                cipher: Arc::new(XChaCha20Poly1305::new(Key::from_slice(&key))),
+            })
+        })?;
+
+        let now = now_unix();
+        let removed = store.delete_where(|record| record.expiry <= now)?;
+        info!(target: LOG_TARGET, removed, "Removed expired sessions");
+        Ok(store)
+    }
+
+    /// Delete all sessions of `rostra_id` ("log out everywhere")
+    ///
+    /// Returns the number of sessions removed.
+    pub async fn delete_by_rostra_id(&self, rostra_id: RostraId) -> session_store::Result<usize> {
+        self.delete_where(|record| record.rostra_id == Some(rostra_id))
+            .map_err(backend_err)
+    }
+
+    fn delete_where(&self, pred: impl Fn(&SessionRecord) -> bool) -> SessionStoreInitResult<usize> {
+        tokio::task::block_in_place(|| {
+            let dbtx = self.db.begin_write()?;
+            let removed = {
+                let mut tbl = dbtx.open_table(&sessions::TABLE)?;
+                let mut ids = vec![];
+                for record in tbl.range(..)? {
+                    let (k, v) = record?;
+                    if pred(&v.value()) {
+                        ids.push(k.value());
+                    }
+                }
+                for id in &ids {
+                    tbl.remove(id)?;
+                }
+                ids.len()
+            };
+            dbtx.commit()?;
+            Ok(removed)
+        })
+    }
+
+    fn seal(&self, record: &Record) -> session_store::Result<SessionRecord> {
+        let plaintext = serde_json::to_vec(record)
+            .map_err(|err| session_store::Error::Encode(err.to_string()))?;
+        let nonce: [u8; 24] = rand::thread_rng().r#gen();
+        let ciphertext = self
+            .cipher
+            .encrypt(
+                XNonce::from_slice(&nonce),
+                Payload {
+                    msg: &plaintext,
+                    // Bind the ciphertext to the session id
+                    aad: &record.id.0.to_le_bytes(),
+                },
+            )
+            .map_err(|_| session_store::Error::Encode("Encryption failed".to_owned()))?;
+
+        Ok(SessionRecord {
+            rostra_id: record
+                .data
+                .get(SESSION_KEY)
+                .and_then(|user| serde_json::from_value::<UserSession>(user.clone()).ok())
+                .map(|user| user.id()),
+            expiry: record.expiry_date.unix_timestamp(),
+            nonce,
+            ciphertext,
+        })
+    }
+
+    fn open_sealed(&self, id: &Id, sealed: &SessionRecord) -> session_store::Result<Record> {
+        let plaintext = self
+            .cipher
+            .decrypt(
+                XNonce::from_slice(&sealed.nonce),
+                Payload {
+                    msg: &sealed.ciphertext,
+                    aad: &id.0.to_le_bytes(),
+                },
+            )
+            .map_err(|_| session_store::Error::Decode("Decryption failed".to_owned()))?;
+        serde_json::from_slice(&plaintext)
+            .map_err(|err| session_store::Error::Decode(err.to_string()))
+    }
+}
+
+#[async_trait]
+impl SessionStore for RedbSessionStore {
+    async fn create(&self, record: &mut Record) -> session_store::Result<()> {
+        tokio::task::block_in_place(|| {
+            let dbtx = self.db.begin_write().map_err(backend_err)?;
+            {
+                let mut tbl = dbtx.open_table(&sessions::TABLE).map_err(backend_err)?;
+                // Session ids are random, but make sure not to overwrite one
+                while tbl.get(&record.id.0).map_err(backend_err)?.is_some() {
+                    record.id = Id::default();
+                }
+                tbl.insert(&record.id.0, &self.seal(record)?)
+                    .map_err(backend_err)?;
+            }
+            dbtx.commit().map_err(backend_err)
+        })
+    }
+
+    async fn save(&self, record: &Record) -> session_store::Result<()> {
+        let sealed = self.seal(record)?;
+        tokio::task::block_in_place(|| {
+            let dbtx = self.db.begin_write().map_err(backend_err)?;
+            dbtx.open_table(&sessions::TABLE)
+                .map_err(backend_err)?
+                .insert(&record.id.0, &sealed)
+                .map_err(backend_err)?;
+            dbtx.commit().map_err(backend_err)
+        })
+    }
+
+    async fn load(&self, id: &Id) -> session_store::Result<Option<Record>> {
+        let sealed = tokio::task::block_in_place(|| {
+            let dbtx = self.db.begin_read().map_err(backend_err)?;
+            let tbl = dbtx.open_table(&sessions::TABLE).map_err(backend_err)?;
+            Ok::<_, session_store::Error>(tbl.get(&id.0).map_err(backend_err)?.map(|g| g.value()))
+        })?;
+
+        let Some(sealed) = sealed else {
+            return Ok(None);
+        };
+        if sealed.expiry <= now_unix() {
+            self.delete(id).await?;
+            return Ok(None);
+        }
+        self.open_sealed(id, &sealed).map(Some)
+    }
+
+    async fn delete(&self, id: &Id) -> session_store::Result<()> {
+        tokio::task::block_in_place(|| {
+            let dbtx = self.db.begin_write().map_err(backend_err)?;
+            dbtx.open_table(&sessions::TABLE)
+                .map_err(backend_err)?
+                .remove(&id.0)
+                .map_err(backend_err)?;
+            dbtx.commit().map_err(backend_err)
+        })
+    }
+}
+
+fn now_unix() -> i64 {
+    OffsetDateTime::now_utc().unix_timestamp()
+}
+
+/// Load the per-install session encryption key, generating it on first use
+fn load_or_create_key(path: &Path) -> SessionStoreInitResult<[u8; 32]> {
+    match std::fs::read(path) {
+        Ok(bytes) => {
+            <[u8; 32]>::try_from(bytes).map_err(|_| KeyFileCorruptedSnafu { path }.build())
+        }
+        Err(err) if err.kind() == io::ErrorKind::NotFound => {
+            let key: [u8; 32] = rand::thread_rng().r#gen();
+            write_key_file(path, &key).context(IoSnafu { path })?;
+            Ok(key)
+        }
+        Err(source) => Err(source).context(IoSnafu { path }),
+    }
+}
+
+fn write_key_file(path: &Path, key: &[u8; 32]) -> io::Result<()> {
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+
+    let mut file = options.open(path)?;
+    file.write_all(key)?;
+    file.sync_all()
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,66 @@
+use std::collections::HashMap;
+
+use rostra_core::id::RostraIdSecretKey;
+use time::{Duration, OffsetDateTime};
+use tower_sessions::SessionStore as _;
+use tower_sessions::session::{Id, Record};
+
+use super::RedbSessionStore;
+use crate::routes::unlock::session::{SESSION_KEY, UserSession};
+
+fn user_record(id_secret: RostraIdSecretKey, expires_in: Duration) -> Record {
+    Record {
+        id: Id::default(),
+        data: HashMap::from([(
+            SESSION_KEY.to_owned(),
+            serde_json::to_value(UserSession::new(id_secret.id(), Some(id_secret))).unwrap(),
+        )]),
+        expiry_date: OffsetDateTime::now_utc() + expires_in,
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn sessions_persist_encrypted() {
+    let dir = tempfile::tempdir().unwrap();
+    let id_secret = RostraIdSecretKey::generate();
+    let mut record = user_record(id_secret, Duration::hours(1));
+
+    {
+        let store = RedbSessionStore::open(dir.path()).await.unwrap();
+        store.create(&mut record).await.unwrap();
+    }
+
+    // The secret must not be stored in plain text
+    let raw = std::fs::read(dir.path().join(super::SESSIONS_DB_FILE)).unwrap();
+    let secret = serde_json::to_string(&id_secret).unwrap();
+    assert!(!raw.windows(secret.len()).any(|w| w == secret.as_bytes()));
+
+    // Reopening (a restart) keeps the session
+    let store = RedbSessionStore::open(dir.path()).await.unwrap();
+    let loaded = store.load(&record.id).await.unwrap().unwrap();
+    assert_eq!(loaded.data, record.data);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn sessions_expire_and_log_out_everywhere() {
+    let dir = tempfile::tempdir().unwrap();
+    let store = RedbSessionStore::open(dir.path()).await.unwrap();
+    let id_secret = RostraIdSecretKey::generate();
+    let other_secret = RostraIdSecretKey::generate();
+
+    let mut expired = user_record(id_secret, Duration::hours(-1));
+    store.create(&mut expired).await.unwrap();
+    assert!(store.load(&expired.id).await.unwrap().is_none());
+
+    let mut first = user_record(id_secret, Duration::hours(1));
+    let mut second = user_record(id_secret, Duration::hours(1));
+    let mut other = user_record(other_secret, Duration::hours(1));
+    for record in [&mut first, &mut second, &mut other] {
+        store.create(record).await.unwrap();
+    }
+
+    assert_eq!(store.delete_by_rostra_id(id_secret.id()).await.unwrap(), 2);
+    assert!(store.load(&first.id).await.unwrap().is_none());
+    assert!(store.load(&second.id).await.unwrap().is_none());
+    assert!(store.load(&other.id).await.unwrap().is_some());
+}
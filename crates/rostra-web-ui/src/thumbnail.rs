@@ -0,0 +1,250 @@
+//! Image normalization and a persistent thumbnail cache
+//!
+//! Avatars are cropped, resized and re-encoded in all [`AvatarSize`]s before
+//! being published, so they stay small. Images published by others (avatars
+//! and attachments) are only ever served as thumbnails, rendered on first use
+//! and cached in a redb database under the data dir, by the content hash of
+//! the event they come from.
+
+use std::io::{self, Cursor};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use bincode::{Decode, Encode};
+use image::codecs::jpeg::JpegEncoder;
+use image::imageops::FilterType;
+use image::{DynamicImage, ImageFormat, ImageReader, Limits};
+use rostra_client_db::def_table;
+use rostra_core::ContentHash;
+use serde::Deserialize;
+use snafu::{ResultExt as _, Snafu};
+
+#[cfg(test)]
+mod tests;
+
+const THUMBNAILS_DB_FILE: &str = "thumbnails.redb";
+
+/// Refuse to decode images larger than this in any dimension
+const MAX_DECODE_DIMENSION: u32 = 8192;
+/// Limit memory used for decoding, to protect against decompression bombs
+const MAX_DECODE_ALLOC: u64 = 256 * 1024 * 1024;
+
+const JPEG_QUALITY: u8 = 85;
+
+def_table! {
+    /// Rendered thumbnails, by content hash of the source event and size
+    thumbnails: (ContentHash, u32) => Thumbnail
+}
+
+#[derive(Debug, Encode, Decode, Clone)]
+pub struct Thumbnail {
+    pub mime: String,
+    pub data: Vec<u8>,
+}
+
+/// Sizes avatars are rendered in
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AvatarSize {
+    /// Timeline items and other small (32pt) images
+    Small,
+    /// Same, on high density displays
+    #[default]
+    Medium,
+    /// The published one
+    Large,
+}
+
+impl AvatarSize {
+    pub const ALL: [Self; 3] = [Self::Small, Self::Medium, Self::Large];
+
+    /// Width and height
+    pub fn pixels(self) -> u32 {
+        match self {
+            AvatarSize::Small => 64,
+            AvatarSize::Medium => 128,
+            AvatarSize::Large => 256,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            AvatarSize::Small => "small",
+            AvatarSize::Medium => "medium",
+            AvatarSize::Large => "large",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum ThumbnailKind {
+    /// Square
+    Avatar(AvatarSize),
+    /// Fits in a box, preserving the aspect ratio
+    Attachment,
+}
+
+impl ThumbnailKind {
+    pub fn size(self) -> u32 {
+        match self {
+            ThumbnailKind::Avatar(size) => size.pixels(),
+            ThumbnailKind::Attachment => 640,
+        }
+    }
+
+    fn render(self, image: DynamicImage) -> DynamicImage {
+        let size = self.size();
+        match self {
+            ThumbnailKind::Avatar(_) => image.resize_to_fill(size, size, FilterType::Lanczos3),
+            ThumbnailKind::Attachment if size < image.width() || size < image.height() => {
+                image.resize(size, size, FilterType::Lanczos3)
+            }
+            ThumbnailKind::Attachment => image,
+        }
+    }
+}
+
+#[derive(Debug, Snafu)]
+pub enum ThumbnailError {
+    #[snafu(display("IO error on {}", path.display()))]
+    Io { source: io::Error, path: PathBuf },
+    #[snafu(display("Invalid image: {source}"))]
+    Decode { source: image::ImageError },
+    #[snafu(display("Image encoding failed: {source}"))]
+    Encode { source: image::ImageError },
+    #[snafu(transparent)]
+    Database { source: redb::DatabaseError },
+    #[snafu(transparent)]
+    Transaction { source: redb::TransactionError },
+    #[snafu(transparent)]
+    Table { source: redb::TableError },
+    #[snafu(transparent)]
+    Storage { source: redb::StorageError },
+    #[snafu(transparent)]
+    Commit { source: redb::CommitError },
+}
+
+pub type ThumbnailResult<T> = std::result::Result<T, ThumbnailError>;
+
+/// Crop an uploaded avatar to squares of all [`AvatarSize`]s and re-encode
+/// them
+pub fn normalize_avatar(data: &[u8]) -> ThumbnailResult<Vec<(AvatarSize, Thumbnail)>> {
+    let image = decode(data)?;
+    AvatarSize::ALL
+        .into_iter()
+        .map(|size| {
+            Ok((
+                size,
+                encode(&ThumbnailKind::Avatar(size).render(image.clone()))?,
+            ))
+        })
+        .collect()
+}
+
+pub fn render_thumbnail(data: &[u8], kind: ThumbnailKind) -> ThumbnailResult<Thumbnail> {
+    encode(&kind.render(decode(data)?))
+}
+
+fn decode(data: &[u8]) -> ThumbnailResult<DynamicImage> {
+    let mut limits = Limits::default();
+    limits.max_image_width = Some(MAX_DECODE_DIMENSION);
+    limits.max_image_height = Some(MAX_DECODE_DIMENSION);
+    limits.max_alloc = Some(MAX_DECODE_ALLOC);
+
+    let mut reader = ImageReader::new(Cursor::new(data))
+        .with_guessed_format()
+        .map_err(image::ImageError::IoError)
+        .context(DecodeSnafu)?;
+    reader.limits(limits);
+    reader.decode().context(DecodeSnafu)
+}
+
+/// Encode as PNG if there is any transparency to preserve, JPEG otherwise
+fn encode(image: &DynamicImage) -> ThumbnailResult<Thumbnail> {
+    let mut data = vec![];
+    let mime = if image.color().has_alpha() {
+        image
+            .write_to(&mut Cursor::new(&mut data), ImageFormat::Png)
+            .context(EncodeSnafu)?;
+        "image/png"
+    } else {
+        JpegEncoder::new_with_quality(&mut data, JPEG_QUALITY)
+            .encode_image(&image.to_rgb8())
+            .context(EncodeSnafu)?;
+        "image/jpeg"
+    };
+    Ok(Thumbnail {
+        mime: mime.to_owned(),
+        data,
+    })
+}
+
+/// Redb backed cache of rendered thumbnails
+#[derive(Debug, Clone)]
+pub struct ThumbnailCache {
+    db: Arc<redb_bincode::Database>,
+}
+
+impl ThumbnailCache {
+    /// Open (or create) the cache in `data_dir`
+    pub async fn open(data_dir: &Path) -> ThumbnailResult<Self> {
+        tokio::task::block_in_place(|| {
+            std::fs::create_dir_all(data_dir).context(IoSnafu { path: data_dir })?;
+            let db = redb_bincode::Database::from(redb::Database::create(
+                data_dir.join(THUMBNAILS_DB_FILE),
+            )?);
+
+            let dbtx = db.begin_write()?;
+            dbtx.open_table(&thumbnails::TABLE)?;
+            dbtx.commit()?;
+
+            Ok(Self { db: Arc::new(db) })
+        })
+    }
+
+    /// Thumbnail of `data` (with `content_hash`), rendering and caching it
+    /// if needed
+    pub async fn get_or_render(
+        &self,
+        content_hash: ContentHash,
+        kind: ThumbnailKind,
+        data: &[u8],
+    ) -> ThumbnailResult<Thumbnail> {
+        let key = (content_hash, kind.size());
+        tokio::task::block_in_place(|| {
+            let dbtx = self.db.begin_read()?;
+            let tbl = dbtx.open_table(&thumbnails::TABLE)?;
+            if let Some(existing) = tbl.get(&key)?.map(|g| g.value()) {
+                return Ok(existing);
+            }
+
+            let thumbnail = render_thumbnail(data, kind)?;
+            self.insert_blocking(key, &thumbnail)?;
+            Ok(thumbnail)
+        })
+    }
+
+    /// Store an already rendered thumbnail, e.g. of an own avatar
+    pub async fn insert(
+        &self,
+        content_hash: ContentHash,
+        kind: ThumbnailKind,
+        thumbnail: &Thumbnail,
+    ) -> ThumbnailResult<()> {
+        tokio::task::block_in_place(|| self.insert_blocking((content_hash, kind.size()), thumbnail))
+    }
+
+    fn insert_blocking(
+        &self,
+        key: (ContentHash, u32),
+        thumbnail: &Thumbnail,
+    ) -> ThumbnailResult<()> {
+        let dbtx = self.db.begin_write()?;
+        {
+            let mut tbl = dbtx.open_table(&thumbnails::TABLE)?;
+            tbl.insert(&key, thumbnail)?;
+        }
+        dbtx.commit()?;
+        Ok(())
+    }
+}
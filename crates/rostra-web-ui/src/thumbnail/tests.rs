@@ -0,0 +1,112 @@
+use std::io::Cursor;
+
+use image::{DynamicImage, ImageFormat, RgbImage, RgbaImage};
+use rostra_core::ContentHash;
+
+use super::{
+    AvatarSize, ThumbnailCache, ThumbnailError, ThumbnailKind, normalize_avatar, render_thumbnail,
+};
+
+fn png(image: DynamicImage) -> Vec<u8> {
+    let mut data = vec![];
+    image
+        .write_to(&mut Cursor::new(&mut data), ImageFormat::Png)
+        .unwrap();
+    data
+}
+
+fn dimensions(data: &[u8]) -> (u32, u32) {
+    let image = image::load_from_memory(data).unwrap();
+    (image.width(), image.height())
+}
+
+#[test]
+fn avatars_are_normalized() {
+    let opaque = png(RgbImage::new(1000, 600).into());
+    let avatars = normalize_avatar(&opaque).unwrap();
+    assert_eq!(
+        avatars.iter().map(|(size, _)| *size).collect::<Vec<_>>(),
+        AvatarSize::ALL
+    );
+    for (size, avatar) in avatars {
+        assert_eq!(avatar.mime, "image/jpeg");
+        assert_eq!(dimensions(&avatar.data), (size.pixels(), size.pixels()));
+    }
+
+    // Transparency is preserved
+    let transparent = png(RgbaImage::new(20, 30).into());
+    for (size, avatar) in normalize_avatar(&transparent).unwrap() {
+        assert_eq!(avatar.mime, "image/png");
+        assert_eq!(dimensions(&avatar.data), (size.pixels(), size.pixels()));
+    }
+
+    assert!(matches!(
+        normalize_avatar(b"<svg></svg>"),
+        Err(ThumbnailError::Decode { .. })
+    ));
+}
+
+#[test]
+fn attachment_thumbnails_keep_aspect_ratio() {
+    let size = ThumbnailKind::Attachment.size();
+
+    let large = png(RgbImage::new(size * 4, size * 2).into());
+    let thumbnail = render_thumbnail(&large, ThumbnailKind::Attachment).unwrap();
+    assert_eq!(dimensions(&thumbnail.data), (size, size / 2));
+
+    // Small images are not upscaled
+    let small = png(RgbImage::new(10, 20).into());
+    let thumbnail = render_thumbnail(&small, ThumbnailKind::Attachment).unwrap();
+    assert_eq!(dimensions(&thumbnail.data), (10, 20));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn thumbnails_are_cached() {
+    let dir = tempfile::tempdir().unwrap();
+    let content_hash = ContentHash::from_bytes([1; 32]);
+    let data = png(RgbImage::new(300, 300).into());
+
+    let cache = ThumbnailCache::open(dir.path()).await.unwrap();
+    let kind = ThumbnailKind::Avatar(AvatarSize::Medium);
+    let thumbnail = cache
+        .get_or_render(content_hash, kind, &data)
+        .await
+        .unwrap();
+    let size = kind.size();
+    assert_eq!(dimensions(&thumbnail.data), (size, size));
+
+    // Once cached, the source is not needed anymore, even after a restart
+    drop(cache);
+    let cache = ThumbnailCache::open(dir.path()).await.unwrap();
+    let cached = cache.get_or_render(content_hash, kind, &[]).await.unwrap();
+    assert_eq!(cached.data, thumbnail.data);
+
+    // Different sizes are cached separately
+    let attachment = cache
+        .get_or_render(content_hash, ThumbnailKind::Attachment, &data)
+        .await
+        .unwrap();
+    assert_eq!(dimensions(&attachment.data), (300, 300));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn inserted_thumbnails_are_served() {
+    let dir = tempfile::tempdir().unwrap();
+    let content_hash = ContentHash::from_bytes([2; 32]);
+    let data = png(RgbImage::new(300, 300).into());
+    let cache = ThumbnailCache::open(dir.path()).await.unwrap();
+
+    for (size, avatar) in normalize_avatar(&data).unwrap() {
+        cache
+            .insert(content_hash, ThumbnailKind::Avatar(size), &avatar)
+            .await
+            .unwrap();
+    }
+    for size in AvatarSize::ALL {
+        let cached = cache
+            .get_or_render(content_hash, ThumbnailKind::Avatar(size), &[])
+            .await
+            .unwrap();
+        assert_eq!(dimensions(&cached.data), (size.pixels(), size.pixels()));
+    }
+}
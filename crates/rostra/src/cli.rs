@@ -1,9 +1,13 @@
+use std::num::NonZeroUsize;
 use std::path::{Path, PathBuf};
 use std::sync::LazyLock;
+use std::time::Duration;
 
 use clap::{Args, Parser, Subcommand};
+use rostra_client::DbBackupOpts;
 use rostra_core::event::PersonaId;
 use rostra_core::id::RostraId;
+use url::Url;
 
 /// Command line options for the Rostra CLI application
 #[derive(Debug, Parser)]
@@ -58,6 +62,13 @@ pub enum OptsCmd {
     #[command(subcommand)]
     Dev(DevCmd),
 
+    /// Manage passphrase protected keystores of identities
+    ///
+    /// Passphrases are prompted for, or read from `ROSTRA_KEYSTORE_PASSPHRASE`
+    /// (and `ROSTRA_KEYSTORE_NEW_PASSPHRASE` when changing it).
+    #[command(subcommand)]
+    Keystore(KeystoreCmd),
+
     /// Post a message
     Post {
         /// Message body to post
@@ -70,7 +81,61 @@ pub enum OptsCmd {
 
         #[arg(long)]
         persona_id: Option<PersonaId>,
+
+        /// Content warning to hide the message behind
+        #[arg(long)]
+        content_warning: Option<String>,
+    },
+
+    /// Export all events of an identity into an archive file
+    Export {
+        /// Identity whose database to export from
+        #[arg(long)]
+        rostra_id: RostraId,
+
+        /// Identity to export (defaults to `--rostra-id`)
+        #[arg(long)]
+        author: Option<RostraId>,
+
+        /// Path of the archive file to write
+        path: PathBuf,
+    },
+
+    /// Render the profile and posts of an identity into a static HTML site
+    ExportSite {
+        /// Identity whose database to export from
+        #[arg(long)]
+        rostra_id: RostraId,
+
+        /// Identity to export (defaults to `--rostra-id`)
+        #[arg(long)]
+        author: Option<RostraId>,
+
+        /// Only export posts of this persona
+        #[arg(long)]
+        persona_id: Option<PersonaId>,
+
+        /// Root directory of the assets dir
+        #[arg(long, env = "ROSTRA_ASSETS_DIR")]
+        assets_dir: Option<PathBuf>,
+
+        /// Directory to write the site into
+        path: PathBuf,
     },
+
+    /// Import events from an archive file, verifying all of them
+    Import {
+        /// Identity whose database to import into
+        #[arg(long)]
+        rostra_id: RostraId,
+
+        /// Path of the archive file to read
+        path: PathBuf,
+    },
+
+    /// Convert posts and profile to and from Nostr events
+    #[command(subcommand)]
+    Nostr(NostrCmd),
 }
 
 /// Global options that apply across all commands
@@ -98,17 +163,124 @@ pub struct WebUiOpts {
     /// Root directory of the assets dir
     #[arg(long, env = "ROSTRA_ASSETS_DIR")]
     pub assets_dir: Option<PathBuf>,
+
+    /// Snapshot the database every this many seconds (disabled if not set)
+    #[arg(long, env = "ROSTRA_BACKUP_INTERVAL_SECS")]
+    pub backup_interval_secs: Option<u64>,
+
+    /// Number of database snapshots to keep (at least one)
+    #[arg(long, env = "ROSTRA_BACKUP_KEEP", default_value = "3")]
+    pub backup_keep: NonZeroUsize,
+
+    /// Log out web UI sessions inactive for this many hours
+    #[arg(long, env = "ROSTRA_SESSION_EXPIRY_HOURS", default_value = "48")]
+    pub session_expiry_hours: u64,
+
+    /// Public url of this node, enables the ActivityPub (fediverse) bridge
+    #[arg(long, env = "ROSTRA_ACTIVITYPUB_URL")]
+    pub activitypub_url: Option<Url>,
+
+    /// Public url of this node, for absolute links (e.g. in feeds)
+    ///
+    /// Defaults to the ActivityPub url.
+    #[arg(long, env = "ROSTRA_PUBLIC_URL")]
+    pub public_url: Option<Url>,
+
+    /// List identities with a keystore on this node on the unlock page
+    #[arg(long, env = "ROSTRA_LIST_KEYSTORES")]
+    pub list_keystores: bool,
+}
+
+pub fn make_backup_opts(data_dir: &Path, opts: &WebUiOpts) -> Option<DbBackupOpts> {
+    opts.backup_interval_secs.map(|secs| DbBackupOpts {
+        dir: data_dir.join("backups"),
+        interval: Duration::from_secs(secs),
+        keep: opts.backup_keep,
+    })
 }
 
 pub fn make_web_opts(data_dir: &Path, opts: &WebUiOpts) -> rostra_web_ui::Opts {
-    rostra_web_ui::Opts::new(
+    let web_opts = rostra_web_ui::Opts::new(
         opts.listen.clone(),
         opts.cors_origin.clone(),
         opts.assets_dir.clone(),
         opts.reuseport,
         data_dir.to_owned(),
         opts.secret_file.clone(),
+        Duration::from_secs(opts.session_expiry_hours.saturating_mul(60 * 60)),
     )
+    .with_list_keystores(opts.list_keystores);
+    let web_opts = match opts.activitypub_url.clone() {
+        Some(url) => web_opts.with_activitypub_url(url),
+        None => web_opts,
+    };
+    match opts.public_url.clone() {
+        Some(url) => web_opts.with_public_url(url),
+        None => web_opts,
+    }
+}
+
+/// Keystore commands
+#[derive(Debug, Subcommand)]
+pub enum KeystoreCmd {
+    /// Create a keystore from a secret (mnemonic) file
+    Create {
+        /// Path to the secret file to encrypt
+        #[arg(long)]
+        secret_file: PathBuf,
+
+        /// Overwrite an existing keystore
+        #[arg(long)]
+        force: bool,
+    },
+    /// Change the passphrase of a keystore
+    ChangePassphrase {
+        #[arg(long)]
+        rostra_id: RostraId,
+    },
+    /// Decrypt a keystore and print the secret (mnemonic)
+    Export {
+        #[arg(long)]
+        rostra_id: RostraId,
+    },
+}
+
+/// Nostr bridge commands
+#[derive(Debug, Subcommand)]
+pub enum NostrCmd {
+    /// Export posts and profile updates as signed Nostr events (JSON lines)
+    Export {
+        /// Identity whose database to export from
+        #[arg(long)]
+        rostra_id: RostraId,
+
+        /// Identity to export (defaults to `--rostra-id`)
+        #[arg(long)]
+        author: Option<RostraId>,
+
+        /// Path to a file with the hex-encoded Nostr secret key to sign with
+        #[arg(long)]
+        nostr_secret_file: PathBuf,
+
+        /// Path of the JSON lines file to write
+        path: PathBuf,
+    },
+    /// Import a Nostr event dump (JSON lines) as posts of an identity
+    Import {
+        /// Path to the secret file of the identity to import as
+        #[arg(long)]
+        secret_file: PathBuf,
+
+        /// Only import events of this (hex-encoded) Nostr public key
+        #[arg(long)]
+        nostr_pubkey: Option<String>,
+
+        #[arg(long)]
+        persona_id: Option<PersonaId>,
+
+        /// Path of the JSON lines file to read
+        path: PathBuf,
+    },
 }
 
 /// Development and debugging commands
@@ -142,4 +314,38 @@ pub enum DevCmd {
         #[arg(long)]
         rostra_id: RostraId,
     },
+    /// Check database integrity
+    DbCheck {
+        #[arg(long)]
+        rostra_id: RostraId,
+        /// Fix found issues by rebuilding derived data from raw events
+        #[arg(long)]
+        repair: bool,
+    },
+    /// Rebuild all tables derived from event content
+    Reindex {
+        #[arg(long)]
+        rostra_id: RostraId,
+    },
+    /// Print database storage statistics
+    DbStats {
+        #[arg(long)]
+        rostra_id: RostraId,
+    },
+    /// Write a consistent snapshot of the database
+    DbSnapshot {
+        #[arg(long)]
+        rostra_id: RostraId,
+        /// Path of the snapshot file to write
+        path: PathBuf,
+    },
+    /// Replace the database with a snapshot
+    ///
+    /// The node must not be running.
+    DbRestore {
+        #[arg(long)]
+        rostra_id: RostraId,
+        /// Path of the snapshot file to restore
+        path: PathBuf,
+    },
 }
@@ -1,23 +1,29 @@
 mod cli;
 
 use std::io;
+use std::path::PathBuf;
 use std::time::Duration;
 
 use clap::Parser;
-use cli::{Opts, make_web_opts};
+use cli::{Opts, make_backup_opts, make_web_opts};
 use duct::cmd;
 use futures::future::pending;
+use rostra_bridge_nostr::{NostrError, NostrEvent, NostrSecretKey};
 use rostra_client::Client;
 use rostra_client::error::{ConnectError, IdResolveError, IdSecretReadError, InitError, PostError};
+use rostra_client::keystore::{KdfParams, Keystore, KeystoreError};
 use rostra_client::multiclient::MultiClient;
-use rostra_client_db::{Database, DbError};
-use rostra_core::event::PersonaId;
+use rostra_client_db::{
+    ArchiveDecodeError, Database, DbError, IdentityArchive, ImportArchiveError,
+};
+use rostra_core::event::{EventExt as _, PersonaId};
 use rostra_core::id::RostraIdSecretKey;
 use rostra_p2p::RpcError;
 use rostra_p2p::connection::Connection;
 use rostra_util_error::{BoxedError, FmtCompact as _};
+use rostra_web_ui::export::{ExportSiteError, export_site};
 use rostra_web_ui::{Server, WebUiServerError};
-use snafu::{FromString, ResultExt, Snafu, Whatever};
+use snafu::{FromString, ResultExt, Snafu, Whatever, ensure};
 use tokio::time::Instant;
 use tracing::level_filters::LevelFilter;
 use tracing::{info, warn};
@@ -50,12 +56,33 @@ pub enum CliError {
     DataDir { source: io::Error },
     #[snafu(display("Database error: {source}"))]
     Database { source: DbError },
+    #[snafu(display("IO error: {source}"))]
+    Io { source: io::Error },
+    #[snafu(display("Archive decoding error: {source}"))]
+    ArchiveDecode { source: ArchiveDecodeError },
+    #[snafu(display("Archive import error: {source}"))]
+    ArchiveImport { source: ImportArchiveError },
+    #[snafu(display("Site export error: {source}"))]
+    ExportSite { source: ExportSiteError },
+    #[snafu(display("Nostr error: {source}"))]
+    Nostr { source: NostrError },
+    #[snafu(display("Keystore error: {source}"))]
+    Keystore { source: KeystoreError },
+    #[snafu(display("Keystore already exists: {}", path.display()))]
+    KeystoreExists { path: PathBuf },
+    #[snafu(display("Passphrases do not match"))]
+    PassphraseMismatch,
+    #[snafu(display("Passphrase must not be empty"))]
+    PassphraseEmpty,
     #[snafu(display("Miscellaneous error: {source}"))]
     Other { source: BoxedError },
 }
 
 pub type CliResult<T> = std::result::Result<T, CliError>;
 
+const KEYSTORE_PASSPHRASE_ENV: &str = "ROSTRA_KEYSTORE_PASSPHRASE";
+const KEYSTORE_NEW_PASSPHRASE_ENV: &str = "ROSTRA_KEYSTORE_NEW_PASSPHRASE";
+
 #[snafu::report]
 #[tokio::main]
 async fn main() -> CliResult<()> {
@@ -168,6 +195,134 @@ async fn handle_cmd(opts: Opts) -> CliResult<serde_json::Value> {
 
                 serde_json::to_value(serde_json::Value::Null).expect("Can't fail")
             }
+            cli::DevCmd::DbCheck {
+                rostra_id: id,
+                repair,
+            } => {
+                let db_path = Database::mk_db_path(opts.global.data_dir(), id)
+                    .await
+                    .context(DataDirSnafu)?;
+
+                let db = Database::open(&db_path, id).await.context(DatabaseSnafu)?;
+
+                let report = db.check_integrity(repair).await.context(DatabaseSnafu)?;
+
+                serde_json::to_value(report).expect("Can't fail")
+            }
+            cli::DevCmd::Reindex { rostra_id: id } => {
+                let db_path = Database::mk_db_path(opts.global.data_dir(), id)
+                    .await
+                    .context(DataDirSnafu)?;
+
+                let db = Database::open(&db_path, id).await.context(DatabaseSnafu)?;
+
+                let progress = db
+                    .reindex(|progress| {
+                        info!(target: LOG_TARGET, processed = progress.processed, total = progress.total, "Reindexing");
+                    })
+                    .await
+                    .context(DatabaseSnafu)?;
+
+                serde_json::to_value(progress).expect("Can't fail")
+            }
+            cli::DevCmd::DbStats { rostra_id: id } => {
+                let db_path = Database::mk_db_path(opts.global.data_dir(), id)
+                    .await
+                    .context(DataDirSnafu)?;
+
+                let db = Database::open(&db_path, id).await.context(DatabaseSnafu)?;
+
+                let stats = db.stats().await.context(DatabaseSnafu)?;
+
+                serde_json::to_value(stats).expect("Can't fail")
+            }
+            cli::DevCmd::DbSnapshot {
+                rostra_id: id,
+                path,
+            } => {
+                let db_path = Database::mk_db_path(opts.global.data_dir(), id)
+                    .await
+                    .context(DataDirSnafu)?;
+
+                let db = Database::open(&db_path, id).await.context(DatabaseSnafu)?;
+
+                db.snapshot_to(&path).await.context(DatabaseSnafu)?;
+
+                serde_json::Value::Null
+            }
+            cli::DevCmd::DbRestore {
+                rostra_id: id,
+                path,
+            } => {
+                let db_path = Database::mk_db_path(opts.global.data_dir(), id)
+                    .await
+                    .context(DataDirSnafu)?;
+
+                let old_db_path = Database::restore_snapshot(&path, &db_path, id)
+                    .await
+                    .context(DatabaseSnafu)?;
+
+                serde_json::json!({
+                    "path": db_path,
+                    "old_path": old_db_path,
+                })
+            }
+        },
+        cli::OptsCmd::Keystore(cmd) => match cmd {
+            cli::KeystoreCmd::Create { secret_file, force } => {
+                let id_secret = Client::read_id_secret(&secret_file)
+                    .await
+                    .context(SecretSnafu)?;
+                let path = Keystore::path(opts.global.data_dir(), id_secret.id());
+                if !force && path.exists() {
+                    return KeystoreExistsSnafu { path }.fail();
+                }
+
+                let passphrase = read_new_passphrase(KEYSTORE_PASSPHRASE_ENV)?;
+                let keystore = tokio::task::block_in_place(|| {
+                    Keystore::encrypt(id_secret, &passphrase, KdfParams::default())
+                })
+                .context(KeystoreSnafu)?;
+                keystore.write(&path).await.context(KeystoreSnafu)?;
+
+                serde_json::json!({
+                    "id": id_secret.id(),
+                    "path": path,
+                })
+            }
+            cli::KeystoreCmd::ChangePassphrase { rostra_id } => {
+                let path = Keystore::path(opts.global.data_dir(), rostra_id);
+                let keystore = Keystore::read(&path).await.context(KeystoreSnafu)?;
+
+                let passphrase = read_passphrase(KEYSTORE_PASSPHRASE_ENV, "Current passphrase: ")?;
+                let id_secret = tokio::task::block_in_place(|| keystore.decrypt(&passphrase))
+                    .context(KeystoreSnafu)?;
+
+                let new_passphrase = read_new_passphrase(KEYSTORE_NEW_PASSPHRASE_ENV)?;
+                let keystore = tokio::task::block_in_place(|| {
+                    Keystore::encrypt(id_secret, &new_passphrase, KdfParams::default())
+                })
+                .context(KeystoreSnafu)?;
+                keystore.write(&path).await.context(KeystoreSnafu)?;
+
+                serde_json::json!({
+                    "id": rostra_id,
+                    "path": path,
+                })
+            }
+            cli::KeystoreCmd::Export { rostra_id } => {
+                let path = Keystore::path(opts.global.data_dir(), rostra_id);
+                let keystore = Keystore::read(&path).await.context(KeystoreSnafu)?;
+
+                let passphrase = read_passphrase(KEYSTORE_PASSPHRASE_ENV, "Passphrase: ")?;
+                let secret = tokio::task::block_in_place(|| keystore.decrypt(&passphrase))
+                    .context(KeystoreSnafu)?;
+
+                serde_json::json!({
+                    "id": rostra_id,
+                    "secret": secret,
+                })
+            }
         },
         cli::OptsCmd::Serve { secret_file } => {
             let secret_id = if let Some(secret_file) = secret_file {
@@ -185,7 +340,10 @@ async fn handle_cmd(opts: Opts) -> CliResult<serde_json::Value> {
             pending().await
         }
         cli::OptsCmd::WebUi(ref web_opts) => {
-            let clients = MultiClient::new(opts.global.data_dir().to_owned());
+            let mut clients = MultiClient::new(opts.global.data_dir().to_owned());
+            if let Some(backup) = make_backup_opts(opts.global.data_dir(), web_opts) {
+                clients = clients.with_backup(backup);
+            }
             let server = Server::init(make_web_opts(opts.global.data_dir(), web_opts), clients)
                 .await
                 .context(WebUiServerSnafu)?;
@@ -220,6 +378,7 @@ async fn handle_cmd(opts: Opts) -> CliResult<serde_json::Value> {
             body,
             secret_file,
             persona_id,
+            content_warning,
         } => {
             let id_secret = Client::read_id_secret(&secret_file)
                 .await
@@ -232,14 +391,192 @@ async fn handle_cmd(opts: Opts) -> CliResult<serde_json::Value> {
                 .context(InitSnafu)?;
 
             client
-                .social_post(id_secret, body, None, persona_id.unwrap_or(PersonaId(0)))
+                .social_post(
+                    id_secret,
+                    body,
+                    None,
+                    persona_id.unwrap_or(PersonaId(0)),
+                    vec![],
+                    None,
+                    content_warning,
+                )
                 .await?;
 
             serde_json::Value::Bool(true)
         }
+        cli::OptsCmd::Export {
+            rostra_id,
+            author,
+            path,
+        } => {
+            let db_path = Database::mk_db_path(opts.global.data_dir(), rostra_id)
+                .await
+                .context(DataDirSnafu)?;
+            let db = Database::open(&db_path, rostra_id)
+                .await
+                .context(DatabaseSnafu)?;
+
+            let archive = db
+                .export_identity(author.unwrap_or(rostra_id))
+                .await
+                .context(DatabaseSnafu)?;
+
+            tokio::fs::write(&path, archive.encode_to_vec())
+                .await
+                .context(IoSnafu)?;
+
+            serde_json::to_value(archive.manifest).expect("Can't fail")
+        }
+        cli::OptsCmd::ExportSite {
+            rostra_id,
+            author,
+            persona_id,
+            assets_dir,
+            path,
+        } => {
+            let db_path = Database::mk_db_path(opts.global.data_dir(), rostra_id)
+                .await
+                .context(DataDirSnafu)?;
+            let db = Database::open(&db_path, rostra_id)
+                .await
+                .context(DatabaseSnafu)?;
+
+            let summary = export_site(
+                &db,
+                author.unwrap_or(rostra_id),
+                persona_id,
+                assets_dir.as_deref(),
+                &path,
+            )
+            .await
+            .context(ExportSiteSnafu)?;
+
+            serde_json::to_value(summary).expect("Can't fail")
+        }
+        cli::OptsCmd::Import { rostra_id, path } => {
+            let archive =
+                IdentityArchive::decode_from_slice(&tokio::fs::read(&path).await.context(IoSnafu)?)
+                    .context(ArchiveDecodeSnafu)?;
+
+            let db_path = Database::mk_db_path(opts.global.data_dir(), rostra_id)
+                .await
+                .context(DataDirSnafu)?;
+            let db = Database::open(&db_path, rostra_id)
+                .await
+                .context(DatabaseSnafu)?;
+
+            let outcome = db
+                .import_archive(&archive)
+                .await
+                .context(ArchiveImportSnafu)?;
+
+            serde_json::to_value(outcome).expect("Can't fail")
+        }
+        cli::OptsCmd::Nostr(cmd) => match cmd {
+            cli::NostrCmd::Export {
+                rostra_id,
+                author,
+                nostr_secret_file,
+                path,
+            } => {
+                let secret: NostrSecretKey = tokio::fs::read_to_string(&nostr_secret_file)
+                    .await
+                    .context(IoSnafu)?
+                    .parse()
+                    .context(NostrSnafu)?;
+
+                let db_path = Database::mk_db_path(opts.global.data_dir(), rostra_id)
+                    .await
+                    .context(DataDirSnafu)?;
+                let db = Database::open(&db_path, rostra_id)
+                    .await
+                    .context(DatabaseSnafu)?;
+
+                let archive = db
+                    .export_identity(author.unwrap_or(rostra_id))
+                    .await
+                    .context(DatabaseSnafu)?;
+                let events = rostra_bridge_nostr::export_events(
+                    archive
+                        .events
+                        .iter()
+                        .map(|event| (event.signed.event(), event.content.as_ref())),
+                    &secret,
+                );
+
+                tokio::fs::write(&path, NostrEvent::to_json_lines(&events))
+                    .await
+                    .context(IoSnafu)?;
+
+                serde_json::json!({
+                    "pubkey": secret.public_key(),
+                    "events": events.len(),
+                })
+            }
+            cli::NostrCmd::Import {
+                secret_file,
+                nostr_pubkey,
+                persona_id,
+                path,
+            } => {
+                let id_secret = Client::read_id_secret(&secret_file)
+                    .await
+                    .context(SecretSnafu)?;
+                let nostr_events = NostrEvent::from_json_lines(
+                    &tokio::fs::read_to_string(&path).await.context(IoSnafu)?,
+                )
+                .context(NostrSnafu)?;
+
+                let rostra_id = id_secret.id();
+                let db_path = Database::mk_db_path(opts.global.data_dir(), rostra_id)
+                    .await
+                    .context(DataDirSnafu)?;
+                let db = Database::open(&db_path, rostra_id)
+                    .await
+                    .context(DatabaseSnafu)?;
+
+                let outcome = rostra_bridge_nostr::import_events(
+                    &nostr_events,
+                    nostr_pubkey.as_deref(),
+                    id_secret,
+                    persona_id.unwrap_or(PersonaId(0)),
+                    db.get_self_current_head().await,
+                );
+                for event in &outcome.events {
+                    let _ = db.process_event_with_content(event).await;
+                }
+
+                serde_json::json!({
+                    "imported": outcome.events.len(),
+                    "skipped": outcome.skipped,
+                })
+            }
+        },
     })
 }
 
+/// Read a passphrase from `env`, or prompt for it
+fn read_passphrase(env: &str, prompt: &str) -> CliResult<String> {
+    if let Ok(passphrase) = std::env::var(env) {
+        return Ok(passphrase);
+    }
+    rpassword::prompt_password(prompt).context(IoSnafu)
+}
+
+/// Like [`read_passphrase`], but prompts twice for confirmation
+fn read_new_passphrase(env: &str) -> CliResult<String> {
+    let passphrase = if let Ok(passphrase) = std::env::var(env) {
+        passphrase
+    } else {
+        let passphrase = rpassword::prompt_password("New passphrase: ").context(IoSnafu)?;
+        let repeated = rpassword::prompt_password("Repeat passphrase: ").context(IoSnafu)?;
+        ensure!(passphrase == repeated, PassphraseMismatchSnafu);
+        passphrase
+    };
+    ensure!(!passphrase.is_empty(), PassphraseEmptySnafu);
+    Ok(passphrase)
+}
+
 pub fn init_logging() -> WhateverResult<()> {
     tracing_subscriber::fmt()
         .with_writer(io::stderr)